@@ -0,0 +1,29 @@
+//! Download a file with an indicatif progress bar.
+//!
+//! ```sh
+//! cargo run --example indicatif --features indicatif -- <url> <dest> [size]
+//! ```
+
+use fetchkit::download::DownloadBuilder;
+use fetchkit::progress::indicatif::{Bar, FinishBehavior, ProgressStyle};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let url = args.next().expect("missing url argument");
+    let dest = args.next().expect("missing dest argument");
+    let size = args.next().map_or(0, |s| s.parse().expect("invalid size"));
+
+    let style = ProgressStyle::with_template(
+        "{bar:40} {bytes}/{total_bytes} {bytes_per_sec} {eta}",
+    )?;
+    let bar = Bar::new()
+        .with_style(style)
+        .on_finish(FinishBehavior::WithMessage("done".into()));
+
+    let client = reqwest::Client::new();
+    DownloadBuilder::new(url, dest, size)
+        .download(&client, bar)
+        .await?;
+    Ok(())
+}
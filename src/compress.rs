@@ -0,0 +1,119 @@
+//! Decompression of single compressed files.
+//!
+//! This handles downloads like `tool.gz` that are a single compressed file
+//! rather than an archive. The supported formats depend on the enabled
+//! features (`gzip`, `zstd`, `xz`).
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+
+/// A single-file compression format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// gzip (`.gz`)
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// zstd (`.zst`)
+    #[cfg(feature = "zstd")]
+    Zstd,
+    /// xz (`.xz`)
+    #[cfg(feature = "xz")]
+    Xz,
+}
+
+impl Compression {
+    /// Guess the compression from the file name extension of `path`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "gzip")]
+            "gz" => Some(Compression::Gzip),
+            #[cfg(feature = "zstd")]
+            "zst" => Some(Compression::Zstd),
+            #[cfg(feature = "xz")]
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    /// Wrap `reader` in a streaming decoder for this compression.
+    pub(crate) fn decoder<'r>(self, reader: impl Read + 'r) -> Result<Box<dyn Read + 'r>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Box::new(flate2::read::GzDecoder::new(reader))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(Box::new(
+                zstd::stream::read::Decoder::new(BufReader::new(reader))
+                    .map_err(Error::from)
+                    .with_desc("failed to initialize zstd decoder")?,
+            )),
+            #[cfg(feature = "xz")]
+            Compression::Xz => Ok(Box::new(xz2::read::XzDecoder::new(reader))),
+        }
+    }
+}
+
+/// Decompress `src` to `dest`, guessing the format from the file name.
+///
+/// Returns the number of decompressed bytes written.
+pub fn decompress(src: &Path, dest: &Path) -> Result<u64> {
+    let compression = Compression::from_path(src).ok_or_else(|| {
+        Error::new(ErrorKind::Other)
+            .with_desc_with(|| format!("unsupported compression format: {}", src.display()))
+    })?;
+    decompress_with(src, dest, compression)
+}
+
+/// Decompress `src` to `dest` with an explicit format.
+pub fn decompress_with(src: &Path, dest: &Path, compression: Compression) -> Result<u64> {
+    let file = File::open(src)
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to open {}", src.display()))?;
+    let mut reader = compression.decoder(BufReader::new(file))?;
+    let out = File::create(dest)
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to create {}", dest.display()))?;
+    let mut writer = BufWriter::new(out);
+    let written = std::io::copy(&mut reader, &mut writer)
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to decompress {}", src.display()))?;
+    writer
+        .flush()
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to write {}", dest.display()))?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn decompress_gz() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("data.txt.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            File::create(&src).unwrap(),
+            flate2::Compression::fast(),
+        );
+        encoder.write_all(b"hello world").unwrap();
+        encoder.finish().unwrap();
+        let dest = dir.path().join("data.txt");
+        let written = decompress(&src, &dest).unwrap();
+        assert_eq!(written, 11);
+        assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn unknown_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("data.txt");
+        std::fs::write(&src, b"plain").unwrap();
+        let err = decompress(&src, &dir.path().join("out")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+    }
+}
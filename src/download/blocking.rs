@@ -0,0 +1,417 @@
+//! A blocking download path for consumers without an async runtime.
+//!
+//! Enabled by the `blocking` feature. [`BlockingClient`] and
+//! [`BlockingResponse`] mirror the async [`Client`](crate::http::Client)
+//! and [`Response`](crate::http::Response) traits with a [`Read`]-based
+//! body, so a simple CLI can plug in e.g. a `ureq` agent and call
+//! [`DownloadBuilder::download_blocking`] without pulling in an executor.
+//! The blocking path shares the builder's policies where they do not need
+//! a timer: verification, progress, mirror selection (probed with a
+//! blocking speedtest), the part-file dance, offline/overwrite/locking,
+//! freshness checks and cancellation. Timer-backed options — timeouts,
+//! speed limits, retries — only apply to the async methods.
+
+use std::io::Read;
+
+use super::*;
+
+/// A blocking HTTP client; the synchronous sibling of
+/// [`Client`](crate::http::Client).
+pub trait BlockingClient {
+    /// The response type produced by this client.
+    type Response: BlockingResponse;
+
+    /// Send a GET request to `url` and return the response.
+    fn get(&self, url: &str) -> Result<Self::Response>;
+
+    /// Send a GET request to `url` with additional request headers.
+    ///
+    /// As in the async trait, the default sends a plain GET, ignoring the
+    /// headers — a server requiring them will answer with an error status.
+    fn get_with_headers(&self, url: &str, headers: &[(String, String)]) -> Result<Self::Response> {
+        let _ = headers;
+        self.get(url)
+    }
+
+    /// Send a GET request to `url` with an `If-None-Match` header.
+    ///
+    /// As in the async trait, the default sends a plain GET, ignoring the
+    /// tag — correct, but never saves a transfer.
+    fn get_if_none_match(&self, url: &str, etag: &str) -> Result<Self::Response> {
+        let _ = etag;
+        self.get(url)
+    }
+
+    /// Send a GET request to `url` with an `If-Modified-Since` header.
+    ///
+    /// The default sends a plain GET, ignoring the date.
+    fn get_if_modified_since(&self, url: &str, date: &str) -> Result<Self::Response> {
+        let _ = date;
+        self.get(url)
+    }
+}
+
+/// A blocking HTTP response whose body is read sequentially; the
+/// synchronous sibling of [`Response`](crate::http::Response).
+pub trait BlockingResponse {
+    /// The HTTP status code of the response.
+    ///
+    /// Only consulted to recognize `304 Not Modified` on conditional
+    /// requests; the default reports plain success.
+    fn status(&self) -> u16 {
+        200
+    }
+
+    /// The `ETag` header of the response, if the server sent one.
+    fn etag(&self) -> Option<String> {
+        None
+    }
+
+    /// The size of the body in bytes, if the server announced one.
+    fn content_length(&self) -> Option<u64> {
+        None
+    }
+
+    /// The RFC 3230 `Digest` header of the response, if the server sent
+    /// one; see [`Response::digest`](crate::http::Response::digest).
+    fn digest(&self) -> Option<String> {
+        None
+    }
+
+    /// The `Content-MD5` header of the response, if the server sent one.
+    fn content_md5(&self) -> Option<String> {
+        None
+    }
+
+    /// Whether the client transparently decoded the body; see
+    /// [`Response::is_decoded`](crate::http::Response::is_decoded).
+    fn is_decoded(&self) -> bool {
+        false
+    }
+
+    /// Consume the response, returning the body as a reader.
+    fn into_body(self) -> impl Read;
+}
+
+/// How much of the body a single read requests from a blocking response.
+const READ_BUFFER: usize = 64 * 1024;
+
+/// Turn a non-success status into an error instead of handing the body
+/// on; the synchronous sibling of the check in the async request path.
+fn reject_error_status<R: BlockingResponse>(
+    response: R,
+    condition: Option<&Condition>,
+) -> Result<R> {
+    let status = response.status();
+    if (200..300).contains(&status) || (status == 304 && condition.is_some()) {
+        return Ok(response);
+    }
+    let error = Error::new(ErrorKind::Network).with_http_status(status);
+    // Servers tend to explain themselves in the body, so its beginning
+    // rides along in the description.
+    let mut buffer = [0u8; 200];
+    let read = response.into_body().read(&mut buffer).unwrap_or(0);
+    let snippet = String::from_utf8_lossy(&buffer[..read]).trim().to_owned();
+    Err(error.with_desc_with(|| {
+        if snippet.is_empty() {
+            format!("the server answered with status {status}")
+        } else {
+            format!("the server answered with status {status}: {snippet}")
+        }
+    }))
+}
+
+impl DownloadBuilder {
+    /// Download the file without an async runtime.
+    ///
+    /// The blocking sibling of [`download`](Self::download): the same
+    /// part-file transfer with verification, progress reporting, mirror
+    /// selection, the offline/overwrite/locking policies, freshness checks
+    /// and cancellation — driven by blocking reads instead of an executor.
+    /// The timer-backed options (timeouts, speed limits, retries) and the
+    /// sidecar fetches do not apply here.
+    pub fn download_blocking<C: BlockingClient>(
+        mut self,
+        client: &C,
+        progress: impl ProgressReceiverBuilder,
+    ) -> Result<DownloadReport> {
+        let started = Instant::now();
+        self.deadline_at = self.deadline.map(|d| Instant::now() + d);
+        self.check_cancelled()?;
+        if self.check_offline()? || self.check_overwrite()? {
+            return Ok(self.report(&self.url, 0, 0, started));
+        }
+        let _lock = match self.lock.take() {
+            Some(wait) => {
+                let lock = DestLock::acquire(&self.dest, wait)?;
+                if self.exist()? {
+                    log::debug!(
+                        "{} appeared while waiting for the lock",
+                        self.dest.display()
+                    );
+                    return Ok(self.report(&self.url, 0, 0, started));
+                }
+                Some(lock)
+            }
+            None => None,
+        };
+
+        let url = match self.mirrors.take() {
+            Some(mut mirrors) => mirrors
+                .select_blocking(client, &self.url, &self.headers, self.cancel.as_ref())
+                .map_err(|e| e.with_url(&self.url))?,
+            None => self.url.clone(),
+        };
+        self.check_disk_space()?;
+
+        let progress = Throttled::with_interval(
+            progress.init((self.size != 0).then_some(self.size)),
+            self.throttle,
+        );
+        let result: Result<u64> = (|| {
+            let fetched = self.fetch_to_file_blocking(client, &url, &progress)?;
+            let (verifier, etag, len) = match fetched {
+                Fetched::Done {
+                    verifier, etag, len, ..
+                } => (verifier, etag, len),
+                Fetched::NotModified => {
+                    log::debug!("{} is still up to date", self.dest.display());
+                    self.remove_part();
+                    return Ok(0);
+                }
+            };
+            if let Some(verifier) = verifier {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::info_span!("verify", url = self.url.as_str()).entered();
+                verifier.verify()?;
+            }
+            if self.verification == VerificationMode::AfterDownload {
+                self.verify_part_file(&progress)?;
+            }
+            self.commit_part()?;
+            self.store_etag(etag.as_deref());
+            Ok(len)
+        })();
+
+        let result = result.map_err(|e| e.with_url(&url).with_path(&self.dest));
+        if result.is_err() {
+            self.discard_part();
+        }
+        // Every exit route resolves the progress receiver exactly once.
+        match result {
+            Ok(bytes) => {
+                progress.finish();
+                Ok(self.report(&url, bytes, 0, started))
+            }
+            Err(error) => {
+                progress.finish_with_error(&error);
+                Err(error)
+            }
+        }
+    }
+
+    /// Fetch `url` to the part file with blocking I/O; the synchronous
+    /// sibling of [`fetch_to_file`](Self::fetch_to_file).
+    fn fetch_to_file_blocking<C: BlockingClient>(
+        &self,
+        client: &C,
+        url: &str,
+        progress: &impl ProgressReceiver,
+    ) -> Result<Fetched> {
+        if !self.replaces_dest() && self.dest.symlink_metadata().is_ok() {
+            return Err(Error::from(std::io::Error::from(
+                std::io::ErrorKind::AlreadyExists,
+            ))
+            .with_desc_with(|| format!("{} already exists", self.dest.display())));
+        }
+        let condition = self.condition();
+        let part = self.part_path();
+        let file = File::create(&part)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to create {}", part.display()))?;
+        if self.preallocate && self.size != 0 {
+            file.set_len(self.size)
+                .map_err(Error::from)
+                .with_desc_with(|| self.preallocate_failure(&part))?;
+        }
+        let mut file = std::io::BufWriter::with_capacity(self.write_buffer, file);
+        let fetched =
+            self.fetch_to_writer_blocking(client, url, &mut file, progress, condition.as_ref())?;
+        file.flush()
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to flush {}", part.display()))?;
+        self.trim_preallocation(&part, &fetched)?;
+        Ok(fetched)
+    }
+
+    /// Read `url` into `writer`, feeding `progress` and the verifier; the
+    /// synchronous sibling of [`fetch_to_writer`](Self::fetch_to_writer).
+    fn fetch_to_writer_blocking<C: BlockingClient>(
+        &self,
+        client: &C,
+        url: &str,
+        writer: &mut impl Write,
+        progress: &impl ProgressReceiver,
+        condition: Option<&Condition>,
+    ) -> Result<Fetched> {
+        let response = if self.headers.is_empty() {
+            match condition {
+                Some(Condition::IfNoneMatch(etag)) => client.get_if_none_match(url, etag),
+                Some(Condition::IfModifiedSince(date)) => client.get_if_modified_since(url, date),
+                None => client.get(url),
+            }
+        } else {
+            // As in the async path, a condition is itself just a header and
+            // rides along with the configured ones.
+            let mut headers = self.headers.clone();
+            match condition {
+                Some(Condition::IfNoneMatch(etag)) => {
+                    headers.push(("If-None-Match".to_owned(), etag.clone()));
+                }
+                Some(Condition::IfModifiedSince(date)) => {
+                    headers.push(("If-Modified-Since".to_owned(), date.clone()));
+                }
+                None => {}
+            }
+            client.get_with_headers(url, &headers)
+        }
+        .and_then(|response| reject_error_status(response, condition))
+        .with_desc_with(|| format!("failed to fetch {url}"))?;
+        if response.status() == 304 {
+            return Ok(Fetched::NotModified);
+        }
+        if !response.is_decoded() {
+            self.check_content_length(response.content_length())?;
+        }
+        let etag = if self.etag_cache {
+            response.etag()
+        } else {
+            None
+        };
+
+        let mut verifier = match self.verification {
+            // The after-download pass re-reads the finished file instead.
+            VerificationMode::AfterDownload => None,
+            VerificationMode::Streaming => match &self.verifier {
+                Some(builder) => Some(builder.build_dyn()?),
+                None if self.server_digest => {
+                    server_digest_verifier(response.digest(), response.content_md5())
+                        .map(|builder| builder.build_dyn())
+                        .transpose()?
+                }
+                None => None,
+            },
+        };
+        progress.set_message(url);
+        if self.size == 0 {
+            if let Some(len) = response.content_length() {
+                progress.set_total(len);
+            }
+        }
+
+        let mut gauge = self
+            .min_speed
+            .map(|(min, window)| SpeedGauge::new(min, window));
+        let mut body = response.into_body();
+        let mut buffer = vec![0u8; READ_BUFFER];
+        let mut position = 0u64;
+        loop {
+            let read = body
+                .read(&mut buffer)
+                .map_err(Error::from)
+                .with_desc_with(|| format!("failed to fetch {url}"))?;
+            if read == 0 {
+                break;
+            }
+            writer
+                .write_all(&buffer[..read])
+                .map_err(Error::from)
+                .with_desc("failed to write the downloaded data")?;
+            position += read as u64;
+            if let Some(gauge) = &mut gauge {
+                gauge.record(read as u64)?;
+            }
+            self.check_deadline()?;
+            self.check_cancelled()?;
+            if let Some(verifier) = &mut verifier {
+                verifier.update(&buffer[..read]);
+            }
+            progress.set_position(position);
+        }
+        Ok(Fetched::Done {
+            verifier,
+            etag,
+            len: position,
+            filename: None,
+        })
+    }
+}
+
+impl MirrorOptions {
+    /// Probe the primary URL and every mirror with blocking reads; the
+    /// synchronous sibling of [`select`](Self::select).
+    fn select_blocking<C: BlockingClient>(
+        &mut self,
+        client: &C,
+        primary: &str,
+        headers: &[(String, String)],
+        cancel: Option<&CancelToken>,
+    ) -> Result<String> {
+        let Self {
+            mirrors,
+            error_handler,
+        } = self;
+        let mut best: Option<(&str, Duration)> = None;
+        let mut last_error = None;
+        for candidate in std::iter::once(primary).chain(mirrors.iter().map(String::as_str)) {
+            if cancel.is_some_and(CancelToken::is_cancelled) {
+                return Err(cancelled());
+            }
+            match Self::probe_blocking(client, candidate, headers) {
+                Ok(elapsed) => {
+                    log::debug!("mirror {candidate} answered in {elapsed:?}");
+                    if best.is_none_or(|(_, best_elapsed)| elapsed < best_elapsed) {
+                        best = Some((candidate, elapsed));
+                    }
+                }
+                Err(e) => {
+                    log::warn!("mirror {candidate} failed: {e:#}");
+                    if let Some(handler) = error_handler.as_mut() {
+                        handler(candidate, &e);
+                    }
+                    // As in the async path, a permanent error would fail
+                    // on every mirror the same way.
+                    if !e.is_retryable() && !e.is_not_found() {
+                        return Err(e);
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+        match best {
+            Some((url, _)) => Ok(url.to_owned()),
+            None => Err(last_error.expect("at least one candidate was probed")),
+        }
+    }
+
+    /// Measure the time until the first body byte arrives from `url`.
+    fn probe_blocking<C: BlockingClient>(
+        client: &C,
+        url: &str,
+        headers: &[(String, String)],
+    ) -> Result<Duration> {
+        let start = Instant::now();
+        let response = if headers.is_empty() {
+            client.get(url)?
+        } else {
+            client.get_with_headers(url, headers)?
+        };
+        let response = reject_error_status(response, None)?;
+        // An empty body is fine, only failures disqualify the mirror.
+        let mut body = response.into_body();
+        body.read(&mut [0u8; 1])
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to read from {url}"))?;
+        Ok(start.elapsed())
+    }
+}
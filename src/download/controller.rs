@@ -0,0 +1,124 @@
+//! Pausing and resuming active downloads.
+//!
+//! A [`DownloadController`] attached via
+//! [`with_controller`](super::DownloadBuilder::with_controller) lets another
+//! task pause the streaming loop between chunks and resume it later. The
+//! download keeps its file handle, verifier state and connection; pausing
+//! only stops reading from the socket.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Whether a controlled download is currently allowed to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DownloadState {
+    /// The download streams normally; the default.
+    #[default]
+    Running,
+    /// The download is parked between chunks until resumed.
+    Paused,
+}
+
+/// A shared handle pausing and resuming downloads.
+///
+/// Clones share one state: [`pause`](Self::pause) parks every download
+/// holding a clone before its next chunk, and [`resume`](Self::resume)
+/// wakes them. While parked, nothing is read from the connection, progress
+/// stops advancing, and the verifier keeps its state, so the transfer
+/// continues seamlessly on resume. The connection itself stays open; a
+/// server may still drop it during a long pause.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadController {
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    paused: bool,
+    wakers: Vec<Waker>,
+}
+
+impl DownloadController {
+    /// Create a controller in the [`Running`](DownloadState::Running)
+    /// state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Park the attached downloads before their next chunk.
+    pub fn pause(&self) {
+        self.shared.lock().unwrap().paused = true;
+    }
+
+    /// Wake the parked downloads.
+    pub fn resume(&self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.paused = false;
+        for waker in shared.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// The current state.
+    pub fn state(&self) -> DownloadState {
+        if self.shared.lock().unwrap().paused {
+            DownloadState::Paused
+        } else {
+            DownloadState::Running
+        }
+    }
+
+    /// Resolve once the controller is not paused.
+    pub(super) fn wait_resumed(&self) -> WaitResumed<'_> {
+        WaitResumed { controller: self }
+    }
+}
+
+/// A future resolving when its controller leaves the paused state.
+pub(super) struct WaitResumed<'c> {
+    controller: &'c DownloadController,
+}
+
+impl Future for WaitResumed<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut shared = self.controller.shared.lock().unwrap();
+        if !shared.paused {
+            return Poll::Ready(());
+        }
+        if !shared.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            shared.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn pause_and_resume_flip_the_state() {
+        let controller = DownloadController::new();
+        assert_eq!(controller.state(), DownloadState::Running);
+        controller.pause();
+        assert_eq!(controller.state(), DownloadState::Paused);
+        controller.resume();
+        assert_eq!(controller.state(), DownloadState::Running);
+    }
+
+    #[test]
+    fn wait_resumed_is_ready_unless_paused() {
+        let controller = DownloadController::new();
+        assert_eq!(controller.wait_resumed().now_or_never(), Some(()));
+        controller.pause();
+        assert_eq!(controller.wait_resumed().now_or_never(), None);
+        controller.resume();
+        assert_eq!(controller.wait_resumed().now_or_never(), Some(()));
+    }
+}
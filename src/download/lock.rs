@@ -0,0 +1,165 @@
+//! Cross-process locking of download destinations.
+//!
+//! [`DestLock`] takes an OS-level advisory exclusive lock on a `<dest>.lock`
+//! sidecar file, so several processes downloading to the same destination do
+//! not clobber each other. Because the lock is held by the operating system
+//! rather than by the file's existence, a process that dies releases it
+//! automatically; a leftover `.lock` file is harmless.
+//!
+//! Locking is opt-in, enabled per download with
+//! [`DownloadBuilder::with_lock`](super::DownloadBuilder::with_lock). A CLI
+//! that may run concurrently typically waits and reuses whatever the first
+//! process produced:
+//!
+//! ```no_run
+//! use fetchkit::download::{DownloadBuilder, LockWait};
+//! use fetchkit::progress::NoProgress;
+//!
+//! # async fn example() -> fetchkit::Result<()> {
+//! let client = reqwest::Client::new();
+//! let dest = std::path::Path::new("tool.tar.gz");
+//! let builder = DownloadBuilder::new("https://example.com/tool.tar.gz", dest, 0)
+//!     .with_lock(LockWait::Wait);
+//! if !builder.exist()? {
+//!     // If another invocation is already downloading, this waits for it
+//!     // and then reuses the file it produced.
+//!     builder.download(&client, NoProgress).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use fs2::FileExt;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+
+/// How long to wait for a contended [`DestLock`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockWait {
+    /// Fail immediately when another process holds the lock.
+    NoWait,
+    /// Wait up to the given duration, then fail with a timeout error.
+    Timeout(Duration),
+    /// Wait until the lock is released, however long that takes.
+    Wait,
+}
+
+/// An exclusive advisory lock on a download destination.
+///
+/// The lock is taken on a `<dest>.lock` sidecar file and held until the
+/// guard is dropped. It is advisory: it only excludes other users of
+/// `DestLock` (or of the same OS locking primitive), not arbitrary writers.
+/// The sidecar file is deliberately not removed on release — deleting it
+/// would race with processes about to lock it.
+#[derive(Debug)]
+pub struct DestLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl DestLock {
+    /// The polling interval while waiting with a timeout.
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Acquire the lock for the destination `dest`.
+    ///
+    /// Creates (or opens) the `<dest>.lock` sidecar next to the destination
+    /// and takes an exclusive lock on it, waiting according to `wait`. When
+    /// the lock is held elsewhere, [`LockWait::NoWait`] fails with an IO
+    /// error and [`LockWait::Timeout`] fails with a timeout error once the
+    /// duration elapses.
+    pub fn acquire(dest: impl AsRef<Path>, wait: LockWait) -> Result<Self> {
+        let path = Self::lock_path(dest.as_ref());
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to create the lock file {}", path.display()))?;
+
+        match wait {
+            LockWait::NoWait => Self::try_lock(&file).map_err(|e| {
+                let contended = e.is_contention();
+                let error = Error::from(e).with_path(&path);
+                if contended {
+                    error.with_desc("another process holds the destination lock")
+                } else {
+                    error
+                }
+            })?,
+            LockWait::Timeout(timeout) => {
+                let deadline = Instant::now() + timeout;
+                loop {
+                    match Self::try_lock(&file) {
+                        Ok(()) => break,
+                        Err(e) if !e.is_contention() => {
+                            return Err(Error::from(e).with_path(&path));
+                        }
+                        Err(_) => {}
+                    }
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(Error::new(ErrorKind::Timeout)
+                            .mark_timeout()
+                            .with_path(&path)
+                            .with_desc_with(|| {
+                                format!("timed out after {timeout:?} waiting for the destination lock")
+                            }));
+                    }
+                    std::thread::sleep(remaining.min(Self::POLL_INTERVAL));
+                }
+            }
+            LockWait::Wait => file
+                .lock_exclusive()
+                .map_err(Error::from)
+                .with_desc_with(|| {
+                    format!("failed to lock {}", path.display())
+                })?,
+        }
+        Ok(Self { file, path })
+    }
+
+    /// The path of the lock file the lock is held on.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The sidecar path: the destination with `.lock` appended.
+    fn lock_path(dest: &Path) -> PathBuf {
+        let mut path = dest.as_os_str().to_os_string();
+        path.push(".lock");
+        path.into()
+    }
+
+    /// Try to lock `file` without blocking.
+    fn try_lock(file: &File) -> std::io::Result<()> {
+        file.try_lock_exclusive()
+    }
+}
+
+impl Drop for DestLock {
+    fn drop(&mut self) {
+        // Qualified call: `File::unlock` also exists in std since 1.89,
+        // which is newer than our MSRV.
+        if let Err(e) = FileExt::unlock(&self.file) {
+            log::warn!("failed to unlock {}: {e}", self.path.display());
+        }
+    }
+}
+
+/// Distinguish "held by someone else" from real IO failures.
+trait Contention {
+    fn is_contention(&self) -> bool;
+}
+
+impl Contention for std::io::Error {
+    fn is_contention(&self) -> bool {
+        let contended = fs2::lock_contended_error();
+        self.kind() == contended.kind() && self.raw_os_error() == contended.raw_os_error()
+    }
+}
@@ -0,0 +1,3373 @@
+//! Downloading files over HTTP.
+//!
+//! The entry point is [`DownloadBuilder`], which describes a single download:
+//! the URL (optionally with mirrors), the destination path, the expected size
+//! and an optional verifier. [`DownloadBuilder::exist`] checks whether a
+//! valid copy is already on disk, and [`DownloadBuilder::download`] streams
+//! the file while feeding the verifier and the progress receiver.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt};
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::http::{Client, Response};
+use crate::progress::{
+    NoProgress, Phase, PhasedProgressBuilder, ProgressReceiver, ProgressReceiverBuilder, Throttled,
+};
+use crate::verify::checksum::ChecksumFile;
+use crate::verify::hash::{DynHashVerifierBuilder, HashAlgorithm};
+#[cfg(feature = "minisign")]
+use crate::verify::minisign;
+use crate::verify::{DynVerifier, DynVerifierBuilder, Verifier, VerifierBuilder};
+
+#[cfg(feature = "blocking")]
+mod blocking;
+mod controller;
+mod lock;
+#[cfg(any(feature = "tokio", feature = "smol"))]
+mod rate_limit;
+#[cfg(any(feature = "tokio", feature = "smol"))]
+mod retry;
+
+#[cfg(feature = "blocking")]
+pub use blocking::{BlockingClient, BlockingResponse};
+pub use controller::{DownloadController, DownloadState};
+pub use lock::{DestLock, LockWait};
+#[cfg(any(feature = "tokio", feature = "smol"))]
+pub use rate_limit::RateLimiter;
+#[cfg(any(feature = "tokio", feature = "smol"))]
+pub use retry::RetryPolicy;
+
+/// Whether a download may touch the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OfflinePolicy {
+    /// Always download; the default.
+    #[default]
+    Online,
+    /// Skip the network (including the mirror speedtest) whenever a valid
+    /// local copy passes [`exist`](DownloadBuilder::exist); download
+    /// otherwise.
+    PreferCache,
+    /// Never touch the network: a valid local copy succeeds, anything else
+    /// fails immediately with [`ErrorKind::Offline`](crate::ErrorKind).
+    Offline,
+}
+
+/// How an already existing destination file is handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverwritePolicy {
+    /// Fail the download with an `AlreadyExists` error; the default.
+    #[default]
+    Error,
+    /// Replace the existing file.
+    Overwrite,
+    /// Return successfully without network traffic when the existing file
+    /// passes [`exist`](DownloadBuilder::exist); replace it when it has the
+    /// wrong size or fails verification.
+    SkipIfValid,
+}
+
+/// When the verifier sees the downloaded bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VerificationMode {
+    /// Feed the verifier each chunk as it arrives; the default. The
+    /// final check after the stream ends is all the verification costs.
+    #[default]
+    Streaming,
+    /// Write the file first, then re-read the completed part file and
+    /// verify that.
+    ///
+    /// For verifiers that cannot be fed in network delivery order, or
+    /// that want the whole file on disk. Costs a second read of the file;
+    /// the re-read reports its own positions to the progress receiver —
+    /// in [`download_phased`](DownloadBuilder::download_phased) as a
+    /// [`Verifying`](crate::progress::Phase::Verifying) phase with a
+    /// known total.
+    AfterDownload,
+}
+
+/// What [`download_if_needed`](DownloadBuilder::download_if_needed) did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadOutcome {
+    /// A valid copy was already at the destination; nothing was fetched.
+    AlreadyValid,
+    /// Nothing was at the destination; the file was downloaded.
+    Downloaded,
+    /// The existing file had the wrong size or failed verification and was
+    /// re-fetched.
+    Replaced,
+}
+
+/// What [`status`](DownloadBuilder::status) found at the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    /// Nothing is at the destination.
+    Missing,
+    /// A file is there, but its size differs from the expected size.
+    SizeMismatch {
+        /// The size the download was configured with.
+        expected: u64,
+        /// The size of the file on disk.
+        actual: u64,
+    },
+    /// A file of the right size is there, but the configured verifier
+    /// rejects its content.
+    VerifyFailed,
+    /// A valid copy is already at the destination.
+    Valid,
+}
+
+/// Fetch a small file into memory in one call.
+///
+/// A convenience wrapper around
+/// [`download_bytes`](DownloadBuilder::download_bytes) for sidecar-sized
+/// fetches — signatures, checksum manifests, release metadata — without
+/// the builder ceremony. The response is streamed into a buffer, capped at
+/// `max_size` so a redirect to an error page or a hostile server cannot
+/// balloon memory, and checked by `verifier` when one is given (pass
+/// `None::<Sha256VerifierBuilder>` or similar otherwise). Anything beyond
+/// that — mirrors, retries, progress — still goes through
+/// [`DownloadBuilder`].
+pub async fn fetch_bytes<C, V>(
+    client: &C,
+    url: impl Into<String>,
+    max_size: u64,
+    verifier: Option<V>,
+) -> Result<Bytes>
+where
+    C: Client,
+    V: VerifierBuilder + Send + Sync + 'static,
+    V::Verifier: 'static,
+{
+    let mut builder = DownloadBuilder::new(url, PathBuf::new(), 0).with_memory_cap(max_size);
+    if let Some(verifier) = verifier {
+        builder = builder.with_verifier(verifier);
+    }
+    builder
+        .download_bytes(client, crate::progress::NoProgress)
+        .await
+}
+
+/// Metadata about a finished download, returned by
+/// [`download`](DownloadBuilder::download).
+///
+/// Downloads that never touched the network — a valid local copy under
+/// [`OfflinePolicy::PreferCache`] or [`OverwritePolicy::SkipIfValid`], or
+/// a `304 Not Modified` answer to a freshness check — report zero bytes
+/// and the primary URL.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DownloadReport {
+    /// The URL that served the file: the selected mirror when mirrors
+    /// were configured, the primary URL otherwise.
+    pub url: String,
+    /// Where the file landed; useful with
+    /// [`new_in_dir`](DownloadBuilder::new_in_dir), where the name is
+    /// derived from the URL or the response headers.
+    pub dest: PathBuf,
+    /// How many body bytes were streamed.
+    pub bytes: u64,
+    /// Wall-clock time spent in the call, including mirror selection,
+    /// locking and verification.
+    pub elapsed: Duration,
+    /// How many extra attempts the retry policy made after the first
+    /// one.
+    pub retries: u32,
+}
+
+impl DownloadReport {
+    /// The average throughput over the whole call, in bytes per second.
+    pub fn throughput(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.bytes as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// A precondition attached to the GET request, allowing the server to
+/// answer `304 Not Modified` instead of resending the body.
+enum Condition {
+    /// `If-None-Match` with the recorded `ETag` of the local copy.
+    IfNoneMatch(String),
+    /// `If-Modified-Since` with the modification time of the local copy.
+    IfModifiedSince(String),
+}
+
+/// The outcome of a transfer, before verification.
+enum Fetched {
+    /// The body was streamed; the verifier (if any) and the response
+    /// `ETag` (when the [cache](DownloadBuilder::with_etag_cache) is
+    /// enabled) are handed back to the caller, along with the streamed
+    /// length in bytes and, in directory-destination mode, the file name
+    /// announced by `Content-Disposition`.
+    Done {
+        verifier: Option<Box<dyn DynVerifier>>,
+        etag: Option<String>,
+        len: u64,
+        filename: Option<String>,
+    },
+    /// The server answered `304 Not Modified`: the existing destination
+    /// is still current and nothing was transferred.
+    NotModified,
+}
+
+/// A handle for cancelling in-flight downloads.
+///
+/// Attach a clone via
+/// [`with_cancel_token`](DownloadBuilder::with_cancel_token) and call
+/// [`cancel`](Self::cancel) from anywhere — a UI thread, a signal handler —
+/// to make every download holding the token fail with
+/// [`Cancelled`](crate::ErrorKind::Cancelled). The token is checked before
+/// the download starts, between received chunks, and between mirror
+/// probes; a stream yielding no chunks only notices the cancellation once
+/// a timer (such as [`with_idle_timeout`](DownloadBuilder::with_idle_timeout))
+/// wakes it.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl CancelToken {
+    /// Create a token with no downloads attached yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every download holding a clone of this token.
+    pub fn cancel(&self) {
+        self.cancelled
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Whether [`cancel`](Self::cancel) has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// The error a fired [`CancelToken`] turns into.
+fn cancelled() -> Error {
+    Error::new(ErrorKind::Cancelled).with_desc("the download was cancelled")
+}
+
+/// Derive a file name from the last path segment of a URL, for
+/// [`new_in_dir`](DownloadBuilder::new_in_dir).
+fn file_name_from_url(url: &str) -> Result<String> {
+    let segment = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| {
+            Error::new(ErrorKind::Other)
+                .with_url(url)
+                .with_desc("cannot derive a file name from the URL")
+        })?;
+    let name = percent_decode(segment).ok_or_else(|| {
+        Error::new(ErrorKind::Other)
+            .with_url(url)
+            .with_desc("invalid percent-encoding in the URL file name")
+    })?;
+    if !is_safe_file_name(&name) {
+        return Err(Error::new(ErrorKind::Other)
+            .with_url(url)
+            .with_desc_with(|| format!("refusing unsafe file name {name:?} from the URL")));
+    }
+    Ok(name)
+}
+
+/// Whether `name` can be joined to a directory without escaping it.
+fn is_safe_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', '\0'])
+}
+
+/// Extract the file name from a `Content-Disposition` header value per
+/// RFC 6266, preferring the `filename*` UTF-8 form over the plain
+/// `filename` parameter.
+///
+/// `None` — falling back to the URL-derived name — when the header has no
+/// usable parameter, the encoding is malformed, or the decoded name would
+/// escape the destination directory.
+fn content_disposition_file_name(value: &str) -> Option<String> {
+    let mut plain = None;
+    for param in value.split(';') {
+        let Some((key, val)) = param.split_once('=') else {
+            continue;
+        };
+        let (key, val) = (key.trim(), val.trim());
+        if key.eq_ignore_ascii_case("filename*") {
+            // RFC 8187 ext-value: `charset'language'percent-encoded`.
+            let mut parts = val.splitn(3, '\'');
+            let charset = parts.next()?;
+            let encoded = parts.nth(1)?;
+            if charset.eq_ignore_ascii_case("utf-8") {
+                if let Some(name) = percent_decode(encoded).filter(|name| is_safe_file_name(name))
+                {
+                    // The ext form wins regardless of parameter order.
+                    return Some(name);
+                }
+            }
+        } else if key.eq_ignore_ascii_case("filename") {
+            let val = val
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(val);
+            if is_safe_file_name(val) {
+                plain = Some(val.to_owned());
+            }
+        }
+    }
+    plain
+}
+
+/// Decode `%XX` escapes; `None` on a truncated or non-hex escape, or when
+/// the decoded bytes are not UTF-8.
+fn percent_decode(s: &str) -> Option<String> {
+    let mut decoded = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// The local path of a `file://` URL, or `None` for any other scheme.
+///
+/// The authority must be empty or `localhost` — a remote host cannot be
+/// served from this filesystem. Percent-escapes in the path are decoded,
+/// and the Windows `file:///C:/…` form drops the slash in front of the
+/// drive letter.
+fn file_url_path(url: &str) -> Option<Result<PathBuf>> {
+    let rest = url.strip_prefix("file://")?;
+    let (host, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    Some(decode_file_url_path(url, host, path))
+}
+
+/// The fallible half of [`file_url_path`], split out so the scheme check
+/// stays an `Option`.
+fn decode_file_url_path(url: &str, host: &str, path: &str) -> Result<PathBuf> {
+    if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+        return Err(Error::new(ErrorKind::Other)
+            .with_url(url)
+            .with_desc("file URLs with a remote host are not supported"));
+    }
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    let path = percent_decode(path).ok_or_else(|| {
+        Error::new(ErrorKind::Other)
+            .with_url(url)
+            .with_desc("invalid percent-encoding in the file URL")
+    })?;
+    // `file:///C:/…` encodes the Windows drive path `C:/…` as `/C:/…`.
+    #[cfg(windows)]
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => path[1..].to_owned(),
+        _ => path,
+    };
+    Ok(PathBuf::from(path))
+}
+
+/// A response served from the local filesystem for a `file://` URL.
+///
+/// Plugs into the same streaming loops as an HTTP response: the body is
+/// read in chunks, so the verifier and progress advance as they would for
+/// a network transfer.
+struct FileResponse {
+    file: File,
+    len: u64,
+}
+
+impl FileResponse {
+    /// How much of the file one body chunk carries.
+    const CHUNK: usize = 64 * 1024;
+
+    /// Open the local file behind `url` at the already-decoded `path`.
+    fn open(url: &str, path: PathBuf) -> Result<Self> {
+        let file = File::open(&path)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to open {}", path.display()))
+            .map_err(|e| e.with_url(url))?;
+        let len = file
+            .metadata()
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to stat {}", path.display()))?
+            .len();
+        Ok(Self { file, len })
+    }
+}
+
+impl Response for FileResponse {
+    fn content_length(&self) -> Option<u64> {
+        Some(self.len)
+    }
+
+    fn bytes_stream(self) -> impl Stream<Item = Result<Bytes>> + Send + Unpin {
+        Box::pin(futures_util::stream::unfold(self.file, |mut file| async {
+            let mut buffer = vec![0u8; Self::CHUNK];
+            match file.read(&mut buffer) {
+                Ok(0) => None,
+                Ok(read) => {
+                    buffer.truncate(read);
+                    Some((Ok(Bytes::from(buffer)), file))
+                }
+                Err(e) => Some((
+                    Err(Error::from(e).with_desc("failed to read the local file")),
+                    file,
+                )),
+            }
+        }))
+    }
+}
+
+/// Either the client's response or a [`FileResponse`]; see [`request`].
+enum MaybeFile<R> {
+    File(FileResponse),
+    Client(R),
+}
+
+impl<R: Response> Response for MaybeFile<R> {
+    fn status(&self) -> u16 {
+        match self {
+            Self::File(response) => response.status(),
+            Self::Client(response) => response.status(),
+        }
+    }
+
+    fn etag(&self) -> Option<String> {
+        match self {
+            Self::File(response) => response.etag(),
+            Self::Client(response) => response.etag(),
+        }
+    }
+
+    fn content_disposition(&self) -> Option<String> {
+        match self {
+            Self::File(response) => response.content_disposition(),
+            Self::Client(response) => response.content_disposition(),
+        }
+    }
+
+    fn content_length(&self) -> Option<u64> {
+        match self {
+            Self::File(response) => response.content_length(),
+            Self::Client(response) => response.content_length(),
+        }
+    }
+
+    fn digest(&self) -> Option<String> {
+        match self {
+            Self::File(response) => response.digest(),
+            Self::Client(response) => response.digest(),
+        }
+    }
+
+    fn content_md5(&self) -> Option<String> {
+        match self {
+            Self::File(response) => response.content_md5(),
+            Self::Client(response) => response.content_md5(),
+        }
+    }
+
+    fn is_decoded(&self) -> bool {
+        match self {
+            Self::File(response) => response.is_decoded(),
+            Self::Client(response) => response.is_decoded(),
+        }
+    }
+
+    fn bytes_stream(self) -> impl Stream<Item = Result<Bytes>> + Send + Unpin {
+        match self {
+            Self::File(response) => futures_util::future::Either::Left(response.bytes_stream()),
+            Self::Client(response) => {
+                futures_util::future::Either::Right(response.bytes_stream())
+            }
+        }
+    }
+}
+
+/// Turn a non-success status into an error instead of handing the body on.
+///
+/// Clients like reqwest map error statuses themselves, but the trait does
+/// not require it — without this check a 404 from a lenient client would
+/// stream the error page into the destination file. `304 Not Modified`
+/// passes through when a condition was sent, for the freshness handling;
+/// a redirect is the underlying client's job to follow and counts as a
+/// failure when it reaches this point unfollowed. Servers tend to explain
+/// themselves in the body, so its beginning rides along in the
+/// description.
+async fn reject_error_status<R: Response>(
+    response: R,
+    condition: Option<&Condition>,
+) -> Result<R> {
+    let status = response.status();
+    if (200..300).contains(&status) || (status == 304 && condition.is_some()) {
+        return Ok(response);
+    }
+    let error = Error::new(ErrorKind::Network).with_http_status(status);
+    let snippet = match response.bytes_stream().next().await {
+        Some(Ok(chunk)) => String::from_utf8_lossy(&chunk)
+            .chars()
+            .take(200)
+            .collect::<String>()
+            .trim()
+            .to_owned(),
+        _ => String::new(),
+    };
+    Err(error.with_desc_with(|| {
+        if snippet.is_empty() {
+            format!("the server answered with status {status}")
+        } else {
+            format!("the server answered with status {status}: {snippet}")
+        }
+    }))
+}
+
+/// A hash verifier from the server's `Digest` or `Content-MD5` header,
+/// when one is present and understood.
+///
+/// `Digest` values are `algo=base64` pairs, possibly several separated by
+/// commas; the first supported entry wins and `Content-MD5` serves as a
+/// fallback. Unknown algorithms and undecodable values are skipped with a
+/// warning — the server's digest is a bonus check, and a misconfigured
+/// header must not fail an otherwise sound download.
+fn server_digest_verifier(
+    digest: Option<String>,
+    content_md5: Option<String>,
+) -> Option<DynHashVerifierBuilder> {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    let mut candidates: Vec<(String, String)> = Vec::new();
+    if let Some(digest) = digest {
+        for entry in digest.split(',') {
+            // Padding makes the value itself contain `=`; split at the
+            // first one only.
+            if let Some((algo, value)) = entry.trim().split_once('=') {
+                candidates.push((algo.to_owned(), value.to_owned()));
+            }
+        }
+    }
+    if let Some(value) = content_md5 {
+        candidates.push(("md5".to_owned(), value));
+    }
+    for (algo, value) in candidates {
+        let Ok(algorithm) = algo.parse::<HashAlgorithm>() else {
+            log::warn!("ignoring a server digest with unsupported algorithm {algo}");
+            continue;
+        };
+        let Ok(decoded) = STANDARD.decode(value.trim()) else {
+            log::warn!(
+                "ignoring an undecodable {} digest from the server",
+                algorithm.name()
+            );
+            continue;
+        };
+        match DynHashVerifierBuilder::new(algorithm, decoded) {
+            Ok(builder) => return Some(builder),
+            Err(e) => log::warn!("ignoring a server digest: {e:#}"),
+        }
+    }
+    None
+}
+
+/// Issue the GET for `url`, serving `file://` URLs straight from disk
+/// without touching the client, so tests and air-gapped environments can
+/// point a download at a local file.
+///
+/// Conditions express HTTP freshness; a local file is always current, so
+/// it is served unconditionally.
+async fn request<C: Client>(
+    client: &C,
+    url: &str,
+    headers: &[(String, String)],
+    condition: Option<&Condition>,
+) -> Result<MaybeFile<C::Response>> {
+    if let Some(path) = file_url_path(url) {
+        return Ok(MaybeFile::File(FileResponse::open(url, path?)?));
+    }
+    let response = if headers.is_empty() {
+        match condition {
+            Some(Condition::IfNoneMatch(etag)) => client.get_if_none_match(url, etag).await,
+            Some(Condition::IfModifiedSince(date)) => {
+                client.get_if_modified_since(url, date).await
+            }
+            None => client.get(url).await,
+        }
+    } else {
+        // A condition is itself just a header, so it rides along with the
+        // configured ones instead of going through the dedicated methods.
+        let mut headers = headers.to_vec();
+        match condition {
+            Some(Condition::IfNoneMatch(etag)) => {
+                headers.push(("If-None-Match".to_owned(), etag.clone()));
+            }
+            Some(Condition::IfModifiedSince(date)) => {
+                headers.push(("If-Modified-Since".to_owned(), date.clone()));
+            }
+            None => {}
+        }
+        client.get_with_headers(url, &headers).await
+    }?;
+    let response = reject_error_status(response, condition).await?;
+    Ok(MaybeFile::Client(response))
+}
+
+/// Paces chunk consumption for the bandwidth cap; see
+/// [`with_max_speed`](DownloadBuilder::with_max_speed).
+#[cfg(any(feature = "tokio", feature = "smol"))]
+struct Pacer {
+    max_speed: u64,
+    started: Instant,
+    bytes: u64,
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+impl Pacer {
+    fn new(max_speed: u64) -> Self {
+        Self {
+            max_speed: max_speed.max(1),
+            started: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    /// Account for a received chunk and sleep off any head start over the
+    /// allowed rate.
+    async fn pace(&mut self, len: u64) {
+        self.bytes += len;
+        let due = Duration::from_secs_f64(self.bytes as f64 / self.max_speed as f64);
+        if let Some(ahead) = due.checked_sub(self.started.elapsed()) {
+            crate::runtime::sleep(ahead).await;
+        }
+    }
+}
+
+/// Tracks chunk arrival for the stall detector; see
+/// [`with_min_speed`](DownloadBuilder::with_min_speed).
+struct SpeedGauge {
+    min_speed: u64,
+    window: Duration,
+    window_start: Instant,
+    bytes: u64,
+}
+
+impl SpeedGauge {
+    fn new(min_speed: u64, window: Duration) -> Self {
+        Self {
+            min_speed,
+            window,
+            window_start: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    /// Shift the window so `paused` time is not counted against the
+    /// throughput.
+    fn suspend_for(&mut self, paused: Duration) {
+        self.window_start += paused;
+    }
+
+    /// Record a received chunk, failing when the last full window averaged
+    /// below the threshold.
+    fn record(&mut self, len: u64) -> Result<()> {
+        self.bytes += len;
+        let elapsed = self.window_start.elapsed();
+        if elapsed < self.window {
+            return Ok(());
+        }
+        let rate = self.bytes as f64 / elapsed.as_secs_f64();
+        if rate < self.min_speed as f64 {
+            return Err(Error::new(ErrorKind::Timeout)
+                .mark_timeout()
+                .with_desc_with(|| {
+                    format!(
+                        "transfer averaged {rate:.0} B/s over the last {elapsed:.1?}, below the minimum of {} B/s",
+                        self.min_speed
+                    )
+                }));
+        }
+        self.window_start = Instant::now();
+        self.bytes = 0;
+        Ok(())
+    }
+}
+
+/// The [`Write`] end of the pipelined mode; see
+/// [`with_pipelined_write`](DownloadBuilder::with_pipelined_write).
+///
+/// Chunks are handed to the writer task through a bounded channel; a
+/// failed send means the task hung up on an I/O error, reported to the
+/// streaming loop as a broken pipe. The caller joins the task afterwards
+/// to surface the root cause.
+#[cfg(any(feature = "tokio", feature = "smol"))]
+struct PipeWriter {
+    sender: std::sync::mpsc::SyncSender<Bytes>,
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.sender
+            .send(Bytes::copy_from_slice(buf))
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        // Batching happens on the writer task; there is nothing local to
+        // flush.
+        Ok(())
+    }
+}
+
+/// The [`Read`] end of the streaming extraction; see
+/// [`download_and_extract`](DownloadBuilder::download_and_extract).
+///
+/// The streaming loop sends chunks into the channel and the extraction
+/// reads them off sequentially; the sender hanging up is a clean end of
+/// stream.
+#[cfg(all(feature = "tar", any(feature = "tokio", feature = "smol")))]
+struct PipeReader {
+    receiver: std::sync::mpsc::Receiver<Bytes>,
+    current: Bytes,
+}
+
+#[cfg(all(feature = "tar", any(feature = "tokio", feature = "smol")))]
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.current.is_empty() {
+            match self.receiver.recv() {
+                Ok(chunk) => self.current = chunk,
+                Err(_) => return Ok(0),
+            }
+        }
+        let len = self.current.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.current.split_to(len));
+        Ok(len)
+    }
+}
+
+/// A builder describing a single download.
+pub struct DownloadBuilder {
+    url: String,
+    dest: PathBuf,
+    /// Set by [`new_in_dir`](Self::new_in_dir): the directory the `dest`
+    /// name may be re-derived into from `Content-Disposition`.
+    dest_dir: Option<PathBuf>,
+    size: u64,
+    verifier: Option<Box<dyn DynVerifierBuilder + Send + Sync>>,
+    verification: VerificationMode,
+    mirrors: Option<MirrorOptions>,
+    lock: Option<LockWait>,
+    offline: OfflinePolicy,
+    overwrite: OverwritePolicy,
+    memory_cap: u64,
+    head_probe: bool,
+    space_check: Option<f64>,
+    check_length: bool,
+    write_buffer: usize,
+    preallocate: bool,
+    sync: bool,
+    keep_partial: bool,
+    part_suffix: String,
+    checksum_sidecar: Option<String>,
+    sidecar_optional: bool,
+    #[cfg(feature = "minisign")]
+    minisign_sidecar: Option<(minisign::PublicKey, String)>,
+    etag_cache: bool,
+    server_digest: bool,
+    headers: Vec<(String, String)>,
+    mtime_check: bool,
+    min_speed: Option<(u64, Duration)>,
+    deadline: Option<Duration>,
+    /// Computed from `deadline` when the download starts.
+    deadline_at: Option<Instant>,
+    cancel: Option<CancelToken>,
+    controller: Option<DownloadController>,
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    timeout: Option<Duration>,
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    idle_timeout: Option<Duration>,
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    max_speed: Option<u64>,
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    rate_limiter: Option<RateLimiter>,
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    pipelined_write: bool,
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    retry: Option<RetryPolicy>,
+    throttle: Duration,
+}
+
+impl DownloadBuilder {
+    /// The default cap for [`download_bytes`](Self::download_bytes), 64 MiB.
+    pub const DEFAULT_MEMORY_CAP: u64 = 64 * 1024 * 1024;
+
+    /// The default buffer between the stream and the destination file,
+    /// 256 KiB.
+    pub const DEFAULT_WRITE_BUFFER: usize = 256 * 1024;
+
+    /// The default suffix of the in-progress sibling file, `.part`.
+    pub const DEFAULT_PART_SUFFIX: &'static str = ".part";
+
+    /// Create a builder downloading `url` to `dest`.
+    ///
+    /// `size` is the expected size in bytes, used for the size check in
+    /// [`exist`](Self::exist) and to initialize progress; pass `0` when the
+    /// size is unknown.
+    ///
+    /// The builder owns everything it is given, so it is `'static` and
+    /// `Send` and can be constructed in one place and awaited in a
+    /// spawned task.
+    ///
+    /// See [`new_in_dir`](Self::new_in_dir) when only the target directory
+    /// is known and the file name should come from the URL.
+    pub fn new(url: impl Into<String>, dest: impl Into<PathBuf>, size: u64) -> Self {
+        Self {
+            url: url.into(),
+            dest: dest.into(),
+            dest_dir: None,
+            size,
+            verifier: None,
+            verification: VerificationMode::default(),
+            mirrors: None,
+            lock: None,
+            offline: OfflinePolicy::default(),
+            overwrite: OverwritePolicy::default(),
+            memory_cap: Self::DEFAULT_MEMORY_CAP,
+            head_probe: false,
+            space_check: None,
+            check_length: true,
+            write_buffer: Self::DEFAULT_WRITE_BUFFER,
+            preallocate: true,
+            sync: false,
+            keep_partial: false,
+            part_suffix: Self::DEFAULT_PART_SUFFIX.to_owned(),
+            checksum_sidecar: None,
+            sidecar_optional: false,
+            #[cfg(feature = "minisign")]
+            minisign_sidecar: None,
+            etag_cache: false,
+            server_digest: false,
+            headers: Vec::new(),
+            mtime_check: false,
+            min_speed: None,
+            deadline: None,
+            deadline_at: None,
+            cancel: None,
+            controller: None,
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            timeout: None,
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            idle_timeout: None,
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            max_speed: None,
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            rate_limiter: None,
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            pipelined_write: false,
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            retry: None,
+            throttle: Throttled::<()>::DEFAULT_INTERVAL,
+        }
+    }
+
+    /// Create a builder downloading `url` into the directory `dir`, named
+    /// after the last path segment of the URL.
+    ///
+    /// The segment is percent-decoded and the directory is created when
+    /// missing. URLs without a usable file name — an empty last segment,
+    /// `.` or `..`, or a decoded name containing a path separator — are
+    /// rejected, so an attacker-controlled URL cannot escape `dir`.
+    ///
+    /// When the response carries a `Content-Disposition` header with a
+    /// usable file name (RFC 6266, including the `filename*` UTF-8 form),
+    /// that name replaces the URL-derived one — API endpoints often serve
+    /// files under opaque path IDs. Header names failing the same safety
+    /// checks fall back to the URL segment. The resolved path is available
+    /// from [`dest`](Self::dest) before the download and in the
+    /// [`DownloadReport`] afterwards.
+    pub fn new_in_dir(url: impl Into<String>, dir: impl Into<PathBuf>, size: u64) -> Result<Self> {
+        let url = url.into();
+        let dir = dir.into();
+        let name = file_name_from_url(&url)?;
+        std::fs::create_dir_all(&dir)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to create {}", dir.display()))?;
+        let mut builder = Self::new(url, dir.join(name), size);
+        builder.dest_dir = Some(dir);
+        Ok(builder)
+    }
+
+    /// Create a builder from a parsed [`Metalink`] document.
+    ///
+    /// The document's first URL becomes the primary and any further ones
+    /// become [mirrors](Self::with_mirrors); the announced size and the
+    /// [strongest recognized hash](Metalink::strongest_hash) are wired up
+    /// when present. A document listing no URLs is an error, as is a
+    /// hash that does not decode to a digest of the right length.
+    ///
+    /// [`Metalink`]: crate::metalink::Metalink
+    #[cfg(feature = "metalink")]
+    pub fn from_metalink(
+        metalink: &crate::metalink::Metalink,
+        dest: impl Into<PathBuf>,
+    ) -> Result<Self> {
+        let [primary, mirrors @ ..] = metalink.urls.as_slice() else {
+            return Err(
+                Error::new(ErrorKind::Other).with_desc("the metalink document lists no URLs")
+            );
+        };
+        let mut builder = Self::new(primary, dest, metalink.size.unwrap_or(0));
+        if !mirrors.is_empty() {
+            builder = builder.with_mirrors(MirrorOptions::new(mirrors));
+        }
+        if let Some((algorithm, digest)) = metalink.strongest_hash() {
+            let digest = hex::decode(digest)
+                .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+                .with_desc("invalid hex digest in metalink document")?;
+            builder = builder.with_verifier(DynHashVerifierBuilder::new(algorithm, digest)?);
+        }
+        Ok(builder)
+    }
+
+    /// The destination path of this download.
+    pub fn dest(&self) -> &Path {
+        &self.dest
+    }
+
+    /// Set the verifier for the downloaded content.
+    pub fn with_verifier<V>(mut self, verifier: V) -> Self
+    where
+        V: VerifierBuilder + Send + Sync + 'static,
+        V::Verifier: 'static,
+    {
+        self.verifier = Some(Box::new(verifier));
+        self
+    }
+
+    /// Set an already boxed verifier builder, used by the high-level
+    /// pipeline.
+    #[cfg(any(feature = "tar", feature = "zip"))]
+    pub(crate) fn with_boxed_verifier(
+        mut self,
+        verifier: Box<dyn DynVerifierBuilder + Send + Sync>,
+    ) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Set mirrors to select from before downloading.
+    pub fn with_mirrors(mut self, mirrors: MirrorOptions) -> Self {
+        self.mirrors = Some(mirrors);
+        self
+    }
+
+    /// Set whether the download may touch the network; see
+    /// [`OfflinePolicy`].
+    pub fn with_offline_policy(mut self, policy: OfflinePolicy) -> Self {
+        self.offline = policy;
+        self
+    }
+
+    /// Set how an existing destination file is handled; see
+    /// [`OverwritePolicy`].
+    pub fn with_overwrite_policy(mut self, policy: OverwritePolicy) -> Self {
+        self.overwrite = policy;
+        self
+    }
+
+    /// Hold a cross-process [`DestLock`] on the destination while
+    /// downloading.
+    ///
+    /// The lock is acquired (waiting according to `wait`) before anything
+    /// else happens, and the destination is re-checked with
+    /// [`exist`](Self::exist) once it is held: when another process produced
+    /// a valid file in the meantime, the download is skipped and its file is
+    /// reused. See [`DestLock`] for the locking semantics.
+    pub fn with_lock(mut self, wait: LockWait) -> Self {
+        self.lock = Some(wait);
+        self
+    }
+
+    /// Set a time limit for the transfer.
+    ///
+    /// When the limit expires mid-stream, the download fails with a
+    /// [`Timeout`](crate::ErrorKind::Timeout) error. Requires a timer
+    /// backend: the `smol` feature works on any executor, the `tokio`
+    /// feature inside a tokio runtime (see the crate docs).
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set a limit on the wait for each chunk of the body.
+    ///
+    /// Where [`with_timeout`](Self::with_timeout) bounds the whole
+    /// transfer, the idle timeout only fires when the server stops
+    /// sending data mid-stream: each wait for the next chunk is raced
+    /// against the timer, and losing fails the download with a retryable
+    /// [`Timeout`](crate::ErrorKind::Timeout) error. Requires a timer
+    /// backend from the `smol` or `tokio` feature.
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Cap the transfer rate at `bytes_per_sec`.
+    ///
+    /// After each chunk the streaming loop sleeps off any head start over
+    /// the allowed rate, keeping background downloads from saturating the
+    /// link; a connection slower than the cap is never delayed. The
+    /// inverse of [`with_min_speed`](Self::with_min_speed), and like the
+    /// timeouts it requires a timer backend from the `smol` or `tokio`
+    /// feature.
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    pub fn with_max_speed(mut self, bytes_per_sec: u64) -> Self {
+        self.max_speed = Some(bytes_per_sec);
+        self
+    }
+
+    /// Share a bandwidth cap with other downloads; see [`RateLimiter`].
+    ///
+    /// Where [`with_max_speed`](Self::with_max_speed) paces this transfer
+    /// alone, clones of one limiter meter every attached download against
+    /// a single budget. Both can be combined: the shared cap bounds the
+    /// total, the per-download cap bounds each stream's share.
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Retry the transfer on transient failures; see [`RetryPolicy`].
+    ///
+    /// Like [`with_timeout`](Self::with_timeout), the backoff delays
+    /// require a timer backend from the `smol` or `tokio` feature.
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Ask the server for the file size with a HEAD request before
+    /// downloading.
+    ///
+    /// The announced `Content-Length` initializes the progress total when
+    /// the expected size is unknown; when it contradicts a known expected
+    /// size, the download aborts with a
+    /// [`Verify`](crate::ErrorKind::Verify) error before any bandwidth is
+    /// spent. Servers not answering HEAD fall back silently.
+    pub fn with_head_probe(mut self) -> Self {
+        self.head_probe = true;
+        self
+    }
+
+    /// Fail fast when the destination filesystem lacks room for the file.
+    ///
+    /// Before the transfer starts, the free space on the destination's
+    /// filesystem is compared against the expected size plus `margin` —
+    /// `0.05` requires 5% headroom, useful when extraction follows — and
+    /// a shortage fails with an [`Io`](crate::ErrorKind::Io) error naming
+    /// the path, the required bytes and the available bytes, instead of
+    /// filling the disk and dying at 95%. Skipped when the expected size
+    /// is unknown (a [HEAD probe](Self::with_head_probe) can supply it)
+    /// and when the filesystem cannot be queried.
+    pub fn with_space_check(mut self, margin: f64) -> Self {
+        self.space_check = Some(margin);
+        self
+    }
+
+    /// Attach a [`DownloadController`] for pausing the transfer.
+    ///
+    /// While paused the streaming loop parks between chunks: nothing is
+    /// read from the connection, progress stops advancing, and the
+    /// [`with_min_speed`](Self::with_min_speed) clock does not count the
+    /// parked time. [`DownloadController::resume`] continues the transfer
+    /// with the verifier state intact.
+    pub fn with_controller(mut self, controller: DownloadController) -> Self {
+        self.controller = Some(controller);
+        self
+    }
+
+    /// Attach a [`CancelToken`] the download checks as it runs.
+    ///
+    /// See [`CancelToken`] for when the cancellation is noticed; it
+    /// surfaces as a [`Cancelled`](crate::ErrorKind::Cancelled) error,
+    /// distinguishable from real failures and never retried, and the
+    /// partial file is removed like for any other failure.
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Give the whole download a wall-clock budget.
+    ///
+    /// Unlike [`with_timeout`](Self::with_timeout), which limits a single
+    /// transfer attempt, the deadline spans everything `download` does —
+    /// including retries — and is checked between chunks, so it needs no
+    /// timer backend. With the `tokio` or `smol` feature the remaining
+    /// budget additionally bounds each attempt like a timeout, covering
+    /// streams that stop yielding chunks entirely. On expiry the download
+    /// fails with a [`Timeout`](crate::ErrorKind::Timeout) error, the
+    /// partial file is removed, and no further retries are attempted.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Abort the transfer when throughput stays below a minimum.
+    ///
+    /// The received bytes are averaged over successive windows of
+    /// `window`; a window averaging below `bytes_per_sec` fails the
+    /// download with a retryable [`Timeout`](crate::ErrorKind::Timeout)
+    /// error, so a trickling mirror is abandoned by the retry policy
+    /// instead of holding the transfer for hours. The check runs on chunk
+    /// arrival and needs no timer backend, but a stream yielding no
+    /// chunks at all never wakes it — combine with
+    /// [`with_timeout`](Self::with_timeout) to cover complete stalls.
+    pub fn with_min_speed(mut self, bytes_per_sec: u64, window: Duration) -> Self {
+        self.min_speed = Some((bytes_per_sec, window));
+        self
+    }
+
+    /// Skip the transfer when the server still has the same version.
+    ///
+    /// The response `ETag` of a completed download is recorded in a
+    /// sibling `<dest>.etag` file. When both the destination and that
+    /// sidecar exist, the next download sends `If-None-Match` and a
+    /// `304 Not Modified` answer returns `Ok` without touching the
+    /// destination. Re-downloading over an existing destination is the
+    /// point of the cache, so the default
+    /// [`OverwritePolicy::Error`] does not apply. Servers without ETags
+    /// simply download every time.
+    pub fn with_etag_cache(mut self) -> Self {
+        self.etag_cache = true;
+        self
+    }
+
+    /// Attach a custom header to every request this download makes.
+    ///
+    /// The header rides on the GET itself, the mirror speedtest probes and
+    /// the sidecar fetches, so e.g. `Authorization: Bearer …` or the
+    /// `Accept: application/octet-stream` required by the GitHub asset API
+    /// reaches every involved endpoint. Call repeatedly to attach several
+    /// headers. The values are never logged and never end up in error
+    /// descriptions, so a token in an `Authorization` header stays out of
+    /// terminal output.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Ask the server to skip on-the-fly compression.
+    ///
+    /// Sends `Accept-Encoding: identity` on every request, so the wire
+    /// bytes are the published artifact and announced lengths and
+    /// published checksums line up. Useful against CDNs that
+    /// gzip-compress responses on the fly, where a transparently
+    /// [decoding](crate::http::Response::is_decoded) client would stream
+    /// bytes that no longer match a checksum taken of the stored file.
+    /// Servers are free to ignore the header, so this is a request, not a
+    /// guarantee.
+    pub fn request_identity_encoding(self) -> Self {
+        self.with_header("Accept-Encoding", "identity")
+    }
+
+    /// Verify the body against a digest the server announces, when no
+    /// explicit verifier is configured.
+    ///
+    /// Artifact servers like Nexus and some CDNs send an RFC 3230
+    /// `Digest: sha-256=<base64>` or a `Content-MD5` header describing
+    /// the body. With this enabled, a supported digest builds the
+    /// corresponding hash verifier on the fly — integrity checking for
+    /// free where the server offers it. An explicit verifier always takes
+    /// precedence, and unknown or unparsable header values are skipped
+    /// with a warning rather than failing the download; this is an
+    /// opportunistic check, not a substitute for a pinned checksum.
+    pub fn verify_server_digest(mut self) -> Self {
+        self.server_digest = true;
+        self
+    }
+
+    /// Choose when the verifier sees the downloaded bytes; see
+    /// [`VerificationMode`].
+    ///
+    /// In [`AfterDownload`](VerificationMode::AfterDownload) mode the
+    /// transfer runs without streaming verifier updates and the finished
+    /// part file is re-read and verified before it is renamed into place.
+    /// Only the explicitly configured verifier takes part — a
+    /// [server digest](Self::verify_server_digest) only exists per
+    /// response and is not re-checked. In-memory downloads
+    /// ([`download_bytes`](Self::download_bytes)) have no file to re-read
+    /// and always verify the streamed bytes.
+    pub fn with_verification_mode(mut self, mode: VerificationMode) -> Self {
+        self.verification = mode;
+        self
+    }
+
+    /// Skip the transfer when the server has nothing newer than the local
+    /// copy.
+    ///
+    /// When the destination exists, its modification time is sent as an
+    /// `If-Modified-Since` header and a `304 Not Modified` answer returns
+    /// `Ok` without touching it — a cheaper cousin of
+    /// [`with_etag_cache`](Self::with_etag_cache) for servers without
+    /// ETags (a recorded ETag takes precedence when both are enabled). As
+    /// with the ETag cache, a full response replaces the existing
+    /// destination, so the default [`OverwritePolicy::Error`] does not
+    /// apply. A destination whose modification time cannot be read
+    /// downloads unconditionally.
+    pub fn with_mtime_check(mut self) -> Self {
+        self.mtime_check = true;
+        self
+    }
+
+    /// Do not compare the response `Content-Length` against the expected
+    /// size.
+    ///
+    /// By default a response announcing a length different from a known
+    /// expected size fails fast with a
+    /// [`Verify`](crate::ErrorKind::Verify) error before anything is
+    /// streamed, instead of letting the verifier reject the wrong file
+    /// after the whole transfer. Opt out for servers sending bogus or
+    /// compressed lengths. The check never applies when the expected size
+    /// is unknown.
+    pub fn skip_content_length_check(mut self) -> Self {
+        self.check_length = false;
+        self
+    }
+
+    /// Do not preallocate the destination file.
+    ///
+    /// When the expected size is known, the part file is grown to it
+    /// right after creation, which helps the filesystem lay it out
+    /// contiguously and fails fast on a full disk instead of deep into
+    /// the transfer. Opt out on filesystems where the sparse file this
+    /// creates is undesirable. Never applies when the size is unknown.
+    pub fn skip_preallocate(mut self) -> Self {
+        self.preallocate = false;
+        self
+    }
+
+    /// Set the size of the buffer between the stream and the destination
+    /// file; the default is
+    /// [`DEFAULT_WRITE_BUFFER`](Self::DEFAULT_WRITE_BUFFER).
+    ///
+    /// Network chunks are often only a few kilobytes, so writing each
+    /// straight to the file costs a syscall per chunk; the buffer batches
+    /// them before they hit the disk. It is flushed before verification
+    /// and the final rename.
+    pub fn with_write_buffer(mut self, capacity: usize) -> Self {
+        self.write_buffer = capacity;
+        self
+    }
+
+    /// Drain chunks to the destination on a dedicated writer task.
+    ///
+    /// By default the streaming loop alternates between awaiting a chunk
+    /// and writing it, so network and disk time add up. With this set,
+    /// chunks go through a bounded channel to a worker on the blocking
+    /// pool while the loop keeps reading from the socket and feeding the
+    /// verifier, overlapping the two. The channel bounds memory use: when
+    /// the disk falls behind, the loop waits for it, just like the default
+    /// path. A write error stops the transfer and is reported as the
+    /// download's failure. Requires a blocking pool from the `smol` or
+    /// `tokio` feature; only affects [`download`](Self::download) and
+    /// [`download_phased`](Self::download_phased).
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    pub fn with_pipelined_write(mut self) -> Self {
+        self.pipelined_write = true;
+        self
+    }
+
+    /// Fetch a checksum sidecar and verify the download against it.
+    ///
+    /// Many release layouts publish `foo.tar.gz` next to a
+    /// `foo.tar.gz.sha256` holding its digest. Before the download starts,
+    /// `<url>.<extension>` is fetched into memory — from the same mirror
+    /// that will serve the file — and its leading hex token (a bare digest
+    /// or the coreutils `HEX  NAME` format) becomes the expected digest,
+    /// replacing any verifier configured earlier. The algorithm is named
+    /// by the extension, so `"sha256"`, `"sha512"`, `"sha1"` and `"md5"`
+    /// work with the matching features enabled.
+    ///
+    /// A sidecar that cannot be fetched fails the download; see
+    /// [`allow_missing_sidecar`](Self::allow_missing_sidecar) to degrade
+    /// to an unverified download instead. A sidecar that fetches but does
+    /// not parse is always a [`Verify`](crate::ErrorKind::Verify) error.
+    pub fn with_checksum_sidecar(mut self, extension: impl Into<String>) -> Self {
+        self.checksum_sidecar = Some(extension.into());
+        self
+    }
+
+    /// Treat a missing checksum sidecar as "no verification".
+    ///
+    /// With [`with_checksum_sidecar`](Self::with_checksum_sidecar), a
+    /// sidecar that cannot be fetched normally fails the download, so a
+    /// dropped `.sha256` cannot silently disable verification. Opt in to
+    /// the silent downgrade for hosts that only publish digests for some
+    /// files; the skipped check is logged at debug level.
+    pub fn allow_missing_sidecar(mut self) -> Self {
+        self.sidecar_optional = true;
+        self
+    }
+
+    /// Verify the download against its entry in a checksum manifest.
+    ///
+    /// Where [`with_checksum_sidecar`](Self::with_checksum_sidecar) expects
+    /// one digest file per asset, many projects publish a single
+    /// `SHA256SUMS` covering the whole release; fetch it once with
+    /// [`ChecksumFile::fetch`] and pass it to every download. The file name
+    /// component of the destination selects the entry, resolved when the
+    /// download starts, so a file the manifest does not list fails with a
+    /// [`Verify`](crate::ErrorKind::Verify) error naming it instead of
+    /// downloading unverified. Replaces any verifier configured earlier.
+    ///
+    /// Returns an error when the destination has no file name component.
+    pub fn with_manifest(mut self, manifest: &ChecksumFile) -> Result<Self> {
+        self.verifier = Some(Box::new(manifest.verifier_for_dest(&self.dest)?));
+        Ok(self)
+    }
+
+    /// Fetch a minisign signature sidecar and verify the download with
+    /// `public_key`.
+    ///
+    /// The signature sidecar counterpart of
+    /// [`with_checksum_sidecar`](Self::with_checksum_sidecar): before the
+    /// download starts, `<url>.<extension>` (conventionally `"minisig"`)
+    /// is fetched into memory from the same mirror that will serve the
+    /// file, decoded, and a
+    /// [`MinisignVerifierBuilder`](crate::verify::minisign::MinisignVerifierBuilder)
+    /// over it replaces any verifier configured earlier. A signature that
+    /// cannot be fetched or decoded fails the download before any bytes of
+    /// the file reach disk;
+    /// [`allow_missing_sidecar`](Self::allow_missing_sidecar) does not
+    /// apply — a signed release without its signature is an error, not an
+    /// unverified download.
+    #[cfg(feature = "minisign")]
+    pub fn with_minisign(
+        mut self,
+        public_key: &minisign::PublicKey,
+        extension: impl Into<String>,
+    ) -> Self {
+        self.minisign_sidecar = Some((public_key.clone(), extension.into()));
+        self
+    }
+
+    /// Set the suffix of the in-progress sibling file; the default is
+    /// [`DEFAULT_PART_SUFFIX`](Self::DEFAULT_PART_SUFFIX).
+    ///
+    /// The transfer never touches the destination name directly — it goes
+    /// to `<dest><suffix>` and is renamed into place only after
+    /// verification — so tools watching the directory only ever see
+    /// complete files. Change the suffix when `.part` collides with
+    /// another tool's convention or when the watcher needs a specific
+    /// pattern to ignore.
+    pub fn with_part_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.part_suffix = suffix.into();
+        self
+    }
+
+    /// Keep the `<dest>.part` file around when the download fails.
+    ///
+    /// By default any failure — a broken stream, a rejecting verifier —
+    /// removes the part file, so the next run starts from a clean slate.
+    /// With this set, the partial data survives for inspection (or a
+    /// future resumption); the destination itself is never affected
+    /// either way, since the part file is only renamed into place after
+    /// verification. A stale part file is still truncated when the next
+    /// download of the same destination starts, and a `304 Not Modified`
+    /// answer removes the empty part file it leaves behind.
+    ///
+    /// The kept file is not resumed from yet: the [`Client`](crate::http::Client)
+    /// abstraction has no ranged-GET pathway, and the digest API exposes
+    /// no checkpointable hasher state to skip re-hashing the existing
+    /// bytes, so a later download starts over.
+    pub fn with_keep_partial(mut self) -> Self {
+        self.keep_partial = true;
+        self
+    }
+
+    /// Set whether the downloaded file is fsynced before success is
+    /// reported; the default is off.
+    ///
+    /// By default the data may still sit in OS caches when
+    /// [`download`](Self::download) returns, so a power loss can leave a
+    /// truncated file that already passed verification. With this set, the
+    /// part file is synced to disk before the final rename, and on unix
+    /// the containing directory is synced after it, so the destination
+    /// either does not exist yet or holds the complete content. Sync
+    /// failures are [`Io`](crate::ErrorKind::Io) errors naming the path.
+    pub fn with_sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Set the largest response [`download_bytes`](Self::download_bytes)
+    /// buffers before giving up; the default is
+    /// [`DEFAULT_MEMORY_CAP`](Self::DEFAULT_MEMORY_CAP).
+    pub fn with_memory_cap(mut self, cap: u64) -> Self {
+        self.memory_cap = cap;
+        self
+    }
+
+    /// Set the minimum interval between forwarded progress updates.
+    ///
+    /// The progress receiver is wrapped in [`Throttled`] so fast transfers
+    /// do not flood it; the default interval is
+    /// [`Throttled::DEFAULT_INTERVAL`]. Pass [`Duration::ZERO`] to deliver
+    /// every update.
+    pub fn with_progress_throttle(mut self, interval: Duration) -> Self {
+        self.throttle = interval;
+        self
+    }
+
+    /// Check whether a valid copy of the file already exists at the
+    /// destination.
+    ///
+    /// Returns `Ok(false)` when the file is missing or its size differs from
+    /// the expected size, and an error when the configured verifier rejects
+    /// the existing content.
+    ///
+    /// To repair a failing file instead of reporting it, use
+    /// [`download_if_needed`](Self::download_if_needed), which replaces it
+    /// automatically.
+    pub fn exist(&self) -> Result<bool> {
+        self.exist_with_progress(NoProgress)
+    }
+
+    /// Report what is at the destination: nothing, a file of the wrong
+    /// size, content the verifier rejects, or a valid copy.
+    ///
+    /// The fine-grained sibling of [`exist`](Self::exist) for callers
+    /// deciding between downloading, repairing and warning the user:
+    /// `exist` folds `Missing` and `SizeMismatch` into `false` and
+    /// surfaces a failing verifier as an error, while this reports each
+    /// outcome as its own [`FileStatus`]. Errors are reserved for the
+    /// destination being unreadable.
+    pub fn status(&self) -> Result<FileStatus> {
+        let metadata = std::fs::metadata(&self.dest);
+        match self.verify_existing(metadata, &NoProgress) {
+            Ok(status) => Ok(status),
+            Err(e) if e.kind() == ErrorKind::Verify => Ok(FileStatus::VerifyFailed),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check for a valid copy like [`exist`](Self::exist), reporting
+    /// progress while the existing file is verified.
+    ///
+    /// Hashing a multi-gigabyte destination reads all of it, which looks
+    /// frozen without feedback. The receiver is initialized with the file
+    /// size (`None` when the file is missing) and fed positions as the
+    /// content is read, throttled like download progress; it is resolved
+    /// exactly once.
+    pub fn exist_with_progress(&self, progress: impl ProgressReceiverBuilder) -> Result<bool> {
+        let metadata = std::fs::metadata(&self.dest);
+        let total = metadata.as_ref().ok().map(|m| m.len()).filter(|len| *len != 0);
+        let progress = Throttled::with_interval(progress.init(total), self.throttle);
+        let result = self.verify_existing(metadata, &progress);
+        match &result {
+            Ok(_) => progress.finish(),
+            Err(error) => progress.finish_with_error(error),
+        }
+        result.map(|status| status == FileStatus::Valid)
+    }
+
+    /// Check for a valid copy like [`exist`](Self::exist) without
+    /// blocking the runtime.
+    ///
+    /// Hashing a multi-gigabyte destination is blocking work; this
+    /// variant offloads the whole check to the runtime's blocking pool
+    /// and awaits the verdict. The return value and semantics match the
+    /// sync version exactly — both run the same implementation.
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    pub async fn exist_async(&self) -> Result<bool> {
+        self.exist_with_progress_async(NoProgress).await
+    }
+
+    /// Check for a valid copy like
+    /// [`exist_with_progress`](Self::exist_with_progress) without
+    /// blocking the runtime; the async sibling pairing
+    /// [`exist_async`](Self::exist_async) with a progress receiver.
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    pub async fn exist_with_progress_async<P>(&self, progress: P) -> Result<bool>
+    where
+        P: ProgressReceiverBuilder,
+        P::Receiver: Send + 'static,
+    {
+        let metadata = std::fs::metadata(&self.dest);
+        let total = metadata.as_ref().ok().map(|m| m.len()).filter(|len| *len != 0);
+        let progress = Throttled::with_interval(progress.init(total), self.throttle);
+        let verifier = match &self.verifier {
+            Some(builder) => match builder.build_dyn() {
+                Ok(verifier) => Some(verifier),
+                Err(e) => {
+                    progress.finish_with_error(&e);
+                    return Err(e);
+                }
+            },
+            None => None,
+        };
+        let dest = self.dest.clone();
+        let size = self.size;
+        let (result, progress) = crate::runtime::spawn_blocking(move || {
+            let result = Self::verify_existing_file(&dest, size, verifier, metadata, &progress);
+            (result, progress)
+        })
+        .await;
+        match &result {
+            Ok(_) => progress.finish(),
+            Err(error) => progress.finish_with_error(error),
+        }
+        result.map(|status| status == FileStatus::Valid)
+    }
+
+    /// The size and verifier checks behind [`exist`](Self::exist) and
+    /// [`status`](Self::status), fed the destination metadata and
+    /// reporting read positions to `progress`.
+    fn verify_existing(
+        &self,
+        metadata: std::io::Result<std::fs::Metadata>,
+        progress: &impl ProgressReceiver,
+    ) -> Result<FileStatus> {
+        let verifier = match &self.verifier {
+            Some(builder) => Some(builder.build_dyn()?),
+            None => None,
+        };
+        Self::verify_existing_file(&self.dest, self.size, verifier, metadata, progress)
+    }
+
+    /// The shared core behind [`exist`](Self::exist) and
+    /// [`exist_async`](Self::exist_async), free of `self` so the async
+    /// variant can move everything onto the blocking pool. A failing
+    /// verifier is reported as an error (with the digest details) rather
+    /// than [`FileStatus::VerifyFailed`]; [`status`](Self::status) folds
+    /// it back into the enum.
+    fn verify_existing_file(
+        dest: &Path,
+        size: u64,
+        verifier: Option<Box<dyn DynVerifier>>,
+        metadata: std::io::Result<std::fs::Metadata>,
+        progress: &impl ProgressReceiver,
+    ) -> Result<FileStatus> {
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(FileStatus::Missing);
+            }
+            Err(e) => {
+                return Err(Error::from(e)
+                    .with_desc_with(|| format!("failed to stat {}", dest.display())));
+            }
+        };
+        if size != 0 && metadata.len() != size {
+            return Ok(FileStatus::SizeMismatch {
+                expected: size,
+                actual: metadata.len(),
+            });
+        }
+        if let Some(mut verifier) = verifier {
+            let mut file = File::open(dest)
+                .map_err(Error::from)
+                .with_desc_with(|| format!("failed to open {}", dest.display()))?;
+            let mut buffer = vec![0u8; 64 * 1024];
+            let mut position = 0u64;
+            loop {
+                let read = file
+                    .read(&mut buffer)
+                    .map_err(Error::from)
+                    .with_desc_with(|| format!("failed to read {}", dest.display()))?;
+                if read == 0 {
+                    break;
+                }
+                verifier.update(&buffer[..read]);
+                position += read as u64;
+                progress.set_position(position);
+            }
+            verifier
+                .verify()
+                .map_err(|e| e.with_path(dest))?;
+        }
+        Ok(FileStatus::Valid)
+    }
+
+    /// Re-read the finished part file and check it against a fresh
+    /// verifier: the [`VerificationMode::AfterDownload`] pass. A no-op
+    /// without a configured verifier.
+    fn verify_part_file(&self, progress: &impl ProgressReceiver) -> Result<()> {
+        let Some(builder) = &self.verifier else {
+            return Ok(());
+        };
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("verify", url = self.url.as_str()).entered();
+        let mut verifier = builder.build_dyn()?;
+        let part = self.part_path();
+        let file = File::open(&part)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to open {}", part.display()))?;
+        if let Ok(metadata) = file.metadata() {
+            progress.set_total(metadata.len());
+        }
+        let mut reader = std::io::BufReader::with_capacity(self.write_buffer, file);
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut position = 0u64;
+        loop {
+            let read = reader
+                .read(&mut buffer)
+                .map_err(Error::from)
+                .with_desc_with(|| format!("failed to read {}", part.display()))?;
+            if read == 0 {
+                break;
+            }
+            verifier.update(&buffer[..read]);
+            position += read as u64;
+            progress.set_position(position);
+        }
+        verifier.verify()
+    }
+
+    /// Download the file.
+    ///
+    /// When mirrors are configured the fastest one is selected first. The
+    /// transfer goes to a sibling `<dest>.part` file (overwriting a stale
+    /// one from a crashed run) which is renamed into place only after the
+    /// stream completes and the verifier passes, so no failure leaves a
+    /// corrupt file at the destination; the part file is removed instead.
+    /// An already existing destination file is an error unless an
+    /// [`OverwritePolicy`] says otherwise.
+    ///
+    /// The returned [`DownloadReport`] records which URL served the file,
+    /// how many bytes were streamed, how long the call took and how often
+    /// it retried, for logging and telemetry.
+    pub async fn download<C: Client>(
+        mut self,
+        client: &C,
+        progress: impl ProgressReceiverBuilder,
+    ) -> Result<DownloadReport> {
+        let started = Instant::now();
+        self.deadline_at = self.deadline.map(|d| Instant::now() + d);
+        self.check_cancelled()?;
+        if self.check_offline()? || self.check_overwrite()? {
+            return Ok(self.report(&self.url, 0, 0, started));
+        }
+        let _lock = match self.lock.take() {
+            Some(wait) => {
+                let lock = DestLock::acquire(&self.dest, wait)?;
+                // Another process may have produced the file while we
+                // waited for the lock; reuse it instead of failing on the
+                // existing destination.
+                if self.exist()? {
+                    log::debug!(
+                        "{} appeared while waiting for the lock",
+                        self.dest.display()
+                    );
+                    return Ok(self.report(&self.url, 0, 0, started));
+                }
+                Some(lock)
+            }
+            None => None,
+        };
+
+        let url = match self.mirrors.take() {
+            Some(mut mirrors) => {
+                let select = mirrors.select(client, &self.url, &self.headers, self.cancel.as_ref());
+                #[cfg(feature = "tracing")]
+                let select = tracing::Instrument::instrument(
+                    select,
+                    tracing::info_span!("select_mirror", url = self.url.as_str()),
+                );
+                select.await.map_err(|e| e.with_url(&self.url))?
+            }
+            None => self.url.clone(),
+        };
+        self.install_sidecar_verifier(client, &url)
+            .await
+            .map_err(|e| e.with_path(&self.dest))?;
+        #[cfg(feature = "minisign")]
+        self.install_minisign_verifier(client, &url)
+            .await
+            .map_err(|e| e.with_path(&self.dest))?;
+        self.probe_size(client, &url)
+            .await
+            .map_err(|e| e.with_url(&url).with_path(&self.dest))?;
+        self.check_disk_space()?;
+
+        let progress = Throttled::with_interval(
+            progress.init((self.size != 0).then_some(self.size)),
+            self.throttle,
+        );
+        let result: Result<(u64, u32)> = async {
+            let (fetched, retries) = self.fetch_to_file_retried(client, &url, &progress).await?;
+            let (verifier, etag, len, filename) = match fetched {
+                Fetched::Done {
+                    verifier,
+                    etag,
+                    len,
+                    filename,
+                } => (verifier, etag, len, filename),
+                Fetched::NotModified => {
+                    log::debug!("{} is still up to date", self.dest.display());
+                    self.remove_part();
+                    return Ok((0, retries));
+                }
+            };
+            if let Some(filename) = filename {
+                self.retarget_dest(&filename)?;
+            }
+            if let Some(verifier) = verifier {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::info_span!("verify", url = self.url.as_str()).entered();
+                verifier.verify()?;
+            }
+            if self.verification == VerificationMode::AfterDownload {
+                self.verify_part_file(&progress)?;
+            }
+            self.commit_part()?;
+            self.store_etag(etag.as_deref());
+            Ok((len, retries))
+        }
+        .await;
+
+        let result = result.map_err(|e| e.with_url(&url).with_path(&self.dest));
+        if result.is_err() {
+            self.discard_part();
+        }
+        // Every exit route resolves the progress receiver exactly once.
+        match result {
+            Ok((bytes, retries)) => {
+                progress.finish();
+                Ok(self.report(&url, bytes, retries, started))
+            }
+            Err(error) => {
+                progress.finish_with_error(&error);
+                Err(error)
+            }
+        }
+    }
+
+    /// Assemble the [`DownloadReport`] for a finished call.
+    fn report(&self, url: &str, bytes: u64, retries: u32, started: Instant) -> DownloadReport {
+        DownloadReport {
+            url: url.to_string(),
+            dest: self.dest.clone(),
+            bytes,
+            elapsed: started.elapsed(),
+            retries,
+        }
+    }
+
+    /// Download the file only when no valid copy exists yet.
+    ///
+    /// Combines [`exist`](Self::exist) and [`download`](Self::download):
+    /// a destination passing the size and verifier checks is left alone, a
+    /// missing one is downloaded, and an existing file failing the checks
+    /// is replaced — the default [`OverwritePolicy::Error`] does not apply
+    /// to a file known to be invalid. The returned [`DownloadOutcome`]
+    /// says which of the three happened.
+    pub async fn download_if_needed<C: Client>(
+        mut self,
+        client: &C,
+        progress: impl ProgressReceiverBuilder,
+    ) -> Result<DownloadOutcome> {
+        let existing = self.dest.symlink_metadata().is_ok();
+        match self.exist() {
+            Ok(true) => {
+                log::debug!("{} already exists and is valid", self.dest.display());
+                return Ok(DownloadOutcome::AlreadyValid);
+            }
+            Ok(false) => {}
+            Err(e) if e.kind() == ErrorKind::Verify => {
+                log::debug!("{} failed verification; replacing it", self.dest.display());
+            }
+            Err(e) => return Err(e),
+        }
+        if existing && self.overwrite == OverwritePolicy::Error {
+            self.overwrite = OverwritePolicy::Overwrite;
+        }
+        self.download(client, progress).await?;
+        Ok(if existing {
+            DownloadOutcome::Replaced
+        } else {
+            DownloadOutcome::Downloaded
+        })
+    }
+
+    /// Download into an arbitrary [`Write`] sink instead of a file.
+    ///
+    /// Streams the response into `writer` with the same verifier and
+    /// progress plumbing as [`download`](Self::download), composing with
+    /// caller-managed tempfiles, encoders or hashers without an
+    /// intermediate file. Note that bytes reach the writer *before* the
+    /// verifier's final check: on an error the caller must discard
+    /// whatever the sink received. The destination path, the file-oriented
+    /// policies (locking, offline, overwrite) and the retry policy —
+    /// written bytes cannot be taken back — are ignored.
+    pub async fn download_to_writer<C: Client>(
+        mut self,
+        client: &C,
+        writer: &mut impl Write,
+        progress: impl ProgressReceiverBuilder,
+    ) -> Result<()> {
+        self.deadline_at = self.deadline.map(|d| Instant::now() + d);
+        self.check_cancelled()?;
+        let url = match self.mirrors.take() {
+            Some(mut mirrors) => {
+                let select = mirrors.select(client, &self.url, &self.headers, self.cancel.as_ref());
+                #[cfg(feature = "tracing")]
+                let select = tracing::Instrument::instrument(
+                    select,
+                    tracing::info_span!("select_mirror", url = self.url.as_str()),
+                );
+                select.await.map_err(|e| e.with_url(&self.url))?
+            }
+            None => self.url.clone(),
+        };
+
+        let progress = Throttled::with_interval(
+            progress.init((self.size != 0).then_some(self.size)),
+            self.throttle,
+        );
+        let result: Result<()> = async {
+            let fetch = self.fetch_to_writer(client, &url, writer, &progress, None);
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            let fetched = match self.timeout {
+                Some(timeout) => crate::runtime::timeout(timeout, fetch).await??,
+                None => fetch.await?,
+            };
+            #[cfg(not(any(feature = "tokio", feature = "smol")))]
+            let fetched = fetch.await?;
+            let Fetched::Done { verifier, .. } = fetched else {
+                // Without a cached etag the request is unconditional.
+                return Ok(());
+            };
+            if let Some(verifier) = verifier {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::info_span!("verify", url = self.url.as_str()).entered();
+                verifier.verify()?;
+            }
+            Ok(())
+        }
+        .await;
+
+        let result = result.map_err(|e| e.with_url(&url));
+        match &result {
+            Ok(()) => progress.finish(),
+            Err(error) => progress.finish_with_error(error),
+        }
+        result
+    }
+
+    /// Download into a [`tokio::io::AsyncWrite`] sink.
+    ///
+    /// The async sibling of [`download_to_writer`](Self::download_to_writer):
+    /// chunks are forwarded with backpressure by awaiting each write
+    /// instead of blocking the executor, while the verifier and progress
+    /// plumbing stay the same — including the caveat that bytes reach the
+    /// writer before the verifier's final check. The writer is flushed
+    /// after the last chunk.
+    #[cfg(feature = "tokio")]
+    pub async fn download_to_async_writer<C, W>(
+        mut self,
+        client: &C,
+        writer: &mut W,
+        progress: impl ProgressReceiverBuilder,
+    ) -> Result<()>
+    where
+        C: Client,
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        self.deadline_at = self.deadline.map(|d| Instant::now() + d);
+        self.check_cancelled()?;
+        let url = match self.mirrors.take() {
+            Some(mut mirrors) => {
+                let select = mirrors.select(client, &self.url, &self.headers, self.cancel.as_ref());
+                #[cfg(feature = "tracing")]
+                let select = tracing::Instrument::instrument(
+                    select,
+                    tracing::info_span!("select_mirror", url = self.url.as_str()),
+                );
+                select.await.map_err(|e| e.with_url(&self.url))?
+            }
+            None => self.url.clone(),
+        };
+
+        let progress = Throttled::with_interval(
+            progress.init((self.size != 0).then_some(self.size)),
+            self.throttle,
+        );
+        let result: Result<()> = async {
+            let fetch = self.fetch_to_async_writer(client, &url, writer, &progress, None);
+            let fetched = match self.timeout {
+                Some(timeout) => crate::runtime::timeout(timeout, fetch).await??,
+                None => fetch.await?,
+            };
+            let Fetched::Done { verifier, .. } = fetched else {
+                // Without a cached etag the request is unconditional.
+                return Ok(());
+            };
+            if let Some(verifier) = verifier {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::info_span!("verify", url = self.url.as_str()).entered();
+                verifier.verify()?;
+            }
+            Ok(())
+        }
+        .await;
+
+        let result = result.map_err(|e| e.with_url(&url));
+        match &result {
+            Ok(()) => progress.finish(),
+            Err(error) => progress.finish_with_error(error),
+        }
+        result
+    }
+
+    /// Download into memory instead of a file.
+    ///
+    /// Streams the response into a buffer (sized from the expected size),
+    /// feeds the verifier, and returns the verified content. Mirrors,
+    /// the time limit and progress reporting work like in
+    /// [`download`](Self::download); the destination path and the
+    /// file-oriented policies (locking, offline, overwrite) are ignored.
+    /// A response larger than the [memory cap](Self::with_memory_cap)
+    /// fails instead of buffering without bound.
+    pub async fn download_bytes<C: Client>(
+        mut self,
+        client: &C,
+        progress: impl ProgressReceiverBuilder,
+    ) -> Result<Bytes> {
+        self.deadline_at = self.deadline.map(|d| Instant::now() + d);
+        self.check_cancelled()?;
+        let url = match self.mirrors.take() {
+            Some(mut mirrors) => {
+                let select = mirrors.select(client, &self.url, &self.headers, self.cancel.as_ref());
+                #[cfg(feature = "tracing")]
+                let select = tracing::Instrument::instrument(
+                    select,
+                    tracing::info_span!("select_mirror", url = self.url.as_str()),
+                );
+                select.await.map_err(|e| e.with_url(&self.url))?
+            }
+            None => self.url.clone(),
+        };
+
+        let progress = Throttled::with_interval(
+            progress.init((self.size != 0).then_some(self.size)),
+            self.throttle,
+        );
+        let result: Result<Bytes> = async {
+            let fetch = self.fetch_to_bytes(client, &url, &progress);
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            let (buffer, verifier) = match self.timeout {
+                Some(timeout) => crate::runtime::timeout(timeout, fetch).await??,
+                None => fetch.await?,
+            };
+            #[cfg(not(any(feature = "tokio", feature = "smol")))]
+            let (buffer, verifier) = fetch.await?;
+            if let Some(verifier) = verifier {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::info_span!("verify", url = self.url.as_str()).entered();
+                verifier.verify()?;
+            }
+            Ok(buffer.freeze())
+        }
+        .await;
+
+        let result = result.map_err(|e| e.with_url(&url));
+        match &result {
+            Ok(_) => progress.finish(),
+            Err(error) => progress.finish_with_error(error),
+        }
+        result
+    }
+
+    /// Download an archive and extract it as it arrives, without the
+    /// archive ever touching disk.
+    ///
+    /// Tar extraction (with any supported compression) only needs a
+    /// sequential read, so the response stream is bridged through a
+    /// bounded channel into an extraction running on the blocking pool —
+    /// the archive's bytes hit the disk once, as extracted files, instead
+    /// of being written out and read back. The verifier runs over the
+    /// compressed bytes as they pass through, but entries are unpacked
+    /// *before* its final verdict: on an error the caller must discard
+    /// the extracted files. Zip archives need random access and are
+    /// rejected (see [`extract_stream`](crate::extract::extract_stream));
+    /// use [`Fetcher`](crate::fetch::Fetcher) to go through a file for
+    /// those. As in [`download_to_writer`](Self::download_to_writer), the
+    /// destination path and the file-oriented policies (locking, offline,
+    /// overwrite) and the retry policy are ignored.
+    #[cfg(all(feature = "tar", any(feature = "tokio", feature = "smol")))]
+    pub async fn download_and_extract<C: Client>(
+        mut self,
+        client: &C,
+        format: crate::extract::ArchiveFormat,
+        options: crate::extract::ExtractOptions<'static>,
+        progress: impl ProgressReceiverBuilder,
+    ) -> Result<crate::extract::ExtractReport> {
+        /// How many chunks may queue between the socket and the extraction.
+        const PIPE_DEPTH: usize = 16;
+
+        self.deadline_at = self.deadline.map(|d| Instant::now() + d);
+        self.check_cancelled()?;
+        let url = match self.mirrors.take() {
+            Some(mut mirrors) => {
+                let select = mirrors.select(client, &self.url, &self.headers, self.cancel.as_ref());
+                #[cfg(feature = "tracing")]
+                let select = tracing::Instrument::instrument(
+                    select,
+                    tracing::info_span!("select_mirror", url = self.url.as_str()),
+                );
+                select.await.map_err(|e| e.with_url(&self.url))?
+            }
+            None => self.url.clone(),
+        };
+
+        let progress = Throttled::with_interval(
+            progress.init((self.size != 0).then_some(self.size)),
+            self.throttle,
+        );
+        let result: Result<crate::extract::ExtractReport> = async {
+            let (sender, receiver) = std::sync::mpsc::sync_channel::<Bytes>(PIPE_DEPTH);
+            let worker = crate::runtime::spawn_blocking(move || {
+                let mut reader = PipeReader {
+                    receiver,
+                    current: Bytes::new(),
+                };
+                let report = crate::extract::extract_stream(&mut reader, format, options);
+                if report.is_ok() {
+                    // The tar reader stops at the end-of-archive marker;
+                    // drain the trailing padding so the fetch side (and
+                    // with it the verifier) runs to the end of the stream
+                    // instead of hitting a broken pipe.
+                    for _ in reader.receiver {}
+                }
+                report
+            });
+            let (extracted, fetched) = futures_util::future::join(worker, async {
+                let mut pipe = PipeWriter { sender };
+                let fetch = self.fetch_to_writer(client, &url, &mut pipe, &progress, None);
+                match self.timeout {
+                    Some(timeout) => crate::runtime::timeout(timeout, fetch).await?,
+                    None => fetch.await,
+                }
+            })
+            .await;
+            // Each side's failure surfaces on the other only as a broken
+            // pipe or a truncated archive, so the fetch error is the root
+            // cause — unless the fetch merely saw the extraction hang up
+            // the channel.
+            let (fetched, report) = match (fetched, extracted) {
+                (Ok(fetched), Ok(report)) => (fetched, report),
+                (Err(fetch), Err(extract)) => {
+                    return Err(
+                        if fetch.io_kind() == Some(std::io::ErrorKind::BrokenPipe) {
+                            extract
+                        } else {
+                            fetch
+                        },
+                    );
+                }
+                (Err(e), Ok(_)) | (Ok(_), Err(e)) => return Err(e),
+            };
+            if let Fetched::Done {
+                verifier: Some(verifier),
+                ..
+            } = fetched
+            {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::info_span!("verify", url = self.url.as_str()).entered();
+                verifier.verify()?;
+            }
+            Ok(report)
+        }
+        .await;
+
+        let result = result.map_err(|e| e.with_url(&url));
+        match &result {
+            Ok(_) => progress.finish(),
+            Err(error) => progress.finish_with_error(error),
+        }
+        result
+    }
+
+    /// Download the file, reporting each phase separately.
+    ///
+    /// Behaves like [`download`](Self::download), but opens mirror
+    /// selection, the transfer itself and the final verification as
+    /// separate phases on `progress`, resolving each receiver before the
+    /// next phase begins. Wrap a plain builder in
+    /// [`PerPhase`](crate::progress::PerPhase) to use it here.
+    pub async fn download_phased<C: Client>(
+        mut self,
+        client: &C,
+        progress: &impl PhasedProgressBuilder,
+    ) -> Result<()> {
+        self.deadline_at = self.deadline.map(|d| Instant::now() + d);
+        self.check_cancelled()?;
+        if self.check_offline()? || self.check_overwrite()? {
+            return Ok(());
+        }
+        let _lock = match self.lock.take() {
+            Some(wait) => {
+                let lock = DestLock::acquire(&self.dest, wait)?;
+                if self.exist()? {
+                    log::debug!(
+                        "{} appeared while waiting for the lock",
+                        self.dest.display()
+                    );
+                    return Ok(());
+                }
+                Some(lock)
+            }
+            None => None,
+        };
+
+        let url = match self.mirrors.take() {
+            Some(mut mirrors) => {
+                let receiver = progress.begin_phase(Phase::SelectingMirror, None);
+                match mirrors
+                    .select(client, &self.url, &self.headers, self.cancel.as_ref())
+                    .await
+                {
+                    Ok(url) => {
+                        receiver.finish();
+                        url
+                    }
+                    Err(e) => {
+                        let e = e.with_url(self.url);
+                        receiver.finish_with_error(&e);
+                        return Err(e);
+                    }
+                }
+            }
+            None => self.url.clone(),
+        };
+        self.install_sidecar_verifier(client, &url)
+            .await
+            .map_err(|e| e.with_path(&self.dest))?;
+        #[cfg(feature = "minisign")]
+        self.install_minisign_verifier(client, &url)
+            .await
+            .map_err(|e| e.with_path(&self.dest))?;
+        self.probe_size(client, &url)
+            .await
+            .map_err(|e| e.with_url(&url).with_path(&self.dest))?;
+        self.check_disk_space()?;
+
+        let receiver = Throttled::with_interval(
+            progress.begin_phase(Phase::Downloading, (self.size != 0).then_some(self.size)),
+            self.throttle,
+        );
+        let fetched = match self.fetch_to_file_retried(client, &url, &receiver).await {
+            Ok((fetched, _retries)) => {
+                receiver.finish();
+                fetched
+            }
+            Err(e) => {
+                let e = e.with_url(&url).with_path(&self.dest);
+                receiver.finish_with_error(&e);
+                self.discard_part();
+                return Err(e);
+            }
+        };
+        let (verifier, etag) = match fetched {
+            Fetched::Done { verifier, etag, .. } => (verifier, etag),
+            Fetched::NotModified => {
+                log::debug!("{} is still up to date", self.dest.display());
+                self.remove_part();
+                return Ok(());
+            }
+        };
+
+        if let Some(verifier) = verifier {
+            let receiver = progress.begin_phase(Phase::Verifying, None);
+            match verifier.verify().and_then(|()| self.commit_part()) {
+                Ok(()) => receiver.finish(),
+                Err(e) => {
+                    let e = e.with_url(&url).with_path(&self.dest);
+                    receiver.finish_with_error(&e);
+                    self.discard_part();
+                    return Err(e);
+                }
+            }
+        } else if self.verification == VerificationMode::AfterDownload && self.verifier.is_some() {
+            // The after-download pass knows the file size, so this phase
+            // gets a real total, unlike the streaming finalization above.
+            let len = std::fs::metadata(self.part_path()).ok().map(|m| m.len());
+            let receiver = Throttled::with_interval(
+                progress.begin_phase(Phase::Verifying, len),
+                self.throttle,
+            );
+            match self
+                .verify_part_file(&receiver)
+                .and_then(|()| self.commit_part())
+            {
+                Ok(()) => receiver.finish(),
+                Err(e) => {
+                    let e = e.with_url(&url).with_path(&self.dest);
+                    receiver.finish_with_error(&e);
+                    self.discard_part();
+                    return Err(e);
+                }
+            }
+        } else if let Err(e) = self.commit_part() {
+            self.discard_part();
+            return Err(e.with_url(&url).with_path(&self.dest));
+        }
+        self.store_etag(etag.as_deref());
+        Ok(())
+    }
+
+    /// Apply the offline policy before a download starts.
+    ///
+    /// Returns `Ok(true)` when a valid local copy makes the download
+    /// unnecessary, and an error in [`OfflinePolicy::Offline`] mode when
+    /// there is none.
+    fn check_offline(&self) -> Result<bool> {
+        match self.offline {
+            OfflinePolicy::Online => Ok(false),
+            policy => {
+                if self.exist()? {
+                    log::debug!("{} served from the local copy", self.dest.display());
+                    return Ok(true);
+                }
+                if policy == OfflinePolicy::Offline {
+                    return Err(Error::new(ErrorKind::Offline)
+                        .with_url(&self.url)
+                        .with_path(&self.dest)
+                        .with_desc("offline and no verified local copy"));
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    /// Fetch `<url>.<ext>` and install the parsed digest as the verifier,
+    /// when [`with_checksum_sidecar`](Self::with_checksum_sidecar) is set.
+    /// Fetch a sidecar-sized file next to the download, forwarding the
+    /// configured request headers.
+    async fn fetch_sidecar_bytes<C: Client>(&self, client: &C, url: &str) -> Result<Bytes> {
+        let mut fetch = DownloadBuilder::new(url, PathBuf::new(), 0).with_memory_cap(64 * 1024);
+        fetch.headers.clone_from(&self.headers);
+        fetch.download_bytes(client, NoProgress).await
+    }
+
+    async fn install_sidecar_verifier<C: Client>(&mut self, client: &C, url: &str) -> Result<()> {
+        let Some(extension) = self.checksum_sidecar.clone() else {
+            return Ok(());
+        };
+        let algorithm: HashAlgorithm = extension.parse()?;
+        let sidecar_url = format!("{url}.{extension}");
+        let bytes =
+            match self.fetch_sidecar_bytes(client, &sidecar_url).await {
+                Ok(bytes) => bytes,
+                Err(e) if self.sidecar_optional => {
+                    log::debug!("no checksum sidecar at {sidecar_url}: {e}");
+                    return Ok(());
+                }
+                Err(e) => {
+                    return Err(e.with_desc_with(|| {
+                        format!("failed to fetch the checksum sidecar {sidecar_url}")
+                    }));
+                }
+            };
+        let text = std::str::from_utf8(&bytes).map_err(|e| {
+            Error::new(ErrorKind::Verify)
+                .with_source(e)
+                .with_url(&sidecar_url)
+                .with_desc("the checksum sidecar is not UTF-8")
+        })?;
+        let verifier = crate::verify::checksum::parse_sidecar(algorithm, text)
+            .map_err(|e| e.with_url(&sidecar_url))?;
+        self.verifier = Some(Box::new(verifier));
+        Ok(())
+    }
+
+    /// Fetch `<url>.<ext>` and install a minisign verifier over it, when
+    /// [`with_minisign`](Self::with_minisign) is set.
+    #[cfg(feature = "minisign")]
+    async fn install_minisign_verifier<C: Client>(
+        &mut self,
+        client: &C,
+        url: &str,
+    ) -> Result<()> {
+        let Some((public_key, extension)) = self.minisign_sidecar.take() else {
+            return Ok(());
+        };
+        let sig_url = format!("{url}.{extension}");
+        let bytes = self
+            .fetch_sidecar_bytes(client, &sig_url)
+            .await
+            .map_err(|e| {
+                e.with_desc_with(|| format!("failed to fetch the minisign signature {sig_url}"))
+            })?;
+        let text = std::str::from_utf8(&bytes).map_err(|e| {
+            Error::new(ErrorKind::Verify)
+                .with_source(e)
+                .with_url(&sig_url)
+                .with_desc("the minisign signature is not UTF-8")
+        })?;
+        let signature = minisign::Signature::decode(text)
+            .map_err(|e| {
+                Error::new(ErrorKind::Verify)
+                    .with_source(e)
+                    .with_url(&sig_url)
+            })
+            .with_desc("failed to parse the minisign signature")?;
+        self.verifier = Some(Box::new(minisign::MinisignVerifierBuilder::new(
+            &public_key,
+            &signature,
+        )));
+        Ok(())
+    }
+
+    /// Ask the server for the size via HEAD, when enabled.
+    ///
+    /// An announced length fills in an unknown expected size; one
+    /// contradicting a known expected size aborts the download before the
+    /// transfer starts.
+    async fn probe_size<C: Client>(&mut self, client: &C, url: &str) -> Result<()> {
+        if !self.head_probe {
+            return Ok(());
+        }
+        // A file URL has no server to ask; its metadata is the probe.
+        let len = match file_url_path(url) {
+            Some(path) => std::fs::metadata(path?).ok().map(|m| m.len()),
+            None => client.head(url).await?,
+        };
+        let Some(len) = len else {
+            return Ok(());
+        };
+        if self.size == 0 {
+            log::debug!("{url} announces {len} bytes");
+            self.size = len;
+        } else if len != self.size {
+            return Err(Error::new(ErrorKind::Verify)
+                .with_verify_details(crate::error::VerifyDetails::Size {
+                    expected: self.size,
+                    actual: len,
+                })
+                .with_desc("the server announces a different size than expected"));
+        }
+        Ok(())
+    }
+
+    /// Compare the announced `Content-Length` against the expected size.
+    ///
+    /// The GET-response counterpart of [`probe_size`](Self::probe_size): a
+    /// response announcing a length contradicting a known expected size is
+    /// the wrong file, so fail before streaming any of it. Skipped when the
+    /// expected size is unknown, the check is
+    /// [opted out](Self::skip_content_length_check), or the client
+    /// [transparently decoded](crate::http::Response::is_decoded) the body
+    /// so the header no longer describes the streamed bytes.
+    fn check_content_length(&self, len: Option<u64>) -> Result<()> {
+        if !self.check_length || self.size == 0 {
+            return Ok(());
+        }
+        match len {
+            Some(len) if len != self.size => Err(Error::new(ErrorKind::Verify)
+                .with_verify_details(crate::error::VerifyDetails::Size {
+                    expected: self.size,
+                    actual: len,
+                })
+                .with_desc("the response announces a different size than expected")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Build the verifier for a response: the configured one, or one from
+    /// the server's digest headers when
+    /// [`verify_server_digest`](Self::verify_server_digest) is enabled.
+    fn build_verifier<R: Response>(&self, response: &R) -> Result<Option<Box<dyn DynVerifier>>> {
+        if let Some(builder) = &self.verifier {
+            return Ok(Some(builder.build_dyn()?));
+        }
+        if self.server_digest {
+            if let Some(builder) = server_digest_verifier(response.digest(), response.content_md5())
+            {
+                return Ok(Some(builder.build_dyn()?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Fail when the attached [`CancelToken`] has fired.
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.cancel {
+            Some(token) if token.is_cancelled() => Err(cancelled()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail when the wall-clock budget has been exhausted; see
+    /// [`with_deadline`](Self::with_deadline).
+    fn check_deadline(&self) -> Result<()> {
+        match (self.deadline_at, self.deadline) {
+            (Some(at), Some(budget)) if Instant::now() >= at => {
+                Err(Error::new(ErrorKind::Timeout)
+                    .mark_timeout()
+                    .with_desc_with(|| {
+                        format!("the download exceeded its deadline of {budget:?}")
+                    }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Apply the overwrite policy before a download starts.
+    ///
+    /// Returns `Ok(true)` when [`OverwritePolicy::SkipIfValid`] finds a
+    /// valid existing file. An existing file failing verification is not an
+    /// error under this policy, only a reason to replace it.
+    fn check_overwrite(&self) -> Result<bool> {
+        if self.overwrite != OverwritePolicy::SkipIfValid {
+            return Ok(false);
+        }
+        match self.exist() {
+            Ok(true) => {
+                log::debug!("{} already exists and is valid", self.dest.display());
+                Ok(true)
+            }
+            Ok(false) => Ok(false),
+            Err(e) if e.kind() == ErrorKind::Verify => {
+                log::debug!("{} failed verification; replacing it", self.dest.display());
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Apply the opt-in disk-space pre-check; see
+    /// [`with_space_check`](Self::with_space_check). A filesystem that
+    /// cannot be queried only logs a warning — the download itself will
+    /// surface a real shortage anyway.
+    fn check_disk_space(&self) -> Result<()> {
+        let Some(margin) = self.space_check else {
+            return Ok(());
+        };
+        if self.size == 0 {
+            return Ok(());
+        }
+        // The destination itself does not exist yet; the nearest existing
+        // ancestor lives on the same filesystem.
+        let mut probe = self.dest.as_path();
+        let available = loop {
+            match fs2::available_space(probe) {
+                Ok(space) => break space,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    probe = match probe.parent() {
+                        Some(parent) if !parent.as_os_str().is_empty() => parent,
+                        _ => Path::new("."),
+                    };
+                }
+                Err(e) => {
+                    log::warn!("failed to query free space of {}: {e}", probe.display());
+                    return Ok(());
+                }
+            }
+        };
+        let required = (self.size as f64 * (1.0 + margin)).ceil() as u64;
+        if available < required {
+            return Err(Error::new(ErrorKind::Io)
+                .with_path(&self.dest)
+                .with_desc_with(|| {
+                    format!(
+                        "not enough disk space for {}: {required} bytes required, \
+                         {available} available",
+                        self.dest.display()
+                    )
+                }));
+        }
+        Ok(())
+    }
+
+    /// [`fetch_to_file_limited`](Self::fetch_to_file_limited), retried
+    /// according to the configured [`RetryPolicy`]; the second half of
+    /// the pair counts the extra attempts for the [`DownloadReport`].
+    async fn fetch_to_file_retried<C: Client>(
+        &mut self,
+        client: &C,
+        url: &str,
+        progress: &impl ProgressReceiver,
+    ) -> Result<(Fetched, u32)> {
+        #[cfg(any(feature = "tokio", feature = "smol"))]
+        {
+            let Some(mut retry) = self.retry.take() else {
+                let fetched = self.fetch_to_file_limited(client, url, progress).await?;
+                return Ok((fetched, 0));
+            };
+            let mut attempt = 1;
+            loop {
+                let error = match self.fetch_to_file_limited(client, url, progress).await {
+                    Ok(fetched) => return Ok((fetched, attempt - 1)),
+                    // An expired deadline also ends the retry loop, no
+                    // matter how retryable the error itself is.
+                    Err(e)
+                        if attempt < retry.max_attempts()
+                            && retry.should_retry(&e)
+                            && self.check_deadline().is_ok() =>
+                    {
+                        e
+                    }
+                    Err(e) => return Err(e),
+                };
+                // Drop the part file so the next attempt starts afresh;
+                // the verifier is rebuilt from its builder inside
+                // `fetch_to_file`.
+                self.remove_part();
+                let delay = retry.delay(attempt);
+                log::warn!("attempt {attempt} for {url} failed, retrying in {delay:?}: {error:#}");
+                retry.notify(attempt, &error);
+                crate::runtime::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+        #[cfg(not(any(feature = "tokio", feature = "smol")))]
+        {
+            let fetched = self.fetch_to_file_limited(client, url, progress).await?;
+            Ok((fetched, 0))
+        }
+    }
+
+    /// [`fetch_to_file`](Self::fetch_to_file), enforcing the configured
+    /// time limit.
+    async fn fetch_to_file_limited<C: Client>(
+        &self,
+        client: &C,
+        url: &str,
+        progress: &impl ProgressReceiver,
+    ) -> Result<Fetched> {
+        #[cfg(any(feature = "tokio", feature = "smol"))]
+        {
+            // The remaining deadline budget bounds the attempt like a
+            // timeout, so even a stream yielding no chunks cannot outlive
+            // it.
+            let remaining = self
+                .deadline_at
+                .map(|at| at.saturating_duration_since(Instant::now()));
+            let limit = match (self.timeout, remaining) {
+                (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+                (timeout, remaining) => timeout.or(remaining),
+            };
+            if let Some(limit) = limit {
+                return crate::runtime::timeout(limit, self.fetch_to_file(client, url, progress))
+                    .await?;
+            }
+        }
+        self.fetch_to_file(client, url, progress).await
+    }
+
+    /// Fetch `url` to the destination, feeding `progress` and the verifier,
+    /// and return the verifier for the caller to check.
+    async fn fetch_to_file<C: Client>(
+        &self,
+        client: &C,
+        url: &str,
+        progress: &impl ProgressReceiver,
+    ) -> Result<Fetched> {
+        // The transfer goes to the part file, but by default an existing
+        // destination is still an error, like `File::create_new` used to
+        // make it. With a freshness check, replacing the destination is
+        // the expected workflow.
+        if !self.replaces_dest() && self.dest.symlink_metadata().is_ok() {
+            return Err(Error::from(std::io::Error::from(
+                std::io::ErrorKind::AlreadyExists,
+            ))
+            .with_desc_with(|| format!("{} already exists", self.dest.display())));
+        }
+        let condition = self.condition();
+        let part = self.part_path();
+        #[cfg(any(feature = "tokio", feature = "smol"))]
+        if self.pipelined_write {
+            return self
+                .fetch_to_file_pipelined(client, url, progress, condition.as_ref(), &part)
+                .await;
+        }
+        // Async file I/O keeps a shared runtime responsive; the blocking
+        // path stays for runtime-agnostic users. `tokio::fs` needs a tokio
+        // runtime context, so as in [`crate::runtime`], the `smol` feature
+        // opts out of it. Either way, `create` truncates a stale part file
+        // left by a crashed run.
+        #[cfg(all(feature = "tokio", not(feature = "smol")))]
+        {
+            let file = tokio::fs::File::create(&part)
+                .await
+                .map_err(Error::from)
+                .with_desc_with(|| format!("failed to create {}", part.display()))?;
+            if self.preallocate && self.size != 0 {
+                file.set_len(self.size)
+                    .await
+                    .map_err(Error::from)
+                    .with_desc_with(|| self.preallocate_failure(&part))?;
+            }
+            let mut file = tokio::io::BufWriter::with_capacity(self.write_buffer, file);
+            let fetched = self
+                .fetch_to_async_writer(client, url, &mut file, progress, condition.as_ref())
+                .await?;
+            self.trim_preallocation(&part, &fetched)?;
+            Ok(fetched)
+        }
+        #[cfg(not(all(feature = "tokio", not(feature = "smol"))))]
+        {
+            let file = File::create(&part)
+                .map_err(Error::from)
+                .with_desc_with(|| format!("failed to create {}", part.display()))?;
+            if self.preallocate && self.size != 0 {
+                file.set_len(self.size)
+                    .map_err(Error::from)
+                    .with_desc_with(|| self.preallocate_failure(&part))?;
+            }
+            let mut file = std::io::BufWriter::with_capacity(self.write_buffer, file);
+            let fetched = self
+                .fetch_to_writer(client, url, &mut file, progress, condition.as_ref())
+                .await?;
+            // Everything must be on disk before verification and the
+            // rename; the flush-on-drop of `BufWriter` would swallow the
+            // error.
+            file.flush()
+                .map_err(Error::from)
+                .with_desc_with(|| format!("failed to flush {}", part.display()))?;
+            self.trim_preallocation(&part, &fetched)?;
+            Ok(fetched)
+        }
+    }
+
+    /// The description of a failed preallocation.
+    fn preallocate_failure(&self, part: &Path) -> String {
+        format!(
+            "failed to preallocate {} bytes for {}",
+            self.size,
+            part.display()
+        )
+    }
+
+    /// Shrink the part file back to the streamed length when the stream
+    /// ended short of the preallocated size, so trailing zeros from
+    /// [`set_len`](File::set_len) never survive into the destination.
+    fn trim_preallocation(&self, part: &Path, fetched: &Fetched) -> Result<()> {
+        let Fetched::Done { len, .. } = fetched else {
+            return Ok(());
+        };
+        if !self.preallocate || self.size == 0 || *len >= self.size {
+            return Ok(());
+        }
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(part)
+            .and_then(|file| file.set_len(*len))
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to trim {}", part.display()))
+    }
+
+    /// Stream `url` into the part file through a dedicated writer task;
+    /// see [`with_pipelined_write`](Self::with_pipelined_write).
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    async fn fetch_to_file_pipelined<C: Client>(
+        &self,
+        client: &C,
+        url: &str,
+        progress: &impl ProgressReceiver,
+        condition: Option<&Condition>,
+        part: &Path,
+    ) -> Result<Fetched> {
+        /// How many chunks may queue between the socket and the disk.
+        const PIPE_DEPTH: usize = 16;
+
+        let file = File::create(part)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to create {}", part.display()))?;
+        if self.preallocate && self.size != 0 {
+            file.set_len(self.size)
+                .map_err(Error::from)
+                .with_desc_with(|| self.preallocate_failure(part))?;
+        }
+        let mut file = std::io::BufWriter::with_capacity(self.write_buffer, file);
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<Bytes>(PIPE_DEPTH);
+        let part_name = part.display().to_string();
+        let worker = crate::runtime::spawn_blocking(move || -> Result<()> {
+            for chunk in receiver {
+                file.write_all(&chunk)
+                    .map_err(Error::from)
+                    .with_desc("failed to write the downloaded data")?;
+            }
+            file.flush()
+                .map_err(Error::from)
+                .with_desc_with(|| format!("failed to flush {part_name}"))?;
+            Ok(())
+        });
+        // Both sides must be polled together: the worker future submits
+        // the closure to the pool on its first poll, and the streaming
+        // loop blocks on a full channel until the worker drains it.
+        // Dropping the pipe closes the channel, letting the worker drain
+        // the queue, flush and finish.
+        let (wrote, fetched) = futures_util::future::join(worker, async {
+            let mut pipe = PipeWriter { sender };
+            self.fetch_to_writer(client, url, &mut pipe, progress, condition)
+                .await
+        })
+        .await;
+        // The worker's I/O error is the root cause when the pipe broke
+        // mid-transfer, so it takes precedence.
+        wrote?;
+        let fetched = fetched?;
+        self.trim_preallocation(part, &fetched)?;
+        Ok(fetched)
+    }
+
+    /// Whether this download may replace an existing destination file.
+    fn replaces_dest(&self) -> bool {
+        self.overwrite != OverwritePolicy::Error || self.etag_cache || self.mtime_check
+    }
+
+    /// The precondition for the GET request, when a freshness check is
+    /// enabled and the local copy provides one.
+    fn condition(&self) -> Option<Condition> {
+        if let Some(etag) = self.cached_etag() {
+            return Some(Condition::IfNoneMatch(etag));
+        }
+        if self.mtime_check {
+            if let Ok(mtime) = self.dest.metadata().and_then(|m| m.modified()) {
+                return Some(Condition::IfModifiedSince(crate::http::http_date(mtime)));
+            }
+        }
+        None
+    }
+
+    /// The sanitized file name announced by the response
+    /// `Content-Disposition`, only consulted in directory-destination
+    /// mode; see [`new_in_dir`](Self::new_in_dir).
+    fn response_file_name(&self, response: &impl Response) -> Option<String> {
+        self.dest_dir.as_ref()?;
+        response
+            .content_disposition()
+            .as_deref()
+            .and_then(content_disposition_file_name)
+    }
+
+    /// Stream `url` into `writer`, feeding `progress` and the verifier,
+    /// and return the verifier for the caller to check.
+    ///
+    /// With a `condition`, the request is conditional and a
+    /// `304 Not Modified` answer short-circuits without streaming.
+    async fn fetch_to_writer<C: Client>(
+        &self,
+        client: &C,
+        url: &str,
+        writer: &mut impl Write,
+        progress: &impl ProgressReceiver,
+        condition: Option<&Condition>,
+    ) -> Result<Fetched> {
+        let response = request(client, url, &self.headers, condition)
+            .await
+            .with_desc_with(|| format!("failed to fetch {url}"))?;
+        if response.status() == 304 {
+            return Ok(Fetched::NotModified);
+        }
+        if !response.is_decoded() {
+            self.check_content_length(response.content_length())?;
+        }
+        let etag = if self.etag_cache {
+            response.etag()
+        } else {
+            None
+        };
+        let filename = self.response_file_name(&response);
+
+        let mut verifier = match self.verification {
+            // The after-download pass re-reads the finished file instead.
+            VerificationMode::AfterDownload => None,
+            VerificationMode::Streaming => self.build_verifier(&response)?,
+        };
+        progress.set_message(url);
+        // When the expected size is unknown, the response headers may still
+        // announce one.
+        if self.size == 0 {
+            if let Some(len) = response.content_length() {
+                progress.set_total(len);
+            }
+        }
+
+        let mut gauge = self
+            .min_speed
+            .map(|(min, window)| SpeedGauge::new(min, window));
+        #[cfg(any(feature = "tokio", feature = "smol"))]
+        let mut limiter = self.max_speed.map(Pacer::new);
+        let mut stream = response.bytes_stream();
+        let mut position = 0u64;
+        loop {
+            let paused = self.wait_if_paused().await;
+            if let Some(gauge) = &mut gauge {
+                gauge.suspend_for(paused);
+            }
+            let Some(chunk) = self
+                .next_chunk(&mut stream)
+                .await
+                .with_desc_with(|| format!("failed to fetch {url}"))?
+            else {
+                break;
+            };
+            writer
+                .write_all(&chunk)
+                .map_err(Error::from)
+                .with_desc("failed to write the downloaded data")?;
+            position += chunk.len() as u64;
+            if let Some(gauge) = &mut gauge {
+                gauge.record(chunk.len() as u64)?;
+            }
+            self.check_deadline()?;
+            self.check_cancelled()?;
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            if let Some(limiter) = &mut limiter {
+                limiter.pace(chunk.len() as u64).await;
+            }
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            if let Some(shared) = &self.rate_limiter {
+                shared.acquire(chunk.len() as u64).await;
+            }
+            if let Some(verifier) = &mut verifier {
+                verifier.update_bytes(chunk);
+            }
+            progress.set_position(position);
+        }
+        Ok(Fetched::Done {
+            verifier,
+            etag,
+            len: position,
+            filename,
+        })
+    }
+
+    /// Stream `url` into an async `writer`, feeding `progress` and the
+    /// verifier, and return the verifier for the caller to check.
+    ///
+    /// With a `condition`, the request is conditional and a
+    /// `304 Not Modified` answer short-circuits without streaming.
+    #[cfg(feature = "tokio")]
+    async fn fetch_to_async_writer<C, W>(
+        &self,
+        client: &C,
+        url: &str,
+        writer: &mut W,
+        progress: &impl ProgressReceiver,
+        condition: Option<&Condition>,
+    ) -> Result<Fetched>
+    where
+        C: Client,
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let response = request(client, url, &self.headers, condition)
+            .await
+            .with_desc_with(|| format!("failed to fetch {url}"))?;
+        if response.status() == 304 {
+            return Ok(Fetched::NotModified);
+        }
+        if !response.is_decoded() {
+            self.check_content_length(response.content_length())?;
+        }
+        let etag = if self.etag_cache {
+            response.etag()
+        } else {
+            None
+        };
+        let filename = self.response_file_name(&response);
+
+        let mut verifier = match self.verification {
+            // The after-download pass re-reads the finished file instead.
+            VerificationMode::AfterDownload => None,
+            VerificationMode::Streaming => self.build_verifier(&response)?,
+        };
+        progress.set_message(url);
+        if self.size == 0 {
+            if let Some(len) = response.content_length() {
+                progress.set_total(len);
+            }
+        }
+
+        let mut gauge = self
+            .min_speed
+            .map(|(min, window)| SpeedGauge::new(min, window));
+        #[cfg(any(feature = "tokio", feature = "smol"))]
+        let mut limiter = self.max_speed.map(Pacer::new);
+        let mut stream = response.bytes_stream();
+        let mut position = 0u64;
+        loop {
+            let paused = self.wait_if_paused().await;
+            if let Some(gauge) = &mut gauge {
+                gauge.suspend_for(paused);
+            }
+            let Some(chunk) = self
+                .next_chunk(&mut stream)
+                .await
+                .with_desc_with(|| format!("failed to fetch {url}"))?
+            else {
+                break;
+            };
+            writer
+                .write_all(&chunk)
+                .await
+                .map_err(Error::from)
+                .with_desc("failed to write the downloaded data")?;
+            position += chunk.len() as u64;
+            if let Some(gauge) = &mut gauge {
+                gauge.record(chunk.len() as u64)?;
+            }
+            self.check_deadline()?;
+            self.check_cancelled()?;
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            if let Some(limiter) = &mut limiter {
+                limiter.pace(chunk.len() as u64).await;
+            }
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            if let Some(shared) = &self.rate_limiter {
+                shared.acquire(chunk.len() as u64).await;
+            }
+            if let Some(verifier) = &mut verifier {
+                verifier.update_bytes(chunk);
+            }
+            progress.set_position(position);
+        }
+        writer
+            .flush()
+            .await
+            .map_err(Error::from)
+            .with_desc("failed to flush the downloaded data")?;
+        Ok(Fetched::Done {
+            verifier,
+            etag,
+            len: position,
+            filename,
+        })
+    }
+
+    /// Stream `url` into memory, feeding `progress` and the verifier, and
+    /// return the buffer with the verifier for the caller to check.
+    async fn fetch_to_bytes<C: Client>(
+        &self,
+        client: &C,
+        url: &str,
+        progress: &impl ProgressReceiver,
+    ) -> Result<(BytesMut, Option<Box<dyn DynVerifier>>)> {
+        let response = request(client, url, &self.headers, None)
+            .await
+            .with_desc_with(|| format!("failed to fetch {url}"))?;
+        if !response.is_decoded() {
+            self.check_content_length(response.content_length())?;
+        }
+
+        let mut verifier = self.build_verifier(&response)?;
+        progress.set_message(url);
+        if self.size == 0 {
+            if let Some(len) = response.content_length() {
+                progress.set_total(len);
+            }
+        }
+        // Refuse an announced oversized response before buffering any of
+        // it; unannounced ones are caught as the buffer grows.
+        if let Some(len) = response.content_length() {
+            if len > self.memory_cap {
+                return Err(self.over_memory_cap());
+            }
+        }
+
+        let mut gauge = self
+            .min_speed
+            .map(|(min, window)| SpeedGauge::new(min, window));
+        #[cfg(any(feature = "tokio", feature = "smol"))]
+        let mut limiter = self.max_speed.map(Pacer::new);
+        let mut buffer = BytesMut::with_capacity(self.size.min(self.memory_cap) as usize);
+        let mut stream = response.bytes_stream();
+        loop {
+            let paused = self.wait_if_paused().await;
+            if let Some(gauge) = &mut gauge {
+                gauge.suspend_for(paused);
+            }
+            let Some(chunk) = self
+                .next_chunk(&mut stream)
+                .await
+                .with_desc_with(|| format!("failed to fetch {url}"))?
+            else {
+                break;
+            };
+            if (buffer.len() + chunk.len()) as u64 > self.memory_cap {
+                return Err(self.over_memory_cap());
+            }
+            if let Some(gauge) = &mut gauge {
+                gauge.record(chunk.len() as u64)?;
+            }
+            self.check_deadline()?;
+            self.check_cancelled()?;
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            if let Some(limiter) = &mut limiter {
+                limiter.pace(chunk.len() as u64).await;
+            }
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            if let Some(shared) = &self.rate_limiter {
+                shared.acquire(chunk.len() as u64).await;
+            }
+            buffer.extend_from_slice(&chunk);
+            if let Some(verifier) = &mut verifier {
+                verifier.update_bytes(chunk);
+            }
+            progress.set_position(buffer.len() as u64);
+        }
+        Ok((buffer, verifier))
+    }
+
+    /// Park while the attached controller is paused, returning the time
+    /// spent parked so time-based checks can ignore it.
+    async fn wait_if_paused(&self) -> Duration {
+        match &self.controller {
+            Some(controller) if controller.state() == DownloadState::Paused => {
+                let parked = Instant::now();
+                controller.wait_resumed().await;
+                parked.elapsed()
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// The next chunk of the body, bounded by the idle timeout when one
+    /// is configured.
+    async fn next_chunk<S>(&self, stream: &mut S) -> Result<Option<Bytes>>
+    where
+        S: Stream<Item = Result<Bytes>> + Send + Unpin,
+    {
+        #[cfg(any(feature = "tokio", feature = "smol"))]
+        if let Some(idle) = self.idle_timeout {
+            return match crate::runtime::timeout(idle, stream.next()).await {
+                Ok(item) => item.transpose(),
+                Err(e) => Err(e.with_desc_with(|| format!("no data received for {idle:?}"))),
+            };
+        }
+        stream.next().await.transpose()
+    }
+
+    fn over_memory_cap(&self) -> Error {
+        Error::new(ErrorKind::Other)
+            .with_desc_with(|| format!("response exceeds the memory cap of {} bytes", self.memory_cap))
+    }
+
+    /// The sibling the transfer is written to before the final rename.
+    fn part_path(&self) -> PathBuf {
+        let mut name = self.dest.as_os_str().to_owned();
+        name.push(&self.part_suffix);
+        PathBuf::from(name)
+    }
+
+    /// The sidecar recording the `ETag` of the destination file.
+    fn etag_path(&self) -> PathBuf {
+        let mut name = self.dest.as_os_str().to_owned();
+        name.push(".etag");
+        PathBuf::from(name)
+    }
+
+    /// The recorded `ETag` for the current destination file, if any.
+    ///
+    /// A sidecar without a destination is stale — the file was removed —
+    /// so it does not suppress the download.
+    fn cached_etag(&self) -> Option<String> {
+        if !self.etag_cache || !self.dest.exists() {
+            return None;
+        }
+        let etag = std::fs::read_to_string(self.etag_path()).ok()?;
+        let etag = etag.trim();
+        (!etag.is_empty()).then(|| etag.to_owned())
+    }
+
+    /// Record the `ETag` of a completed download next to the destination.
+    ///
+    /// Best-effort: a failed write only costs a future re-download. A
+    /// server that stopped sending ETags clears the sidecar.
+    fn store_etag(&self, etag: Option<&str>) {
+        if !self.etag_cache {
+            return;
+        }
+        let path = self.etag_path();
+        let result = match etag {
+            Some(etag) => std::fs::write(&path, etag),
+            None => match std::fs::remove_file(&path) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                other => other,
+            },
+        };
+        if let Err(e) = result {
+            log::warn!("failed to record the etag at {}: {e}", path.display());
+        }
+    }
+
+    /// Point the download at `name` inside the destination directory,
+    /// after the response announced the real file name; see
+    /// [`new_in_dir`](Self::new_in_dir).
+    ///
+    /// The part file moves along, so the later
+    /// [`commit_part`](Self::commit_part) and failure cleanup see a
+    /// consistent pair of paths.
+    fn retarget_dest(&mut self, name: &str) -> Result<()> {
+        let Some(dir) = &self.dest_dir else {
+            return Ok(());
+        };
+        let dest = dir.join(name);
+        if dest == self.dest {
+            return Ok(());
+        }
+        // The existing-destination check in `fetch_to_file` saw the
+        // URL-derived name; the renamed target deserves the same guard.
+        if !self.replaces_dest() && dest.symlink_metadata().is_ok() {
+            return Err(Error::from(std::io::Error::from(
+                std::io::ErrorKind::AlreadyExists,
+            ))
+            .with_desc_with(|| format!("{} already exists", dest.display())));
+        }
+        let part = self.part_path();
+        let old_dest = std::mem::replace(&mut self.dest, dest);
+        if let Err(e) = std::fs::rename(&part, self.part_path()) {
+            // Leave the download where it was, so cleanup still finds it.
+            self.dest = old_dest;
+            return Err(Error::from(e)
+                .with_desc_with(|| format!("failed to rename {}", part.display())));
+        }
+        Ok(())
+    }
+
+    /// Move the completed part file to the destination.
+    fn commit_part(&self) -> Result<()> {
+        let part = self.part_path();
+        // The writer is gone by now, so durability means reopening the
+        // part file; syncing before the rename makes sure the destination
+        // never names a truncated file.
+        if self.sync {
+            File::open(&part)
+                .and_then(|file| file.sync_all())
+                .map_err(Error::from)
+                .with_desc_with(|| format!("failed to sync {}", part.display()))?;
+        }
+        // `rename` does not replace an existing file on every platform.
+        if self.replaces_dest() {
+            if let Err(e) = std::fs::remove_file(&self.dest) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    return Err(Error::from(e).with_desc_with(|| {
+                        format!("failed to replace {}", self.dest.display())
+                    }));
+                }
+            }
+        }
+        // The part file is a sibling of the destination, but overlay and
+        // union filesystems can still refuse the rename with `EXDEV`; fall
+        // back to copy-and-remove there, giving up the atomicity.
+        let moved = std::fs::rename(&part, &self.dest).or_else(|e| {
+            if e.kind() != std::io::ErrorKind::CrossesDevices {
+                return Err(e);
+            }
+            std::fs::copy(&part, &self.dest)?;
+            std::fs::remove_file(&part)
+        });
+        moved.map_err(Error::from).with_desc_with(|| {
+            format!("failed to move the download to {}", self.dest.display())
+        })?;
+        // The rename itself is metadata; on unix the directory holding it
+        // needs its own sync to survive a power loss.
+        #[cfg(unix)]
+        if self.sync {
+            if let Some(dir) = self.dest.parent() {
+                File::open(dir)
+                    .and_then(|file| file.sync_all())
+                    .map_err(Error::from)
+                    .with_desc_with(|| format!("failed to sync {}", dir.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Clean up the part file after a failure, unless
+    /// [`with_keep_partial`](Self::with_keep_partial) asked for it to
+    /// survive.
+    fn discard_part(&self) {
+        if self.keep_partial {
+            log::debug!("keeping the partial file {}", self.part_path().display());
+            return;
+        }
+        self.remove_part();
+    }
+
+    /// Remove the part file unconditionally; a missing one is fine, since
+    /// not every failure gets as far as creating it.
+    fn remove_part(&self) {
+        let part = self.part_path();
+        if let Err(e) = std::fs::remove_file(&part) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("failed to remove {}: {e}", part.display());
+            }
+        }
+    }
+}
+
+/// Join a mirror base URL with a path relative to it; see
+/// [`MirrorOptions::from_bases`].
+fn expand_mirror_base(base: &str, path: &str) -> String {
+    let path = path.strip_prefix('/').unwrap_or(path);
+    if base.contains("{path}") {
+        return base.replace("{path}", path);
+    }
+    let (prefix, query) = match base.split_once('?') {
+        Some((prefix, query)) => (prefix, Some(query)),
+        None => (base, None),
+    };
+    let mut url = String::with_capacity(base.len() + path.len() + 1);
+    url.push_str(prefix.strip_suffix('/').unwrap_or(prefix));
+    if !path.is_empty() {
+        url.push('/');
+        url.push_str(path);
+    }
+    if let Some(query) = query {
+        url.push('?');
+        url.push_str(query);
+    }
+    url
+}
+
+/// Options for downloading from a set of mirrors.
+///
+/// Mirror URLs are alternative, fully expanded URLs serving the same file
+/// (see [`from_bases`](Self::from_bases) for assembling them from common
+/// roots). Before the download starts, every mirror (and the primary URL)
+/// is probed and the one answering fastest is used.
+pub struct MirrorOptions {
+    mirrors: Vec<String>,
+    #[allow(clippy::type_complexity)]
+    error_handler: Option<Box<dyn FnMut(&str, &Error) + Send + Sync>>,
+}
+
+impl MirrorOptions {
+    /// Create mirror options from a list of alternative URLs.
+    pub fn new(mirrors: &[impl AsRef<str>]) -> Self {
+        Self {
+            mirrors: mirrors.iter().map(|m| m.as_ref().to_owned()).collect(),
+            error_handler: None,
+        }
+    }
+
+    /// Create mirror options from base URLs plus a path relative to them.
+    ///
+    /// For mirror sets differing only in host or prefix, this joins every
+    /// base with `path` so the same base slice serves many files without
+    /// assembling full URLs per download:
+    ///
+    /// ```
+    /// # use fetchkit::download::MirrorOptions;
+    /// let bases = ["https://mirror-a.example.com/pub/", "https://mirror-b.example.com/pub"];
+    /// let mirrors = MirrorOptions::from_bases(&bases, "tool/tool-1.0.tar.gz");
+    /// ```
+    ///
+    /// The slash between base and path is inserted or deduplicated as
+    /// needed, and a query string on the base stays at the end — the path
+    /// is joined in front of the `?`. A base containing a literal
+    /// `{path}` placeholder substitutes the path there instead, for
+    /// mirrors that carry the file path in the middle of the URL or in a
+    /// query parameter.
+    pub fn from_bases(bases: &[impl AsRef<str>], path: &str) -> Self {
+        let mirrors: Vec<String> = bases
+            .iter()
+            .map(|base| expand_mirror_base(base.as_ref(), path))
+            .collect();
+        Self::new(&mirrors)
+    }
+
+    /// Set a handler called with the mirror URL and the error when probing a
+    /// mirror fails. By default failures are only logged.
+    pub fn on_error(mut self, handler: impl FnMut(&str, &Error) + Send + Sync + 'static) -> Self {
+        self.error_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Probe the primary URL and every mirror, returning the fastest one.
+    ///
+    /// A probe issues a GET request and measures the time until the first
+    /// body chunk arrives. Mirrors that fail to answer are skipped; if no
+    /// candidate answers, the last error is returned.
+    async fn select<C: Client>(
+        &mut self,
+        client: &C,
+        primary: &str,
+        headers: &[(String, String)],
+        cancel: Option<&CancelToken>,
+    ) -> Result<String> {
+        let Self {
+            mirrors,
+            error_handler,
+        } = self;
+        let mut best: Option<(&str, Duration)> = None;
+        let mut last_error = None;
+        for candidate in std::iter::once(primary).chain(mirrors.iter().map(String::as_str)) {
+            // Probing the remaining candidates would only delay the
+            // cancellation.
+            if cancel.is_some_and(CancelToken::is_cancelled) {
+                return Err(cancelled());
+            }
+            match Self::probe(client, candidate, headers).await {
+                Ok(elapsed) => {
+                    log::debug!("mirror {candidate} answered in {elapsed:?}");
+                    if best.is_none_or(|(_, best_elapsed)| elapsed < best_elapsed) {
+                        best = Some((candidate, elapsed));
+                    }
+                }
+                Err(e) => {
+                    log::warn!("mirror {candidate} failed: {e:#}");
+                    if let Some(handler) = error_handler.as_mut() {
+                        handler(candidate, &e);
+                    }
+                    // A candidate-specific failure — a dead host, a file
+                    // missing from one mirror — disqualifies only this
+                    // candidate, but a permanent error like a rejected
+                    // authorization would fail on every mirror the same
+                    // way, so stop instead of hammering the rest.
+                    if !e.is_retryable() && !e.is_not_found() {
+                        return Err(e);
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+        match best {
+            Some((url, _)) => Ok(url.to_owned()),
+            None => Err(last_error.expect("at least one candidate was probed")),
+        }
+    }
+
+    pub(crate) async fn probe<C: Client>(
+        client: &C,
+        url: &str,
+        headers: &[(String, String)],
+    ) -> Result<Duration> {
+        let start = Instant::now();
+        let response = request(client, url, headers, None).await?;
+        let mut stream = response.bytes_stream();
+        // An empty body is fine, only failures disqualify the mirror.
+        if let Some(chunk) = stream.next().await {
+            chunk?;
+        }
+        Ok(start.elapsed())
+    }
+}
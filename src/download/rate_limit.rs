@@ -0,0 +1,92 @@
+//! A bandwidth cap shared by concurrent downloads.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A shared cap on the combined throughput of several downloads.
+///
+/// Where [`with_max_speed`](super::DownloadBuilder::with_max_speed) paces
+/// one transfer, a `RateLimiter` is cloned into any number of builders via
+/// [`with_rate_limiter`](super::DownloadBuilder::with_rate_limiter) and
+/// meters their bytes against a single budget, so ten parallel downloads
+/// together stay under one cap. Clones share the budget; independent
+/// limiters do not.
+///
+/// The limiter schedules by virtual time: each chunk reserves the next
+/// slice of the shared timeline and the download sleeps until its slice
+/// starts. Reservations are first-come-first-served, so a busy stream
+/// cannot starve the others — everyone queues for the same clock. Idle
+/// time is not banked: after a quiet period the next chunks pass
+/// immediately, but no burst credit beyond that accumulates.
+///
+/// Like the other timer-based options, waiting needs the `smol` or
+/// `tokio` feature (see the crate docs for which backend serves which
+/// executors).
+#[derive(Clone)]
+pub struct RateLimiter {
+    inner: Arc<Mutex<Timeline>>,
+}
+
+/// The shared virtual clock: the instant the budget is consumed up to.
+struct Timeline {
+    bytes_per_sec: u64,
+    reserved_until: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter capping the combined rate at `bytes_per_sec`.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Timeline {
+                bytes_per_sec: bytes_per_sec.max(1),
+                reserved_until: Instant::now(),
+            })),
+        }
+    }
+
+    /// Reserve the next `bytes` of the shared budget and wait for the
+    /// reservation to start.
+    pub(super) async fn acquire(&self, bytes: u64) {
+        let wait = {
+            let mut timeline = self.inner.lock().unwrap();
+            let now = Instant::now();
+            // A reservation in the past means the link was idle; the
+            // budget restarts from now instead of paying out the gap.
+            let start = timeline.reserved_until.max(now);
+            let cost = Duration::from_secs_f64(bytes as f64 / timeline.bytes_per_sec as f64);
+            timeline.reserved_until = start + cost;
+            start - now
+        };
+        if !wait.is_zero() {
+            crate::runtime::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn idle_time_is_not_banked() {
+        let limiter = RateLimiter::new(1000);
+        limiter.inner.lock().unwrap().reserved_until = Instant::now() - Duration::from_secs(60);
+        let started = Instant::now();
+        // A full second of budget, but no minute of credit to burn it
+        // against: the reservation starts from now and passes at once.
+        limiter.acquire(1000).await;
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn reservations_space_out_over_time() {
+        let limiter = RateLimiter::new(1_000_000);
+        let started = Instant::now();
+        // 3 reservations of half a second's budget each: the first passes
+        // immediately, the rest queue behind it.
+        for _ in 0..3 {
+            limiter.acquire(500_000).await;
+        }
+        assert!(started.elapsed() >= Duration::from_millis(900));
+    }
+}
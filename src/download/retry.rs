@@ -0,0 +1,160 @@
+//! Retrying downloads that fail transiently.
+//!
+//! A [`RetryPolicy`] attached via
+//! [`DownloadBuilder::with_retry`](super::DownloadBuilder::with_retry)
+//! re-runs the transfer when it fails with a
+//! [retryable](crate::Error::is_retryable) error: the partial destination
+//! file is removed, the verifier is rebuilt from its builder, and the
+//! download starts over after an exponentially growing delay.
+//! Non-retryable failures — verification mismatches, local I/O errors, an
+//! already existing destination — surface immediately. The verdict can be
+//! overridden per policy with [`RetryPolicy::retry_if`].
+
+use std::time::Duration;
+
+use crate::error::Error;
+
+/// The longest delay between attempts, regardless of the multiplier.
+const MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// How transient download failures are retried.
+///
+/// The delay before attempt `n + 1` is `base_delay * multiplier^(n - 1)`,
+/// capped at five minutes. With jitter enabled, each delay is additionally
+/// scaled by a random factor in `[0.5, 1.5)` so parallel downloads do not
+/// retry in lockstep.
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    multiplier: f64,
+    jitter: bool,
+    #[allow(clippy::type_complexity)]
+    on_retry: Option<Box<dyn FnMut(u32, &Error) + Send + Sync>>,
+    #[allow(clippy::type_complexity)]
+    classify: Option<Box<dyn Fn(&Error) -> bool + Send + Sync>>,
+}
+
+impl RetryPolicy {
+    /// Create a policy making at most `max_attempts` attempts in total,
+    /// with a base delay of 500 ms doubling per attempt and no jitter.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay: Duration::from_millis(500),
+            multiplier: 2.0,
+            jitter: false,
+            on_retry: None,
+            classify: None,
+        }
+    }
+
+    /// Set the delay before the first retry.
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Set the factor the delay grows by per attempt.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Scale each delay by a random factor in `[0.5, 1.5)`.
+    pub fn with_jitter(mut self) -> Self {
+        self.jitter = true;
+        self
+    }
+
+    /// Set a handler called with the attempt number and the error before
+    /// each retry. By default failed attempts are only logged.
+    pub fn on_retry(mut self, handler: impl FnMut(u32, &Error) + Send + Sync + 'static) -> Self {
+        self.on_retry = Some(Box::new(handler));
+        self
+    }
+
+    /// Replace the default retryability heuristic with `classifier`.
+    ///
+    /// By default an attempt is retried when the error passes
+    /// [`Error::is_retryable`]; a classifier overrides that verdict
+    /// entirely, so it can e.g. retry 404s from an eventually consistent
+    /// CDN, or give up on anything but timeouts.
+    pub fn retry_if(mut self, classifier: impl Fn(&Error) -> bool + Send + Sync + 'static) -> Self {
+        self.classify = Some(Box::new(classifier));
+        self
+    }
+
+    pub(super) fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Whether a failed attempt should be retried, per the configured
+    /// classifier or the default heuristic.
+    pub(super) fn should_retry(&self, error: &Error) -> bool {
+        match &self.classify {
+            Some(classifier) => classifier(error),
+            None => error.is_retryable(),
+        }
+    }
+
+    /// The delay after the failed `attempt` (counting from 1).
+    pub(super) fn delay(&self, attempt: u32) -> Duration {
+        let secs = self.base_delay.as_secs_f64() * self.multiplier.powi(attempt as i32 - 1);
+        let mut delay = Duration::try_from_secs_f64(secs)
+            .unwrap_or(MAX_DELAY)
+            .min(MAX_DELAY);
+        if self.jitter {
+            delay = delay.mul_f64(jitter_factor());
+        }
+        delay
+    }
+
+    pub(super) fn notify(&mut self, attempt: u32, error: &Error) {
+        if let Some(handler) = &mut self.on_retry {
+            handler(attempt, error);
+        }
+    }
+}
+
+/// A factor in `[0.5, 1.5)` from the sub-second clock; enough entropy to
+/// spread retry herds without pulling in a random number generator.
+fn jitter_factor() -> f64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    0.5 + f64::from(nanos) / 1e9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_grow_exponentially() {
+        let policy = RetryPolicy::new(5)
+            .with_base_delay(Duration::from_millis(100))
+            .with_multiplier(3.0);
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(300));
+        assert_eq!(policy.delay(3), Duration::from_millis(900));
+    }
+
+    #[test]
+    fn delays_are_capped() {
+        let policy = RetryPolicy::new(100).with_base_delay(Duration::from_secs(60));
+        assert_eq!(policy.delay(50), MAX_DELAY);
+    }
+
+    #[test]
+    fn jitter_stays_in_range() {
+        let policy = RetryPolicy::new(2)
+            .with_base_delay(Duration::from_secs(1))
+            .with_jitter();
+        for _ in 0..32 {
+            let delay = policy.delay(1);
+            assert!(delay >= Duration::from_millis(500), "{delay:?}");
+            assert!(delay < Duration::from_millis(1500), "{delay:?}");
+        }
+    }
+}
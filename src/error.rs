@@ -0,0 +1,913 @@
+//! Error types used across the crate.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A specialized [`Result`] type for fetchkit operations.
+///
+/// [`Result`]: std::result::Result
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The category of an [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A local I/O operation failed.
+    Io,
+    /// A network operation failed.
+    Network,
+    /// Downloaded or local content failed verification.
+    Verify,
+    /// An archive could not be extracted.
+    Extract,
+    /// An operation did not complete in time.
+    Timeout,
+    /// The operation was cancelled by the caller.
+    Cancelled,
+    /// The operation would need the network, but offline mode is active.
+    Offline,
+    /// Any error that does not fit the other categories.
+    Other,
+}
+
+impl ErrorKind {
+    /// The stable lowercase identifier used in serialized reports.
+    #[cfg(feature = "serde")]
+    fn identifier(self) -> &'static str {
+        match self {
+            ErrorKind::Io => "io",
+            ErrorKind::Network => "network",
+            ErrorKind::Verify => "verify",
+            ErrorKind::Extract => "extract",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Offline => "offline",
+            ErrorKind::Other => "other",
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::Io => "IO error",
+            ErrorKind::Network => "Network error",
+            ErrorKind::Verify => "Verification error",
+            ErrorKind::Extract => "Extraction error",
+            ErrorKind::Timeout => "Timeout error",
+            ErrorKind::Cancelled => "Cancelled",
+            ErrorKind::Offline => "Offline",
+            ErrorKind::Other => "Error",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Structured details of a verification failure.
+///
+/// Attached with [`Error::with_verify_details`] by the built-in verifiers,
+/// so programs can react to the expected and actual values (e.g. offer to
+/// delete a stale file) without parsing prose. The Display implementation
+/// renders the human-readable form used in error descriptions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VerifyDetails {
+    /// The content size differed from the expected size.
+    Size {
+        /// The expected size in bytes.
+        expected: u64,
+        /// The actual size in bytes.
+        actual: u64,
+    },
+    /// The content digest matched none of the expected digests.
+    Hash {
+        /// The algorithm name, when the verifier knows it.
+        algorithm: Option<String>,
+        /// The expected (candidate) digests.
+        expected: Vec<Vec<u8>>,
+        /// The computed digest.
+        actual: Vec<u8>,
+    },
+    /// A signature did not verify.
+    Signature {
+        /// The ID of the key involved, when known.
+        key_id: Option<String>,
+        /// A human-readable reason.
+        reason: String,
+    },
+}
+
+impl fmt::Display for VerifyDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyDetails::Size { expected, actual } => write!(
+                f,
+                "size mismatch: expected {expected} bytes, got {actual} bytes"
+            ),
+            VerifyDetails::Hash {
+                expected, actual, ..
+            } => {
+                if let [expected] = expected.as_slice() {
+                    write!(
+                        f,
+                        "digest mismatch: expected {}, got {}",
+                        hex::encode(expected),
+                        hex::encode(actual)
+                    )
+                } else {
+                    let expected: Vec<_> = expected.iter().map(hex::encode).collect();
+                    write!(
+                        f,
+                        "digest mismatch: expected one of [{}], got {}",
+                        expected.join(", "),
+                        hex::encode(actual)
+                    )
+                }
+            }
+            VerifyDetails::Signature { reason, .. } => f.write_str(reason),
+        }
+    }
+}
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The error type of this crate.
+///
+/// An error always carries an [`ErrorKind`], and optionally a human readable
+/// description and a source error. Descriptions can be attached with
+/// [`WithDesc::with_desc`], which is also implemented for [`Result`] for
+/// convenient use in call chains.
+pub struct Error {
+    kind: ErrorKind,
+    /// Context descriptions, innermost first; each wrapping layer pushes
+    /// its own.
+    desc: Vec<Cow<'static, str>>,
+    source: Option<BoxedError>,
+    class: Class,
+    url: Option<String>,
+    path: Option<PathBuf>,
+    // Boxed to keep `Error` (and every `Result`) small.
+    verify_details: Option<Box<VerifyDetails>>,
+}
+
+/// Machine-readable classification flags, recorded when the error is
+/// constructed (e.g. in the `From` conversions).
+#[derive(Debug, Clone, Copy, Default)]
+struct Class {
+    timeout: bool,
+    connect: bool,
+    not_found: bool,
+    status: Option<u16>,
+}
+
+impl Error {
+    /// Create a new error of the given kind without further details.
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            desc: Vec::new(),
+            source: None,
+            class: Class::default(),
+            url: None,
+            path: None,
+            verify_details: None,
+        }
+    }
+
+    /// Attach a source error.
+    pub fn with_source(mut self, source: impl Into<BoxedError>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// The kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The outermost (most recently attached) description, if any.
+    pub fn description(&self) -> Option<&str> {
+        self.desc.last().map(|d| d.as_ref())
+    }
+
+    /// All attached descriptions, outermost first.
+    ///
+    /// Each call to [`WithDesc::with_desc`] adds a layer; the full narrative
+    /// (outer context down to the most specific detail) is rendered in this
+    /// order by Display.
+    pub fn descriptions(&self) -> impl Iterator<Item = &str> {
+        self.desc.iter().rev().map(|d| d.as_ref())
+    }
+
+    /// The descriptions joined outermost-first with `": "`, or `None`.
+    fn rendered_desc(&self) -> Option<String> {
+        if self.desc.is_empty() {
+            return None;
+        }
+        Some(
+            self.descriptions()
+                .collect::<Vec<_>>()
+                .join(": "),
+        )
+    }
+
+    /// Mark this error as a timeout; see [`is_timeout`](Self::is_timeout).
+    ///
+    /// The built-in conversions record this automatically; custom [`Client`]
+    /// backends should mark their errors so retry logic can classify them.
+    ///
+    /// [`Client`]: crate::http::Client
+    pub fn mark_timeout(mut self) -> Self {
+        self.class.timeout = true;
+        self
+    }
+
+    /// Mark this error as a connection failure; see
+    /// [`is_connect`](Self::is_connect).
+    pub fn mark_connect(mut self) -> Self {
+        self.class.connect = true;
+        self
+    }
+
+    /// Mark this error as "not found"; see
+    /// [`is_not_found`](Self::is_not_found).
+    pub fn mark_not_found(mut self) -> Self {
+        self.class.not_found = true;
+        self
+    }
+
+    /// Attach the URL the failed operation was working on.
+    ///
+    /// Shown in the Display output, so aggregated reports from parallel
+    /// downloads stay unambiguous.
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// The URL the failed operation was working on, if recorded.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Attach the local file path the failed operation was working on.
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// The local file path the failed operation was working on, if
+    /// recorded.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Attach structured verification failure details.
+    ///
+    /// The rendered form of the details is also pushed as a description
+    /// layer, so Display stays consistent with the structured data.
+    pub fn with_verify_details(mut self, details: VerifyDetails) -> Self {
+        self.desc.push(details.to_string().into());
+        self.verify_details = Some(Box::new(details));
+        self
+    }
+
+    /// The structured verification failure details, if any.
+    pub fn verify_details(&self) -> Option<&VerifyDetails> {
+        self.verify_details.as_deref()
+    }
+
+    /// Attach the HTTP status code the server answered with.
+    ///
+    /// A `404` also marks the error as not found. Backends should record
+    /// the status whenever one is known, so callers can branch on it (e.g.
+    /// back off on 429, drop a mirror on 403).
+    pub fn with_http_status(mut self, status: u16) -> Self {
+        self.class.status = Some(status);
+        if status == 404 {
+            self.class.not_found = true;
+        }
+        self
+    }
+
+    /// The HTTP status code the server answered with, if known.
+    pub fn http_status(&self) -> Option<u16> {
+        self.class.status
+    }
+
+    /// Whether the operation timed out.
+    pub fn is_timeout(&self) -> bool {
+        self.class.timeout
+    }
+
+    /// Whether a connection could not be established (refused, reset or
+    /// aborted).
+    pub fn is_connect(&self) -> bool {
+        self.class.connect
+    }
+
+    /// Whether the requested resource does not exist (HTTP 404, local file
+    /// missing).
+    pub fn is_not_found(&self) -> bool {
+        self.class.not_found
+    }
+
+    /// The [`std::io::ErrorKind`] of the underlying I/O failure, if any.
+    ///
+    /// Walks the source chain for the first [`std::io::Error`], so callers
+    /// can special-case conditions like `PermissionDenied` or
+    /// `AlreadyExists` without downcasting through the wrappers themselves.
+    pub fn io_kind(&self) -> Option<std::io::ErrorKind> {
+        self.downcast_source::<std::io::Error>().map(|e| e.kind())
+    }
+
+    /// An iterator over the source chain, outermost source first.
+    pub fn chain(&self) -> impl Iterator<Item = &(dyn std::error::Error + 'static)> {
+        let mut source: Option<&(dyn std::error::Error + 'static)> =
+            self.source.as_deref().map(|s| s as _);
+        std::iter::from_fn(move || {
+            let error = source?;
+            source = error.source();
+            Some(error)
+        })
+    }
+
+    /// Render this error and its whole source chain on one line.
+    ///
+    /// Equivalent to the alternate Display format (`format!("{self:#}")`):
+    /// nested messages are joined with `": "`, e.g. `"Network error: failed
+    /// to fetch: error sending request: connection reset by peer"`.
+    pub fn to_report_string(&self) -> String {
+        format!("{self:#}")
+    }
+
+    /// Render this error with its source chain on separate lines, for logs.
+    ///
+    /// The first line is the plain Display output; each source follows
+    /// indented under a `Caused by:` header.
+    pub fn to_multiline_string(&self) -> String {
+        use fmt::Write;
+
+        let mut out = self.to_string();
+        if self.source.is_some() {
+            out.push_str("
+Caused by:");
+            for (i, source) in self.chain().enumerate() {
+                let _ = write!(out, "
+  {i}: {source}");
+            }
+        }
+        out
+    }
+
+    /// The first error of type `T` in the source chain, if any.
+    pub fn downcast_source<T: std::error::Error + 'static>(&self) -> Option<&T> {
+        let mut source: Option<&(dyn std::error::Error + 'static)> =
+            self.source.as_deref().map(|s| s as _);
+        while let Some(error) = source {
+            if let Some(found) = error.downcast_ref::<T>() {
+                return Some(found);
+            }
+            source = error.source();
+        }
+        None
+    }
+
+    /// Whether retrying the failed operation could plausibly succeed.
+    ///
+    /// The rules are intentionally simple and fixed:
+    ///
+    /// - timeouts and connection failures are retryable, whatever the kind;
+    /// - with a known HTTP status, `408`, `429` and all `5xx` are
+    ///   retryable, everything else is not;
+    /// - [`Timeout`](ErrorKind::Timeout) errors are retryable, while
+    ///   [`Cancelled`](ErrorKind::Cancelled) is final: the caller asked to
+    ///   stop;
+    /// - other [`Network`](ErrorKind::Network) errors are retryable unless
+    ///   the resource was not found;
+    /// - everything else ([`Io`](ErrorKind::Io), [`Verify`](ErrorKind::Verify),
+    ///   [`Extract`](ErrorKind::Extract), [`Other`](ErrorKind::Other)) is not
+    ///   retryable: the same disk, digest or archive will fail the same way
+    ///   again.
+    pub fn is_retryable(&self) -> bool {
+        if self.class.timeout || self.class.connect {
+            return true;
+        }
+        if let Some(status) = self.class.status {
+            return matches!(status, 408 | 429 | 500..=599);
+        }
+        match self.kind {
+            ErrorKind::Network => !self.class.not_found,
+            ErrorKind::Timeout => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("Error");
+        s.field("kind", &self.kind);
+        if let Some(desc) = self.rendered_desc() {
+            s.field("desc", &desc);
+        }
+        if let Some(source) = &self.source {
+            s.field("source", source);
+        }
+        if let Some(url) = &self.url {
+            s.field("url", url);
+        }
+        if let Some(path) = &self.path {
+            s.field("path", path);
+        }
+        s.finish()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.rendered_desc() {
+            Some(desc) => write!(f, "{}: {}", self.kind, desc)?,
+            None => write!(f, "{}", self.kind)?,
+        }
+        if let Some(status) = self.class.status {
+            write!(f, " (HTTP status {status})")?;
+        }
+        if let Some(url) = &self.url {
+            write!(f, " (url: {url})")?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, " (path: {})", path.display())?;
+        }
+        // The alternate format appends the whole source chain on one line.
+        if f.alternate() {
+            for source in self.chain() {
+                write!(f, ": {source}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|s| s as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        use std::io::ErrorKind as IoKind;
+
+        let error = match e.kind() {
+            IoKind::TimedOut => Error::new(ErrorKind::Timeout).mark_timeout(),
+            IoKind::ConnectionRefused
+            | IoKind::ConnectionReset
+            | IoKind::ConnectionAborted
+            | IoKind::NotConnected => Error::new(ErrorKind::Io).mark_connect(),
+            IoKind::NotFound => Error::new(ErrorKind::Io).mark_not_found(),
+            _ => Error::new(ErrorKind::Io),
+        };
+        error.with_source(e)
+    }
+}
+
+/// Serialize the error for structured failure reports.
+///
+/// Requires the `serde` feature; only serialization is supported. The field
+/// names are stable and safe to build analytics schemas on:
+///
+/// - `kind`: the lowercase kind identifier (`"io"`, `"network"`, `"verify"`,
+///   `"extract"`, `"timeout"`, `"cancelled"`, `"other"`);
+/// - `description`: the description, or `null`;
+/// - `url` and `path`: the recorded context, or `null` (paths with invalid
+///   unicode are serialized lossily);
+/// - `http_status`: the HTTP status code, or `null`;
+/// - `retryable`: the [`is_retryable`](Error::is_retryable) verdict;
+/// - `chain`: the rendered source chain as an array of strings, outermost
+///   first.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("Error", 7)?;
+        s.serialize_field("kind", self.kind.identifier())?;
+        s.serialize_field("description", &self.rendered_desc())?;
+        s.serialize_field("url", &self.url)?;
+        s.serialize_field(
+            "path",
+            &self.path.as_deref().map(|p| p.to_string_lossy()),
+        )?;
+        s.serialize_field("http_status", &self.class.status)?;
+        s.serialize_field("retryable", &self.is_retryable())?;
+        let chain: Vec<String> = self.chain().map(|e| e.to_string()).collect();
+        s.serialize_field("chain", &chain)?;
+        s.end()
+    }
+}
+
+/// Convert into an [`std::io::Error`] for io-based APIs.
+///
+/// Needed when a fetchkit failure has to cross an `io::Result` boundary
+/// (`Read`/`Write` adapter implementations, callbacks of other crates). The
+/// kind maps onto the closest [`std::io::ErrorKind`] — timeouts become
+/// `TimedOut`, verification and extraction failures `InvalidData`,
+/// cancellation `Interrupted`, and I/O errors recover their original kind
+/// from the source chain — and the full error is preserved as the source, so
+/// nothing is lost in the round trip.
+impl From<Error> for std::io::Error {
+    fn from(e: Error) -> Self {
+        use std::io::ErrorKind as IoKind;
+
+        let kind = if e.is_timeout() {
+            IoKind::TimedOut
+        } else if e.is_connect() {
+            IoKind::ConnectionReset
+        } else if e.is_not_found() {
+            IoKind::NotFound
+        } else {
+            match e.kind {
+                ErrorKind::Io => e.io_kind().unwrap_or(IoKind::Other),
+                ErrorKind::Verify | ErrorKind::Extract => IoKind::InvalidData,
+                ErrorKind::Timeout => IoKind::TimedOut,
+                ErrorKind::Cancelled => IoKind::Interrupted,
+                ErrorKind::Offline => IoKind::NotConnected,
+                _ => IoKind::Other,
+            }
+        };
+        std::io::Error::new(kind, e)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        let mut error = if e.is_timeout() {
+            Error::new(ErrorKind::Timeout).mark_timeout()
+        } else {
+            Error::new(ErrorKind::Network)
+        };
+        if e.is_connect() {
+            error = error.mark_connect();
+        }
+        if let Some(status) = e.status() {
+            error = error.with_http_status(status.as_u16());
+        }
+        error.with_source(e)
+    }
+}
+
+#[cfg(feature = "ftp")]
+impl From<suppaftp::FtpError> for Error {
+    fn from(e: suppaftp::FtpError) -> Self {
+        use std::io::ErrorKind as IoKind;
+
+        use suppaftp::{FtpError, Status};
+
+        let error = match &e {
+            FtpError::ConnectionError(io) => {
+                let error = Error::new(ErrorKind::Network);
+                match io.kind() {
+                    IoKind::TimedOut => error.mark_timeout(),
+                    IoKind::ConnectionRefused
+                    | IoKind::ConnectionReset
+                    | IoKind::ConnectionAborted
+                    | IoKind::NotConnected => error.mark_connect(),
+                    _ => error,
+                }
+            }
+            // 550 is the FTP equivalent of a 404.
+            FtpError::UnexpectedResponse(resp) if resp.status == Status::FileUnavailable => {
+                Error::new(ErrorKind::Network).mark_not_found()
+            }
+            _ => Error::new(ErrorKind::Network),
+        };
+        error.with_source(e)
+    }
+}
+
+/// Attach a description to an error, layering over any existing ones.
+///
+/// Descriptions stack: an inner helper can record the specific detail
+/// ("failed to create directory x") and an outer layer the operation
+/// ("failed to extract y") without either being lost. Display renders them
+/// outermost-first, joined with `": "`.
+pub trait WithDesc {
+    /// Add `desc` as the outermost description layer.
+    fn with_desc(self, desc: impl Into<Cow<'static, str>>) -> Self;
+
+    /// Set the description lazily, only constructing it in the error case.
+    fn with_desc_with(self, f: impl FnOnce() -> String) -> Self;
+}
+
+impl WithDesc for Error {
+    fn with_desc(mut self, desc: impl Into<Cow<'static, str>>) -> Self {
+        self.desc.push(desc.into());
+        self
+    }
+
+    fn with_desc_with(self, f: impl FnOnce() -> String) -> Self {
+        self.with_desc(f())
+    }
+}
+
+impl<T> WithDesc for Result<T> {
+    fn with_desc(self, desc: impl Into<Cow<'static, str>>) -> Self {
+        self.map_err(|e| e.with_desc(desc))
+    }
+
+    fn with_desc_with(self, f: impl FnOnce() -> String) -> Self {
+        self.map_err(|e| e.with_desc_with(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classification_rules() {
+        // (error, retryable, timeout, connect, not_found)
+        let table: Vec<(Error, bool, bool, bool, bool)> = vec![
+            (Error::new(ErrorKind::Network), true, false, false, false),
+            (
+                Error::new(ErrorKind::Network).mark_timeout(),
+                true,
+                true,
+                false,
+                false,
+            ),
+            (
+                Error::new(ErrorKind::Network).mark_connect(),
+                true,
+                false,
+                true,
+                false,
+            ),
+            (
+                Error::new(ErrorKind::Network).mark_not_found(),
+                false,
+                false,
+                false,
+                true,
+            ),
+            (Error::new(ErrorKind::Timeout), true, false, false, false),
+            (Error::new(ErrorKind::Cancelled), false, false, false, false),
+            (Error::new(ErrorKind::Offline), false, false, false, false),
+            (Error::new(ErrorKind::Verify), false, false, false, false),
+            (Error::new(ErrorKind::Extract), false, false, false, false),
+            (Error::new(ErrorKind::Other), false, false, false, false),
+            // A timeout is retryable whatever the kind.
+            (
+                Error::new(ErrorKind::Io).mark_timeout(),
+                true,
+                true,
+                false,
+                false,
+            ),
+            (
+                Error::from(std::io::Error::from(std::io::ErrorKind::TimedOut)),
+                true,
+                true,
+                false,
+                false,
+            ),
+            (
+                Error::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)),
+                true,
+                false,
+                true,
+                false,
+            ),
+            (
+                Error::from(std::io::Error::from(std::io::ErrorKind::NotFound)),
+                false,
+                false,
+                false,
+                true,
+            ),
+            (
+                Error::from(std::io::Error::from(std::io::ErrorKind::PermissionDenied)),
+                false,
+                false,
+                false,
+                false,
+            ),
+            (
+                Error::new(ErrorKind::Network).with_http_status(404),
+                false,
+                false,
+                false,
+                true,
+            ),
+            (
+                Error::new(ErrorKind::Network).with_http_status(403),
+                false,
+                false,
+                false,
+                false,
+            ),
+            (
+                Error::new(ErrorKind::Network).with_http_status(429),
+                true,
+                false,
+                false,
+                false,
+            ),
+            (
+                Error::new(ErrorKind::Network).with_http_status(503),
+                true,
+                false,
+                false,
+                false,
+            ),
+        ];
+        for (error, retryable, timeout, connect, not_found) in table {
+            assert_eq!(error.is_retryable(), retryable, "{error:?}");
+            assert_eq!(error.is_timeout(), timeout, "{error:?}");
+            assert_eq!(error.is_connect(), connect, "{error:?}");
+            assert_eq!(error.is_not_found(), not_found, "{error:?}");
+        }
+    }
+
+    /// A test error with a real source, since `io::Error` flattens its own
+    /// chain.
+    #[derive(Debug)]
+    struct Wrap(&'static str, std::io::Error);
+
+    impl fmt::Display for Wrap {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl std::error::Error for Wrap {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.1)
+        }
+    }
+
+    #[test]
+    fn report_formats_pin_the_source_chain() {
+        let inner = std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "connection reset by peer",
+        );
+        let error = Error::new(ErrorKind::Network)
+            .with_source(Wrap("error sending request", inner))
+            .with_desc("failed to fetch");
+        assert_eq!(error.chain().count(), 2);
+        assert_eq!(
+            error.to_report_string(),
+            "Network error: failed to fetch: error sending request: \
+             connection reset by peer"
+        );
+        assert_eq!(
+            error.to_multiline_string(),
+            "Network error: failed to fetch\n\
+             Caused by:\n  \
+             0: error sending request\n  \
+             1: connection reset by peer"
+        );
+        // Without a source both renderings match the plain Display.
+        let bare = Error::new(ErrorKind::Verify).with_desc("digest mismatch");
+        assert_eq!(bare.to_report_string(), bare.to_string());
+        assert_eq!(bare.to_multiline_string(), bare.to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_to_stable_json() {
+        let inner = std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "connection reset by peer",
+        );
+        let error = Error::new(ErrorKind::Network)
+            .with_source(Wrap("error sending request", inner))
+            .with_desc("failed to fetch")
+            .with_url("https://example.com/data")
+            .with_http_status(503);
+        assert_eq!(
+            serde_json::to_string(&error).unwrap(),
+            r#"{"kind":"network","description":"failed to fetch","url":"https://example.com/data","path":null,"http_status":503,"retryable":true,"chain":["error sending request","connection reset by peer"]}"#
+        );
+
+        // Descriptions with quotes and control characters are escaped.
+        let error = Error::new(ErrorKind::Verify)
+            .with_desc("digest \"abc\"\nmismatch")
+            .with_path("/tmp/data");
+        assert_eq!(
+            serde_json::to_string(&error).unwrap(),
+            r#"{"kind":"verify","description":"digest \"abc\"\nmismatch","url":null,"path":"/tmp/data","http_status":null,"retryable":false,"chain":[]}"#
+        );
+    }
+
+    #[test]
+    fn round_trips_through_io_error() {
+        use std::io::ErrorKind as IoKind;
+
+        let table: Vec<(Error, IoKind)> = vec![
+            (Error::new(ErrorKind::Network), IoKind::Other),
+            (Error::new(ErrorKind::Network).mark_timeout(), IoKind::TimedOut),
+            (
+                Error::new(ErrorKind::Network).mark_connect(),
+                IoKind::ConnectionReset,
+            ),
+            (
+                Error::new(ErrorKind::Network).mark_not_found(),
+                IoKind::NotFound,
+            ),
+            (
+                Error::from(std::io::Error::from(IoKind::PermissionDenied)),
+                IoKind::PermissionDenied,
+            ),
+            (Error::new(ErrorKind::Verify), IoKind::InvalidData),
+            (Error::new(ErrorKind::Extract), IoKind::InvalidData),
+            (Error::new(ErrorKind::Timeout), IoKind::TimedOut),
+            (Error::new(ErrorKind::Cancelled), IoKind::Interrupted),
+            (Error::new(ErrorKind::Offline), IoKind::NotConnected),
+            (Error::new(ErrorKind::Other), IoKind::Other),
+        ];
+        for (error, expected) in table {
+            let kind = error.kind();
+            let io: std::io::Error = error.into();
+            assert_eq!(io.kind(), expected, "{kind:?}");
+            // The original error is preserved as the source.
+            let source = io.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+            assert_eq!(source.kind(), kind);
+        }
+    }
+
+    #[test]
+    fn io_details_are_recoverable_from_the_chain() {
+        let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        let error = Error::from(io).with_desc("failed to create file");
+        assert_eq!(error.io_kind(), Some(std::io::ErrorKind::PermissionDenied));
+        assert!(error.downcast_source::<std::io::Error>().is_some());
+        assert!(error.downcast_source::<std::fmt::Error>().is_none());
+
+        // Nested one level deeper: io::Error wrapping another error.
+        let inner = std::io::Error::from(std::io::ErrorKind::StorageFull);
+        let outer = std::io::Error::other(inner);
+        let error = Error::new(ErrorKind::Extract).with_source(outer);
+        assert_eq!(error.io_kind(), Some(std::io::ErrorKind::Other));
+    }
+
+    #[test]
+    fn descriptions_layer_outermost_first() {
+        let error = Error::new(ErrorKind::Extract)
+            .with_desc("failed to create bin")
+            .with_desc("failed to unpack bin/tool")
+            .with_desc("failed to extract tool.tar.gz");
+        assert_eq!(
+            error.descriptions().collect::<Vec<_>>(),
+            [
+                "failed to extract tool.tar.gz",
+                "failed to unpack bin/tool",
+                "failed to create bin",
+            ]
+        );
+        assert_eq!(error.description(), Some("failed to extract tool.tar.gz"));
+        assert_eq!(
+            error.to_string(),
+            "Extraction error: failed to extract tool.tar.gz: \
+             failed to unpack bin/tool: failed to create bin"
+        );
+    }
+
+    #[test]
+    fn context_survives_with_desc() {
+        let error: Result<()> = Err(Error::new(ErrorKind::Network)
+            .with_url("https://example.com/data")
+            .with_path("/tmp/data"));
+        let error = error.with_desc("failed to fetch").unwrap_err();
+        assert_eq!(error.url(), Some("https://example.com/data"));
+        assert_eq!(error.path(), Some(Path::new("/tmp/data")));
+        assert_eq!(
+            error.to_string(),
+            "Network error: failed to fetch (url: https://example.com/data) (path: /tmp/data)"
+        );
+    }
+
+    #[test]
+    fn display_includes_the_http_status() {
+        let error = Error::new(ErrorKind::Network)
+            .with_desc("failed to fetch https://example.com/data")
+            .with_http_status(429);
+        assert_eq!(
+            error.to_string(),
+            "Network error: failed to fetch https://example.com/data (HTTP status 429)"
+        );
+        assert_eq!(error.http_status(), Some(429));
+    }
+}
@@ -0,0 +1,233 @@
+//! Extraction of downloaded archives.
+//!
+//! [`ArchiveFile`] wraps a path to an archive on disk with a detected (or
+//! explicitly given) [`ArchiveFormat`], and extracts it with
+//! [`ArchiveFile::extract`]. Entry paths can be remapped or filtered with a
+//! mapper, and extraction can either stop at the first failing entry or
+//! collect per-entry errors into the [`ExtractReport`].
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+
+#[cfg(feature = "tar")]
+mod tar;
+#[cfg(feature = "zip")]
+mod zip;
+
+/// The format of an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArchiveFormat {
+    /// An uncompressed tar archive (`.tar`).
+    #[cfg(feature = "tar")]
+    Tar,
+    /// A gzip compressed tar archive (`.tar.gz`, `.tgz`).
+    #[cfg(all(feature = "tar", feature = "gzip"))]
+    TarGz,
+    /// A zstd compressed tar archive (`.tar.zst`).
+    #[cfg(all(feature = "tar", feature = "zstd"))]
+    TarZst,
+    /// A xz compressed tar archive (`.tar.xz`).
+    #[cfg(all(feature = "tar", feature = "xz"))]
+    TarXz,
+    /// A zip archive (`.zip`).
+    #[cfg(feature = "zip")]
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Guess the format from the file name extension(s) of `path`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        #[cfg(feature = "tar")]
+        if name.ends_with(".tar") {
+            return Some(ArchiveFormat::Tar);
+        }
+        #[cfg(all(feature = "tar", feature = "gzip"))]
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            return Some(ArchiveFormat::TarGz);
+        }
+        #[cfg(all(feature = "tar", feature = "zstd"))]
+        if name.ends_with(".tar.zst") {
+            return Some(ArchiveFormat::TarZst);
+        }
+        #[cfg(all(feature = "tar", feature = "xz"))]
+        if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            return Some(ArchiveFormat::TarXz);
+        }
+        #[cfg(feature = "zip")]
+        if name.ends_with(".zip") {
+            return Some(ArchiveFormat::Zip);
+        }
+        None
+    }
+}
+
+/// A mapper deciding where an archive entry is extracted to.
+///
+/// Called with the entry path inside the archive; returns the path relative
+/// to the extraction destination, or `None` to skip the entry. Mappers are
+/// `Send` so extraction can run on the blocking pool (see
+/// [`extract_stream`]).
+pub type Mapper<'f> = Box<dyn FnMut(&Path) -> Option<PathBuf> + Send + 'f>;
+
+/// Options controlling extraction.
+pub struct ExtractOptions<'f> {
+    dest: PathBuf,
+    mapper: Option<Mapper<'f>>,
+    collect_errors: bool,
+}
+
+impl<'f> ExtractOptions<'f> {
+    /// Create options extracting into the directory `dest`.
+    pub fn new(dest: impl Into<PathBuf>) -> Self {
+        Self {
+            dest: dest.into(),
+            mapper: None,
+            collect_errors: false,
+        }
+    }
+
+    /// Set a mapper remapping or filtering entry paths.
+    pub fn with_mapper(
+        mut self,
+        mapper: impl FnMut(&Path) -> Option<PathBuf> + Send + 'f,
+    ) -> Self {
+        self.mapper = Some(Box::new(mapper));
+        self
+    }
+
+    /// Collect per-entry errors into the report instead of aborting at the
+    /// first failing entry.
+    pub fn collect_errors(mut self, collect: bool) -> Self {
+        self.collect_errors = collect;
+        self
+    }
+}
+
+/// A report of a completed extraction.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExtractReport {
+    /// The extracted files, as paths relative to the destination.
+    pub files: Vec<PathBuf>,
+    /// Per-entry errors, only populated in collect-errors mode.
+    pub errors: Vec<Error>,
+}
+
+/// An archive file on disk.
+pub struct ArchiveFile<'a> {
+    path: &'a Path,
+    format: ArchiveFormat,
+}
+
+impl<'a> ArchiveFile<'a> {
+    /// Create an archive file, guessing the format from the file name.
+    pub fn new(path: &'a Path) -> Result<Self> {
+        let format = ArchiveFormat::from_path(path).ok_or_else(|| {
+            Error::new(ErrorKind::Extract)
+                .with_desc_with(|| format!("unsupported archive format: {}", path.display()))
+        })?;
+        Ok(Self { path, format })
+    }
+
+    /// Create an archive file with an explicit format.
+    pub fn with_format(path: &'a Path, format: ArchiveFormat) -> Self {
+        Self { path, format }
+    }
+
+    /// The format of this archive.
+    pub fn format(&self) -> ArchiveFormat {
+        self.format
+    }
+
+    /// Extract the archive according to `options`.
+    pub fn extract(&self, mut options: ExtractOptions<'_>) -> Result<ExtractReport> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("extract", path = %self.path.display()).entered();
+        self.extract_inner(&mut options).map_err(|e| {
+            // Entry level errors already carry the entry path.
+            if e.path().is_none() {
+                e.with_path(self.path)
+            } else {
+                e
+            }
+        })
+    }
+
+    fn extract_inner(&self, options: &mut ExtractOptions<'_>) -> Result<ExtractReport> {
+        std::fs::create_dir_all(&options.dest)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to create {}", options.dest.display()))?;
+        match self.format {
+            #[cfg(feature = "tar")]
+            ArchiveFormat::Tar => tar::extract(self.path, self.format, options),
+            #[cfg(all(feature = "tar", feature = "gzip"))]
+            ArchiveFormat::TarGz => tar::extract(self.path, self.format, options),
+            #[cfg(all(feature = "tar", feature = "zstd"))]
+            ArchiveFormat::TarZst => tar::extract(self.path, self.format, options),
+            #[cfg(all(feature = "tar", feature = "xz"))]
+            ArchiveFormat::TarXz => tar::extract(self.path, self.format, options),
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => zip::extract(self.path, options),
+        }
+    }
+}
+
+/// Extract an archive read sequentially from `reader`.
+///
+/// The streaming sibling of [`ArchiveFile::extract`] for archives that are
+/// not on disk, e.g. a response body piped through
+/// [`download_and_extract`](crate::download::DownloadBuilder::download_and_extract).
+/// Tar archives (with any supported compression) only need a sequential
+/// [`Read`](std::io::Read); zip archives require random access to their
+/// central directory and are rejected with an `Extract` error — download
+/// those to a file first.
+#[cfg(feature = "tar")]
+pub fn extract_stream(
+    reader: impl std::io::Read,
+    format: ArchiveFormat,
+    mut options: ExtractOptions<'_>,
+) -> Result<ExtractReport> {
+    std::fs::create_dir_all(&options.dest)
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to create {}", options.dest.display()))?;
+    match format {
+        #[cfg(feature = "zip")]
+        ArchiveFormat::Zip => Err(Error::new(ErrorKind::Extract)
+            .with_desc("zip archives need random access and cannot be extracted from a stream")),
+        _ => tar::extract_stream(reader, format, &mut options),
+    }
+}
+
+/// Resolve the destination for an archive entry, applying the mapper and
+/// rejecting entry paths escaping the destination directory.
+///
+/// Returns `Ok(None)` when the mapper skips the entry.
+fn entry_dest(
+    entry_path: &Path,
+    options: &mut ExtractOptions<'_>,
+) -> Result<Option<(PathBuf, PathBuf)>> {
+    let relative = match &mut options.mapper {
+        Some(mapper) => match mapper(entry_path) {
+            Some(mapped) => mapped,
+            None => return Ok(None),
+        },
+        None => entry_path.to_path_buf(),
+    };
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(Error::new(ErrorKind::Extract).with_desc_with(|| {
+            format!(
+                "entry path escapes the destination directory: {}",
+                relative.display()
+            )
+        }));
+    }
+    let dest = options.dest.join(&relative);
+    Ok(Some((relative, dest)))
+}
@@ -0,0 +1,188 @@
+//! Extraction of (optionally compressed) tar archives.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::extract::{ArchiveFormat, ExtractOptions, ExtractReport, entry_dest};
+
+/// Wrap `input` in the decoder matching `format`.
+fn decoder<'r>(input: impl Read + 'r, format: ArchiveFormat) -> Result<Box<dyn Read + 'r>> {
+    match format {
+        ArchiveFormat::Tar => Ok(Box::new(input)),
+        #[cfg(feature = "gzip")]
+        ArchiveFormat::TarGz => Ok(Box::new(flate2::read::GzDecoder::new(input))),
+        #[cfg(feature = "zstd")]
+        ArchiveFormat::TarZst => Ok(Box::new(
+            zstd::stream::read::Decoder::new(input)
+                .map_err(Error::from)
+                .with_desc("failed to initialize zstd decoder")?,
+        )),
+        #[cfg(feature = "xz")]
+        ArchiveFormat::TarXz => Ok(Box::new(xz2::read::XzDecoder::new(input))),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!("not a tar format: {format:?}"),
+    }
+}
+
+pub(super) fn extract(
+    path: &Path,
+    format: ArchiveFormat,
+    options: &mut ExtractOptions<'_>,
+) -> Result<ExtractReport> {
+    let file = File::open(path)
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to open {}", path.display()))?;
+    let reader = decoder(BufReader::new(file), format)?;
+    extract_entries(reader, &path.display().to_string(), options)
+}
+
+/// Extract a tar archive read sequentially from `input`; see
+/// [`extract_stream`](crate::extract::extract_stream).
+pub(super) fn extract_stream(
+    input: impl Read,
+    format: ArchiveFormat,
+    options: &mut ExtractOptions<'_>,
+) -> Result<ExtractReport> {
+    extract_entries(decoder(input, format)?, "the archive stream", options)
+}
+
+/// The shared entry loop over an already-decoded tar stream; `source`
+/// names the archive in error messages.
+fn extract_entries(
+    reader: impl Read,
+    source: &str,
+    options: &mut ExtractOptions<'_>,
+) -> Result<ExtractReport> {
+    let mut archive = tar::Archive::new(reader);
+    let mut report = ExtractReport::default();
+    let entries = archive
+        .entries()
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to read {source}"))?;
+    for entry in entries {
+        let mut entry = entry
+            .map_err(|e| Error::new(ErrorKind::Extract).with_source(e))
+            .with_desc_with(|| format!("failed to read an entry of {source}"))?;
+        let entry_path = entry
+            .path()
+            .map_err(|e| Error::new(ErrorKind::Extract).with_source(e))
+            .with_desc("entry has a malformed path")?
+            .into_owned();
+        let Some((relative, dest)) = entry_dest(&entry_path, options)? else {
+            continue;
+        };
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(Error::from)
+                .with_desc_with(|| format!("failed to create {}", parent.display()))?;
+        }
+        let result = entry
+            .unpack(&dest)
+            .map_err(|e| Error::new(ErrorKind::Extract).with_source(e))
+            .with_desc_with(|| format!("failed to unpack {}", entry_path.display()))
+            .map_err(|e| e.with_path(&entry_path));
+        match result {
+            Ok(_) => {
+                if entry.header().entry_type().is_file() {
+                    report.files.push(relative);
+                }
+            }
+            Err(e) if options.collect_errors => report.errors.push(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::extract::ArchiveFile;
+
+    /// Build a small tar archive with `bin/tool` and `doc/README`.
+    fn build_tar(path: &Path) {
+        let mut builder = tar::Builder::new(File::create(path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "bin/tool", &b"tool\n"[..])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(7);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "doc/README", &b"readme\n"[..])
+            .unwrap();
+        builder.finish().unwrap();
+    }
+
+    #[test]
+    fn extract_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("archive.tar");
+        build_tar(&archive_path);
+        let archive = ArchiveFile::new(&archive_path).unwrap();
+        let dest = dir.path().join("out");
+        let report = archive.extract(ExtractOptions::new(&dest)).unwrap();
+        assert_eq!(report.files.len(), 2);
+        assert!(dest.join("bin/tool").is_file());
+        assert!(dest.join("doc/README").is_file());
+    }
+
+    #[test]
+    fn extract_with_mapper() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("archive.tar");
+        build_tar(&archive_path);
+        let archive = ArchiveFile::new(&archive_path).unwrap();
+        let dest = dir.path().join("out");
+        let options = ExtractOptions::new(&dest).with_mapper(|path| {
+            // Only extract files under bin/, flattened.
+            path.strip_prefix("bin").ok().map(Path::to_path_buf)
+        });
+        let report = archive.extract(options).unwrap();
+        assert_eq!(report.files, [Path::new("tool")]);
+        assert!(dest.join("tool").is_file());
+        assert!(!dest.join("doc").exists());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn extract_tar_gz() {
+        let dir = tempfile::tempdir().unwrap();
+        let tar_path = dir.path().join("archive.tar");
+        build_tar(&tar_path);
+        let gz_path = dir.path().join("archive.tar.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            File::create(&gz_path).unwrap(),
+            flate2::Compression::fast(),
+        );
+        encoder.write_all(&std::fs::read(&tar_path).unwrap()).unwrap();
+        encoder.finish().unwrap();
+        let archive = ArchiveFile::new(&gz_path).unwrap();
+        assert_eq!(archive.format(), ArchiveFormat::TarGz);
+        let dest = dir.path().join("out");
+        let report = archive.extract(ExtractOptions::new(&dest)).unwrap();
+        assert_eq!(report.files.len(), 2);
+        assert!(dest.join("bin/tool").is_file());
+    }
+
+    #[test]
+    fn reject_escaping_mapper() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("archive.tar");
+        build_tar(&archive_path);
+        let archive = ArchiveFile::new(&archive_path).unwrap();
+        let options = ExtractOptions::new(dir.path().join("out"))
+            .with_mapper(|_| Some("../escape".into()));
+        let err = archive.extract(options).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Extract);
+    }
+}
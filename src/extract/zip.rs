@@ -0,0 +1,191 @@
+//! Extraction of zip archives.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::extract::{ExtractOptions, ExtractReport, entry_dest};
+
+pub(super) fn extract(path: &Path, options: &mut ExtractOptions<'_>) -> Result<ExtractReport> {
+    let file = File::open(path)
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to open {}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))
+        .map_err(|e| Error::new(ErrorKind::Extract).with_source(e))
+        .with_desc_with(|| format!("failed to read {}", path.display()))?;
+    let mut report = ExtractReport::default();
+    for index in 0..archive.len() {
+        let result = extract_entry(&mut archive, index, options);
+        match result {
+            Ok(Some(relative)) => report.files.push(relative),
+            Ok(None) => {}
+            Err(e) if options.collect_errors => report.errors.push(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(report)
+}
+
+/// Extract the entry at `index`, returning its relative path when a file was
+/// written.
+fn extract_entry<R: Read + std::io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    index: usize,
+    options: &mut ExtractOptions<'_>,
+) -> Result<Option<std::path::PathBuf>> {
+    let mut entry = archive
+        .by_index(index)
+        .map_err(|e| Error::new(ErrorKind::Extract).with_source(e))
+        .with_desc_with(|| format!("failed to read zip entry {index}"))?;
+    let Some(entry_path) = entry.enclosed_name() else {
+        return Err(Error::new(ErrorKind::Extract)
+            .with_desc_with(|| format!("zip entry has an unsafe path: {}", entry.name())));
+    };
+    let Some((relative, dest)) = entry_dest(&entry_path, options)? else {
+        return Ok(None);
+    };
+    if entry.is_dir() {
+        std::fs::create_dir_all(&dest)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to create {}", dest.display()))?;
+        return Ok(None);
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to create {}", parent.display()))?;
+    }
+    let mut out = File::create(&dest)
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to create {}", dest.display()))?;
+    std::io::copy(&mut entry, &mut out)
+        .map_err(|e| {
+            // The zip crate validates the per-entry CRC32 (and the integrity
+            // of the compressed stream) while reading and reports failures as
+            // `InvalidData`; that is corruption of the archive itself, not a
+            // local IO problem, so surface it as a verification failure.
+            if e.kind() == std::io::ErrorKind::InvalidData {
+                Error::new(ErrorKind::Verify).with_source(e).with_desc_with(|| {
+                    format!(
+                        "zip entry {} is corrupt (archive integrity failure)",
+                        relative.display()
+                    )
+                })
+            } else {
+                Error::new(ErrorKind::Extract)
+                    .with_source(e)
+                    .with_desc_with(|| format!("failed to unpack {}", relative.display()))
+            }
+        })
+        .map_err(|e| e.with_path(&relative))?;
+    out.flush()
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to write {}", dest.display()))?;
+    #[cfg(unix)]
+    if let Some(mode) = entry.unix_mode() {
+        use std::fs::Permissions;
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&dest, Permissions::from_mode(mode))
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to set permissions on {}", dest.display()))?;
+    }
+    Ok(Some(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+    use crate::extract::{ArchiveFile, ArchiveFormat};
+
+    fn build_zip(path: &Path) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        writer
+            .start_file("bin/tool", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(b"tool\n").unwrap();
+        writer
+            .start_file("doc/README", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(b"readme\n").unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn extract_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("archive.zip");
+        build_zip(&archive_path);
+        let archive = ArchiveFile::new(&archive_path).unwrap();
+        assert_eq!(archive.format(), ArchiveFormat::Zip);
+        let dest = dir.path().join("out");
+        let report = archive.extract(ExtractOptions::new(&dest)).unwrap();
+        assert_eq!(report.files.len(), 2);
+        assert!(dest.join("bin/tool").is_file());
+        assert!(dest.join("doc/README").is_file());
+    }
+
+    /// Build a zip with a stored (uncompressed) entry and flip a bit in its
+    /// content bytes, leaving the recorded CRC32 stale.
+    fn build_corrupt_zip(path: &Path) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        let stored = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        writer.start_file("bin/tool", stored).unwrap();
+        writer.write_all(b"tool\n").unwrap();
+        writer.start_file("doc/README", stored).unwrap();
+        writer.write_all(b"readme\n").unwrap();
+        writer.finish().unwrap();
+        let mut bytes = std::fs::read(path).unwrap();
+        let offset = bytes
+            .windows(5)
+            .position(|w| w == b"tool\n")
+            .expect("stored entry content not found");
+        bytes[offset] ^= 0x01;
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn crc_failure_is_verify_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("archive.zip");
+        build_corrupt_zip(&archive_path);
+        let archive = ArchiveFile::new(&archive_path).unwrap();
+        let err = archive
+            .extract(ExtractOptions::new(dir.path().join("out")))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(err.description().unwrap().contains("bin/tool"));
+    }
+
+    #[test]
+    fn crc_failure_collected() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("archive.zip");
+        build_corrupt_zip(&archive_path);
+        let archive = ArchiveFile::new(&archive_path).unwrap();
+        let dest = dir.path().join("out");
+        let report = archive
+            .extract(ExtractOptions::new(&dest).collect_errors(true))
+            .unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].kind(), ErrorKind::Verify);
+        // The intact entry is still extracted.
+        assert_eq!(report.files, [Path::new("doc/README")]);
+    }
+
+    #[test]
+    fn extract_with_mapper() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("archive.zip");
+        build_zip(&archive_path);
+        let archive = ArchiveFile::new(&archive_path).unwrap();
+        let dest = dir.path().join("out");
+        let options = ExtractOptions::new(&dest)
+            .with_mapper(|path| path.strip_prefix("bin").ok().map(Path::to_path_buf));
+        let report = archive.extract(options).unwrap();
+        assert_eq!(report.files, [Path::new("tool")]);
+        assert!(!dest.join("doc").exists());
+    }
+}
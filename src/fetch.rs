@@ -0,0 +1,366 @@
+//! The high-level fetch pipeline: download, verify and extract in one call.
+//!
+//! [`Fetcher`] composes the building blocks from [`download`](crate::download),
+//! [`verify`](crate::verify) and [`extract`](crate::extract) into the
+//! sequence nearly every consumer writes by hand: select a mirror, download
+//! the archive next to the destination, verify it, extract it with a mapper,
+//! and clean up the archive. Every failure path leaves a predictable state:
+//! a failed download or verification removes the partial or invalid archive,
+//! while a failed extraction keeps the verified archive so a rerun does not
+//! have to download it again.
+
+use std::path::{Path, PathBuf};
+
+use crate::download::{DownloadBuilder, MirrorOptions, OfflinePolicy};
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::extract::{ArchiveFile, ArchiveFormat, ExtractOptions, ExtractReport};
+use crate::http::Client;
+use crate::progress::{NoProgress, PerPhase, Phase, PhasedProgressBuilder, ProgressReceiver};
+use crate::verify::hash::DynHashVerifierBuilder;
+use crate::verify::{DynVerifierBuilder, VerifierBuilder};
+
+/// A specification for the one-shot [`fetch`] function.
+///
+/// Only [`dest_dir`](Self::dest_dir) is required; everything else has a
+/// sensible default, so the 90% case is a struct literal with functional
+/// update syntax (see [`fetch`]).
+#[derive(Debug, Default)]
+pub struct FetchSpec {
+    /// The directory the fetched content ends up in.
+    pub dest_dir: PathBuf,
+    /// The expected checksum as an `"algo:hex"` string, e.g.
+    /// `"sha256:9f86…"`.
+    pub checksum: Option<String>,
+    /// Strip this many leading path components from every archive entry
+    /// (entries with fewer components are skipped).
+    pub strip_components: usize,
+    /// The expected size in bytes, `0` when unknown.
+    pub size: u64,
+    /// Keep the archive after extraction instead of deleting it.
+    pub keep_archive: bool,
+}
+
+/// Download, verify and extract `url` into `spec.dest_dir` in one call.
+///
+/// This is the one-shot layer over [`Fetcher`]: extraction is confined to
+/// the destination directory, a valid already-downloaded copy skips the
+/// download, and the archive is cleaned up afterwards unless
+/// [`keep_archive`](FetchSpec::keep_archive) is set. When the URL does not
+/// point at a recognized archive format, the file is simply downloaded into
+/// the destination directory instead of being extracted.
+///
+/// # Example
+///
+/// ```no_run
+/// use fetchkit::fetch::{fetch, FetchSpec};
+///
+/// # async fn example() -> fetchkit::Result<()> {
+/// let client = reqwest::Client::new();
+/// let report = fetch(
+///     &client,
+///     "https://example.com/tool.tar.gz",
+///     FetchSpec {
+///         dest_dir: "/opt/tool".into(),
+///         checksum: Some("sha256:9f86…".to_string()),
+///         strip_components: 1,
+///         ..Default::default()
+///     },
+/// )
+/// .await?;
+/// println!("extracted {} files", report.extract.files.len());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn fetch<C: Client>(client: &C, url: &str, spec: FetchSpec) -> Result<FetchReport> {
+    fetch_with(client, url, spec, &PerPhase::new(NoProgress)).await
+}
+
+/// [`fetch`] with an explicit progress builder, used by the manifest layer.
+pub(crate) async fn fetch_with<C: Client>(
+    client: &C,
+    url: &str,
+    spec: FetchSpec,
+    progress: &impl PhasedProgressBuilder,
+) -> Result<FetchReport> {
+    let verifier = spec
+        .checksum
+        .as_deref()
+        .map(DynHashVerifierBuilder::parse)
+        .transpose()?;
+
+    let mut fetcher = Fetcher::new(url, &spec.dest_dir, spec.size);
+    // A URL without a recognized archive extension degrades to a plain
+    // download into the destination directory.
+    let archive = fetcher.default_archive_path()?;
+    if ArchiveFormat::from_path(&archive).is_none() {
+        let name = archive.file_name().expect("derived from the URL");
+        let dest = spec.dest_dir.join(name);
+        std::fs::create_dir_all(&spec.dest_dir)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to create {}", spec.dest_dir.display()))?;
+        let mut builder = DownloadBuilder::new(url, &dest, spec.size);
+        if let Some(verifier) = &verifier {
+            builder = builder.with_verifier(verifier.clone());
+        }
+        let downloaded = if builder.exist()? {
+            false
+        } else {
+            if let Err(e) = builder.download_phased(client, progress).await {
+                if e.io_kind() != Some(std::io::ErrorKind::AlreadyExists) {
+                    let _ = std::fs::remove_file(&dest);
+                }
+                return Err(e);
+            }
+            true
+        };
+        return Ok(FetchReport {
+            downloaded,
+            archive: None,
+            extract: ExtractReport {
+                files: vec![name.into()],
+                ..Default::default()
+            },
+        });
+    }
+
+    if let Some(verifier) = verifier {
+        fetcher = fetcher.with_verifier(verifier);
+    }
+    if spec.strip_components > 0 {
+        let strip = spec.strip_components;
+        fetcher = fetcher.with_mapper(move |path: &Path| {
+            let mut components = path.components();
+            for _ in 0..strip {
+                components.next()?;
+            }
+            let rest: PathBuf = components.collect();
+            (rest != Path::new("")).then_some(rest)
+        });
+    }
+    fetcher
+        .keep_archive(spec.keep_archive)
+        .run(client, progress)
+        .await
+}
+
+/// A builder describing a complete fetch: download, verify and extract.
+pub struct Fetcher<'m> {
+    url: &'m str,
+    dest: PathBuf,
+    size: u64,
+    verifier: Option<Box<dyn DynVerifierBuilder + Send + Sync>>,
+    mirrors: Option<MirrorOptions>,
+    archive_path: Option<PathBuf>,
+    format: Option<ArchiveFormat>,
+    mapper: Option<crate::extract::Mapper<'m>>,
+    offline: OfflinePolicy,
+    collect_errors: bool,
+    keep_archive: bool,
+}
+
+/// The report of a completed fetch.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct FetchReport {
+    /// Whether the archive was downloaded, `false` when a valid copy
+    /// already existed at the archive path.
+    pub downloaded: bool,
+    /// The path of the kept archive; `None` when it was cleaned up.
+    pub archive: Option<PathBuf>,
+    /// The report of the extraction.
+    pub extract: ExtractReport,
+}
+
+impl<'m> Fetcher<'m> {
+    /// Create a fetcher downloading `url` and extracting into the directory
+    /// `dest`.
+    ///
+    /// `size` is the expected archive size in bytes, `0` when unknown; it is
+    /// used like in [`DownloadBuilder::new`].
+    pub fn new(url: &'m str, dest: impl Into<PathBuf>, size: u64) -> Self {
+        Self {
+            url,
+            dest: dest.into(),
+            size,
+            verifier: None,
+            mirrors: None,
+            archive_path: None,
+            format: None,
+            mapper: None,
+            offline: OfflinePolicy::default(),
+            collect_errors: false,
+            keep_archive: false,
+        }
+    }
+
+    /// Set the verifier for the downloaded archive.
+    pub fn with_verifier<V>(mut self, verifier: V) -> Self
+    where
+        V: VerifierBuilder + Send + Sync + 'static,
+        V::Verifier: 'static,
+    {
+        self.verifier = Some(Box::new(verifier));
+        self
+    }
+
+    /// Set mirrors to select from before downloading.
+    pub fn with_mirrors(mut self, mirrors: MirrorOptions) -> Self {
+        self.mirrors = Some(mirrors);
+        self
+    }
+
+    /// Set the path the archive is downloaded to.
+    ///
+    /// By default the archive is placed next to the destination directory,
+    /// named after the last segment of the URL.
+    pub fn with_archive_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.archive_path = Some(path.into());
+        self
+    }
+
+    /// Set the archive format explicitly instead of guessing it from the
+    /// archive file name.
+    pub fn with_format(mut self, format: ArchiveFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Set a mapper remapping or filtering entry paths; see
+    /// [`ExtractOptions::with_mapper`].
+    pub fn with_mapper(
+        mut self,
+        mapper: impl FnMut(&Path) -> Option<PathBuf> + Send + 'm,
+    ) -> Self {
+        self.mapper = Some(Box::new(mapper));
+        self
+    }
+
+    /// Set whether the download may touch the network; see
+    /// [`OfflinePolicy`].
+    pub fn with_offline_policy(mut self, policy: OfflinePolicy) -> Self {
+        self.offline = policy;
+        self
+    }
+
+    /// Collect per-entry extraction errors into the report instead of
+    /// aborting at the first failing entry.
+    pub fn collect_errors(mut self, collect: bool) -> Self {
+        self.collect_errors = collect;
+        self
+    }
+
+    /// Keep the downloaded archive after a successful extraction instead of
+    /// deleting it.
+    pub fn keep_archive(mut self, keep: bool) -> Self {
+        self.keep_archive = keep;
+        self
+    }
+
+    /// The default archive path: a sibling of the destination named after
+    /// the last path segment of the URL.
+    fn default_archive_path(&self) -> Result<PathBuf> {
+        let name = self
+            .url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit('/').next())
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| {
+                Error::new(ErrorKind::Other)
+                    .with_url(self.url)
+                    .with_desc("cannot derive an archive file name from the URL")
+            })?;
+        let parent = self.dest.parent().unwrap_or(Path::new("."));
+        Ok(parent.join(name))
+    }
+
+    /// Run the full pipeline: download (with mirror selection), verify,
+    /// extract, clean up.
+    ///
+    /// When a valid copy already exists at the archive path the download is
+    /// skipped. A failed download or verification removes the archive; a
+    /// failed extraction keeps it, so rerunning the fetch skips straight to
+    /// extraction. On success the archive is deleted unless
+    /// [`keep_archive`](Self::keep_archive) is set.
+    pub async fn run<C: Client>(
+        mut self,
+        client: &C,
+        progress: &impl PhasedProgressBuilder,
+    ) -> Result<FetchReport> {
+        let archive = match self.archive_path.take() {
+            Some(path) => path,
+            None => self.default_archive_path()?,
+        };
+
+        let downloaded = {
+            let mut builder = DownloadBuilder::new(self.url, &archive, self.size)
+                .with_offline_policy(self.offline);
+            if let Some(verifier) = self.verifier.take() {
+                builder = builder.with_boxed_verifier(verifier);
+            }
+            if let Some(mirrors) = self.mirrors.take() {
+                builder = builder.with_mirrors(mirrors);
+            }
+            if builder.exist()? {
+                log::debug!("{} already exists and is valid", archive.display());
+                false
+            } else {
+                if let Err(e) = builder.download_phased(client, progress).await {
+                    // The archive is partial or failed verification; remove
+                    // it unless it pre-existed and the download refused to
+                    // overwrite it.
+                    if e.io_kind() != Some(std::io::ErrorKind::AlreadyExists) {
+                        let _ = std::fs::remove_file(&archive);
+                    }
+                    return Err(e);
+                }
+                true
+            }
+        };
+
+        let keep_archive = self.keep_archive;
+        let receiver = progress.begin_phase(Phase::Extracting, None);
+        let result = self.extract(&archive);
+        let report = match result {
+            Ok(extract) => {
+                receiver.finish();
+                extract
+            }
+            Err(e) => {
+                // The archive is downloaded and verified; keep it so a
+                // rerun skips straight to extraction.
+                receiver.finish_with_error(&e);
+                return Err(e);
+            }
+        };
+
+        let archive = if keep_archive {
+            Some(archive)
+        } else {
+            if let Err(e) = std::fs::remove_file(&archive) {
+                log::warn!("failed to remove {}: {e}", archive.display());
+            }
+            None
+        };
+        Ok(FetchReport {
+            downloaded,
+            archive,
+            extract: report,
+        })
+    }
+
+    /// Extract `archive` into the destination.
+    fn extract(self, archive: &Path) -> Result<ExtractReport> {
+        let file = match self.format {
+            Some(format) => ArchiveFile::with_format(archive, format),
+            None => ArchiveFile::new(archive)?,
+        };
+        let mut options =
+            ExtractOptions::new(&self.dest).collect_errors(self.collect_errors);
+        if let Some(mapper) = self.mapper {
+            options = options.with_mapper(mapper);
+        }
+        file.extract(options)
+            .with_desc_with(|| format!("failed to extract {}", archive.display()))
+    }
+}
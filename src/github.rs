@@ -0,0 +1,147 @@
+//! GitHub release asset helpers.
+//!
+//! Requires the `github` feature. [`Release::fetch`] loads a release from
+//! the GitHub REST API and parses it into typed [`Asset`]s; an asset can
+//! then be bridged into a ready [`DownloadBuilder`] with the size pre-filled
+//! and a digest verifier attached when GitHub provides one.
+//!
+//! For private repositories and higher rate limits, pass a client that
+//! sends an `Authorization: Bearer <token>` header with every request; see
+//! [`authenticated_client`].
+
+use std::path::PathBuf;
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+
+use crate::download::DownloadBuilder;
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::http::{Client, Response};
+use crate::verify::hash::DynHashVerifierBuilder;
+
+/// The maximum accepted size of an API response.
+const MAX_RESPONSE_SIZE: usize = 4 * 1024 * 1024;
+
+/// A GitHub release with its assets.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    /// The git tag of the release.
+    pub tag_name: String,
+    /// The human-readable release name, if set.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Whether the release is marked as a prerelease.
+    #[serde(default)]
+    pub prerelease: bool,
+    /// The downloadable assets of the release.
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+/// A downloadable asset of a [`Release`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct Asset {
+    /// The file name of the asset.
+    pub name: String,
+    /// The size of the asset in bytes.
+    pub size: u64,
+    /// The direct download URL.
+    pub browser_download_url: String,
+    /// The MIME content type.
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// The digest of the asset as an `"algo:hex"` string, when GitHub
+    /// provides one.
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+impl Release {
+    /// Fetch a release of `owner/repo` from the GitHub REST API.
+    ///
+    /// `tag` selects the release; `None` fetches the latest (non-prerelease)
+    /// release.
+    pub async fn fetch(
+        client: &impl Client,
+        owner: &str,
+        repo: &str,
+        tag: Option<&str>,
+    ) -> Result<Self> {
+        let url = match tag {
+            Some(tag) => {
+                format!("https://api.github.com/repos/{owner}/{repo}/releases/tags/{tag}")
+            }
+            None => format!("https://api.github.com/repos/{owner}/{repo}/releases/latest"),
+        };
+        let body = fetch_body(client, &url).await?;
+        serde_json::from_slice(&body)
+            .map_err(|e| Error::new(ErrorKind::Other).with_source(e).with_url(&*url))
+            .with_desc("failed to parse the GitHub release")
+    }
+
+    /// The asset with exactly the given file name.
+    pub fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+
+    /// The first asset matching the predicate.
+    ///
+    /// Useful for platform selection, e.g.
+    /// `release.find_asset(|a| a.name.contains("x86_64-linux"))`.
+    pub fn find_asset(&self, mut predicate: impl FnMut(&Asset) -> bool) -> Option<&Asset> {
+        self.assets.iter().find(|asset| predicate(asset))
+    }
+}
+
+impl Asset {
+    /// Create a [`DownloadBuilder`] downloading this asset to `dest`.
+    ///
+    /// The expected size is pre-filled from the asset metadata, and when
+    /// GitHub provides a digest a matching verifier is attached.
+    pub fn download_builder(&self, dest: impl Into<PathBuf>) -> Result<DownloadBuilder> {
+        let mut builder = DownloadBuilder::new(&self.browser_download_url, dest, self.size);
+        if let Some(digest) = &self.digest {
+            builder = builder.with_verifier(
+                DynHashVerifierBuilder::parse(digest)
+                    .with_desc_with(|| format!("invalid digest on asset {}", self.name))?,
+            );
+        }
+        Ok(builder)
+    }
+}
+
+/// Create a [`reqwest::Client`] sending `Authorization: Bearer <token>` with
+/// every request, for private repositories and higher rate limits.
+#[cfg(feature = "reqwest")]
+pub fn authenticated_client(token: &str) -> Result<reqwest::Client> {
+    use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+
+    let mut value = HeaderValue::from_str(&format!("Bearer {token}"))
+        .map_err(|e| Error::new(ErrorKind::Other).with_source(e))
+        .with_desc("invalid characters in the GitHub token")?;
+    value.set_sensitive(true);
+    let mut headers = HeaderMap::new();
+    headers.insert(AUTHORIZATION, value);
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .map_err(Error::from)
+        .with_desc("failed to build the HTTP client")
+}
+
+/// Fetch `url` into memory, rejecting bodies over [`MAX_RESPONSE_SIZE`].
+async fn fetch_body(client: &impl Client, url: &str) -> Result<Vec<u8>> {
+    let response = client.get(url).await.map_err(|e| e.with_url(url))?;
+    let mut stream = response.bytes_stream();
+    let mut buf = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        if buf.len() + chunk.len() > MAX_RESPONSE_SIZE {
+            return Err(Error::new(ErrorKind::Other)
+                .with_url(url)
+                .with_desc_with(|| format!("API response exceeds {MAX_RESPONSE_SIZE} bytes")));
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(buf)
+}
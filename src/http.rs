@@ -0,0 +1,325 @@
+//! Abstractions over HTTP clients.
+//!
+//! The download machinery is generic over a [`Client`] so that it does not
+//! force a particular HTTP implementation (or its TLS stack) on consumers.
+//! With the `reqwest` feature enabled, [`reqwest::Client`] implements
+//! [`Client`] out of the box. The `ftp` feature provides
+//! [`FtpClient`](crate::ftp::FtpClient), serving `ftp://` and `ftps://`
+//! URLs through the same traits.
+
+use std::future::Future;
+
+use bytes::Bytes;
+use futures_util::Stream;
+
+use crate::error::Result;
+
+/// An asynchronous HTTP client.
+pub trait Client {
+    /// The response type produced by this client.
+    type Response: Response;
+
+    /// Send a GET request to `url` and return the response.
+    fn get(&self, url: &str) -> impl Future<Output = Result<Self::Response>> + Send;
+
+    /// Send a GET request to `url` with additional request headers.
+    ///
+    /// Carries headers configured via
+    /// [`DownloadBuilder::with_header`](crate::download::DownloadBuilder::with_header),
+    /// e.g. `Authorization` for a private server or the
+    /// `Accept: application/octet-stream` the GitHub asset API requires.
+    /// The default implementation sends a plain GET, ignoring the headers —
+    /// a server requiring them will answer with an error status.
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+    ) -> impl Future<Output = Result<Self::Response>> + Send {
+        let _ = headers;
+        self.get(url)
+    }
+
+    /// Send a GET request to `url` with an `If-None-Match` header.
+    ///
+    /// Servers supporting conditional requests answer `304 Not Modified`
+    /// (see [`Response::status`]) with an empty body when the resource
+    /// still matches `etag`. The default implementation sends a plain GET,
+    /// ignoring the tag — correct, but never saves a transfer.
+    fn get_if_none_match(
+        &self,
+        url: &str,
+        etag: &str,
+    ) -> impl Future<Output = Result<Self::Response>> + Send {
+        let _ = etag;
+        self.get(url)
+    }
+
+    /// Send a GET request to `url` with an `If-Modified-Since` header.
+    ///
+    /// The counterpart of [`get_if_none_match`](Self::get_if_none_match)
+    /// for servers without ETags: `date` is an [HTTP date](http_date) and
+    /// a `304 Not Modified` answer means the resource has not changed
+    /// since then. The default implementation sends a plain GET, ignoring
+    /// the date.
+    fn get_if_modified_since(
+        &self,
+        url: &str,
+        date: &str,
+    ) -> impl Future<Output = Result<Self::Response>> + Send {
+        let _ = date;
+        self.get(url)
+    }
+
+    /// Send a HEAD request to `url` and return the announced content
+    /// length.
+    ///
+    /// `None` means the server did not answer the HEAD request or did not
+    /// announce a length; callers fall back to the GET-only behavior. The
+    /// default implementation never probes.
+    fn head(&self, url: &str) -> impl Future<Output = Result<Option<u64>>> + Send {
+        let _ = url;
+        async { Ok(None) }
+    }
+}
+
+/// An HTTP response whose body can be streamed.
+pub trait Response: Send {
+    /// The HTTP status code of the response.
+    ///
+    /// Only consulted to recognize `304 Not Modified` on conditional
+    /// requests; the default reports plain success.
+    fn status(&self) -> u16 {
+        200
+    }
+
+    /// The `ETag` header of the response, if the server sent one.
+    fn etag(&self) -> Option<String> {
+        None
+    }
+
+    /// The `Content-Disposition` header of the response, if the server
+    /// sent one.
+    ///
+    /// Consulted by the directory-destination mode
+    /// ([`DownloadBuilder::new_in_dir`](crate::download::DownloadBuilder::new_in_dir))
+    /// to name the file when the URL path is an opaque ID.
+    fn content_disposition(&self) -> Option<String> {
+        None
+    }
+
+    /// The size of the body in bytes, if the server announced one.
+    fn content_length(&self) -> Option<u64> {
+        None
+    }
+
+    /// The RFC 3230 `Digest` header of the response, if the server sent
+    /// one.
+    ///
+    /// Artifact servers like Nexus and some CDNs announce a digest of the
+    /// body, e.g. `sha-256=<base64>`; see
+    /// [`DownloadBuilder::verify_server_digest`](crate::download::DownloadBuilder::verify_server_digest).
+    fn digest(&self) -> Option<String> {
+        None
+    }
+
+    /// The `Content-MD5` header of the response, if the server sent one.
+    fn content_md5(&self) -> Option<String> {
+        None
+    }
+
+    /// Whether the client transparently decoded the body.
+    ///
+    /// Some servers compress responses on the fly, and some clients —
+    /// reqwest with its `gzip` feature enabled, for one — decompress them
+    /// before handing the body on. The streamed bytes then no longer match
+    /// the wire bytes that `Content-Length` describes, so reporting `true`
+    /// here disables the content-length fast-fail. The default reports
+    /// `false`, which is correct for clients passing the body through
+    /// untouched — including reqwest as this crate depends on it, which
+    /// requests no automatic decompression. When a byte-exact artifact is
+    /// required, ask the server to skip on-the-fly compression with
+    /// [`DownloadBuilder::request_identity_encoding`](crate::download::DownloadBuilder::request_identity_encoding).
+    fn is_decoded(&self) -> bool {
+        false
+    }
+
+    /// Consume the response, returning a stream of body chunks.
+    fn bytes_stream(self) -> impl Stream<Item = Result<Bytes>> + Send + Unpin;
+}
+
+/// Format a timestamp as an HTTP date (IMF-fixdate per RFC 9110), e.g.
+/// `Sun, 06 Nov 1994 08:49:37 GMT`.
+///
+/// The output is always in GMT, independent of the local timezone; times
+/// before the Unix epoch are clamped to it.
+pub fn http_date(time: std::time::SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let days = secs / 86_400;
+    let (year, month, day) = civil_from_days(days as i64);
+    // The epoch, day 0, was a Thursday.
+    let weekday = WEEKDAYS[((days + 4) % 7) as usize];
+    format!(
+        "{weekday}, {day:02} {} {year} {:02}:{:02}:{:02} GMT",
+        MONTHS[month as usize - 1],
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
+
+/// Convert days since the epoch to a civil `(year, month, day)` date, via
+/// the era-based algorithm from Howard Hinnant's calendrical notes.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    #[test]
+    fn formats_the_rfc_example_date() {
+        let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+    }
+
+    #[test]
+    fn handles_leap_days() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_164_800);
+        assert_eq!(http_date(time), "Thu, 29 Feb 2024 00:00:00 GMT");
+    }
+
+    #[test]
+    fn clamps_times_before_the_epoch() {
+        let time = UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(http_date(time), "Thu, 01 Jan 1970 00:00:00 GMT");
+    }
+
+    #[test]
+    fn ignores_the_local_timezone() {
+        // The formatting is pure UTC arithmetic; the ambient timezone must
+        // not leak into the output.
+        std::env::set_var("TZ", "America/New_York");
+        let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+    }
+}
+
+#[cfg(feature = "reqwest")]
+mod reqwest_impl {
+    use futures_util::TryStreamExt;
+
+    use super::*;
+
+    impl Client for reqwest::Client {
+        type Response = reqwest::Response;
+
+        async fn get(&self, url: &str) -> Result<Self::Response> {
+            // Turn non-success statuses into errors here, so a 404 becomes
+            // a structured not-found error instead of streaming the error
+            // page into the destination file.
+            Ok(self.get(url).send().await?.error_for_status()?)
+        }
+
+        async fn get_with_headers(
+            &self,
+            url: &str,
+            headers: &[(String, String)],
+        ) -> Result<Self::Response> {
+            let mut request = self.get(url);
+            for (name, value) in headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+            Ok(request.send().await?.error_for_status()?)
+        }
+
+        async fn get_if_none_match(&self, url: &str, etag: &str) -> Result<Self::Response> {
+            // A 304 is not an error status, so it passes through here.
+            Ok(self
+                .get(url)
+                .header(reqwest::header::IF_NONE_MATCH, etag)
+                .send()
+                .await?
+                .error_for_status()?)
+        }
+
+        async fn get_if_modified_since(&self, url: &str, date: &str) -> Result<Self::Response> {
+            Ok(self
+                .get(url)
+                .header(reqwest::header::IF_MODIFIED_SINCE, date)
+                .send()
+                .await?
+                .error_for_status()?)
+        }
+
+        async fn head(&self, url: &str) -> Result<Option<u64>> {
+            // A server refusing HEAD is not an error, only a missing hint.
+            match self.head(url).send().await {
+                Ok(response) if response.status().is_success() => {
+                    Ok(reqwest::Response::content_length(&response))
+                }
+                _ => Ok(None),
+            }
+        }
+    }
+
+    impl Response for reqwest::Response {
+        fn status(&self) -> u16 {
+            reqwest::Response::status(self).as_u16()
+        }
+
+        fn etag(&self) -> Option<String> {
+            self.headers()
+                .get(reqwest::header::ETAG)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        }
+
+        fn content_disposition(&self) -> Option<String> {
+            self.headers()
+                .get(reqwest::header::CONTENT_DISPOSITION)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        }
+
+        fn content_length(&self) -> Option<u64> {
+            reqwest::Response::content_length(self)
+        }
+
+        fn digest(&self) -> Option<String> {
+            self.headers()
+                .get("digest")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        }
+
+        fn content_md5(&self) -> Option<String> {
+            self.headers()
+                .get("content-md5")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        }
+
+        fn bytes_stream(self) -> impl Stream<Item = Result<Bytes>> + Send + Unpin {
+            reqwest::Response::bytes_stream(self).map_err(Into::into)
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! A toolkit to download, verify and extract files.
+//!
+//! This crate provides composable building blocks for fetching artifacts:
+//!
+//! - [`download`]: stream a URL to a local file, with optional mirror
+//!   selection and progress reporting;
+//! - [`verify`]: check downloaded content against expected sizes, digests
+//!   or signatures, during the download or afterwards;
+//! - [`extract`]: unpack downloaded archives (feature gated per format);
+//! - [`fetch`]: the high-level pipeline running all of the above in one
+//!   call;
+//! - [`compress`]: decompress single compressed files (feature gated per
+//!   format).
+//!
+//! # Example
+//!
+//! For the common "download this archive, check its digest, unpack it here"
+//! case, [`fetch`](fetch::fetch) runs the whole pipeline in one call:
+//!
+//! ```no_run
+//! # #[cfg(feature = "tar")]
+//! # async fn example() -> fetchkit::Result<()> {
+//! use fetchkit::{fetch, FetchSpec};
+//!
+//! let client = reqwest::Client::new();
+//! let sha256 = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+//! fetch(
+//!     &client,
+//!     "https://example.com/tool.tar.gz",
+//!     FetchSpec {
+//!         dest_dir: "tool".into(),
+//!         checksum: Some(format!("sha256:{sha256}")),
+//!         strip_components: 1,
+//!         ..Default::default()
+//!     },
+//! )
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The individual download, verification and extraction building blocks
+//! remain available for pipelines that need more control; see
+//! [`DownloadBuilder`](download::DownloadBuilder) for the download-only
+//! entry point.
+//!
+//! # Async runtimes
+//!
+//! The download machinery itself only depends on `futures_util` and runs
+//! on any executor. Features that need timers or a blocking pool pick
+//! their backend by feature flag:
+//!
+//! - `tokio`: channel based progress reporting, and timers via
+//!   `tokio::time` — these must run inside a tokio runtime;
+//! - `smol`: timers via `async-io` and a blocking pool via `blocking`,
+//!   which drive themselves and work on any executor (smol, async-std,
+//!   or even tokio). When both features are enabled, this backend is used
+//!   for timers.
+//!
+//! APIs that need a timer (e.g.
+//! [`DownloadBuilder::with_timeout`](download::DownloadBuilder::with_timeout))
+//! are only available with at least one of these features.
+
+pub mod download;
+pub mod error;
+pub mod http;
+pub mod progress;
+pub mod target;
+pub mod verify;
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+pub(crate) mod runtime;
+
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "xz"))]
+pub mod compress;
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub mod extract;
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub mod fetch;
+#[cfg(feature = "ftp")]
+pub mod ftp;
+#[cfg(feature = "github")]
+pub mod github;
+#[cfg(feature = "lockfile")]
+pub mod lockfile;
+#[cfg(feature = "manifest")]
+pub mod manifest;
+#[cfg(feature = "metalink")]
+pub mod metalink;
+#[cfg(feature = "self-update")]
+pub mod self_update;
+
+pub use error::{Error, ErrorKind, Result, VerifyDetails};
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub use fetch::{fetch, FetchReport, FetchSpec, Fetcher};
@@ -0,0 +1,244 @@
+//! Lockfiles pinning fetched artifacts for reproducible installs.
+//!
+//! Requires the `lockfile` feature. A [`Lockfile`] records, per artifact
+//! name, the URL an artifact was fetched from, its byte size, its SHA-256
+//! digest and when it was fetched. The first successful fetch populates it
+//! (capture the digest with a
+//! [`DigestRecorder`](crate::verify::hash::DigestRecorder), or build an
+//! entry from the file on disk with [`LockEntry::for_file`]); later runs
+//! consume it as the source of truth: [`LockEntry::verifier`] turns the
+//! pinned digest into a verifier, so any drift fails verification with an
+//! error naming both digests.
+//!
+//! The on-disk format is JSON with sorted keys and a schema version, so
+//! lockfiles diff cleanly and can be committed.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::download::{DestLock, LockWait};
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::verify::hash::DynHashVerifierBuilder;
+
+/// The newest lockfile schema version this crate reads and writes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A lockfile pinning fetched artifacts by name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// The schema version of the file.
+    pub version: u32,
+    /// The pinned artifacts, keyed by name.
+    pub artifacts: BTreeMap<String, LockEntry>,
+}
+
+/// The pinned facts of one artifact.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockEntry {
+    /// The URL the artifact was fetched from.
+    pub url: String,
+    /// The size of the artifact in bytes.
+    pub size: u64,
+    /// The SHA-256 digest of the artifact in hex.
+    pub sha256: String,
+    /// When the artifact was fetched, in seconds since the Unix epoch.
+    pub fetched_at: u64,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            artifacts: BTreeMap::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Create an empty lockfile at the current schema version.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a lockfile, rejecting schema versions newer than this crate
+    /// writes.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::from(e).with_path(path))
+            .with_desc("failed to read the lockfile")?;
+        let lockfile: Self = serde_json::from_str(&content)
+            .map_err(|e| Error::new(ErrorKind::Other).with_source(e).with_path(path))
+            .with_desc("failed to parse the lockfile")?;
+        if lockfile.version > SCHEMA_VERSION {
+            return Err(Error::new(ErrorKind::Other).with_path(path).with_desc_with(
+                || {
+                    format!(
+                        "unsupported lockfile version {} (newest supported: {SCHEMA_VERSION})",
+                        lockfile.version
+                    )
+                },
+            ));
+        }
+        Ok(lockfile)
+    }
+
+    /// Save the lockfile with stable formatting.
+    ///
+    /// Entries are written sorted by name, so saving an unchanged lockfile
+    /// is byte-identical. The file is written to a sibling temp file and
+    /// renamed into place under a [`DestLock`], so concurrent writers do
+    /// not interleave.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let _lock = DestLock::acquire(path, LockWait::Wait)
+            .with_desc("failed to lock the lockfile")?;
+        let mut json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::new(ErrorKind::Other).with_source(e))
+            .with_desc("failed to serialize the lockfile")?;
+        json.push('\n');
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, json)
+            .map_err(|e| Error::from(e).with_path(&tmp))
+            .with_desc("failed to write the lockfile")?;
+        std::fs::rename(&tmp, path)
+            .map_err(|e| Error::from(e).with_path(path))
+            .with_desc("failed to replace the lockfile")
+    }
+
+    /// The pinned entry of an artifact, if any.
+    pub fn entry(&self, name: &str) -> Option<&LockEntry> {
+        self.artifacts.get(name)
+    }
+
+    /// Merge the facts of a completed fetch, replacing any previous entry.
+    ///
+    /// Returns whether the lockfile changed (the timestamp is ignored in
+    /// the comparison, so re-fetching an identical artifact is not a
+    /// change).
+    pub fn update_from(&mut self, name: &str, entry: LockEntry) -> bool {
+        match self.artifacts.get(name) {
+            Some(previous)
+                if previous.url == entry.url
+                    && previous.size == entry.size
+                    && previous.sha256 == entry.sha256 =>
+            {
+                false
+            }
+            _ => {
+                self.artifacts.insert(name.to_string(), entry);
+                true
+            }
+        }
+    }
+}
+
+impl LockEntry {
+    /// Create an entry from a fetch's facts, timestamped now.
+    pub fn new(url: impl Into<String>, size: u64, sha256: impl Into<String>) -> Self {
+        let fetched_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            url: url.into(),
+            size,
+            sha256: sha256.into(),
+            fetched_at,
+        }
+    }
+
+    /// Create an entry by hashing an already fetched file.
+    #[cfg(feature = "sha2")]
+    pub fn for_file(url: impl Into<String>, path: &Path) -> Result<Self> {
+        use sha2::{Digest, Sha256};
+
+        let file = std::fs::File::open(path)
+            .map_err(|e| Error::from(e).with_path(path))
+            .with_desc("failed to open the fetched file")?;
+        let mut reader = std::io::BufReader::new(file);
+        let mut hasher = Sha256::new();
+        let size = std::io::copy(&mut reader, &mut hasher)
+            .map_err(|e| Error::from(e).with_path(path))
+            .with_desc("failed to read the fetched file")?;
+        Ok(Self::new(url, size, hex::encode(hasher.finalize())))
+    }
+
+    /// A verifier checking content against the pinned digest.
+    ///
+    /// Content that drifted from the pin fails with a
+    /// [`Verify`](crate::ErrorKind::Verify) error naming both digests.
+    pub fn verifier(&self) -> Result<DynHashVerifierBuilder> {
+        DynHashVerifierBuilder::parse(&format!("sha256:{}", self.sha256))
+            .with_desc("invalid digest in the lockfile")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_disk_with_stable_formatting() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fetch.lock");
+        let mut lockfile = Lockfile::new();
+        // Inserted out of order; the serialization sorts by name.
+        lockfile.update_from("zlib", LockEntry::new("https://example.com/z", 2, "beef"));
+        lockfile.update_from("tool", LockEntry::new("https://example.com/t", 1, "dead"));
+        lockfile.save(&path).unwrap();
+
+        let loaded = Lockfile::load(&path).unwrap();
+        assert_eq!(loaded, lockfile);
+
+        let first = std::fs::read(&path).unwrap();
+        let tool = first.windows(6).position(|w| w == b"\"tool\"").unwrap();
+        let zlib = first.windows(6).position(|w| w == b"\"zlib\"").unwrap();
+        assert!(tool < zlib, "entries are not sorted by name");
+        // Saving again is byte-identical.
+        loaded.save(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), first);
+    }
+
+    #[test]
+    fn update_from_ignores_the_timestamp() {
+        let mut lockfile = Lockfile::new();
+        assert!(lockfile.update_from("tool", LockEntry::new("u", 1, "dead")));
+        let mut same = LockEntry::new("u", 1, "dead");
+        same.fetched_at += 1000;
+        assert!(!lockfile.update_from("tool", same));
+        assert!(lockfile.update_from("tool", LockEntry::new("u", 1, "beef")));
+    }
+
+    #[test]
+    fn rejects_newer_schema_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fetch.lock");
+        std::fs::write(&path, r#"{"version": 99, "artifacts": {}}"#).unwrap();
+        let err = Lockfile::load(&path).unwrap_err();
+        assert!(err.to_string().contains("unsupported lockfile version 99"));
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn for_file_hashes_the_fetched_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data");
+        std::fs::write(&path, b"hello world").unwrap();
+        let entry = LockEntry::for_file("https://example.com/data", &path).unwrap();
+        assert_eq!(entry.size, 11);
+        assert_eq!(
+            entry.sha256,
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+        // Drift against the pin is a verification error naming both
+        // digests.
+        use crate::verify::{Verifier, VerifierBuilder};
+        let mut verifier = entry.verifier().unwrap().build().unwrap();
+        verifier.update(b"tampered");
+        let err = verifier.verify().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(err.to_string().contains(&entry.sha256));
+    }
+}
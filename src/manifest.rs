@@ -0,0 +1,335 @@
+//! Manifest-driven multi-artifact fetches.
+//!
+//! Requires the `manifest` feature. A [`FetchManifest`] is a declarative
+//! list of artifacts — URL, mirrors, checksum, destination, extraction
+//! options — parsed from TOML or JSON and executed through the
+//! [`fetch`](crate::fetch) pipeline with bounded concurrency:
+//!
+//! ```toml
+//! [artifacts.tool]
+//! url = "https://example.com/tool.tar.gz"
+//! mirrors = ["https://mirror.example.com/tool.tar.gz"]
+//! checksum = "sha256:9f86…"
+//! dest = "opt/tool"
+//! strip_components = 1
+//!
+//! [artifacts.data]
+//! url = "https://example.com/data.txt"
+//! dest = "opt/data"
+//! ```
+//!
+//! Mirror ranking is shared across the whole run: each mirror host is
+//! probed once, so artifacts served from the same mirrors do not probe
+//! them again.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+
+use crate::download::MirrorOptions;
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::fetch::{fetch_with, FetchReport, FetchSpec};
+use crate::http::Client;
+use crate::progress::{Group, PerPhase};
+use crate::verify::hash::DynHashVerifierBuilder;
+
+/// A declarative list of artifacts to fetch.
+///
+/// Parse one with [`from_toml`](Self::from_toml) or
+/// [`from_json`](Self::from_json) and run it with
+/// [`execute`](Self::execute). Artifacts are keyed by name; the name keys
+/// the per-artifact results of the run.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FetchManifest {
+    /// The artifacts to fetch, keyed by name.
+    pub artifacts: BTreeMap<String, ArtifactSpec>,
+}
+
+/// One artifact of a [`FetchManifest`].
+///
+/// The fields mirror [`FetchSpec`], plus the URL and its mirrors.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArtifactSpec {
+    /// The primary download URL.
+    pub url: String,
+    /// Alternative, fully expanded URLs serving the same file.
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// The directory the artifact ends up in.
+    pub dest: PathBuf,
+    /// The expected checksum as an `"algo:hex"` string.
+    #[serde(default)]
+    pub checksum: Option<String>,
+    /// The expected size in bytes, `0` when unknown.
+    #[serde(default)]
+    pub size: u64,
+    /// Strip this many leading path components from every archive entry.
+    #[serde(default)]
+    pub strip_components: usize,
+    /// Keep the archive after extraction instead of deleting it.
+    #[serde(default)]
+    pub keep_archive: bool,
+}
+
+/// Options for [`FetchManifest::execute`].
+#[derive(Clone, Default)]
+pub struct ExecuteOptions {
+    /// How many artifacts to fetch concurrently; `0` means one at a time.
+    pub concurrency: usize,
+    /// Continue with the remaining artifacts when one fails, collecting the
+    /// failure into the report, instead of aborting the run.
+    pub continue_on_error: bool,
+    /// A progress group aggregating the byte progress of all artifacts.
+    pub progress: Group,
+    /// A lockfile pinning artifact digests: when an entry exists for an
+    /// artifact, its digest replaces the manifest checksum, so drift fails
+    /// verification.
+    #[cfg(feature = "lockfile")]
+    pub lockfile: Option<crate::lockfile::Lockfile>,
+}
+
+/// The per-artifact results of a manifest run, keyed by artifact name.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ManifestReport {
+    /// The result of each artifact.
+    pub artifacts: BTreeMap<String, Result<FetchReport>>,
+}
+
+impl ManifestReport {
+    /// Whether every artifact succeeded.
+    pub fn is_success(&self) -> bool {
+        self.artifacts.values().all(|result| result.is_ok())
+    }
+
+    /// The failed artifacts and their errors.
+    pub fn failures(&self) -> impl Iterator<Item = (&str, &Error)> {
+        self.artifacts
+            .iter()
+            .filter_map(|(name, result)| Some((name.as_str(), result.as_ref().err()?)))
+    }
+}
+
+impl FetchManifest {
+    /// Parse and validate a manifest from its TOML representation.
+    pub fn from_toml(manifest: &str) -> Result<Self> {
+        let manifest: Self = toml::from_str(manifest)
+            .map_err(|e| Error::new(ErrorKind::Other).with_source(e))
+            .with_desc("failed to parse the manifest")?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Parse and validate a manifest from its JSON representation.
+    pub fn from_json(manifest: &str) -> Result<Self> {
+        let manifest: Self = serde_json::from_str(manifest)
+            .map_err(|e| Error::new(ErrorKind::Other).with_source(e))
+            .with_desc("failed to parse the manifest")?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Validate a manifest constructed in code.
+    ///
+    /// Rejects empty URLs or destinations, destinations shared between
+    /// artifacts, and checksums with an unknown algorithm or invalid digest.
+    /// [`from_toml`](Self::from_toml) and [`from_json`](Self::from_json)
+    /// already validate.
+    pub fn validate(&self) -> Result<()> {
+        let mut destinations: HashSet<&Path> = HashSet::new();
+        for (name, artifact) in &self.artifacts {
+            let invalid =
+                |desc| Error::new(ErrorKind::Other).with_desc(desc).with_desc_with(|| {
+                    format!("invalid artifact {name}")
+                });
+            if artifact.url.is_empty() {
+                return Err(invalid("url must not be empty"));
+            }
+            if artifact.dest.as_os_str().is_empty() {
+                return Err(invalid("dest must not be empty"));
+            }
+            if !destinations.insert(&artifact.dest) {
+                return Err(invalid("dest is already used by another artifact"));
+            }
+            if let Some(checksum) = &artifact.checksum {
+                DynHashVerifierBuilder::parse(checksum)
+                    .with_desc_with(|| format!("invalid artifact {name}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Expand `{os}`-style placeholders in every URL and mirror.
+    ///
+    /// See [`target::expand`](crate::target::expand) for the template
+    /// syntax. Destinations and checksums are not templated.
+    pub fn expand(mut self, vars: &crate::target::TargetVars) -> Result<Self> {
+        for artifact in self.artifacts.values_mut() {
+            artifact.url = crate::target::expand(&artifact.url, vars)?;
+            for mirror in &mut artifact.mirrors {
+                *mirror = crate::target::expand(mirror, vars)?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Fetch all artifacts of the manifest.
+    ///
+    /// Artifacts run through the [`fetch`](crate::fetch) pipeline with at
+    /// most [`concurrency`](ExecuteOptions::concurrency) in flight at once.
+    /// Mirrors are ranked by probing each mirror host once for the whole
+    /// run; the fastest answering candidate of each artifact is used. By
+    /// default the first failure aborts the run (artifacts still in flight
+    /// are cancelled); with
+    /// [`continue_on_error`](ExecuteOptions::continue_on_error) failures
+    /// are collected into the report instead.
+    pub async fn execute<C: Client>(
+        &self,
+        client: &C,
+        options: ExecuteOptions,
+    ) -> Result<ManifestReport> {
+        self.validate()?;
+        let ranking = MirrorRanking::default();
+        let options_ref = &options;
+        let mut results = futures_util::stream::iter(self.artifacts.iter().map(
+            |(name, artifact)| {
+                let ranking = &ranking;
+                // A lock entry pins the digest, overriding the manifest
+                // checksum.
+                #[cfg(feature = "lockfile")]
+                let checksum = options_ref
+                    .lockfile
+                    .as_ref()
+                    .and_then(|lockfile| lockfile.entry(name))
+                    .map(|entry| format!("sha256:{}", entry.sha256))
+                    .or_else(|| artifact.checksum.clone());
+                #[cfg(not(feature = "lockfile"))]
+                let checksum = artifact.checksum.clone();
+                async move {
+                    let result = Self::execute_artifact(
+                        client,
+                        artifact,
+                        checksum,
+                        ranking,
+                        &options_ref.progress,
+                    )
+                    .await
+                    .with_desc_with(|| format!("failed to fetch artifact {name}"));
+                    (name.clone(), result)
+                }
+            },
+        ))
+        .buffer_unordered(options.concurrency.max(1));
+
+        let mut report = ManifestReport::default();
+        while let Some((name, result)) = results.next().await {
+            if let Err(e) = result {
+                if !options.continue_on_error {
+                    return Err(e);
+                }
+                report.artifacts.insert(name, Err(e));
+            } else {
+                report.artifacts.insert(name, result);
+            }
+        }
+        options.progress.finish();
+        Ok(report)
+    }
+
+    /// Fetch a single artifact, resolving its URL through the shared mirror
+    /// ranking.
+    async fn execute_artifact<C: Client>(
+        client: &C,
+        artifact: &ArtifactSpec,
+        checksum: Option<String>,
+        ranking: &MirrorRanking,
+        progress: &Group,
+    ) -> Result<FetchReport> {
+        let url = if artifact.mirrors.is_empty() {
+            artifact.url.as_str()
+        } else {
+            ranking.resolve(client, artifact).await?
+        };
+        let spec = FetchSpec {
+            dest_dir: artifact.dest.clone(),
+            checksum,
+            strip_components: artifact.strip_components,
+            size: artifact.size,
+            keep_archive: artifact.keep_archive,
+        };
+        fetch_with(client, url, spec, &PerPhase::new(progress.child())).await
+    }
+}
+
+/// Probe timings shared across all artifacts of a run, keyed by mirror
+/// host.
+#[derive(Default)]
+struct MirrorRanking {
+    hosts: Mutex<HashMap<String, Option<Duration>>>,
+}
+
+impl MirrorRanking {
+    /// The fastest answering candidate URL of `artifact`.
+    ///
+    /// Each host is probed at most once per run; hosts that failed their
+    /// probe disqualify all their candidates. Two artifacts racing on the
+    /// same fresh host may both probe it, which is harmless.
+    async fn resolve<'a, C: Client>(
+        &self,
+        client: &C,
+        artifact: &'a ArtifactSpec,
+    ) -> Result<&'a str> {
+        let mut best: Option<(&'a str, Duration)> = None;
+        let candidates = std::iter::once(artifact.url.as_str())
+            .chain(artifact.mirrors.iter().map(String::as_str));
+        for candidate in candidates {
+            let host = host_of(candidate);
+            let cached = self.hosts.lock().expect("not poisoned").get(host).copied();
+            let elapsed = match cached {
+                Some(elapsed) => elapsed,
+                None => {
+                    let elapsed = match MirrorOptions::probe(client, candidate, &[]).await {
+                        Ok(elapsed) => Some(elapsed),
+                        Err(e) => {
+                            log::warn!("mirror {candidate} failed: {e:#}");
+                            None
+                        }
+                    };
+                    self.hosts
+                        .lock()
+                        .expect("not poisoned")
+                        .insert(host.to_string(), elapsed);
+                    elapsed
+                }
+            };
+            if let Some(elapsed) = elapsed {
+                if best.is_none_or(|(_, best_elapsed)| elapsed < best_elapsed) {
+                    best = Some((candidate, elapsed));
+                }
+            }
+        }
+        match best {
+            Some((url, _)) => Ok(url),
+            None => Err(Error::new(ErrorKind::Network)
+                .with_url(&*artifact.url)
+                .with_desc("no mirror candidate answered")),
+        }
+    }
+}
+
+/// The scheme and host part of a URL, used as the ranking key.
+fn host_of(url: &str) -> &str {
+    match url.find("://") {
+        Some(scheme) => match url[scheme + 3..].find('/') {
+            Some(path) => &url[..scheme + 3 + path],
+            None => url,
+        },
+        None => url,
+    }
+}
@@ -0,0 +1,245 @@
+//! Metalink download descriptions.
+//!
+//! Requires the `metalink` feature. Distros publish Metalink documents —
+//! `.meta4` (RFC 5854) or the older `.metalink` version 3 — describing a
+//! file's download URLs, size and checksums in one place.
+//! [`Metalink::parse`] reads either flavor, and
+//! [`DownloadBuilder::from_metalink`](crate::download::DownloadBuilder::from_metalink)
+//! turns the result into a ready-made download: the first URL as the
+//! primary, the rest as mirrors, and the strongest recognized hash as
+//! the verifier.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::verify::hash::HashAlgorithm;
+
+/// A parsed Metalink document, covering the first file it describes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metalink {
+    /// The URLs serving the file, in document order.
+    pub urls: Vec<String>,
+    /// The size of the file in bytes, when the document announces one.
+    pub size: Option<u64>,
+    /// The announced whole-file hashes as `(algorithm, hex digest)`
+    /// pairs, in document order. Algorithm names are kept as written,
+    /// e.g. `sha-256`; piece hashes are not included.
+    pub hashes: Vec<(String, String)>,
+}
+
+/// The element whose text content is being collected.
+enum Field {
+    Url,
+    Size,
+    Hash(String),
+}
+
+impl Metalink {
+    /// Parse a `.meta4` or `.metalink` document.
+    ///
+    /// Both the Metalink 4 layout of RFC 5854 and the older version 3
+    /// layout (`<resources>`/`<verification>` wrappers) are understood;
+    /// only the first `<file>` element is read. A document describing no
+    /// file is an error, while a missing size or missing hashes merely
+    /// leave those fields empty.
+    pub fn parse(document: &str) -> Result<Self> {
+        let mut reader = Reader::from_str(document);
+        reader.config_mut().trim_text(true);
+
+        let mut metalink = Self::default();
+        let mut seen_file = false;
+        let mut in_file = false;
+        let mut in_pieces = false;
+        let mut field = None;
+        loop {
+            let event = reader.read_event().map_err(|e| {
+                Error::new(ErrorKind::Other)
+                    .with_source(e)
+                    .with_desc("invalid metalink document")
+            })?;
+            match event {
+                Event::Start(start) => match start.local_name().as_ref() {
+                    b"file" if !seen_file => {
+                        seen_file = true;
+                        in_file = true;
+                    }
+                    // Piece hashes cover segments, not the whole file.
+                    b"pieces" if in_file => in_pieces = true,
+                    b"url" if in_file && !in_pieces => field = Some(Field::Url),
+                    b"size" if in_file && !in_pieces => field = Some(Field::Size),
+                    b"hash" if in_file && !in_pieces => {
+                        let algorithm = start
+                            .try_get_attribute("type")
+                            .ok()
+                            .flatten()
+                            .and_then(|a| a.unescape_value().ok())
+                            .unwrap_or_default();
+                        field = Some(Field::Hash(algorithm.into_owned()));
+                    }
+                    _ => {}
+                },
+                Event::Text(text) => {
+                    let value = text.unescape().map_err(|e| {
+                        Error::new(ErrorKind::Other)
+                            .with_source(e)
+                            .with_desc("invalid metalink document")
+                    })?;
+                    match field.take() {
+                        Some(Field::Url) => metalink.urls.push(value.into_owned()),
+                        Some(Field::Size) => {
+                            let size = value.trim().parse().map_err(|e| {
+                                Error::new(ErrorKind::Other).with_source(e).with_desc_with(
+                                    || format!("invalid size in metalink document: {value}"),
+                                )
+                            })?;
+                            metalink.size = Some(size);
+                        }
+                        Some(Field::Hash(algorithm)) => {
+                            metalink.hashes.push((algorithm, value.trim().to_owned()));
+                        }
+                        None => {}
+                    }
+                }
+                Event::End(end) => match end.local_name().as_ref() {
+                    b"file" if in_file => break,
+                    b"pieces" => in_pieces = false,
+                    _ => field = None,
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        if !seen_file {
+            return Err(Error::new(ErrorKind::Other)
+                .with_desc("the metalink document describes no file"));
+        }
+        Ok(metalink)
+    }
+
+    /// The strongest hash this build can verify, preferring `sha-512`
+    /// over `sha-256` over `sha-1`.
+    ///
+    /// Hashes with an unknown algorithm name — or one whose feature is
+    /// disabled — are skipped; `None` means no usable hash is listed.
+    pub fn strongest_hash(&self) -> Option<(HashAlgorithm, &str)> {
+        for preferred in ["sha-512", "sha-256", "sha-1"] {
+            let Ok(wanted) = preferred.parse::<HashAlgorithm>() else {
+                continue;
+            };
+            if let Some((_, digest)) = self
+                .hashes
+                .iter()
+                .find(|(name, _)| name.parse::<HashAlgorithm>().is_ok_and(|a| a == wanted))
+            {
+                return Some((wanted, digest));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const META4: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metalink xmlns="urn:ietf:params:xml:ns:metalink">
+  <file name="example.tar.gz">
+    <size>11</size>
+    <hash type="sha-256">b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9</hash>
+    <hash type="whirlpool">deadbeef</hash>
+    <pieces type="sha-256" length="4">
+      <hash>0000</hash>
+    </pieces>
+    <url location="de">https://mirror.example.de/example.tar.gz</url>
+    <url>https://example.com/example.tar.gz</url>
+  </file>
+</metalink>"#;
+
+    const METALINK_V3: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<metalink version="3.0" xmlns="http://www.metalinker.org/">
+  <files>
+    <file name="example.tar.gz">
+      <size>11</size>
+      <verification>
+        <hash type="sha1">2aae6c35c94fcfb415dbe95f408b9ce91ee846ed</hash>
+      </verification>
+      <resources>
+        <url type="http">https://example.com/example.tar.gz</url>
+      </resources>
+    </file>
+  </files>
+</metalink>"#;
+
+    #[test]
+    fn parses_a_meta4_document() {
+        let metalink = Metalink::parse(META4).unwrap();
+        assert_eq!(
+            metalink.urls,
+            [
+                "https://mirror.example.de/example.tar.gz",
+                "https://example.com/example.tar.gz"
+            ]
+        );
+        assert_eq!(metalink.size, Some(11));
+        // The whole-file hashes, including the unknown algorithm, but not
+        // the piece hashes.
+        assert_eq!(metalink.hashes.len(), 2);
+        assert_eq!(metalink.hashes[0].0, "sha-256");
+        assert_eq!(metalink.hashes[1].0, "whirlpool");
+    }
+
+    #[test]
+    fn parses_a_version_3_document() {
+        let metalink = Metalink::parse(METALINK_V3).unwrap();
+        assert_eq!(metalink.urls, ["https://example.com/example.tar.gz"]);
+        assert_eq!(metalink.size, Some(11));
+        assert_eq!(
+            metalink.hashes,
+            [(
+                "sha1".to_owned(),
+                "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn only_the_first_file_is_read() {
+        let document = r#"<metalink xmlns="urn:ietf:params:xml:ns:metalink">
+          <file name="a"><url>https://example.com/a</url></file>
+          <file name="b"><url>https://example.com/b</url></file>
+        </metalink>"#;
+        let metalink = Metalink::parse(document).unwrap();
+        assert_eq!(metalink.urls, ["https://example.com/a"]);
+    }
+
+    #[test]
+    fn a_document_without_a_file_is_rejected() {
+        let err = Metalink::parse("<metalink></metalink>").unwrap_err();
+        assert!(err.to_string().contains("describes no file"));
+    }
+
+    #[test]
+    fn malformed_xml_is_rejected() {
+        let err = Metalink::parse("<metalink><file></metalink>").unwrap_err();
+        assert!(err.to_string().contains("invalid metalink document"));
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn the_strongest_recognized_hash_wins() {
+        let metalink = Metalink {
+            hashes: vec![
+                ("md5".to_owned(), "aa".repeat(16)),
+                ("sha-256".to_owned(), "bb".repeat(32)),
+                ("whirlpool".to_owned(), "cc".repeat(64)),
+                ("sha-512".to_owned(), "dd".repeat(64)),
+            ],
+            ..Default::default()
+        };
+        let (algorithm, digest) = metalink.strongest_hash().unwrap();
+        assert_eq!(algorithm, HashAlgorithm::Sha512);
+        assert_eq!(digest, "dd".repeat(64));
+    }
+}
@@ -0,0 +1,190 @@
+//! Channel based progress reporting for event loops.
+//!
+//! GUI frameworks want progress as messages on a channel instead of
+//! callbacks from the download task. [`sender`] builds a
+//! [`ProgressReceiverBuilder`] whose events arrive on a bounded tokio
+//! channel (requires the `tokio` feature); [`sync_sender`] is the std-mpsc
+//! variant for non-async UIs. Events are sent with `try_send`, so a slow
+//! consumer never blocks the download: when the channel is full,
+//! intermediate positions are simply dropped and the consumer only sees the
+//! most recent ones it managed to drain.
+
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder};
+
+/// A progress event sent over a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// The download started; `total` is the expected size in bytes when
+    /// known.
+    Started {
+        /// The total number of bytes expected, if known.
+        total: Option<u64>,
+    },
+    /// The total became known after the download started (e.g. from
+    /// response headers).
+    Total(u64),
+    /// The current position in bytes.
+    Position(u64),
+    /// The download finished.
+    Finished,
+}
+
+/// Create a progress receiver builder reporting over a bounded tokio
+/// channel of the given capacity.
+pub fn sender(capacity: usize) -> (ChannelProgressBuilder, tokio::sync::mpsc::Receiver<ProgressEvent>) {
+    let (tx, rx) = tokio::sync::mpsc::channel(capacity);
+    (ChannelProgressBuilder { tx }, rx)
+}
+
+/// The builder returned by [`sender`].
+#[derive(Debug, Clone)]
+pub struct ChannelProgressBuilder {
+    tx: tokio::sync::mpsc::Sender<ProgressEvent>,
+}
+
+impl ProgressReceiverBuilder for ChannelProgressBuilder {
+    type Receiver = ChannelProgressReceiver;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        let _ = self.tx.try_send(ProgressEvent::Started { total });
+        ChannelProgressReceiver { tx: self.tx }
+    }
+}
+
+/// The receiver built by [`ChannelProgressBuilder`].
+#[derive(Debug)]
+pub struct ChannelProgressReceiver {
+    tx: tokio::sync::mpsc::Sender<ProgressEvent>,
+}
+
+impl ProgressReceiver for ChannelProgressReceiver {
+    fn set_position(&self, position: u64) {
+        let _ = self.tx.try_send(ProgressEvent::Position(position));
+    }
+
+    fn set_total(&self, total: u64) {
+        let _ = self.tx.try_send(ProgressEvent::Total(total));
+    }
+
+    fn finish(&self) {
+        let _ = self.tx.try_send(ProgressEvent::Finished);
+    }
+}
+
+/// Create a progress receiver builder reporting over a bounded std-mpsc
+/// channel of the given capacity, for consumers without an async runtime.
+pub fn sync_sender(
+    capacity: usize,
+) -> (SyncChannelProgressBuilder, std::sync::mpsc::Receiver<ProgressEvent>) {
+    let (tx, rx) = std::sync::mpsc::sync_channel(capacity);
+    (SyncChannelProgressBuilder { tx }, rx)
+}
+
+/// The builder returned by [`sync_sender`].
+#[derive(Debug, Clone)]
+pub struct SyncChannelProgressBuilder {
+    tx: std::sync::mpsc::SyncSender<ProgressEvent>,
+}
+
+impl ProgressReceiverBuilder for SyncChannelProgressBuilder {
+    type Receiver = SyncChannelProgressReceiver;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        let _ = self.tx.try_send(ProgressEvent::Started { total });
+        SyncChannelProgressReceiver { tx: self.tx }
+    }
+}
+
+/// The receiver built by [`SyncChannelProgressBuilder`].
+#[derive(Debug)]
+pub struct SyncChannelProgressReceiver {
+    tx: std::sync::mpsc::SyncSender<ProgressEvent>,
+}
+
+impl ProgressReceiver for SyncChannelProgressReceiver {
+    fn set_position(&self, position: u64) {
+        let _ = self.tx.try_send(ProgressEvent::Position(position));
+    }
+
+    fn set_total(&self, total: u64) {
+        let _ = self.tx.try_send(ProgressEvent::Total(total));
+    }
+
+    fn finish(&self) {
+        let _ = self.tx.try_send(ProgressEvent::Finished);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_ordering() {
+        let (builder, mut rx) = sender(16);
+        let receiver = builder.init(None);
+        receiver.set_total(10);
+        receiver.set_position(3);
+        receiver.set_position(7);
+        receiver.finish();
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(
+            events,
+            [
+                ProgressEvent::Started { total: None },
+                ProgressEvent::Total(10),
+                ProgressEvent::Position(3),
+                ProgressEvent::Position(7),
+                ProgressEvent::Finished,
+            ]
+        );
+    }
+
+    #[test]
+    fn positions_are_dropped_under_backpressure() {
+        let (builder, mut rx) = sender(2);
+        let receiver = builder.init(Some(100));
+        for position in 1..=100 {
+            receiver.set_position(position);
+        }
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        // The channel was never drained, so only the first events fit; the
+        // download was not blocked by the full channel.
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], ProgressEvent::Started { total: Some(100) });
+    }
+
+    #[test]
+    fn dropped_consumer_is_ignored() {
+        let (builder, rx) = sender(2);
+        let receiver = builder.init(Some(10));
+        drop(rx);
+        receiver.set_position(5);
+        receiver.finish();
+    }
+
+    #[test]
+    fn sync_variant() {
+        let (builder, rx) = sync_sender(16);
+        let receiver = builder.init(Some(10));
+        receiver.set_position(4);
+        receiver.finish();
+        let events: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            events,
+            [
+                ProgressEvent::Started { total: Some(10) },
+                ProgressEvent::Position(4),
+                ProgressEvent::Finished,
+            ]
+        );
+        drop(rx);
+        receiver.set_position(5);
+    }
+}
@@ -0,0 +1,153 @@
+//! Clamping positions to the declared total.
+
+use std::sync::Mutex;
+
+use crate::progress::ProgressReceiver;
+
+/// A receiver wrapper capping positions at the declared total.
+///
+/// Servers sending more bytes than announced, stale expected sizes on
+/// resume, and wrong decompressed-size estimates can all push positions
+/// past the total, which makes percentage math go over 100%. `Clamped`
+/// caps positions at the total, logs a debug warning the first time it
+/// happens, and remembers it in [`clamped`](Self::clamped) so callers can
+/// escalate an oversize transfer into an error. Positions moving backwards
+/// (a retry starting over) are forwarded unchanged.
+pub struct Clamped<R> {
+    inner: R,
+    state: Mutex<ClampedState>,
+}
+
+struct ClampedState {
+    total: Option<u64>,
+    position: u64,
+    clamped: bool,
+}
+
+impl<R: ProgressReceiver> Clamped<R> {
+    /// Wrap `inner`, capping positions at `total` (`None` disables
+    /// clamping until [`set_total`](ProgressReceiver::set_total) is
+    /// called).
+    pub fn new(inner: R, total: Option<u64>) -> Self {
+        Self {
+            inner,
+            state: Mutex::new(ClampedState {
+                total,
+                position: 0,
+                clamped: false,
+            }),
+        }
+    }
+
+    /// Whether any position exceeded the total so far.
+    pub fn clamped(&self) -> bool {
+        self.state.lock().unwrap().clamped
+    }
+
+    /// Clamp `position` against the total, recording the overflow.
+    fn clamp(&self, position: u64) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let clamped = match state.total {
+            Some(total) if position > total => {
+                if !state.clamped {
+                    log::debug!("progress position {position} exceeds the total {total}");
+                }
+                state.clamped = true;
+                total
+            }
+            _ => position,
+        };
+        state.position = clamped;
+        clamped
+    }
+}
+
+impl<R: ProgressReceiver> ProgressReceiver for Clamped<R> {
+    fn set_position(&self, position: u64) {
+        let position = self.clamp(position);
+        self.inner.set_position(position);
+    }
+
+    fn set_total(&self, total: u64) {
+        self.state.lock().unwrap().total = Some(total);
+        self.inner.set_total(total);
+    }
+
+    fn inc(&self, delta: u64) {
+        let position = self.state.lock().unwrap().position + delta;
+        let position = self.clamp(position);
+        self.inner.set_position(position);
+    }
+
+    fn set_message(&self, msg: &str) {
+        self.inner.set_message(msg);
+    }
+
+    fn finish(&self) {
+        self.inner.finish();
+    }
+
+    fn finish_with_error(&self, error: &crate::error::Error) {
+        self.inner.finish_with_error(error);
+    }
+
+    fn abandon(&self) {
+        self.inner.abandon();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        positions: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl ProgressReceiver for Recorder {
+        fn set_position(&self, position: u64) {
+            self.positions.lock().unwrap().push(position);
+        }
+
+        fn finish(&self) {}
+    }
+
+    #[test]
+    fn positions_are_capped_at_the_total() {
+        let recorder = Recorder::default();
+        let clamped = Clamped::new(recorder.clone(), Some(10));
+        clamped.set_position(5);
+        assert!(!clamped.clamped());
+        clamped.set_position(15);
+        clamped.inc(3);
+        assert!(clamped.clamped());
+        assert_eq!(*recorder.positions.lock().unwrap(), [5, 10, 10]);
+    }
+
+    #[test]
+    fn unknown_total_passes_through_until_learned() {
+        let recorder = Recorder::default();
+        let clamped = Clamped::new(recorder.clone(), None);
+        clamped.set_position(100);
+        assert!(!clamped.clamped());
+        clamped.set_total(50);
+        clamped.set_position(120);
+        assert!(clamped.clamped());
+        assert_eq!(*recorder.positions.lock().unwrap(), [100, 50]);
+    }
+
+    #[test]
+    fn backwards_positions_are_forwarded() {
+        let recorder = Recorder::default();
+        let clamped = Clamped::new(recorder.clone(), Some(10));
+        clamped.set_position(8);
+        // A retry started over.
+        clamped.set_position(2);
+        clamped.inc(1);
+        assert_eq!(*recorder.positions.lock().unwrap(), [8, 2, 3]);
+        assert!(!clamped.clamped());
+    }
+}
@@ -0,0 +1,263 @@
+//! Aggregated progress across multiple concurrent downloads.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder};
+
+/// Aggregated progress over a group of downloads.
+///
+/// A group starts out empty and hands out child builders via
+/// [`child`](Self::child); each child's `init(total)` adds to the group
+/// total, and position updates are summed into a shared position, so the
+/// group shows "3.2 GB / 7.9 GB" style overall progress. Children may join
+/// late (the total grows), finish early, or have unknown totals (they
+/// contribute positions but no total, so the overall position can exceed
+/// the overall total).
+///
+/// The group itself does not know how many children will join, so it never
+/// finishes on its own; call [`finish`](Self::finish) once all downloads are
+/// done to forward the finish to a user-supplied receiver.
+#[derive(Clone, Default)]
+pub struct Group {
+    state: Arc<GroupState>,
+}
+
+#[derive(Default)]
+struct GroupState {
+    total: AtomicU64,
+    position: AtomicU64,
+    receiver: Option<Box<dyn ProgressReceiver + Send + Sync>>,
+}
+
+impl Group {
+    /// Create an empty group.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty group forwarding the aggregated position (and
+    /// [`finish`](Self::finish)) to `receiver`.
+    pub fn forward_to(receiver: impl ProgressReceiver + Send + Sync + 'static) -> Self {
+        Self {
+            state: Arc::new(GroupState {
+                total: AtomicU64::new(0),
+                position: AtomicU64::new(0),
+                receiver: Some(Box::new(receiver)),
+            }),
+        }
+    }
+
+    /// Hand out a builder for one download of the group.
+    pub fn child(&self) -> GroupChild {
+        GroupChild {
+            state: self.state.clone(),
+        }
+    }
+
+    /// The sum of the known totals of all children in bytes.
+    pub fn total(&self) -> u64 {
+        self.state.total.load(Ordering::Relaxed)
+    }
+
+    /// The sum of the current positions of all children in bytes.
+    pub fn position(&self) -> u64 {
+        self.state.position.load(Ordering::Relaxed)
+    }
+
+    /// Mark the whole group as finished, forwarding to the receiver passed
+    /// to [`forward_to`](Self::forward_to).
+    pub fn finish(&self) {
+        if let Some(receiver) = &self.state.receiver {
+            receiver.finish();
+        }
+    }
+}
+
+/// The child builder handed out by [`Group::child`].
+#[derive(Clone)]
+pub struct GroupChild {
+    state: Arc<GroupState>,
+}
+
+impl ProgressReceiverBuilder for GroupChild {
+    type Receiver = GroupChildReceiver;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        let total = total.unwrap_or(0);
+        self.state.total.fetch_add(total, Ordering::Relaxed);
+        GroupChildReceiver {
+            state: self.state,
+            last: AtomicU64::new(0),
+            announced: AtomicU64::new(total),
+        }
+    }
+}
+
+/// The receiver built by [`GroupChild`].
+///
+/// Tracks its own last position so concurrent children contribute deltas to
+/// the shared sum.
+pub struct GroupChildReceiver {
+    state: Arc<GroupState>,
+    last: AtomicU64,
+    announced: AtomicU64,
+}
+
+impl ProgressReceiver for GroupChildReceiver {
+    fn set_position(&self, position: u64) {
+        let last = self.last.swap(position, Ordering::Relaxed);
+        let group = if position >= last {
+            self.state
+                .position
+                .fetch_add(position - last, Ordering::Relaxed)
+                + (position - last)
+        } else {
+            // The position went backwards (a retry started over).
+            self.state
+                .position
+                .fetch_sub(last - position, Ordering::Relaxed)
+                - (last - position)
+        };
+        if let Some(receiver) = &self.state.receiver {
+            receiver.set_position(group);
+        }
+    }
+
+    fn set_total(&self, total: u64) {
+        // Replace this child's contribution to the group total.
+        let announced = self.announced.swap(total, Ordering::Relaxed);
+        if total >= announced {
+            self.state.total.fetch_add(total - announced, Ordering::Relaxed);
+        } else {
+            self.state.total.fetch_sub(announced - total, Ordering::Relaxed);
+        }
+    }
+
+    fn inc(&self, delta: u64) {
+        self.last.fetch_add(delta, Ordering::Relaxed);
+        let group = self.state.position.fetch_add(delta, Ordering::Relaxed) + delta;
+        if let Some(receiver) = &self.state.receiver {
+            receiver.set_position(group);
+        }
+    }
+
+    fn finish(&self) {
+        // A child finishing early does not finish the group; see
+        // [`Group::finish`].
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn sums_totals_and_positions() {
+        let group = Group::new();
+        let a = group.child().init(Some(100));
+        let b = group.child().init(Some(200));
+        assert_eq!(group.total(), 300);
+        a.set_position(50);
+        b.set_position(120);
+        assert_eq!(group.position(), 170);
+        a.set_position(100);
+        a.finish();
+        assert_eq!(group.position(), 220);
+    }
+
+    #[test]
+    fn late_children_grow_the_total() {
+        let group = Group::new();
+        let _a = group.child().init(Some(100));
+        assert_eq!(group.total(), 100);
+        let _b = group.child().init(Some(50));
+        assert_eq!(group.total(), 150);
+    }
+
+    #[test]
+    fn unknown_total_child() {
+        let group = Group::new();
+        let a = group.child().init(None);
+        assert_eq!(group.total(), 0);
+        a.set_position(42);
+        assert_eq!(group.position(), 42);
+        // The total learned late is added to the group total.
+        a.set_total(100);
+        assert_eq!(group.total(), 100);
+        a.set_total(80);
+        assert_eq!(group.total(), 80);
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let group = Group::new();
+        let a = group.child().init(Some(100));
+        a.set_position(10);
+        a.inc(5);
+        a.inc(5);
+        assert_eq!(group.position(), 20);
+        // Absolute updates keep working after increments.
+        a.set_position(50);
+        assert_eq!(group.position(), 50);
+    }
+
+    #[test]
+    fn position_reset_subtracts() {
+        let group = Group::new();
+        let a = group.child().init(Some(100));
+        a.set_position(80);
+        a.set_position(10);
+        assert_eq!(group.position(), 10);
+    }
+
+    #[test]
+    fn forwards_to_receiver() {
+        #[derive(Default)]
+        struct Recorder {
+            positions: Mutex<Vec<u64>>,
+            finished: Mutex<bool>,
+        }
+        impl ProgressReceiver for &'static Recorder {
+            fn set_position(&self, position: u64) {
+                self.positions.lock().unwrap().push(position);
+            }
+            fn finish(&self) {
+                *self.finished.lock().unwrap() = true;
+            }
+        }
+
+        let recorder: &'static Recorder = Box::leak(Box::default());
+        let group = Group::forward_to(recorder);
+        let a = group.child().init(Some(10));
+        a.set_position(4);
+        a.set_position(10);
+        group.finish();
+        assert_eq!(*recorder.positions.lock().unwrap(), [4, 10]);
+        assert!(*recorder.finished.lock().unwrap());
+    }
+
+    #[test]
+    fn concurrent_children() {
+        const THREADS: u64 = 8;
+        const STEPS: u64 = 1000;
+
+        let group = Group::new();
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                let child = group.child();
+                scope.spawn(move || {
+                    let receiver = child.init(Some(STEPS));
+                    for position in 1..=STEPS {
+                        receiver.set_position(position);
+                    }
+                    receiver.finish();
+                });
+            }
+        });
+        assert_eq!(group.total(), THREADS * STEPS);
+        assert_eq!(group.position(), THREADS * STEPS);
+    }
+}
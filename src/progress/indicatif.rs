@@ -0,0 +1,201 @@
+//! An [`indicatif`] adapter for the progress traits.
+//!
+//! Requires the `indicatif` feature. [`Bar`] implements
+//! [`ProgressReceiverBuilder`] and constructs an [`indicatif::ProgressBar`]
+//! once the total size is known; an unknown total turns it into a spinner
+//! until [`ProgressReceiver::set_total`] supplies one. Attach a [`MultiProgress`] to stack the bars of several
+//! concurrent downloads.
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+pub use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+use crate::error::Error;
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder};
+
+/// How [`ProgressReceiver::finish`] resolves the bar.
+#[derive(Debug, Clone, Default)]
+pub enum FinishBehavior {
+    /// Leave the bar at its final position.
+    #[default]
+    Retain,
+    /// Clear the bar from the terminal.
+    Clear,
+    /// Finish with the given message.
+    WithMessage(Cow<'static, str>),
+}
+
+/// A progress receiver builder drawing an [`indicatif`] progress bar.
+#[derive(Clone, Default)]
+pub struct Bar {
+    style: Option<ProgressStyle>,
+    multi: Option<MultiProgress>,
+    on_finish: FinishBehavior,
+}
+
+impl Bar {
+    /// Create a builder with indicatif's default style.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the style of the constructed bar.
+    pub fn with_style(mut self, style: ProgressStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Add the constructed bar to `multi`, so several downloads stack.
+    pub fn attach_to(mut self, multi: &MultiProgress) -> Self {
+        self.multi = Some(multi.clone());
+        self
+    }
+
+    /// Set what happens to the bar when the download finishes.
+    pub fn on_finish(mut self, behavior: FinishBehavior) -> Self {
+        self.on_finish = behavior;
+        self
+    }
+}
+
+impl ProgressReceiverBuilder for Bar {
+    type Receiver = BarReceiver;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        let bar = match total {
+            Some(total) => ProgressBar::new(total),
+            None => {
+                let bar = ProgressBar::new_spinner();
+                bar.enable_steady_tick(Duration::from_millis(100));
+                bar
+            }
+        };
+        if let Some(style) = self.style {
+            bar.set_style(style);
+        }
+        let bar = match &self.multi {
+            Some(multi) => multi.add(bar),
+            None => bar,
+        };
+        BarReceiver {
+            bar,
+            on_finish: self.on_finish,
+        }
+    }
+}
+
+/// The receiver built by [`Bar`], wrapping an [`indicatif::ProgressBar`].
+pub struct BarReceiver {
+    bar: ProgressBar,
+    on_finish: FinishBehavior,
+}
+
+impl BarReceiver {
+    /// The wrapped progress bar.
+    pub fn bar(&self) -> &ProgressBar {
+        &self.bar
+    }
+}
+
+impl ProgressReceiver for BarReceiver {
+    fn set_position(&self, position: u64) {
+        // Cap at the length so a server sending extra bytes never renders
+        // more than 100%.
+        let position = match self.bar.length() {
+            Some(length) => position.min(length),
+            None => position,
+        };
+        self.bar.set_position(position);
+    }
+
+    fn set_total(&self, total: u64) {
+        self.bar.set_length(total);
+    }
+
+    fn inc(&self, delta: u64) {
+        self.bar.inc(delta);
+    }
+
+    fn set_message(&self, msg: &str) {
+        self.bar.set_message(msg.to_string());
+    }
+
+    fn finish(&self) {
+        match &self.on_finish {
+            FinishBehavior::Retain => self.bar.finish(),
+            FinishBehavior::Clear => self.bar.finish_and_clear(),
+            FinishBehavior::WithMessage(message) => {
+                self.bar.finish_with_message(message.clone())
+            }
+        }
+    }
+
+    fn finish_with_error(&self, error: &Error) {
+        self.bar.abandon_with_message(error.to_string());
+    }
+
+    fn abandon(&self) {
+        self.bar.abandon();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indicatif::ProgressDrawTarget;
+
+    use super::*;
+
+    #[test]
+    fn forwards_positions() {
+        let receiver = Bar::new().init(Some(10));
+        receiver.bar().set_draw_target(ProgressDrawTarget::hidden());
+        receiver.set_position(3);
+        receiver.inc(4);
+        assert_eq!(receiver.bar().position(), 7);
+        receiver.set_message("extracting");
+        assert_eq!(receiver.bar().message(), "extracting");
+        assert_eq!(receiver.bar().length(), Some(10));
+        receiver.finish();
+        assert!(receiver.bar().is_finished());
+    }
+
+    #[test]
+    fn unknown_total_is_a_spinner() {
+        let receiver = Bar::new().init(None);
+        receiver.bar().set_draw_target(ProgressDrawTarget::hidden());
+        assert_eq!(receiver.bar().length(), None);
+        // A total learned late (e.g. from response headers) is applied.
+        receiver.set_total(42);
+        assert_eq!(receiver.bar().length(), Some(42));
+    }
+
+    #[test]
+    fn errors_abandon_the_bar() {
+        use crate::error::ErrorKind;
+        use crate::error::WithDesc;
+
+        let receiver = Bar::new().init(Some(10));
+        receiver.bar().set_draw_target(ProgressDrawTarget::hidden());
+        let error = Error::new(ErrorKind::Verify).with_desc("digest mismatch");
+        receiver.finish_with_error(&error);
+        assert!(receiver.bar().is_finished());
+        assert_eq!(receiver.bar().message(), error.to_string());
+    }
+
+    #[test]
+    fn finish_and_clear() {
+        let receiver = Bar::new().on_finish(FinishBehavior::Clear).init(Some(10));
+        receiver.bar().set_draw_target(ProgressDrawTarget::hidden());
+        receiver.finish();
+        assert!(receiver.bar().is_finished());
+    }
+
+    #[test]
+    fn attaches_to_multi_progress() {
+        let multi = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let receiver = Bar::new().attach_to(&multi).init(Some(10));
+        receiver.set_position(5);
+        assert_eq!(receiver.bar().position(), 5);
+    }
+}
@@ -0,0 +1,274 @@
+//! A progress receiver writing periodic lines to the [`log`] crate.
+//!
+//! In CI and daemons there is no TTY to draw a bar on, but periodic lines
+//! like `downloaded 512.0 MiB / 2.0 GiB (25%, 48.0 MiB/s)` in the logs
+//! still show that a transfer is alive. [`LogProgress`] emits at a
+//! configurable level and cadence (by time and by percentage step,
+//! whichever fires first) and a summary line at the end.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder, Throughput};
+
+/// A progress receiver builder logging periodic progress lines.
+#[derive(Debug, Clone)]
+pub struct LogProgress {
+    level: log::Level,
+    interval: Duration,
+    percent_step: u64,
+}
+
+impl Default for LogProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogProgress {
+    /// Create a builder logging at [`log::Level::Info`], every 10% or 30
+    /// seconds, whichever comes first.
+    pub fn new() -> Self {
+        Self {
+            level: log::Level::Info,
+            interval: Duration::from_secs(30),
+            percent_step: 10,
+        }
+    }
+
+    /// Set the level the progress lines are logged at.
+    pub fn with_level(mut self, level: log::Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set the time cadence: a line is logged when this much time passed
+    /// since the last one.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the percentage cadence: a line is logged whenever the position
+    /// crosses a multiple of `step` percent. `0` disables the percentage
+    /// trigger.
+    pub fn with_percent_step(mut self, step: u64) -> Self {
+        self.percent_step = step;
+        self
+    }
+}
+
+impl ProgressReceiverBuilder for LogProgress {
+    type Receiver = LogProgressReceiver;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        LogProgressReceiver {
+            options: self,
+            state: Mutex::new(LogState {
+                total,
+                position: 0,
+                message: None,
+                started: Instant::now(),
+                last_log: Instant::now(),
+                last_bucket: 0,
+                throughput: Throughput::new(),
+            }),
+        }
+    }
+}
+
+/// The receiver built by [`LogProgress`].
+pub struct LogProgressReceiver {
+    options: LogProgress,
+    state: Mutex<LogState>,
+}
+
+struct LogState {
+    total: Option<u64>,
+    position: u64,
+    message: Option<String>,
+    started: Instant,
+    last_log: Instant,
+    last_bucket: u64,
+    throughput: Throughput,
+}
+
+impl LogProgressReceiver {
+    fn log_line(&self, state: &LogState, now: Instant) {
+        let prefix = match &state.message {
+            Some(message) => format!("{message}: "),
+            None => String::new(),
+        };
+        let rate = format_bytes(state.throughput.rate(now) as u64);
+        match state.total {
+            Some(total) if total > 0 => {
+                let percent = (state.position * 100 / total).min(100);
+                log::log!(
+                    self.options.level,
+                    "{prefix}downloaded {} / {} ({percent}%, {rate}/s)",
+                    format_bytes(state.position),
+                    format_bytes(total),
+                );
+            }
+            _ => {
+                log::log!(
+                    self.options.level,
+                    "{prefix}downloaded {} ({rate}/s)",
+                    format_bytes(state.position),
+                );
+            }
+        }
+    }
+}
+
+impl ProgressReceiver for LogProgressReceiver {
+    fn set_position(&self, position: u64) {
+        // Nothing is recorded while the level is disabled, keeping the
+        // receiver free when logging is off.
+        if !log::log_enabled!(self.options.level) {
+            return;
+        }
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.position = position;
+        state.throughput.record(now, position);
+        let bucket = match (state.total, self.options.percent_step) {
+            (Some(total), step) if total > 0 && step > 0 => position * 100 / total / step,
+            _ => 0,
+        };
+        let due = bucket > state.last_bucket
+            || now.duration_since(state.last_log) >= self.options.interval;
+        if due {
+            state.last_bucket = bucket;
+            state.last_log = now;
+            self.log_line(&state, now);
+        }
+    }
+
+    fn set_total(&self, total: u64) {
+        self.state.lock().unwrap().total = Some(total);
+    }
+
+    fn inc(&self, delta: u64) {
+        let position = self.state.lock().unwrap().position + delta;
+        self.set_position(position);
+    }
+
+    fn set_message(&self, msg: &str) {
+        self.state.lock().unwrap().message = Some(msg.to_string());
+    }
+
+    fn finish(&self) {
+        if !log::log_enabled!(self.options.level) {
+            return;
+        }
+        let state = self.state.lock().unwrap();
+        let prefix = match &state.message {
+            Some(message) => format!("{message}: "),
+            None => String::new(),
+        };
+        let elapsed = state.started.elapsed();
+        let rate = (state.position as f64 / elapsed.as_secs_f64().max(f64::EPSILON)) as u64;
+        log::log!(
+            self.options.level,
+            "{prefix}downloaded {} in {:.1}s ({}/s)",
+            format_bytes(state.position),
+            elapsed.as_secs_f64(),
+            format_bytes(rate),
+        );
+    }
+
+    fn finish_with_error(&self, error: &crate::error::Error) {
+        let state = self.state.lock().unwrap();
+        let prefix = match &state.message {
+            Some(message) => format!("{message}: "),
+            None => String::new(),
+        };
+        log::warn!(
+            "{prefix}download failed after {}: {error}",
+            format_bytes(state.position)
+        );
+    }
+}
+
+/// Format a byte count with binary units, e.g. `512.0 MiB`.
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Capture {
+        lines: Mutex<Vec<String>>,
+    }
+
+    impl log::Log for Capture {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Info
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                self.lines.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn format_bytes_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(2048), "2.0 KiB");
+        assert_eq!(format_bytes(512 * 1024 * 1024), "512.0 MiB");
+        assert_eq!(format_bytes(2 * 1024 * 1024 * 1024), "2.0 GiB");
+    }
+
+    /// One test owns the global logger: `log::set_logger` can only succeed
+    /// once per process.
+    #[test]
+    fn logs_on_percent_steps_and_a_summary() {
+        let capture: &'static Capture = Box::leak(Box::default());
+        log::set_logger(capture).unwrap();
+        log::set_max_level(log::LevelFilter::Info);
+
+        let receiver = LogProgress::new()
+            .with_percent_step(25)
+            .with_interval(Duration::from_secs(3600))
+            .init(Some(100));
+        for position in 1..=100 {
+            receiver.set_position(position);
+        }
+        receiver.finish();
+
+        let lines = capture.lines.lock().unwrap().clone();
+        // One line per 25% bucket crossed, plus the summary.
+        assert_eq!(lines.len(), 5, "lines were {lines:?}");
+        assert!(lines[0].starts_with("downloaded 25 B / 100 B (25%"));
+        assert!(lines[3].starts_with("downloaded 100 B / 100 B (100%"));
+        assert!(lines[4].contains(" in "), "summary was {}", lines[4]);
+
+        // Nothing is logged once the level is disabled.
+        log::set_max_level(log::LevelFilter::Off);
+        let receiver = LogProgress::new().init(Some(100));
+        receiver.set_position(50);
+        receiver.finish();
+        assert_eq!(capture.lines.lock().unwrap().len(), 5);
+        log::set_max_level(log::LevelFilter::Info);
+    }
+}
@@ -0,0 +1,248 @@
+//! Traits for reporting download progress.
+//!
+//! The crate itself does not draw progress bars; instead, operations that
+//! can report progress accept a [`ProgressReceiverBuilder`], which is
+//! initialized with the total size once it is known and then receives
+//! position updates as bytes arrive.
+
+#[cfg(feature = "tokio")]
+pub mod channel;
+#[cfg(feature = "indicatif")]
+pub mod indicatif;
+pub mod record;
+pub mod term;
+#[cfg(feature = "tracing")]
+pub mod tracing;
+
+mod clamped;
+mod group;
+mod log;
+mod phase;
+mod prepared;
+mod shared;
+mod throttled;
+mod throughput;
+mod weighted;
+
+pub use clamped::Clamped;
+pub use group::{Group, GroupChild, GroupChildReceiver};
+pub use log::{LogProgress, LogProgressReceiver};
+pub use phase::{PerPhase, Phase, PhasedProgressBuilder};
+pub use prepared::Prepared;
+pub use shared::{Shared, Snapshot};
+pub use throttled::Throttled;
+pub use weighted::{WeightedChild, WeightedChildReceiver, split};
+pub use throughput::{Throughput, ThroughputBuilder, ThroughputHandle, ThroughputReceiver};
+
+use crate::error::Error;
+
+/// A builder for a [`ProgressReceiver`].
+///
+/// Separating construction from reporting lets the download decide when the
+/// total size is known (e.g. from the expected size, or not at all) before
+/// any progress is shown.
+pub trait ProgressReceiverBuilder {
+    /// The receiver produced by this builder.
+    type Receiver: ProgressReceiver;
+
+    /// Initialize the receiver with the total number of bytes expected.
+    ///
+    /// `None` means the size is unknown; receivers should render
+    /// indeterminate progress (e.g. a spinner) until
+    /// [`ProgressReceiver::set_total`] supplies a total.
+    fn init(self, total: Option<u64>) -> Self::Receiver;
+
+    /// Initialize the receiver at a nonzero starting position, e.g. when
+    /// resuming a download with part of the file already on disk.
+    ///
+    /// The default initializes via [`init`](Self::init) and reports `start`
+    /// as the first position; rate estimators should treat `start` as the
+    /// session baseline so only bytes transferred this session count.
+    fn init_at(self, total: Option<u64>, start: u64) -> Self::Receiver
+    where
+        Self: Sized,
+    {
+        let receiver = self.init(total);
+        receiver.set_position(start);
+        receiver
+    }
+}
+
+/// A receiver of progress updates.
+pub trait ProgressReceiver {
+    /// Set the current position in bytes.
+    fn set_position(&self, position: u64);
+
+    /// Set the total number of bytes expected, when it is only learned
+    /// after initialization (e.g. from response headers).
+    ///
+    /// The default does nothing.
+    fn set_total(&self, total: u64) {
+        let _ = total;
+    }
+
+    /// Advance the position by `delta` bytes.
+    ///
+    /// Producers that cannot cheaply track an absolute position (parallel
+    /// segments, multiple extraction workers) report increments instead.
+    /// The default does nothing; receivers that can apply increments should
+    /// override it.
+    fn inc(&self, delta: u64) {
+        let _ = delta;
+    }
+
+    /// Describe what is currently happening (e.g. the file being
+    /// extracted).
+    ///
+    /// The default does nothing.
+    fn set_message(&self, msg: &str) {
+        let _ = msg;
+    }
+
+    /// Mark the operation as finished.
+    ///
+    /// Exactly one of [`finish`](Self::finish),
+    /// [`finish_with_error`](Self::finish_with_error) and
+    /// [`abandon`](Self::abandon) is called per operation.
+    fn finish(&self);
+
+    /// Mark the operation as failed with `error`.
+    ///
+    /// The default does nothing.
+    fn finish_with_error(&self, error: &Error) {
+        let _ = error;
+    }
+
+    /// Mark the operation as abandoned (e.g. cancelled), without a result.
+    ///
+    /// The default does nothing.
+    fn abandon(&self) {}
+}
+
+/// The "no progress reporting" sentinel.
+///
+/// `Option<impl ProgressReceiverBuilder>` cannot infer its type from a bare
+/// `None`, so operations take `impl ProgressReceiverBuilder` directly and
+/// `NoProgress` is the explicit nothing:
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use fetchkit::download::DownloadBuilder;
+/// use fetchkit::progress::NoProgress;
+///
+/// # async fn example() -> fetchkit::Result<()> {
+/// # let client = reqwest::Client::new();
+/// DownloadBuilder::new("https://example.com/data", Path::new("data"), 0)
+///     .download(&client, NoProgress)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressReceiverBuilder for NoProgress {
+    type Receiver = NoProgress;
+
+    fn init(self, _total: Option<u64>) -> Self::Receiver {
+        self
+    }
+}
+
+impl ProgressReceiver for NoProgress {
+    fn set_position(&self, _position: u64) {}
+
+    fn finish(&self) {}
+}
+
+/// `Option<B>` reports to the receiver when `Some` and does nothing when
+/// `None`, so optional progress can be threaded through without a wrapper
+/// type.
+impl<B: ProgressReceiverBuilder> ProgressReceiverBuilder for Option<B> {
+    type Receiver = Option<B::Receiver>;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        self.map(|builder| builder.init(total))
+    }
+
+    fn init_at(self, total: Option<u64>, start: u64) -> Self::Receiver {
+        self.map(|builder| builder.init_at(total, start))
+    }
+}
+
+impl<R: ProgressReceiver> ProgressReceiver for Option<R> {
+    fn set_position(&self, position: u64) {
+        if let Some(receiver) = self {
+            receiver.set_position(position);
+        }
+    }
+
+    fn set_total(&self, total: u64) {
+        if let Some(receiver) = self {
+            receiver.set_total(total);
+        }
+    }
+
+    fn inc(&self, delta: u64) {
+        if let Some(receiver) = self {
+            receiver.inc(delta);
+        }
+    }
+
+    fn set_message(&self, msg: &str) {
+        if let Some(receiver) = self {
+            receiver.set_message(msg);
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(receiver) = self {
+            receiver.finish();
+        }
+    }
+
+    fn finish_with_error(&self, error: &Error) {
+        if let Some(receiver) = self {
+            receiver.finish_with_error(error);
+        }
+    }
+
+    fn abandon(&self) {
+        if let Some(receiver) = self {
+            receiver.abandon();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn init_at_reports_the_starting_position() {
+        #[derive(Default)]
+        struct Recorder {
+            positions: Mutex<Vec<u64>>,
+        }
+        impl ProgressReceiverBuilder for &'static Recorder {
+            type Receiver = Self;
+
+            fn init(self, _total: Option<u64>) -> Self::Receiver {
+                self
+            }
+        }
+        impl ProgressReceiver for &'static Recorder {
+            fn set_position(&self, position: u64) {
+                self.positions.lock().unwrap().push(position);
+            }
+            fn finish(&self) {}
+        }
+
+        let recorder: &'static Recorder = Box::leak(Box::default());
+        let receiver = recorder.init_at(Some(10), 7);
+        receiver.set_position(8);
+        assert_eq!(*recorder.positions.lock().unwrap(), [7, 8]);
+    }
+}
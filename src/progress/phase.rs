@@ -0,0 +1,120 @@
+//! Phase-aware progress reporting.
+//!
+//! A complete fetch goes through several phases (selecting a mirror,
+//! downloading, verifying, extracting), and a receiver that knows which
+//! phase it is in can say so instead of showing one bar that mysteriously
+//! pauses. [`PhasedProgressBuilder`] hands out one receiver per phase;
+//! [`PerPhase`] adapts any cloneable [`ProgressReceiverBuilder`] so plain
+//! receivers keep working.
+
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder};
+
+/// A phase of a fetch operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Phase {
+    /// Probing mirrors to pick the fastest one.
+    SelectingMirror,
+    /// Streaming the file to disk.
+    Downloading,
+    /// Verifying the downloaded content.
+    Verifying,
+    /// Extracting the downloaded archive.
+    Extracting,
+}
+
+impl Phase {
+    /// A short human-readable name of the phase.
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::SelectingMirror => "selecting mirror",
+            Phase::Downloading => "downloading",
+            Phase::Verifying => "verifying",
+            Phase::Extracting => "extracting",
+        }
+    }
+}
+
+/// A progress builder handing out one receiver per [`Phase`].
+///
+/// Unlike [`ProgressReceiverBuilder`], which is consumed by a single
+/// operation, a phased builder is borrowed so it can open several phases in
+/// sequence. Each receiver is resolved (finished, failed or abandoned)
+/// before the next phase begins.
+pub trait PhasedProgressBuilder {
+    /// The receiver reporting a single phase.
+    type Receiver: ProgressReceiver;
+
+    /// Open a phase with the given total, `None` when unknown.
+    fn begin_phase(&self, phase: Phase, total: Option<u64>) -> Self::Receiver;
+}
+
+/// An adapter using a plain [`ProgressReceiverBuilder`] for every phase.
+///
+/// Each phase gets a fresh receiver built from a clone of the wrapped
+/// builder, with the phase name as the initial message.
+#[derive(Debug, Clone)]
+pub struct PerPhase<B> {
+    inner: B,
+}
+
+impl<B: ProgressReceiverBuilder + Clone> PerPhase<B> {
+    /// Wrap `inner`, using it for every phase.
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+}
+
+impl<B: ProgressReceiverBuilder + Clone> PhasedProgressBuilder for PerPhase<B> {
+    type Receiver = B::Receiver;
+
+    fn begin_phase(&self, phase: Phase, total: Option<u64>) -> Self::Receiver {
+        let receiver = self.inner.clone().init(total);
+        receiver.set_message(phase.name());
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl ProgressReceiverBuilder for Recorder {
+        type Receiver = Recorder;
+
+        fn init(self, _total: Option<u64>) -> Self::Receiver {
+            self
+        }
+    }
+
+    impl ProgressReceiver for Recorder {
+        fn set_position(&self, _position: u64) {}
+
+        fn set_message(&self, msg: &str) {
+            self.messages.lock().unwrap().push(msg.to_string());
+        }
+
+        fn finish(&self) {}
+    }
+
+    #[test]
+    fn per_phase_names_the_phase() {
+        let recorder = Recorder::default();
+        let phased = PerPhase::new(recorder.clone());
+        let receiver = phased.begin_phase(Phase::Downloading, Some(10));
+        receiver.finish();
+        let receiver = phased.begin_phase(Phase::Extracting, None);
+        receiver.finish();
+        assert_eq!(
+            *recorder.messages.lock().unwrap(),
+            ["downloading", "extracting"]
+        );
+    }
+}
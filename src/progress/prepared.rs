@@ -0,0 +1,72 @@
+//! Passing an already-initialized receiver where a builder is expected.
+
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder};
+
+/// A builder handing out a receiver that already exists.
+///
+/// Operations take a [`ProgressReceiverBuilder`] and initialize it
+/// themselves, so passing the same bar to several of them — an
+/// [`exist`](crate::download::DownloadBuilder::exist) check followed by the
+/// download, or successive attempts of a caller-managed retry loop — would
+/// create a fresh receiver each time. `Prepared` wraps a receiver the
+/// caller constructed up front: [`init`](ProgressReceiverBuilder::init)
+/// forwards a known total through
+/// [`set_total`](ProgressReceiver::set_total) and hands the receiver
+/// straight back.
+///
+/// Each operation still drives the terminal calls, so a receiver reused
+/// across several operations sees one `finish` (or error) per operation
+/// and must tolerate being revived afterwards.
+pub struct Prepared<R>(pub R);
+
+impl<R: ProgressReceiver> ProgressReceiverBuilder for Prepared<R> {
+    type Receiver = R;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        if let Some(total) = total {
+            self.0.set_total(total);
+        }
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        totals: Arc<Mutex<Vec<u64>>>,
+        finishes: Arc<Mutex<u32>>,
+    }
+
+    impl ProgressReceiver for Recorder {
+        fn set_position(&self, _position: u64) {}
+
+        fn set_total(&self, total: u64) {
+            self.totals.lock().unwrap().push(total);
+        }
+
+        fn finish(&self) {
+            *self.finishes.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn the_wrapped_receiver_is_handed_back() {
+        let recorder = Recorder::default();
+        let receiver = Prepared(recorder.clone()).init(Some(42));
+        receiver.finish();
+        assert_eq!(*recorder.totals.lock().unwrap(), [42]);
+        assert_eq!(*recorder.finishes.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn an_unknown_total_is_not_forwarded() {
+        let recorder = Recorder::default();
+        Prepared(recorder.clone()).init(None);
+        assert!(recorder.totals.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+//! Recording progress as structured events.
+//!
+//! For bandwidth accounting and post-hoc analysis, positions alone are not
+//! enough: each update here carries a monotonic timestamp, the byte delta
+//! since the previous event, and the phase it belongs to, so percentile
+//! throughput and stalls can be computed offline. With the `serde` feature
+//! a recording can be serialized to JSON and attached to bug reports.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::progress::{Phase, PhasedProgressBuilder, ProgressReceiver, ProgressReceiverBuilder};
+
+/// One recorded progress update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProgressEvent {
+    /// Time since the recording started.
+    pub at: Duration,
+    /// The absolute position in bytes.
+    pub position: u64,
+    /// Bytes since the previous event. When throttling coalesces several
+    /// updates into one, the coalesced event carries the summed delta.
+    pub delta: u64,
+    /// The phase the update belongs to, if phases are in use.
+    pub phase: Option<Phase>,
+    /// The message current at the time of the update (e.g. the file being
+    /// extracted).
+    pub message: Option<String>,
+}
+
+/// A progress receiver appending events into a capped ring buffer.
+///
+/// Implements both the plain builder and the phased builder, so it can
+/// record a single download or a whole phased fetch. When the buffer is
+/// full the oldest events are dropped, but the transferred byte count keeps
+/// accumulating in [`total_bytes`](Self::total_bytes).
+#[derive(Clone)]
+pub struct Recorder {
+    capacity: usize,
+    state: Arc<Mutex<RecorderState>>,
+}
+
+struct RecorderState {
+    started: Instant,
+    events: VecDeque<ProgressEvent>,
+    position: u64,
+    total_bytes: u64,
+    phase: Option<Phase>,
+    message: Option<String>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recorder {
+    /// The default event capacity.
+    pub const DEFAULT_CAPACITY: usize = 4096;
+
+    /// Create a recorder with the default capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(Self::DEFAULT_CAPACITY)
+    }
+
+    /// Create a recorder keeping at most `capacity` events; the oldest are
+    /// dropped first.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Arc::new(Mutex::new(RecorderState {
+                started: Instant::now(),
+                events: VecDeque::new(),
+                position: 0,
+                total_bytes: 0,
+                phase: None,
+                message: None,
+            })),
+        }
+    }
+
+    /// The recorded events, oldest first.
+    pub fn events(&self) -> Vec<ProgressEvent> {
+        self.state.lock().unwrap().events.iter().cloned().collect()
+    }
+
+    /// The total number of bytes seen, including events dropped from the
+    /// buffer.
+    pub fn total_bytes(&self) -> u64 {
+        self.state.lock().unwrap().total_bytes
+    }
+
+    /// Serialize the recorded events to JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> crate::Result<String> {
+        use crate::error::{Error, ErrorKind, WithDesc};
+
+        serde_json::to_string(&self.events())
+            .map_err(|e| Error::new(ErrorKind::Other).with_source(e))
+            .with_desc("failed to serialize progress recording")
+    }
+
+    fn record(&self, position: u64) {
+        let mut state = self.state.lock().unwrap();
+        // A position below the previous one is a restart; the delta of the
+        // restarting event is the new absolute position.
+        let delta = position.saturating_sub(state.position);
+        state.position = position;
+        state.total_bytes += delta;
+        let event = ProgressEvent {
+            at: state.started.elapsed(),
+            position,
+            delta,
+            phase: state.phase,
+            message: state.message.clone(),
+        };
+        if state.events.len() == self.capacity {
+            state.events.pop_front();
+        }
+        state.events.push_back(event);
+    }
+}
+
+impl ProgressReceiverBuilder for Recorder {
+    type Receiver = Recorder;
+
+    fn init(self, _total: Option<u64>) -> Self::Receiver {
+        self
+    }
+}
+
+impl PhasedProgressBuilder for Recorder {
+    type Receiver = Recorder;
+
+    fn begin_phase(&self, phase: Phase, _total: Option<u64>) -> Self::Receiver {
+        let mut state = self.state.lock().unwrap();
+        state.phase = Some(phase);
+        state.position = 0;
+        drop(state);
+        self.clone()
+    }
+}
+
+impl ProgressReceiver for Recorder {
+    fn set_position(&self, position: u64) {
+        self.record(position);
+    }
+
+    fn inc(&self, delta: u64) {
+        let position = self.state.lock().unwrap().position + delta;
+        self.record(position);
+    }
+
+    fn set_message(&self, msg: &str) {
+        self.state.lock().unwrap().message = Some(msg.to_string());
+    }
+
+    fn finish(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deltas_sum_to_total_bytes() {
+        let recorder = Recorder::new().init(Some(11));
+        recorder.set_position(3);
+        recorder.set_position(7);
+        recorder.inc(4);
+        let events = recorder.events();
+        assert_eq!(
+            events.iter().map(|e| e.delta).collect::<Vec<_>>(),
+            [3, 4, 4]
+        );
+        assert_eq!(events.iter().map(|e| e.delta).sum::<u64>(), 11);
+        assert_eq!(recorder.total_bytes(), 11);
+        assert!(events.windows(2).all(|w| w[0].at <= w[1].at));
+    }
+
+    #[test]
+    fn coalesced_updates_carry_the_summed_delta() {
+        use crate::progress::Throttled;
+
+        let recorder = Recorder::new();
+        let throttled = Throttled::with_interval(
+            recorder.clone().init(Some(100)),
+            Duration::from_secs(3600),
+        );
+        for position in 1..=100 {
+            throttled.set_position(position);
+        }
+        throttled.finish();
+        let events = recorder.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].delta, 99);
+        assert_eq!(recorder.total_bytes(), 100);
+    }
+
+    #[test]
+    fn capacity_drops_oldest_but_keeps_the_byte_count() {
+        let recorder = Recorder::with_capacity(2).init(None);
+        recorder.set_position(3);
+        recorder.set_position(7);
+        recorder.set_position(11);
+        let events = recorder.events();
+        assert_eq!(
+            events.iter().map(|e| e.position).collect::<Vec<_>>(),
+            [7, 11]
+        );
+        assert_eq!(recorder.total_bytes(), 11);
+    }
+
+    #[test]
+    fn phases_are_attached_to_events() {
+        let recorder = Recorder::new();
+        let download = recorder.begin_phase(Phase::Downloading, Some(10));
+        download.set_position(10);
+        download.finish();
+        let extract = recorder.begin_phase(Phase::Extracting, None);
+        extract.set_message("bin/tool");
+        extract.set_position(4);
+        extract.finish();
+        let events = recorder.events();
+        assert_eq!(events[0].phase, Some(Phase::Downloading));
+        assert_eq!(events[1].phase, Some(Phase::Extracting));
+        assert_eq!(events[1].message.as_deref(), Some("bin/tool"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn recording_serializes_to_json() {
+        let recorder = Recorder::new().init(Some(10));
+        recorder.set_position(10);
+        let json = recorder.to_json().unwrap();
+        assert!(json.contains("\"position\":10"), "{json}");
+        assert!(json.contains("\"delta\":10"), "{json}");
+    }
+}
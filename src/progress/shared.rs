@@ -0,0 +1,211 @@
+//! A shareable atomic progress sink for parallel producers.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder};
+
+/// An `Arc`-backed progress receiver for many concurrent producers.
+///
+/// Segmented downloads and parallel extraction workers all report into the
+/// same operation; `Shared` holds the position in an [`AtomicU64`] so
+/// clones can [`add`](Self::add) from any thread without double counting.
+/// Consumers either poll [`snapshot`](Self::snapshot) and call
+/// [`flush`](Self::flush) at their own pace, or pass an inner receiver to
+/// [`forward_to`](Self::forward_to) to have every update forwarded
+/// immediately (wrap it in [`Throttled`](crate::progress::Throttled) when
+/// that is too chatty).
+#[derive(Clone, Default)]
+pub struct Shared {
+    state: Arc<SharedState>,
+}
+
+#[derive(Default)]
+struct SharedState {
+    position: AtomicU64,
+    /// The expected total in bytes, `0` when unknown.
+    total: AtomicU64,
+    receiver: Option<Box<dyn ProgressReceiver + Send + Sync>>,
+}
+
+/// A point-in-time view of a [`Shared`] receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The current position in bytes.
+    pub position: u64,
+    /// The expected total in bytes, if known.
+    pub total: Option<u64>,
+}
+
+impl Shared {
+    /// Create a sink with no inner receiver, to be polled via
+    /// [`snapshot`](Self::snapshot).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a sink forwarding every update (and the terminal calls) to
+    /// `receiver`.
+    pub fn forward_to(receiver: impl ProgressReceiver + Send + Sync + 'static) -> Self {
+        Self {
+            state: Arc::new(SharedState {
+                position: AtomicU64::new(0),
+                total: AtomicU64::new(0),
+                receiver: Some(Box::new(receiver)),
+            }),
+        }
+    }
+
+    /// Advance the position by `delta` bytes.
+    pub fn add(&self, delta: u64) {
+        let position = self.state.position.fetch_add(delta, Ordering::Relaxed) + delta;
+        if let Some(receiver) = &self.state.receiver {
+            receiver.set_position(position);
+        }
+    }
+
+    /// The current position and total.
+    pub fn snapshot(&self) -> Snapshot {
+        let total = self.state.total.load(Ordering::Relaxed);
+        Snapshot {
+            position: self.state.position.load(Ordering::Relaxed),
+            total: (total != 0).then_some(total),
+        }
+    }
+
+    /// Push the current position to the inner receiver.
+    ///
+    /// Only useful together with [`forward_to`](Self::forward_to); a driver
+    /// task can call this periodically instead of forwarding from the hot
+    /// path.
+    pub fn flush(&self) {
+        if let Some(receiver) = &self.state.receiver {
+            receiver.set_position(self.state.position.load(Ordering::Relaxed));
+        }
+    }
+}
+
+impl ProgressReceiverBuilder for Shared {
+    type Receiver = Shared;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        if let Some(total) = total {
+            self.set_total(total);
+        }
+        self
+    }
+}
+
+impl ProgressReceiver for Shared {
+    fn set_position(&self, position: u64) {
+        self.state.position.store(position, Ordering::Relaxed);
+        if let Some(receiver) = &self.state.receiver {
+            receiver.set_position(position);
+        }
+    }
+
+    fn set_total(&self, total: u64) {
+        self.state.total.store(total, Ordering::Relaxed);
+        if let Some(receiver) = &self.state.receiver {
+            receiver.set_total(total);
+        }
+    }
+
+    fn inc(&self, delta: u64) {
+        self.add(delta);
+    }
+
+    fn set_message(&self, msg: &str) {
+        if let Some(receiver) = &self.state.receiver {
+            receiver.set_message(msg);
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(receiver) = &self.state.receiver {
+            receiver.finish();
+        }
+    }
+
+    fn finish_with_error(&self, error: &crate::error::Error) {
+        if let Some(receiver) = &self.state.receiver {
+            receiver.finish_with_error(error);
+        }
+    }
+
+    fn abandon(&self) {
+        if let Some(receiver) = &self.state.receiver {
+            receiver.abandon();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn snapshot_reflects_adds() {
+        let shared = Shared::new().init(Some(100));
+        shared.add(30);
+        shared.add(12);
+        assert_eq!(
+            shared.snapshot(),
+            Snapshot {
+                position: 42,
+                total: Some(100),
+            }
+        );
+        let unknown = Shared::new().init(None);
+        assert_eq!(unknown.snapshot().total, None);
+        unknown.set_total(7);
+        assert_eq!(unknown.snapshot().total, Some(7));
+    }
+
+    #[test]
+    fn forwards_to_inner_receiver() {
+        #[derive(Default)]
+        struct Recorder {
+            positions: Mutex<Vec<u64>>,
+            finished: Mutex<bool>,
+        }
+        impl ProgressReceiver for &'static Recorder {
+            fn set_position(&self, position: u64) {
+                self.positions.lock().unwrap().push(position);
+            }
+            fn finish(&self) {
+                *self.finished.lock().unwrap() = true;
+            }
+        }
+
+        let recorder: &'static Recorder = Box::leak(Box::default());
+        let shared = Shared::forward_to(recorder);
+        shared.add(3);
+        shared.set_position(10);
+        shared.flush();
+        shared.finish();
+        assert_eq!(*recorder.positions.lock().unwrap(), [3, 10, 10]);
+        assert!(*recorder.finished.lock().unwrap());
+    }
+
+    #[test]
+    fn concurrent_adds_sum_exactly() {
+        const THREADS: u64 = 8;
+        const STEPS: u64 = 10_000;
+
+        let shared = Shared::new();
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                let shared = shared.clone();
+                scope.spawn(move || {
+                    for _ in 0..STEPS {
+                        shared.add(1);
+                    }
+                });
+            }
+        });
+        assert_eq!(shared.snapshot().position, THREADS * STEPS);
+    }
+}
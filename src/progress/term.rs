@@ -0,0 +1,295 @@
+//! A minimal terminal progress bar without external dependencies.
+//!
+//! Not every consumer wants to pull in `indicatif`; [`TermBar`] draws a
+//! simple `[#####-----] 52% 12.3 MiB/s` line to stderr using only
+//! carriage-return redraws (no ANSI sequences, so Windows consoles work).
+//! When stderr is not a terminal it degrades to periodic plain lines.
+
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::progress::log::format_bytes;
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder, Throughput};
+
+/// A progress receiver builder drawing a plain terminal bar.
+///
+/// The generic sink is stderr by default; tests can inject any
+/// [`Write`] implementation and inspect the rendered frames.
+pub struct TermBar<W = std::io::Stderr> {
+    sink: W,
+    tty: bool,
+    width: usize,
+    interval: Duration,
+}
+
+impl TermBar<std::io::Stderr> {
+    /// Create a bar drawing to stderr, redrawing at most every 100 ms.
+    ///
+    /// When stderr is not a terminal, a plain line is printed every few
+    /// seconds instead of redrawing in place.
+    pub fn new() -> Self {
+        let tty = std::io::stderr().is_terminal();
+        Self {
+            sink: std::io::stderr(),
+            tty,
+            width: 20,
+            interval: if tty {
+                Duration::from_millis(100)
+            } else {
+                Duration::from_secs(10)
+            },
+        }
+    }
+}
+
+impl Default for TermBar<std::io::Stderr> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write + Send> TermBar<W> {
+    /// Create a bar drawing to `sink`; `tty` selects in-place redraws over
+    /// periodic plain lines.
+    pub fn with_sink(sink: W, tty: bool) -> Self {
+        Self {
+            sink,
+            tty,
+            width: 20,
+            interval: Duration::from_millis(100),
+        }
+    }
+
+    /// Set the width of the bar in characters.
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set the minimum interval between redraws.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl<W: Write + Send> ProgressReceiverBuilder for TermBar<W> {
+    type Receiver = TermBarReceiver<W>;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        TermBarReceiver {
+            tty: self.tty,
+            width: self.width,
+            interval: self.interval,
+            state: Mutex::new(TermState {
+                sink: self.sink,
+                total,
+                position: 0,
+                last_draw: None,
+                last_len: 0,
+                throughput: Throughput::new(),
+            }),
+        }
+    }
+}
+
+/// The receiver built by [`TermBar`].
+pub struct TermBarReceiver<W> {
+    tty: bool,
+    width: usize,
+    interval: Duration,
+    state: Mutex<TermState<W>>,
+}
+
+struct TermState<W> {
+    sink: W,
+    total: Option<u64>,
+    position: u64,
+    last_draw: Option<Instant>,
+    /// The length of the last frame, to overwrite leftovers of a longer
+    /// one.
+    last_len: usize,
+    throughput: Throughput,
+}
+
+impl<W: Write> TermBarReceiver<W> {
+    fn frame(&self, state: &TermState<W>, now: Instant) -> String {
+        let rate = format_bytes(state.throughput.rate(now) as u64);
+        match state.total {
+            Some(total) if total > 0 => {
+                let percent = (state.position * 100 / total).min(100);
+                let filled = (state.position as usize * self.width / total as usize)
+                    .min(self.width);
+                format!(
+                    "[{}{}] {percent}% {rate}/s",
+                    "#".repeat(filled),
+                    "-".repeat(self.width - filled),
+                )
+            }
+            _ => format!("{} {rate}/s", format_bytes(state.position)),
+        }
+    }
+
+    /// Draw the current frame; errors writing to the sink are ignored, a
+    /// broken terminal must not fail the download.
+    fn draw(&self, state: &mut TermState<W>, now: Instant, terminal: bool) {
+        let frame = self.frame(state, now);
+        let padding = state.last_len.saturating_sub(frame.len());
+        state.last_len = frame.len();
+        let result = if self.tty {
+            let end = if terminal { "\n" } else { "" };
+            write!(state.sink, "\r{frame}{}{end}", " ".repeat(padding))
+        } else {
+            writeln!(state.sink, "{frame}")
+        };
+        let _ = result.and_then(|()| state.sink.flush());
+    }
+}
+
+impl<W: Write + Send> ProgressReceiver for TermBarReceiver<W> {
+    fn set_position(&self, position: u64) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.position = position;
+        state.throughput.record(now, position);
+        let due = state
+            .last_draw
+            .is_none_or(|last| now.duration_since(last) >= self.interval);
+        if due {
+            state.last_draw = Some(now);
+            self.draw(&mut state, now, false);
+        }
+    }
+
+    fn set_total(&self, total: u64) {
+        self.state.lock().unwrap().total = Some(total);
+    }
+
+    fn inc(&self, delta: u64) {
+        let position = self.state.lock().unwrap().position + delta;
+        self.set_position(position);
+    }
+
+    fn finish(&self) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        self.draw(&mut state, now, true);
+    }
+
+    fn finish_with_error(&self, error: &crate::error::Error) {
+        let mut state = self.state.lock().unwrap();
+        let padding = " ".repeat(state.last_len);
+        let result = if self.tty {
+            write!(state.sink, "\rerror: {error}{padding}\n")
+        } else {
+            writeln!(state.sink, "error: {error}")
+        };
+        let _ = result.and_then(|()| state.sink.flush());
+    }
+
+    fn abandon(&self) {
+        let mut state = self.state.lock().unwrap();
+        let result = if self.tty {
+            writeln!(state.sink)
+        } else {
+            Ok(())
+        };
+        let _ = result.and_then(|()| state.sink.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// A cloneable in-memory sink, so the output can be inspected while the
+    /// receiver owns the writer.
+    #[derive(Clone, Default)]
+    struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedSink {
+        fn rendered(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl Write for SharedSink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn renders_bar_frames() {
+        let sink = SharedSink::default();
+        let receiver = TermBar::with_sink(sink.clone(), true)
+            .with_width(10)
+            .with_interval(Duration::ZERO)
+            .init(Some(100));
+        receiver.set_position(50);
+        receiver.set_position(100);
+        receiver.finish();
+        let rendered = sink.rendered();
+        let frames: Vec<&str> = rendered
+            .split('\r')
+            .filter(|f| !f.is_empty())
+            .map(|f| f.trim_end_matches(['\n', ' ']))
+            .collect();
+        assert!(frames[0].starts_with("[#####-----] 50%"), "{:?}", frames[0]);
+        let last = frames.last().unwrap();
+        assert!(last.starts_with("[##########] 100%"), "{last:?}");
+        assert!(sink.rendered().ends_with('\n'));
+    }
+
+    #[test]
+    fn unknown_total_shows_bytes() {
+        let sink = SharedSink::default();
+        let receiver = TermBar::with_sink(sink.clone(), true)
+            .with_interval(Duration::ZERO)
+            .init(None);
+        receiver.set_position(2048);
+        assert!(sink.rendered().contains("2.0 KiB"));
+        receiver.set_total(4096);
+        receiver.set_position(4096);
+        receiver.finish();
+        assert!(sink.rendered().contains("100%"));
+    }
+
+    #[test]
+    fn non_tty_prints_plain_lines() {
+        let sink = SharedSink::default();
+        let receiver = TermBar::with_sink(sink.clone(), false)
+            .with_width(10)
+            .with_interval(Duration::ZERO)
+            .init(Some(100));
+        receiver.set_position(30);
+        receiver.finish();
+        let rendered = sink.rendered();
+        assert!(!rendered.contains('\r'));
+        assert_eq!(rendered.lines().count(), 2);
+    }
+
+    #[test]
+    fn error_clears_the_bar() {
+        use crate::error::{Error, ErrorKind, WithDesc};
+
+        let sink = SharedSink::default();
+        let receiver = TermBar::with_sink(sink.clone(), true)
+            .with_width(10)
+            .with_interval(Duration::ZERO)
+            .init(Some(100));
+        receiver.set_position(50);
+        receiver.finish_with_error(&Error::new(ErrorKind::Network).with_desc("timed out"));
+        let rendered = sink.rendered();
+        assert!(rendered.ends_with('\n'));
+        assert!(rendered.rsplit('\r').next().unwrap().starts_with("error: "));
+    }
+}
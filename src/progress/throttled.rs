@@ -0,0 +1,205 @@
+//! Rate limiting for progress updates.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::progress::ProgressReceiver;
+
+/// A receiver wrapper limiting how often position updates are forwarded.
+///
+/// Fast local transfers produce tens of thousands of position updates per
+/// second, which makes redrawing terminal bars and filling channels a
+/// measurable cost. `Throttled` forwards [`set_position`] at most once per
+/// interval and drops the updates in between, but always delivers the final
+/// position before a terminal call so bars end at 100%. Messages bypass the
+/// throttle. An interval of zero forwards everything.
+///
+/// [`set_position`]: ProgressReceiver::set_position
+pub struct Throttled<R> {
+    inner: R,
+    interval: Duration,
+    state: Mutex<ThrottledState>,
+}
+
+#[derive(Default)]
+struct ThrottledState {
+    last_update: Option<Instant>,
+    position: u64,
+    /// A position received but not yet forwarded.
+    pending: bool,
+}
+
+impl<R> Throttled<R> {
+    /// The default minimum interval between forwarded updates.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(50);
+}
+
+impl<R: ProgressReceiver> Throttled<R> {
+    /// Wrap `inner` with the default interval.
+    pub fn new(inner: R) -> Self {
+        Self::with_interval(inner, Self::DEFAULT_INTERVAL)
+    }
+
+    /// Wrap `inner`, forwarding at most one position update per `interval`.
+    pub fn with_interval(inner: R, interval: Duration) -> Self {
+        Self {
+            inner,
+            interval,
+            state: Mutex::new(ThrottledState::default()),
+        }
+    }
+
+    /// Record `position` and decide whether to forward it now.
+    fn record(&self, position: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.position = position;
+        let now = Instant::now();
+        match state.last_update {
+            Some(last) if now.duration_since(last) < self.interval => {
+                state.pending = true;
+                false
+            }
+            _ => {
+                state.last_update = Some(now);
+                state.pending = false;
+                true
+            }
+        }
+    }
+
+    /// Forward the pending position, if any, ahead of a terminal call.
+    fn flush(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.pending {
+            state.pending = false;
+            let position = state.position;
+            drop(state);
+            self.inner.set_position(position);
+        }
+    }
+}
+
+impl<R: ProgressReceiver> ProgressReceiver for Throttled<R> {
+    fn set_position(&self, position: u64) {
+        if self.record(position) {
+            self.inner.set_position(position);
+        }
+    }
+
+    fn set_total(&self, total: u64) {
+        self.inner.set_total(total);
+    }
+
+    fn inc(&self, delta: u64) {
+        let position = self.state.lock().unwrap().position + delta;
+        if self.record(position) {
+            self.inner.set_position(position);
+        }
+    }
+
+    fn set_message(&self, msg: &str) {
+        self.inner.set_message(msg);
+    }
+
+    fn finish(&self) {
+        self.flush();
+        self.inner.finish();
+    }
+
+    fn finish_with_error(&self, error: &Error) {
+        self.flush();
+        self.inner.finish_with_error(error);
+    }
+
+    fn abandon(&self) {
+        self.flush();
+        self.inner.abandon();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Counter {
+        positions: Arc<Mutex<Vec<u64>>>,
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl ProgressReceiver for Counter {
+        fn set_position(&self, position: u64) {
+            self.positions.lock().unwrap().push(position);
+        }
+
+        fn set_message(&self, msg: &str) {
+            self.messages.lock().unwrap().push(msg.to_string());
+        }
+
+        fn finish(&self) {}
+    }
+
+    #[test]
+    fn drops_intermediate_positions() {
+        let counter = Counter::default();
+        // An interval no test will ever exceed: only the first update goes
+        // through until the flush at finish.
+        let throttled = Throttled::with_interval(counter.clone(), Duration::from_secs(3600));
+        for position in 1..=100 {
+            throttled.set_position(position);
+        }
+        throttled.finish();
+        assert_eq!(*counter.positions.lock().unwrap(), [1, 100]);
+    }
+
+    #[test]
+    fn zero_interval_forwards_everything() {
+        let counter = Counter::default();
+        let throttled = Throttled::with_interval(counter.clone(), Duration::ZERO);
+        for position in 1..=10 {
+            throttled.set_position(position);
+        }
+        throttled.finish();
+        assert_eq!(
+            *counter.positions.lock().unwrap(),
+            (1..=10).collect::<Vec<u64>>()
+        );
+    }
+
+    #[test]
+    fn messages_bypass_the_throttle() {
+        let counter = Counter::default();
+        let throttled = Throttled::with_interval(counter.clone(), Duration::from_secs(3600));
+        throttled.set_position(1);
+        throttled.set_message("a");
+        throttled.set_position(2);
+        throttled.set_message("b");
+        throttled.finish();
+        assert_eq!(*counter.messages.lock().unwrap(), ["a", "b"]);
+    }
+
+    #[test]
+    fn final_position_delivered_on_error() {
+        use crate::error::ErrorKind;
+
+        let counter = Counter::default();
+        let throttled = Throttled::with_interval(counter.clone(), Duration::from_secs(3600));
+        throttled.set_position(1);
+        throttled.set_position(7);
+        throttled.finish_with_error(&Error::new(ErrorKind::Network));
+        assert_eq!(*counter.positions.lock().unwrap(), [1, 7]);
+    }
+
+    #[test]
+    fn increments_are_throttled_too() {
+        let counter = Counter::default();
+        let throttled = Throttled::with_interval(counter.clone(), Duration::from_secs(3600));
+        throttled.inc(3);
+        throttled.inc(4);
+        throttled.finish();
+        assert_eq!(*counter.positions.lock().unwrap(), [3, 7]);
+    }
+}
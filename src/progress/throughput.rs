@@ -0,0 +1,359 @@
+//! Throughput and ETA estimation for progress receivers.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder};
+
+/// The default averaging window of [`Throughput`].
+const DEFAULT_WINDOW: Duration = Duration::from_secs(5);
+
+/// A windowed throughput estimator.
+///
+/// Feed it `(instant, position)` samples and query the average rate over the
+/// recent window, or an ETA given the total size. The rate is averaged over
+/// the sample window so it does not jitter with individual chunks, and it
+/// decays toward zero while no new samples arrive (a stalled download). A
+/// position lower than the previous one is treated as a restart and resets
+/// the estimator.
+#[derive(Debug, Clone)]
+pub struct Throughput {
+    window: Duration,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl Default for Throughput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Throughput {
+    /// Create an estimator with the default 5 second window.
+    pub fn new() -> Self {
+        Self::with_window(DEFAULT_WINDOW)
+    }
+
+    /// Create an estimator averaging over the given window.
+    pub fn with_window(window: Duration) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Start a session at `position`, taken at `at`.
+    ///
+    /// Clears any recorded samples and records `position` as the baseline,
+    /// so a download resumed with bytes already on disk reports the rate of
+    /// this session only.
+    pub fn start_at(&mut self, at: Instant, position: u64) {
+        self.samples.clear();
+        self.samples.push_back((at, position));
+    }
+
+    /// Record a position sample taken at `at`.
+    pub fn record(&mut self, at: Instant, position: u64) {
+        if let Some(&(_, last)) = self.samples.back() {
+            if position < last {
+                // Position went backwards: a retry restarted the download.
+                self.samples.clear();
+            }
+        }
+        self.samples.push_back((at, position));
+        // Drop samples that fell out of the window, but keep one sample at
+        // or before the cutoff as the anchor, so the rate is computed over
+        // the full window.
+        if let Some(cutoff) = at.checked_sub(self.window) {
+            while let Some(&(t, _)) = self.samples.get(1) {
+                if t > cutoff {
+                    break;
+                }
+                self.samples.pop_front();
+            }
+        }
+    }
+
+    /// The average rate in bytes per second as of `now`.
+    ///
+    /// Returns `0.0` until two samples are recorded.
+    pub fn rate(&self, now: Instant) -> f64 {
+        let (Some(&(first_t, first_p)), Some(&(last_t, last_p))) =
+            (self.samples.front(), self.samples.back())
+        else {
+            return 0.0;
+        };
+        if first_t == last_t {
+            return 0.0;
+        }
+        // Extending the span to `now` makes the rate decay while the
+        // download is stalled.
+        let span = now.max(last_t).saturating_duration_since(first_t);
+        (last_p - first_p) as f64 / span.as_secs_f64()
+    }
+
+    /// The estimated time until `total` bytes are reached, as of `now`.
+    ///
+    /// Returns `None` when the total is unknown (`0`) or the rate is zero.
+    pub fn eta(&self, now: Instant, total: u64) -> Option<Duration> {
+        if total == 0 {
+            return None;
+        }
+        let &(_, position) = self.samples.back()?;
+        let remaining = total.saturating_sub(position);
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rate(now);
+        if rate <= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(remaining as f64 / rate))
+    }
+}
+
+/// A progress receiver builder wrapping `B` with throughput tracking.
+///
+/// The [`ThroughputHandle`] can be obtained before the download starts and
+/// queried from another task while it runs.
+#[derive(Debug, Clone)]
+pub struct ThroughputBuilder<B> {
+    inner: B,
+    handle: ThroughputHandle,
+}
+
+impl<B: ProgressReceiverBuilder> ThroughputBuilder<B> {
+    /// Create a builder tracking throughput around `inner`.
+    pub fn wrapping(inner: B) -> Self {
+        Self {
+            inner,
+            handle: ThroughputHandle::default(),
+        }
+    }
+
+    /// A shared handle to the rate and ETA estimates.
+    pub fn handle(&self) -> ThroughputHandle {
+        self.handle.clone()
+    }
+}
+
+impl<B: ProgressReceiverBuilder> ProgressReceiverBuilder for ThroughputBuilder<B> {
+    type Receiver = ThroughputReceiver<B::Receiver>;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        self.handle.state.lock().unwrap().total = total.unwrap_or(0);
+        ThroughputReceiver {
+            inner: self.inner.init(total),
+            handle: self.handle,
+        }
+    }
+
+    fn init_at(self, total: Option<u64>, start: u64) -> Self::Receiver {
+        {
+            let mut state = self.handle.state.lock().unwrap();
+            state.total = total.unwrap_or(0);
+            state.throughput.start_at(Instant::now(), start);
+        }
+        ThroughputReceiver {
+            inner: self.inner.init_at(total, start),
+            handle: self.handle,
+        }
+    }
+}
+
+/// A progress receiver maintaining a [`Throughput`] alongside an inner
+/// receiver.
+pub struct ThroughputReceiver<R> {
+    inner: R,
+    handle: ThroughputHandle,
+}
+
+impl<R: ProgressReceiver> ThroughputReceiver<R> {
+    /// Create a receiver tracking throughput around `inner`; `total` is the
+    /// expected size in bytes when known.
+    pub fn new(inner: R, total: Option<u64>) -> Self {
+        let handle = ThroughputHandle::default();
+        handle.state.lock().unwrap().total = total.unwrap_or(0);
+        Self { inner, handle }
+    }
+
+    /// A shared handle to the rate and ETA estimates.
+    pub fn handle(&self) -> ThroughputHandle {
+        self.handle.clone()
+    }
+}
+
+impl<R: ProgressReceiver> ProgressReceiver for ThroughputReceiver<R> {
+    fn set_position(&self, position: u64) {
+        self.handle
+            .state
+            .lock()
+            .unwrap()
+            .throughput
+            .record(Instant::now(), position);
+        self.inner.set_position(position);
+    }
+
+    fn set_total(&self, total: u64) {
+        self.handle.state.lock().unwrap().total = total;
+        self.inner.set_total(total);
+    }
+
+    fn inc(&self, delta: u64) {
+        let mut state = self.handle.state.lock().unwrap();
+        let position = state.throughput.samples.back().map_or(0, |&(_, p)| p) + delta;
+        state.throughput.record(Instant::now(), position);
+        drop(state);
+        self.inner.inc(delta);
+    }
+
+    fn set_message(&self, msg: &str) {
+        self.inner.set_message(msg);
+    }
+
+    fn finish(&self) {
+        self.inner.finish();
+    }
+}
+
+/// A cloneable handle to the estimates of a [`ThroughputReceiver`].
+#[derive(Debug, Clone, Default)]
+pub struct ThroughputHandle {
+    state: Arc<Mutex<ThroughputState>>,
+}
+
+#[derive(Debug, Default)]
+struct ThroughputState {
+    throughput: Throughput,
+    total: u64,
+}
+
+impl ThroughputHandle {
+    /// The current average rate in bytes per second.
+    pub fn rate(&self) -> f64 {
+        self.state.lock().unwrap().throughput.rate(Instant::now())
+    }
+
+    /// The current ETA, `None` when the total or the rate is unknown.
+    pub fn eta(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        state.throughput.eta(Instant::now(), state.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeline() -> (Instant, Throughput) {
+        (Instant::now(), Throughput::new())
+    }
+
+    #[test]
+    fn no_rate_without_samples() {
+        let (base, throughput) = timeline();
+        assert_eq!(throughput.rate(base), 0.0);
+        assert_eq!(throughput.eta(base, 100), None);
+    }
+
+    #[test]
+    fn no_rate_with_one_sample() {
+        let (base, mut throughput) = timeline();
+        throughput.record(base, 10);
+        assert_eq!(throughput.rate(base + Duration::from_millis(1)), 0.0);
+    }
+
+    #[test]
+    fn steady_rate() {
+        let (base, mut throughput) = timeline();
+        for i in 0..=4u64 {
+            throughput.record(base + Duration::from_secs(i), i * 100);
+        }
+        let rate = throughput.rate(base + Duration::from_secs(4));
+        assert!((rate - 100.0).abs() < 1.0, "rate was {rate}");
+    }
+
+    #[test]
+    fn rate_windows_out_old_samples() {
+        let (base, mut throughput) = timeline();
+        // 1000 B/s for 5 seconds, then 100 B/s for 10 seconds: the old fast
+        // samples must not inflate the rate.
+        let mut position = 0;
+        for i in 0..=15u64 {
+            position += if i <= 5 { 1000 } else { 100 };
+            throughput.record(base + Duration::from_secs(i), position);
+        }
+        let rate = throughput.rate(base + Duration::from_secs(15));
+        assert!((rate - 100.0).abs() < 1.0, "rate was {rate}");
+    }
+
+    #[test]
+    fn rate_decays_during_stall() {
+        let (base, mut throughput) = timeline();
+        throughput.record(base, 0);
+        throughput.record(base + Duration::from_secs(1), 1000);
+        let fresh = throughput.rate(base + Duration::from_secs(1));
+        let stalled = throughput.rate(base + Duration::from_secs(10));
+        let dead = throughput.rate(base + Duration::from_secs(100));
+        assert!(fresh > stalled && stalled > dead);
+        assert!(dead < 11.0, "rate was {dead}");
+    }
+
+    #[test]
+    fn position_reset_restarts_estimate() {
+        let (base, mut throughput) = timeline();
+        throughput.record(base, 0);
+        throughput.record(base + Duration::from_secs(1), 10_000);
+        // A retry started over from zero.
+        throughput.record(base + Duration::from_secs(2), 0);
+        throughput.record(base + Duration::from_secs(3), 100);
+        let rate = throughput.rate(base + Duration::from_secs(3));
+        assert!((rate - 100.0).abs() < 1.0, "rate was {rate}");
+    }
+
+    #[test]
+    fn eta_from_rate() {
+        let (base, mut throughput) = timeline();
+        throughput.record(base, 0);
+        throughput.record(base + Duration::from_secs(1), 100);
+        let eta = throughput.eta(base + Duration::from_secs(1), 1100).unwrap();
+        assert!((eta.as_secs_f64() - 10.0).abs() < 0.1, "eta was {eta:?}");
+        // Unknown total has no ETA.
+        assert_eq!(throughput.eta(base + Duration::from_secs(1), 0), None);
+        // Reaching the total pins the ETA to zero.
+        assert_eq!(
+            throughput.eta(base + Duration::from_secs(1), 100),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn resume_baseline_counts_only_new_bytes() {
+        let (base, mut throughput) = timeline();
+        // 7 of 10 bytes were already on disk when the session started.
+        throughput.start_at(base, 7_000);
+        throughput.record(base + Duration::from_secs(1), 7_100);
+        let rate = throughput.rate(base + Duration::from_secs(1));
+        assert!((rate - 100.0).abs() < 1.0, "rate was {rate}");
+    }
+
+    #[test]
+    fn receiver_updates_handle() {
+        struct Sink;
+        impl ProgressReceiver for Sink {
+            fn set_position(&self, _: u64) {}
+            fn finish(&self) {}
+        }
+
+        let receiver = ThroughputReceiver::new(Sink, Some(1_000_000));
+        let handle = receiver.handle();
+        assert_eq!(handle.rate(), 0.0);
+        receiver.set_position(100);
+        std::thread::sleep(Duration::from_millis(20));
+        receiver.set_position(10_000);
+        assert!(handle.rate() > 0.0);
+        assert!(handle.eta().is_some());
+        receiver.finish();
+    }
+}
@@ -0,0 +1,262 @@
+//! A [`tracing`] adapter for the progress traits.
+//!
+//! Requires the `tracing` feature. [`TracingProgress`] opens a span per
+//! operation and emits periodic events instead of driving a separate
+//! progress pipeline, so fetches appear in the same traces as everything
+//! else. The span and field names are stable and dashboards can rely on
+//! them:
+//!
+//! - span `fetch` with field `total` (bytes, 0 when unknown) and an
+//!   `outcome` field recorded at the end (`"success"` or `"error"`);
+//! - periodic events with fields `position` (bytes) and `rate` (bytes per
+//!   second);
+//! - a final event with field `position`, and `error` on failure.
+//!
+//! With the feature enabled the download module also opens a
+//! `select_mirror` span around mirror probing, and the extraction module an
+//! `extract` span per archive.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::{Level, Span};
+
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder, Throughput};
+
+/// A progress receiver builder reporting through [`tracing`].
+#[derive(Debug, Clone)]
+pub struct TracingProgress {
+    level: Level,
+    interval: Duration,
+}
+
+impl Default for TracingProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TracingProgress {
+    /// Create a builder emitting periodic events at [`Level::DEBUG`], at
+    /// most once per second.
+    pub fn new() -> Self {
+        Self {
+            level: Level::DEBUG,
+            interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Set the level of the periodic position events. Only `TRACE` and
+    /// `DEBUG` are meaningful; anything else is emitted at `DEBUG`.
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set the minimum interval between periodic events.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl ProgressReceiverBuilder for TracingProgress {
+    type Receiver = TracingReceiver;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        let span = tracing::info_span!(
+            "fetch",
+            total = total.unwrap_or(0),
+            outcome = tracing::field::Empty,
+        );
+        TracingReceiver {
+            span,
+            level: self.level,
+            interval: self.interval,
+            state: Mutex::new(TracingState {
+                position: 0,
+                last_emit: None,
+                throughput: Throughput::new(),
+            }),
+        }
+    }
+}
+
+/// The receiver built by [`TracingProgress`].
+pub struct TracingReceiver {
+    span: Span,
+    level: Level,
+    interval: Duration,
+    state: Mutex<TracingState>,
+}
+
+struct TracingState {
+    position: u64,
+    last_emit: Option<Instant>,
+    throughput: Throughput,
+}
+
+impl TracingReceiver {
+    fn emit(&self, position: u64, rate: u64) {
+        // Event macros need a const level; anything unexpected falls back
+        // to DEBUG.
+        self.span.in_scope(|| match self.level {
+            Level::TRACE => tracing::trace!(position, rate, "transferred"),
+            _ => tracing::debug!(position, rate, "transferred"),
+        });
+    }
+}
+
+impl ProgressReceiver for TracingReceiver {
+    fn set_position(&self, position: u64) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.position = position;
+        state.throughput.record(now, position);
+        let due = state
+            .last_emit
+            .is_none_or(|last| now.duration_since(last) >= self.interval);
+        if due {
+            state.last_emit = Some(now);
+            let rate = state.throughput.rate(now) as u64;
+            drop(state);
+            self.emit(position, rate);
+        }
+    }
+
+    fn set_total(&self, total: u64) {
+        self.span.record("total", total);
+    }
+
+    fn inc(&self, delta: u64) {
+        let position = self.state.lock().unwrap().position + delta;
+        self.set_position(position);
+    }
+
+    fn set_message(&self, msg: &str) {
+        self.span.in_scope(|| tracing::debug!(message = msg));
+    }
+
+    fn finish(&self) {
+        let position = self.state.lock().unwrap().position;
+        self.span.record("outcome", "success");
+        self.span.in_scope(|| tracing::debug!(position, "finished"));
+    }
+
+    fn finish_with_error(&self, error: &crate::error::Error) {
+        let position = self.state.lock().unwrap().position;
+        self.span.record("outcome", "error");
+        self.span
+            .in_scope(|| tracing::warn!(position, error = %error, "failed"));
+    }
+
+    fn abandon(&self) {
+        self.span.record("outcome", "abandoned");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span;
+
+    use super::*;
+
+    /// A minimal collecting subscriber: flattens every span and event into
+    /// a `name: field=value` line.
+    #[derive(Clone, Default)]
+    struct Collector {
+        lines: Arc<Mutex<Vec<String>>>,
+        ids: Arc<AtomicU64>,
+    }
+
+    struct Flatten(String);
+
+    impl Visit for Flatten {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    impl tracing::Subscriber for Collector {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+            let mut line = Flatten(format!("span {}:", attrs.metadata().name()));
+            attrs.record(&mut line);
+            self.lines.lock().unwrap().push(line.0);
+            span::Id::from_u64(self.ids.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _id: &span::Id, values: &span::Record<'_>) {
+            let mut line = Flatten("record:".to_string());
+            values.record(&mut line);
+            self.lines.lock().unwrap().push(line.0);
+        }
+
+        fn record_follows_from(&self, _id: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut line = Flatten("event:".to_string());
+            event.record(&mut line);
+            self.lines.lock().unwrap().push(line.0);
+        }
+
+        fn enter(&self, _id: &span::Id) {}
+
+        fn exit(&self, _id: &span::Id) {}
+    }
+
+    #[test]
+    fn span_and_events_have_stable_fields() {
+        let collector = Collector::default();
+        tracing::subscriber::with_default(collector.clone(), || {
+            let receiver = TracingProgress::new()
+                .with_interval(Duration::ZERO)
+                .init(Some(100));
+            receiver.set_position(50);
+            receiver.finish();
+        });
+        let lines = collector.lines.lock().unwrap().clone();
+        assert!(lines[0].starts_with("span fetch: total=100"), "{lines:?}");
+        assert!(
+            lines.iter().any(|l| l.contains("position=50")),
+            "{lines:?}"
+        );
+        assert!(
+            lines.iter().any(|l| l.contains("outcome=\"success\"")),
+            "{lines:?}"
+        );
+        assert!(
+            lines.iter().any(|l| l.contains("message=finished")),
+            "{lines:?}"
+        );
+    }
+
+    #[test]
+    fn errors_are_recorded_on_the_span() {
+        use crate::error::{Error, ErrorKind, WithDesc};
+
+        let collector = Collector::default();
+        tracing::subscriber::with_default(collector.clone(), || {
+            let receiver = TracingProgress::new().init(None);
+            receiver.set_position(7);
+            receiver
+                .finish_with_error(&Error::new(ErrorKind::Network).with_desc("stream interrupted"));
+        });
+        let lines = collector.lines.lock().unwrap().clone();
+        assert!(
+            lines.iter().any(|l| l.contains("outcome=\"error\"")),
+            "{lines:?}"
+        );
+        assert!(
+            lines.iter().any(|l| l.contains("stream interrupted")),
+            "{lines:?}"
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! Weighted sub-progress for multi-step operations.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::progress::{ProgressReceiver, ProgressReceiverBuilder};
+
+/// The resolution of the parent range: positions are projected onto
+/// `0..SCALE`, so a weight of `0.8` occupies 8000 parent units.
+const SCALE: u64 = 10_000;
+
+/// Split a parent receiver into weighted children.
+///
+/// Each child maps its own `0..total` range onto a slice of the parent's
+/// range proportional to its weight, so one bar can present "80% download,
+/// 15% extraction, 5% verification" as a single continuous 0–100%. The
+/// parent is initialized with a total of 10000 units; rounding remainders
+/// go to the last child so the slices exactly cover the range. A child with
+/// an unknown total contributes nothing until it finishes, at which point
+/// its whole slice is credited. The parent finishes once every child has
+/// finished.
+///
+/// # Panics
+///
+/// Panics when `weights` is empty or the weights do not sum to a positive
+/// number.
+pub fn split<B>(parent: B, weights: &[f64]) -> Vec<WeightedChild>
+where
+    B: ProgressReceiverBuilder,
+    B::Receiver: Send + Sync + 'static,
+{
+    assert!(!weights.is_empty(), "at least one weight is required");
+    let sum: f64 = weights.iter().sum();
+    assert!(sum > 0.0, "weights must sum to a positive number");
+
+    let mut spans = Vec::with_capacity(weights.len());
+    let mut used = 0;
+    for (i, weight) in weights.iter().enumerate() {
+        let span = if i + 1 == weights.len() {
+            // The rounding remainder goes to the last child.
+            SCALE - used
+        } else {
+            (weight / sum * SCALE as f64).round() as u64
+        };
+        used += span;
+        spans.push(span);
+    }
+
+    let state = Arc::new(SplitState {
+        parent: Box::new(parent.init(Some(SCALE))),
+        contributions: spans.iter().map(|_| AtomicU64::new(0)).collect(),
+        finished: AtomicUsize::new(0),
+    });
+    spans
+        .into_iter()
+        .enumerate()
+        .map(|(index, span)| WeightedChild {
+            state: state.clone(),
+            index,
+            span,
+        })
+        .collect()
+}
+
+struct SplitState {
+    parent: Box<dyn ProgressReceiver + Send + Sync>,
+    contributions: Vec<AtomicU64>,
+    finished: AtomicUsize,
+}
+
+impl SplitState {
+    /// Push the sum of all contributions to the parent.
+    fn publish(&self) {
+        let sum = self
+            .contributions
+            .iter()
+            .map(|c| c.load(Ordering::Relaxed))
+            .sum();
+        self.parent.set_position(sum);
+    }
+}
+
+/// One weighted slice of a parent receiver, returned by [`split`].
+pub struct WeightedChild {
+    state: Arc<SplitState>,
+    index: usize,
+    span: u64,
+}
+
+impl ProgressReceiverBuilder for WeightedChild {
+    type Receiver = WeightedChildReceiver;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        WeightedChildReceiver {
+            total: AtomicU64::new(total.unwrap_or(0)),
+            position: AtomicU64::new(0),
+            child: self,
+        }
+    }
+}
+
+/// The receiver built by a [`WeightedChild`].
+pub struct WeightedChildReceiver {
+    /// The child's own total, `0` when unknown.
+    total: AtomicU64,
+    position: AtomicU64,
+    child: WeightedChild,
+}
+
+impl WeightedChildReceiver {
+    /// Project the child position onto the parent slice and publish.
+    fn project(&self) {
+        let total = self.total.load(Ordering::Relaxed);
+        let contribution = if total > 0 {
+            let position = self.position.load(Ordering::Relaxed);
+            ((position as u128 * self.child.span as u128 / total as u128) as u64)
+                .min(self.child.span)
+        } else {
+            // An unknown total has no defined fraction; the slice is
+            // credited when the child finishes.
+            0
+        };
+        self.child.state.contributions[self.child.index].store(contribution, Ordering::Relaxed);
+        self.child.state.publish();
+    }
+}
+
+impl ProgressReceiver for WeightedChildReceiver {
+    fn set_position(&self, position: u64) {
+        self.position.store(position, Ordering::Relaxed);
+        self.project();
+    }
+
+    fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+        self.project();
+    }
+
+    fn inc(&self, delta: u64) {
+        self.position.fetch_add(delta, Ordering::Relaxed);
+        self.project();
+    }
+
+    fn set_message(&self, msg: &str) {
+        self.child.state.parent.set_message(msg);
+    }
+
+    fn finish(&self) {
+        let state = &self.child.state;
+        state.contributions[self.child.index].store(self.child.span, Ordering::Relaxed);
+        state.publish();
+        let finished = state.finished.fetch_add(1, Ordering::Relaxed) + 1;
+        if finished == state.contributions.len() {
+            state.parent.finish();
+        }
+    }
+
+    fn finish_with_error(&self, error: &crate::error::Error) {
+        self.child.state.parent.finish_with_error(error);
+    }
+
+    fn abandon(&self) {
+        self.child.state.parent.abandon();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        state: Arc<RecorderState>,
+    }
+
+    #[derive(Default)]
+    struct RecorderState {
+        total: Mutex<Option<u64>>,
+        positions: Mutex<Vec<u64>>,
+        finished: Mutex<bool>,
+    }
+
+    impl ProgressReceiverBuilder for Recorder {
+        type Receiver = Recorder;
+
+        fn init(self, total: Option<u64>) -> Self::Receiver {
+            *self.state.total.lock().unwrap() = total;
+            self
+        }
+    }
+
+    impl ProgressReceiver for Recorder {
+        fn set_position(&self, position: u64) {
+            self.state.positions.lock().unwrap().push(position);
+        }
+
+        fn finish(&self) {
+            *self.state.finished.lock().unwrap() = true;
+        }
+    }
+
+    fn last_position(recorder: &Recorder) -> u64 {
+        recorder
+            .state
+            .positions
+            .lock()
+            .unwrap()
+            .last()
+            .copied()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn projects_onto_weighted_slices() {
+        let parent = Recorder::default();
+        let children = split(parent.clone(), &[0.8, 0.15, 0.05]);
+        assert_eq!(*parent.state.total.lock().unwrap(), Some(SCALE));
+        let [download, extract, verify] = children.try_into().ok().unwrap();
+
+        let download = download.init(Some(100));
+        download.set_position(50);
+        // Half of the 80% slice.
+        assert_eq!(last_position(&parent), 4000);
+        download.set_position(100);
+        download.finish();
+        assert_eq!(last_position(&parent), 8000);
+
+        let extract = extract.init(Some(10));
+        extract.set_position(10);
+        extract.finish();
+        assert_eq!(last_position(&parent), 9500);
+        assert!(!*parent.state.finished.lock().unwrap());
+
+        let verify = verify.init(Some(1));
+        verify.set_position(1);
+        verify.finish();
+        assert_eq!(last_position(&parent), SCALE);
+        assert!(*parent.state.finished.lock().unwrap());
+    }
+
+    #[test]
+    fn rounding_remainder_goes_to_the_last_child() {
+        let parent = Recorder::default();
+        let children = split(parent.clone(), &[1.0, 1.0, 1.0]);
+        for child in children {
+            let receiver = child.init(Some(1));
+            receiver.set_position(1);
+            receiver.finish();
+        }
+        assert_eq!(last_position(&parent), SCALE);
+        assert!(*parent.state.finished.lock().unwrap());
+    }
+
+    #[test]
+    fn unknown_total_counts_only_at_finish() {
+        let parent = Recorder::default();
+        let [known, unknown] = split(parent.clone(), &[0.5, 0.5]).try_into().ok().unwrap();
+        let known = known.init(Some(10));
+        let unknown = unknown.init(None);
+        unknown.set_position(12345);
+        known.set_position(10);
+        assert_eq!(last_position(&parent), 5000);
+        unknown.finish();
+        assert_eq!(last_position(&parent), SCALE);
+    }
+
+    #[test]
+    fn interleaved_children_stay_monotonic() {
+        let parent = Recorder::default();
+        let [a, b] = split(parent.clone(), &[0.5, 0.5]).try_into().ok().unwrap();
+        let a = a.init(Some(100));
+        let b = b.init(Some(100));
+        for position in (10..=100).step_by(10) {
+            a.set_position(position);
+            b.set_position(position.saturating_sub(5));
+        }
+        let positions = parent.state.positions.lock().unwrap();
+        assert!(positions.windows(2).all(|w| w[0] <= w[1]), "{positions:?}");
+    }
+}
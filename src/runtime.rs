@@ -0,0 +1,62 @@
+//! Executor-specific primitives behind one internal facade.
+//!
+//! The core download loop only needs `futures_util` and runs on any
+//! executor, but timers and the blocking pool do not exist in the futures
+//! ecosystem itself. This module routes them through whichever backend is
+//! enabled:
+//!
+//! - the `smol` feature uses `async-io` timers and the `blocking` pool,
+//!   which run on their own reactor threads and therefore work under *any*
+//!   executor (including tokio);
+//! - otherwise the `tokio` feature uses `tokio::time` and
+//!   `tokio::task::spawn_blocking`, which require a tokio runtime context.
+//!
+//! When both are enabled, the executor-agnostic `smol` backend wins.
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+
+/// Sleep for the given duration.
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(feature = "smol")]
+    {
+        async_io::Timer::after(duration).await;
+    }
+    #[cfg(all(feature = "tokio", not(feature = "smol")))]
+    tokio::time::sleep(duration).await;
+}
+
+/// Run a future with a time limit.
+///
+/// When the limit expires, the future is dropped and a
+/// [`Timeout`](ErrorKind::Timeout) error is returned.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output> {
+    let sleep = std::pin::pin!(sleep(duration));
+    let future = std::pin::pin!(future);
+    match futures_util::future::select(future, sleep).await {
+        futures_util::future::Either::Left((output, _)) => Ok(output),
+        futures_util::future::Either::Right(((), _)) => Err(Error::new(ErrorKind::Timeout)
+            .mark_timeout()
+            .with_desc_with(|| format!("timed out after {duration:?}"))),
+    }
+}
+
+/// Run a blocking closure off the executor threads.
+pub(crate) async fn spawn_blocking<T, F>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    #[cfg(feature = "smol")]
+    {
+        blocking::unblock(f).await
+    }
+    #[cfg(all(feature = "tokio", not(feature = "smol")))]
+    {
+        tokio::task::spawn_blocking(f)
+            .await
+            .expect("the blocking task panicked")
+    }
+}
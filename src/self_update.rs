@@ -0,0 +1,165 @@
+//! Replacing the currently running executable.
+//!
+//! Requires the `self-update` feature. Overwriting a running binary in
+//! place is a trap on every platform: Windows refuses to open it for
+//! writing, and on unix it risks `ETXTBSY` and torn executions. The safe
+//! dance is to rename the running executable aside and move the new one
+//! into its place, which [`replace_current_exe`] implements:
+//!
+//! 1. the current executable is renamed to `<exe>.old` (renaming a running
+//!    binary is allowed on all platforms);
+//! 2. the new binary is moved (or copied, across filesystems) into place
+//!    and the old file's permissions — including the executable bit — are
+//!    re-applied;
+//! 3. the `.old` file is deleted. On Windows deleting a running executable
+//!    fails; the file is left behind and removed by
+//!    [`cleanup_old_exe`], which updaters should call on startup.
+//!
+//! This composes with the rest of the crate as a final step: fetch a
+//! release with [`Fetcher`](crate::fetch::Fetcher) (or
+//! [`fetch`](crate::fetch::fetch)) and hand the extracted binary to
+//! [`replace_current_exe`].
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result, WithDesc};
+
+/// Replace the currently running executable with `new_binary`.
+///
+/// See the [module documentation](self) for the replacement scheme. On
+/// failure the original executable is restored.
+pub fn replace_current_exe(new_binary: &Path) -> Result<()> {
+    let current = current_exe()?;
+    replace_exe(&current, new_binary)
+}
+
+/// Remove the `.old` file a previous [`replace_current_exe`] left behind.
+///
+/// Returns whether a leftover was removed. On Windows the old executable
+/// cannot be deleted while it is still running, so updaters call this at
+/// the start of the *next* run.
+pub fn cleanup_old_exe() -> Result<bool> {
+    let old = old_path(&current_exe()?);
+    match std::fs::remove_file(&old) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(Error::from(e)
+            .with_path(&old)
+            .with_desc("failed to remove the old executable")),
+    }
+}
+
+/// Replace the executable at `current` with `new_binary`.
+///
+/// This is [`replace_current_exe`] with an explicit target, for updaters
+/// managing a binary other than themselves (and for tests).
+pub fn replace_exe(current: &Path, new_binary: &Path) -> Result<()> {
+    let metadata = std::fs::metadata(current)
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to stat {}", current.display()))?;
+    let old = old_path(current);
+    // A leftover from an earlier replacement; a failure to remove it
+    // surfaces below when the rename runs into it.
+    let _ = std::fs::remove_file(&old);
+
+    std::fs::rename(current, &old)
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to move {} aside", current.display()))?;
+
+    if let Err(e) = install(new_binary, current, &metadata) {
+        // Put the original executable back; the rename failing too leaves
+        // the `.old` file as the only copy, so prefer reporting the
+        // original error either way.
+        let _ = std::fs::rename(&old, current);
+        return Err(e);
+    }
+
+    // On Windows the old executable cannot be deleted while it still runs;
+    // leave it for `cleanup_old_exe` on the next start.
+    if let Err(e) = std::fs::remove_file(&old) {
+        log::debug!("failed to remove {}: {e}", old.display());
+    }
+    Ok(())
+}
+
+/// Move `new_binary` to `dest`, re-applying the replaced file's
+/// permissions.
+fn install(new_binary: &Path, dest: &Path, metadata: &std::fs::Metadata) -> Result<()> {
+    // A rename is atomic but only works within one filesystem; fall back
+    // to a copy when the new binary comes from elsewhere (e.g. a tempdir).
+    if std::fs::rename(new_binary, dest).is_err() {
+        std::fs::copy(new_binary, dest)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to install {}", new_binary.display()))?;
+        let _ = std::fs::remove_file(new_binary);
+    }
+    std::fs::set_permissions(dest, metadata.permissions())
+        .map_err(Error::from)
+        .with_desc_with(|| format!("failed to set permissions on {}", dest.display()))
+}
+
+/// The sidecar path the replaced executable is parked at: the executable
+/// path with `.old` appended.
+fn old_path(current: &Path) -> PathBuf {
+    let mut path = current.as_os_str().to_os_string();
+    path.push(".old");
+    path.into()
+}
+
+fn current_exe() -> Result<PathBuf> {
+    std::env::current_exe()
+        .map_err(Error::from)
+        .with_desc("failed to locate the current executable")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_a_dummy_executable() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("tool");
+        std::fs::write(&exe, b"old binary").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        // The new binary comes from a different directory, like an
+        // extracted archive would.
+        let new_dir = tempfile::tempdir().unwrap();
+        let new = new_dir.path().join("tool");
+        std::fs::write(&new, b"new binary").unwrap();
+
+        replace_exe(&exe, &new).unwrap();
+        assert_eq!(std::fs::read(&exe).unwrap(), b"new binary");
+        assert!(!old_path(&exe).exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&exe).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755, "executable bit was not preserved");
+        }
+    }
+
+    #[test]
+    fn a_failed_install_restores_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("tool");
+        std::fs::write(&exe, b"old binary").unwrap();
+        // The new binary does not exist, so the install step fails.
+        let err = replace_exe(&exe, &dir.path().join("missing")).unwrap_err();
+        assert!(err.to_string().contains("failed to install"));
+        assert_eq!(std::fs::read(&exe).unwrap(), b"old binary");
+        assert!(!old_path(&exe).exists());
+    }
+
+    #[test]
+    fn replacing_a_missing_executable_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let err =
+            replace_exe(&dir.path().join("missing"), &dir.path().join("new")).unwrap_err();
+        assert!(err.to_string().contains("failed to stat"));
+    }
+}
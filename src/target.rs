@@ -0,0 +1,256 @@
+//! Platform-aware expansion of URL templates.
+//!
+//! Release URLs usually differ only by platform strings, but every project
+//! names platforms differently (`darwin` vs `macos` vs `apple-darwin`,
+//! `amd64` vs `x86_64`). [`TargetVars::current`] detects the running
+//! platform and [`expand`] substitutes `{os}`-style placeholders, with
+//! aliases to match a project's naming convention:
+//!
+//! ```
+//! use fetchkit::target::{expand, TargetVars};
+//!
+//! let vars = TargetVars::current()
+//!     .version("1.2.3")
+//!     .arch_alias("amd64");
+//! let url = expand("https://example.com/tool-v{version}-{os}-{arch}.tar.gz", &vars)?;
+//! # let _ = url;
+//! # Ok::<(), fetchkit::Error>(())
+//! ```
+//!
+//! Unknown placeholders are an error (listing every offender), and literal
+//! braces are written as `{{` and `}}`.
+
+use std::collections::BTreeMap;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+
+/// The variables available to [`expand`].
+///
+/// The conventional names are `os`, `arch`, `env` (the libc flavor, e.g.
+/// `gnu` or `musl`) and `version`; arbitrary additional variables can be
+/// added with [`var`](Self::var). Starts out empty with [`Default`]; use
+/// [`current`](Self::current) to pre-fill the detected platform.
+#[derive(Debug, Clone, Default)]
+pub struct TargetVars {
+    vars: BTreeMap<String, String>,
+}
+
+impl TargetVars {
+    /// The variables of the running platform.
+    ///
+    /// `os` and `arch` are filled from [`std::env::consts`] (so `macos`,
+    /// `linux`, `windows` and `x86_64`, `aarch64`, …), and `env` from the
+    /// compile-time target environment (`gnu`, `musl`, `msvc` or empty).
+    /// `version` is not set; supply it with [`version`](Self::version).
+    pub fn current() -> Self {
+        let mut vars = Self::default();
+        vars.set("os", std::env::consts::OS);
+        vars.set("arch", std::env::consts::ARCH);
+        vars.set("env", current_env());
+        vars
+    }
+
+    /// Override the `os` variable, e.g. `darwin` for projects naming macOS
+    /// that way.
+    pub fn os_alias(mut self, os: impl Into<String>) -> Self {
+        self.set("os", os);
+        self
+    }
+
+    /// Override the `arch` variable, e.g. `amd64` for Go-style names.
+    pub fn arch_alias(mut self, arch: impl Into<String>) -> Self {
+        self.set("arch", arch);
+        self
+    }
+
+    /// Override the `env` variable, e.g. `musl` to force static binaries.
+    pub fn env_alias(mut self, env: impl Into<String>) -> Self {
+        self.set("env", env);
+        self
+    }
+
+    /// Set the `version` variable.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.set("version", version);
+        self
+    }
+
+    /// Set an arbitrary variable.
+    pub fn var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// The current value of a variable.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(String::as_str)
+    }
+
+    fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.vars.insert(name.into(), value.into());
+    }
+}
+
+/// The target environment the crate was compiled for.
+fn current_env() -> &'static str {
+    if cfg!(target_env = "musl") {
+        "musl"
+    } else if cfg!(target_env = "msvc") {
+        "msvc"
+    } else if cfg!(target_env = "gnu") {
+        "gnu"
+    } else {
+        ""
+    }
+}
+
+/// Expand `{name}` placeholders in `template` from `vars`.
+///
+/// `{{` and `}}` escape literal braces. Placeholders without a variable are
+/// collected and reported in one error, as are unbalanced braces.
+pub fn expand(template: &str, vars: &TargetVars) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut unknown: Vec<&str> = Vec::new();
+    let mut rest = template;
+    while let Some(brace) = rest.find(['{', '}']) {
+        out.push_str(&rest[..brace]);
+        let (symbol, after) = rest[brace..].split_at(1);
+        if let Some(after) = after.strip_prefix(symbol) {
+            // An escaped literal brace.
+            out.push_str(symbol);
+            rest = after;
+            continue;
+        }
+        if symbol == "}" {
+            return Err(unbalanced(template));
+        }
+        let Some(close) = after.find(['{', '}']) else {
+            return Err(unbalanced(template));
+        };
+        let (name, after) = after.split_at(close);
+        let Some(after) = after.strip_prefix('}') else {
+            return Err(unbalanced(template));
+        };
+        match vars.get(name) {
+            Some(value) => out.push_str(value),
+            None => {
+                if !unknown.contains(&name) {
+                    unknown.push(name);
+                }
+            }
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    if !unknown.is_empty() {
+        return Err(Error::new(ErrorKind::Other).with_desc(format!(
+            "unknown placeholders in template `{template}`: {}",
+            unknown.join(", ")
+        )));
+    }
+    Ok(out)
+}
+
+/// Expand every template of a list, e.g. a mirror list.
+pub fn expand_all<'a, I>(templates: I, vars: &TargetVars) -> Result<Vec<String>>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    templates
+        .into_iter()
+        .map(|template| expand(template, vars))
+        .collect()
+}
+
+fn unbalanced(template: &str) -> Error {
+    Error::new(ErrorKind::Other)
+        .with_desc(format!("unbalanced braces in template `{template}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_common_naming_schemes() {
+        let cases = [
+            // BurntSushi-style
+            (
+                "ripgrep-{version}-{arch}-unknown-linux-{env}.tar.gz",
+                TargetVars::default()
+                    .version("14.1.0")
+                    .arch_alias("x86_64")
+                    .env_alias("musl"),
+                "ripgrep-14.1.0-x86_64-unknown-linux-musl.tar.gz",
+            ),
+            // Go-style
+            (
+                "go{version}.{os}-{arch}.tar.gz",
+                TargetVars::default()
+                    .version("1.22.0")
+                    .os_alias("linux")
+                    .arch_alias("amd64"),
+                "go1.22.0.linux-amd64.tar.gz",
+            ),
+            // Node-style
+            (
+                "node-v{version}-{os}-{arch}.tar.xz",
+                TargetVars::default()
+                    .version("20.11.1")
+                    .os_alias("darwin")
+                    .arch_alias("arm64"),
+                "node-v20.11.1-darwin-arm64.tar.xz",
+            ),
+        ];
+        for (template, vars, expected) in cases {
+            assert_eq!(expand(template, &vars).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn current_detects_the_platform() {
+        let vars = TargetVars::current();
+        assert_eq!(vars.get("os"), Some(std::env::consts::OS));
+        assert_eq!(vars.get("arch"), Some(std::env::consts::ARCH));
+        assert!(vars.get("env").is_some());
+        assert_eq!(vars.get("version"), None);
+    }
+
+    #[test]
+    fn escapes_literal_braces() {
+        let vars = TargetVars::default().var("a", "x");
+        assert_eq!(expand("{{a}} is {a}", &vars).unwrap(), "{a} is x");
+        assert_eq!(expand("}}{{", &vars).unwrap(), "}{");
+    }
+
+    #[test]
+    fn unknown_placeholders_are_listed() {
+        let vars = TargetVars::default().var("os", "linux");
+        let err = expand("{os}-{arch}-{version}", &vars).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("arch, version"), "unexpected message: {msg}");
+    }
+
+    #[test]
+    fn unbalanced_braces_are_an_error() {
+        let vars = TargetVars::default();
+        for template in ["{os", "os}", "{os{arch}}"] {
+            let msg = expand(template, &vars).unwrap_err().to_string();
+            assert!(msg.contains("unbalanced braces"), "accepted `{template}`");
+        }
+    }
+
+    #[test]
+    fn expands_a_mirror_list() {
+        let vars = TargetVars::default().var("os", "linux");
+        let mirrors = expand_all(
+            ["https://a.example.com/{os}", "https://b.example.com/{os}"],
+            &vars,
+        )
+        .unwrap();
+        assert_eq!(
+            mirrors,
+            ["https://a.example.com/linux", "https://b.example.com/linux"]
+        );
+    }
+}
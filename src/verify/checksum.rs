@@ -0,0 +1,362 @@
+//! Parsing of checksum files like `SHA256SUMS`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use futures_util::StreamExt;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::http::{Client, Response};
+use crate::verify::hash::{DynHashVerifierBuilder, HashAlgorithm};
+use crate::verify::{DynVerifier, VerifierBuilder};
+
+/// A parsed checksum file mapping file names to expected digests.
+#[derive(Debug, Clone)]
+pub struct ChecksumFile {
+    algorithm: HashAlgorithm,
+    entries: HashMap<String, Vec<Vec<u8>>>,
+    name: Option<String>,
+}
+
+impl ChecksumFile {
+    /// Parse a checksum file in the GNU coreutils format (`HEX  NAME` per
+    /// line, with an optional `*` binary marker before the name) or the BSD
+    /// tagged format (`SHA256 (NAME) = HEX`).
+    ///
+    /// Empty lines and lines starting with `#` are skipped. A name listed
+    /// more than once (e.g. a concatenation of checksum files spanning a
+    /// rotation) accepts any of its digests. A BSD line tagged with a
+    /// different algorithm than `algorithm` is rejected rather than
+    /// silently skipped.
+    pub fn parse(algorithm: HashAlgorithm, text: &str) -> Result<Self> {
+        let mut entries: HashMap<String, Vec<Vec<u8>>> = HashMap::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // BSD tagged style: `SHA256 (NAME) = HEX`.
+            if let Some((tag, rest)) = line.split_once(" (") {
+                if let Some((name, digest)) = rest.rsplit_once(") = ") {
+                    if !tag.eq_ignore_ascii_case(algorithm.name()) {
+                        return Err(Error::new(ErrorKind::Verify).with_desc_with(|| {
+                            format!("checksum line is tagged {tag}, expected {}", algorithm.name())
+                        }));
+                    }
+                    let digest = Self::decode_digest(algorithm, digest, line)?;
+                    entries.entry(name.to_string()).or_default().push(digest);
+                    continue;
+                }
+            }
+            let (digest, name) = line.split_once(char::is_whitespace).ok_or_else(|| {
+                Error::new(ErrorKind::Verify)
+                    .with_desc_with(|| format!("malformed checksum line: {line}"))
+            })?;
+            let digest = Self::decode_digest(algorithm, digest, line)?;
+            let name = name.trim_start().trim_start_matches('*');
+            entries.entry(name.to_string()).or_default().push(digest);
+        }
+        Ok(Self {
+            algorithm,
+            entries,
+            name: None,
+        })
+    }
+
+    /// Decode the hex `digest` of a checksum `line`, checking its length
+    /// against `algorithm`.
+    fn decode_digest(algorithm: HashAlgorithm, digest: &str, line: &str) -> Result<Vec<u8>> {
+        let digest = hex::decode(digest)
+            .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+            .with_desc_with(|| format!("invalid hex digest in checksum line: {line}"))?;
+        if digest.len() != algorithm.digest_len() {
+            return Err(Error::new(ErrorKind::Verify)
+                .with_desc_with(|| format!("invalid digest length in checksum line: {line}")));
+        }
+        Ok(digest)
+    }
+
+    /// Fetch and parse a checksum manifest like `SHA256SUMS` over HTTP.
+    ///
+    /// The manifest is fetched into memory — capped at 1 MiB, plenty for a
+    /// release's worth of digests — parsed with [`parse`](Self::parse),
+    /// and named after the last path segment of `url` for error messages.
+    pub async fn fetch(
+        client: &impl Client,
+        algorithm: HashAlgorithm,
+        url: &str,
+    ) -> Result<Self> {
+        const MAX_FETCH_SIZE: usize = 1024 * 1024;
+        let response = client.get(url).await.map_err(|e| e.with_url(url))?;
+        let mut stream = response.bytes_stream();
+        let mut buf = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| e.with_url(url))?;
+            if buf.len() + chunk.len() > MAX_FETCH_SIZE {
+                return Err(Error::new(ErrorKind::Verify).with_url(url).with_desc_with(
+                    || format!("checksum manifest exceeds {MAX_FETCH_SIZE} bytes"),
+                ));
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        let text = std::str::from_utf8(&buf)
+            .map_err(|e| Error::new(ErrorKind::Verify).with_source(e).with_url(url))
+            .with_desc("the checksum manifest is not valid UTF-8")?;
+        let file = Self::parse(algorithm, text).map_err(|e| e.with_url(url))?;
+        Ok(match url.rsplit('/').next() {
+            Some(name) if !name.is_empty() => file.with_name(name),
+            _ => file,
+        })
+    }
+
+    /// Set a display name for this checksum file (e.g. `"SHA256SUMS"`), used
+    /// in error messages.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// The hash algorithm of this checksum file.
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// The first expected digest for `name`, if listed.
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.get_all(name)?.first().map(Vec::as_slice)
+    }
+
+    /// All expected digests for `name`, if listed.
+    pub fn get_all(&self, name: &str) -> Option<&[Vec<u8>]> {
+        self.entries.get(name).map(Vec::as_slice)
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the file contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Build a verifier builder for the entry named `name`, accepting any of
+    /// its listed digests.
+    ///
+    /// Returns a `Verify` error when `name` is not listed.
+    pub fn verifier_for(&self, name: &str) -> Result<DynHashVerifierBuilder> {
+        let digests = self.get_all(name).ok_or_else(|| self.not_listed(name))?;
+        DynHashVerifierBuilder::new_any(self.algorithm, digests.to_vec())
+    }
+
+    /// Build a [`ManifestVerifierBuilder`] for the file name component of
+    /// `dest`, resolving the entry lazily at build time.
+    ///
+    /// Returns a `Verify` error when `dest` has no file name component.
+    pub fn verifier_for_dest(&self, dest: impl AsRef<Path>) -> Result<ManifestVerifierBuilder> {
+        let dest = dest.as_ref();
+        let name = dest
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| {
+                Error::new(ErrorKind::Verify).with_desc_with(|| {
+                    format!("{} has no file name to look up", dest.display())
+                })
+            })?;
+        Ok(ManifestVerifierBuilder::new(self.clone(), name))
+    }
+
+    /// The error for a `name` missing from this checksum file.
+    pub(crate) fn not_listed(&self, name: &str) -> Error {
+        Error::new(ErrorKind::Verify).with_desc_with(|| match &self.name {
+            Some(file) => format!("{name} is not listed in checksum file {file}"),
+            None => format!("{name} is not listed in the checksum file"),
+        })
+    }
+}
+
+/// A verifier builder resolving the expected digest from a [`ChecksumFile`]
+/// by file name.
+///
+/// Unlike [`ChecksumFile::verifier_for`] the lookup happens at
+/// [`build`](VerifierBuilder::build) time, so the builder can be constructed
+/// for a batch of downloads before knowing which names the manifest lists; a
+/// missing entry surfaces as a `Verify` error when the download starts.
+#[derive(Debug, Clone)]
+pub struct ManifestVerifierBuilder {
+    manifest: ChecksumFile,
+    name: String,
+}
+
+impl ManifestVerifierBuilder {
+    /// Create a builder verifying the entry named `name` of `manifest`.
+    pub fn new(manifest: ChecksumFile, name: impl Into<String>) -> Self {
+        Self {
+            manifest,
+            name: name.into(),
+        }
+    }
+}
+
+impl VerifierBuilder for ManifestVerifierBuilder {
+    type Verifier = Box<dyn DynVerifier>;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        self.manifest.verifier_for(&self.name)?.build()
+    }
+}
+
+/// Parse a single-digest sidecar file like `foo.tar.gz.sha256`.
+///
+/// The digest is the leading hex token of the first non-comment line, so
+/// both a bare digest and the coreutils `HEX  NAME` format work. Returns
+/// a `Verify` error for an empty file, a non-hex token or a digest of the
+/// wrong length.
+pub fn parse_sidecar(algorithm: HashAlgorithm, text: &str) -> Result<DynHashVerifierBuilder> {
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let digest = line.split_whitespace().next().unwrap_or(line);
+        let digest = hex::decode(digest)
+            .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+            .with_desc_with(|| format!("invalid hex digest in checksum sidecar: {line}"))?;
+        return DynHashVerifierBuilder::new(algorithm, digest);
+    }
+    Err(Error::new(ErrorKind::Verify).with_desc("the checksum sidecar is empty"))
+}
+
+#[cfg(all(test, feature = "sha2"))]
+mod tests {
+    use super::*;
+    use crate::verify::{Verifier, VerifierBuilder};
+
+    const SUMS: &str = "\
+# comment
+2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  hello.txt
+486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7 *world.txt
+";
+
+    #[test]
+    fn parse_entries() {
+        let file = ChecksumFile::parse(HashAlgorithm::Sha256, SUMS).unwrap();
+        assert_eq!(file.len(), 2);
+        assert!(file.get("hello.txt").is_some());
+        assert!(file.get("world.txt").is_some());
+        assert!(file.get("missing.txt").is_none());
+    }
+
+    #[test]
+    fn parse_bsd_lines() {
+        let sums = "\
+SHA256 (hello.txt) = 2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824
+486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7  world.txt
+";
+        let file = ChecksumFile::parse(HashAlgorithm::Sha256, sums).unwrap();
+        assert_eq!(file.len(), 2);
+        let mut verifier = file.verifier_for("hello.txt").unwrap().build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn bsd_algorithm_mismatch() {
+        let sums = "MD5 (hello.txt) = 5d41402abc4b2a76b9719d911017c592";
+        let err = ChecksumFile::parse(HashAlgorithm::Sha256, sums).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(err.description().unwrap().contains("tagged MD5"));
+    }
+
+    #[test]
+    fn sidecar_accepts_bare_and_coreutils_digests() {
+        let hello = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        for text in [
+            hello.to_string(),
+            format!("{hello}  hello.txt\n"),
+            format!("# comment\n{hello} *hello.txt\n"),
+        ] {
+            let mut verifier = parse_sidecar(HashAlgorithm::Sha256, &text)
+                .unwrap()
+                .build()
+                .unwrap();
+            verifier.update(b"hello");
+            verifier.verify().unwrap();
+        }
+    }
+
+    #[test]
+    fn sidecar_rejects_garbage() {
+        assert!(parse_sidecar(HashAlgorithm::Sha256, "").is_err());
+        assert!(parse_sidecar(HashAlgorithm::Sha256, "not hex").is_err());
+        assert!(parse_sidecar(HashAlgorithm::Sha256, "abcd").is_err());
+    }
+
+    #[test]
+    fn verifier_for_entry() {
+        let file = ChecksumFile::parse(HashAlgorithm::Sha256, SUMS).unwrap();
+        let mut verifier = file.verifier_for("hello.txt").unwrap().build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn verifier_for_missing_entry() {
+        let file = ChecksumFile::parse(HashAlgorithm::Sha256, SUMS).unwrap();
+        let err = file.verifier_for("missing.txt").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(err.description().unwrap().contains("missing.txt"));
+    }
+
+    #[test]
+    fn manifest_verifier_present() {
+        let file = ChecksumFile::parse(HashAlgorithm::Sha256, SUMS).unwrap();
+        let builder = file.verifier_for_dest("downloads/hello.txt").unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn manifest_verifier_absent() {
+        let file = ChecksumFile::parse(HashAlgorithm::Sha256, SUMS)
+            .unwrap()
+            .with_name("SHA256SUMS");
+        let builder = file.verifier_for_dest("downloads/missing.txt").unwrap();
+        let err = builder.build().err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(
+            err.description()
+                .unwrap()
+                .contains("missing.txt is not listed in checksum file SHA256SUMS")
+        );
+    }
+
+    #[test]
+    fn manifest_verifier_no_file_name() {
+        let file = ChecksumFile::parse(HashAlgorithm::Sha256, SUMS).unwrap();
+        assert!(file.verifier_for_dest("..").is_err());
+    }
+
+    #[test]
+    fn duplicate_name_accepts_any() {
+        // hello.txt listed with two digests: sha256 of "bye" and of "hello".
+        let sums = "\
+b49f425a7e1f9cff3856329ada223f2f9d368f15a00cf48df16ca95986137fe8  hello.txt
+2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  hello.txt
+";
+        let file = ChecksumFile::parse(HashAlgorithm::Sha256, sums).unwrap();
+        assert_eq!(file.len(), 1);
+        assert_eq!(file.get_all("hello.txt").unwrap().len(), 2);
+        let mut verifier = file.verifier_for("hello.txt").unwrap().build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn malformed_line() {
+        assert!(ChecksumFile::parse(HashAlgorithm::Sha256, "nonsense").is_err());
+        assert!(ChecksumFile::parse(HashAlgorithm::Sha256, "xyz  file").is_err());
+    }
+}
@@ -0,0 +1,343 @@
+//! Verification of compressed content against the uncompressed expectation.
+//!
+//! Upstreams often publish the digest of the *uncompressed* artifact but
+//! serve it compressed. The adapters here wrap any [`VerifierBuilder`]: the
+//! built verifier feeds the compressed bytes through a streaming decoder and
+//! pushes the decompressed output into the inner verifier, so the content
+//! can be checked while the compressed file is still downloading.
+//! [`Verifier::verify`] first checks that the compressed stream terminated
+//! cleanly, then delegates to the inner verifier.
+
+use std::io::Write;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::verify::{Verifier, VerifierBuilder};
+
+/// A [`Write`] adapter feeding written bytes into a [`Verifier`].
+pub struct VerifierWriter<V>(V);
+
+impl<V: Verifier> Write for VerifierWriter<V> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A write-side streaming decoder, unified over the compression backends.
+pub trait WriteDecoder: Write + Send {
+    /// The wrapped writer receiving the decompressed output.
+    type Inner;
+
+    /// Check that the compressed stream terminated cleanly and return the
+    /// wrapped writer.
+    fn finish(self) -> std::io::Result<Self::Inner>;
+}
+
+#[cfg(feature = "gzip")]
+impl<W: Write + Send> WriteDecoder for flate2::write::GzDecoder<W> {
+    type Inner = W;
+
+    fn finish(self) -> std::io::Result<W> {
+        flate2::write::GzDecoder::finish(self)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<W: Write + Send> WriteDecoder for ZstWriteDecoder<W> {
+    type Inner = W;
+
+    fn finish(mut self) -> std::io::Result<W> {
+        self.0.finish()?;
+        Ok(self.0.into_inner().0)
+    }
+}
+
+/// The write-side zstd decoder; [`zstd::stream::write::Decoder`] does not
+/// expose the frame-completeness check, so this wraps the lower level
+/// [`zstd::stream::zio::Writer`] directly.
+#[cfg(feature = "zstd")]
+pub struct ZstWriteDecoder<W: Write>(
+    zstd::stream::zio::Writer<W, zstd::stream::raw::Decoder<'static>>,
+);
+
+#[cfg(feature = "zstd")]
+impl<W: Write> Write for ZstWriteDecoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(feature = "xz")]
+impl<W: Write + Send> WriteDecoder for xz2::write::XzDecoder<W> {
+    type Inner = W;
+
+    fn finish(mut self) -> std::io::Result<W> {
+        xz2::write::XzDecoder::finish(&mut self)
+    }
+}
+
+/// The verifier built by the decompressing verifier builders.
+///
+/// Decoder errors cannot be reported from [`Verifier::update`], so the first
+/// error is remembered and surfaced by [`Verifier::verify`].
+pub struct DecompressVerifier<D> {
+    decoder: D,
+    error: Option<std::io::Error>,
+    format: &'static str,
+}
+
+impl<D, V> Verifier for DecompressVerifier<D>
+where
+    D: WriteDecoder<Inner = VerifierWriter<V>>,
+    V: Verifier,
+{
+    fn update(&mut self, data: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = self.decoder.write_all(data) {
+            self.error = Some(e);
+        }
+    }
+
+    fn verify(self) -> Result<()> {
+        let format = self.format;
+        if let Some(e) = self.error {
+            return Err(Error::new(ErrorKind::Verify)
+                .with_source(e)
+                .with_desc_with(|| format!("corrupt {format} stream")));
+        }
+        let inner = self
+            .decoder
+            .finish()
+            .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+            .with_desc_with(|| format!("truncated or corrupt {format} stream"))?;
+        inner.0.verify()
+    }
+}
+
+macro_rules! decompress_builder {
+    ($(#[$attr:meta])* $name:ident, $feature:literal, $format:literal, $decoder:ty, $new:expr) => {
+        $(#[$attr])*
+        #[cfg(feature = $feature)]
+        #[derive(Debug, Clone)]
+        pub struct $name<B> {
+            inner: B,
+        }
+
+        #[cfg(feature = $feature)]
+        impl<B: VerifierBuilder> $name<B> {
+            /// Create a builder decompressing the content into `inner`.
+            pub fn wrapping(inner: B) -> Self {
+                Self { inner }
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl<B: VerifierBuilder> VerifierBuilder for $name<B> {
+            type Verifier = DecompressVerifier<$decoder>;
+
+            fn build(&self) -> Result<Self::Verifier> {
+                let writer = VerifierWriter(self.inner.build()?);
+                #[allow(clippy::redundant_closure_call)]
+                Ok(DecompressVerifier {
+                    decoder: ($new)(writer)?,
+                    error: None,
+                    format: $format,
+                })
+            }
+        }
+    };
+}
+
+decompress_builder!(
+    /// A verifier builder verifying gzip compressed content against an inner
+    /// verifier of the uncompressed content.
+    GzVerifierBuilder,
+    "gzip",
+    "gzip",
+    flate2::write::GzDecoder<VerifierWriter<B::Verifier>>,
+    |writer| Result::Ok(flate2::write::GzDecoder::new(writer))
+);
+
+decompress_builder!(
+    /// A verifier builder verifying zstd compressed content against an inner
+    /// verifier of the uncompressed content.
+    ZstVerifierBuilder,
+    "zstd",
+    "zstd",
+    ZstWriteDecoder<VerifierWriter<B::Verifier>>,
+    |writer| {
+        let decoder = zstd::stream::raw::Decoder::new()
+            .map_err(Error::from)
+            .with_desc("failed to initialize zstd decoder")?;
+        Result::Ok(ZstWriteDecoder(zstd::stream::zio::Writer::new(
+            writer, decoder,
+        )))
+    }
+);
+
+decompress_builder!(
+    /// A verifier builder verifying xz compressed content against an inner
+    /// verifier of the uncompressed content.
+    XzVerifierBuilder,
+    "xz",
+    "xz",
+    xz2::write::XzDecoder<VerifierWriter<B::Verifier>>,
+    |writer| Result::Ok(xz2::write::XzDecoder::new(writer))
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify::SizeVerifierBuilder;
+
+    #[cfg(feature = "gzip")]
+    mod gz {
+        use super::*;
+
+        fn compress(data: &[u8]) -> Vec<u8> {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+
+        #[test]
+        fn verify_ok() {
+            let compressed = compress(b"hello world");
+            let builder = GzVerifierBuilder::wrapping(SizeVerifierBuilder::new(11));
+            let mut verifier = builder.build().unwrap();
+            verifier.update(&compressed);
+            assert!(verifier.verify().is_ok());
+        }
+
+        #[test]
+        fn inner_mismatch() {
+            let compressed = compress(b"hello world");
+            let builder = GzVerifierBuilder::wrapping(SizeVerifierBuilder::new(10));
+            let mut verifier = builder.build().unwrap();
+            verifier.update(&compressed);
+            let err = verifier.verify().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Verify);
+            assert!(err.description().unwrap().contains("size mismatch"));
+        }
+
+        #[test]
+        fn corrupt_stream() {
+            let mut compressed = compress(b"hello world");
+            let middle = compressed.len() / 2;
+            compressed[middle] ^= 0xff;
+            let builder = GzVerifierBuilder::wrapping(SizeVerifierBuilder::new(11));
+            let mut verifier = builder.build().unwrap();
+            verifier.update(&compressed);
+            let err = verifier.verify().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Verify);
+        }
+
+        #[test]
+        fn truncated_stream() {
+            let compressed = compress(b"hello world");
+            let builder = GzVerifierBuilder::wrapping(SizeVerifierBuilder::new(11));
+            let mut verifier = builder.build().unwrap();
+            verifier.update(&compressed[..compressed.len() - 5]);
+            let err = verifier.verify().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Verify);
+            assert!(err.description().unwrap().contains("gzip"));
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    mod zst {
+        use super::*;
+
+        fn compress(data: &[u8]) -> Vec<u8> {
+            zstd::stream::encode_all(data, 1).unwrap()
+        }
+
+        #[test]
+        fn verify_ok() {
+            let compressed = compress(b"hello world");
+            let builder = ZstVerifierBuilder::wrapping(SizeVerifierBuilder::new(11));
+            let mut verifier = builder.build().unwrap();
+            verifier.update(&compressed);
+            assert!(verifier.verify().is_ok());
+        }
+
+        #[test]
+        fn truncated_stream() {
+            let compressed = compress(b"hello world");
+            let builder = ZstVerifierBuilder::wrapping(SizeVerifierBuilder::new(11));
+            let mut verifier = builder.build().unwrap();
+            verifier.update(&compressed[..compressed.len() - 4]);
+            let err = verifier.verify().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Verify);
+        }
+
+        #[test]
+        fn corrupt_stream() {
+            let mut compressed = compress(b"hello world");
+            compressed[4] ^= 0xff;
+            let builder = ZstVerifierBuilder::wrapping(SizeVerifierBuilder::new(11));
+            let mut verifier = builder.build().unwrap();
+            verifier.update(&compressed);
+            let err = verifier.verify().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Verify);
+        }
+    }
+
+    #[cfg(feature = "xz")]
+    mod xz {
+        use super::*;
+
+        fn compress(data: &[u8]) -> Vec<u8> {
+            let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 1);
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+
+        #[test]
+        fn verify_ok() {
+            let compressed = compress(b"hello world");
+            let builder = XzVerifierBuilder::wrapping(SizeVerifierBuilder::new(11));
+            let mut verifier = builder.build().unwrap();
+            verifier.update(&compressed);
+            assert!(verifier.verify().is_ok());
+        }
+
+        #[test]
+        fn truncated_stream() {
+            let compressed = compress(b"hello world");
+            let builder = XzVerifierBuilder::wrapping(SizeVerifierBuilder::new(11));
+            let mut verifier = builder.build().unwrap();
+            verifier.update(&compressed[..compressed.len() - 4]);
+            let err = verifier.verify().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Verify);
+        }
+    }
+
+    #[cfg(all(feature = "gzip", feature = "sha2"))]
+    #[test]
+    fn composes_with_hash_verifier() {
+        use crate::verify::hash::Sha256VerifierBuilder;
+
+        // sha256 of "hello world"
+        let expected = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(b"hello world").unwrap();
+        let compressed = encoder.finish().unwrap();
+        let builder =
+            GzVerifierBuilder::wrapping(Sha256VerifierBuilder::from_hex(expected).unwrap());
+        let mut verifier = builder.build().unwrap();
+        verifier.update(&compressed);
+        assert!(verifier.verify().is_ok());
+    }
+}
@@ -0,0 +1,546 @@
+//! Digest based verification.
+//!
+//! [`HashVerifierBuilder`] is generic over a [`Digest`] implementation and
+//! is compile-time dispatched; convenient aliases are provided for the
+//! algorithms enabled by feature flags. When the algorithm is only known at
+//! runtime (e.g. parsed from a manifest), use [`DynHashVerifierBuilder`].
+
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use digest::Digest;
+
+use crate::error::{Error, ErrorKind, Result, VerifyDetails, WithDesc};
+use crate::verify::{DynVerifier, Verifier, VerifierBuilder};
+
+/// A verifier builder checking the content against an expected digest.
+///
+/// More than one candidate digest can be accepted (e.g. during a checksum
+/// rotation, when both the old and the new digest are temporarily valid);
+/// the content is hashed once and verification succeeds when the computed
+/// digest matches any candidate.
+#[derive(Debug, Clone)]
+pub struct HashVerifierBuilder<D> {
+    candidates: Vec<Vec<u8>>,
+    _digest: PhantomData<D>,
+}
+
+impl<D: Digest> HashVerifierBuilder<D> {
+    /// Create a builder from the raw expected digest.
+    pub fn new(expected: Vec<u8>) -> Self {
+        Self::new_any(vec![expected])
+    }
+
+    /// Create a builder accepting any of the raw expected digests.
+    pub fn new_any(expected: Vec<Vec<u8>>) -> Self {
+        Self {
+            candidates: expected,
+            _digest: PhantomData,
+        }
+    }
+
+    /// Create a builder from a hex encoded expected digest.
+    pub fn from_hex(expected: &str) -> Result<Self> {
+        let expected = hex::decode(expected.trim())
+            .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+            .with_desc("invalid hex digest")?;
+        Ok(Self::new(expected))
+    }
+}
+
+impl<D: Digest + Send> VerifierBuilder for HashVerifierBuilder<D> {
+    type Verifier = HashVerifier<D>;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        Ok(HashVerifier {
+            hasher: D::new(),
+            candidates: self.candidates.clone(),
+        })
+    }
+}
+
+/// The verifier built by [`HashVerifierBuilder`].
+pub struct HashVerifier<D> {
+    hasher: D,
+    candidates: Vec<Vec<u8>>,
+}
+
+impl<D: Digest + Send> Verifier for HashVerifier<D> {
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    fn verify(self) -> Result<()> {
+        let actual = self.hasher.finalize();
+        check_candidates(None, &self.candidates, actual.as_slice())
+    }
+}
+
+/// Check `actual` against the candidate digests, with a structured
+/// [`VerifyDetails::Hash`] error listing the computed digest and every
+/// candidate on failure.
+pub(crate) fn check_candidates(
+    algorithm: Option<&str>,
+    candidates: &[Vec<u8>],
+    actual: &[u8],
+) -> Result<()> {
+    if candidates.iter().any(|c| c == actual) {
+        return Ok(());
+    }
+    Err(
+        Error::new(ErrorKind::Verify).with_verify_details(VerifyDetails::Hash {
+            algorithm: algorithm.map(String::from),
+            expected: candidates.to_vec(),
+            actual: actual.to_vec(),
+        }),
+    )
+}
+
+/// A verifier builder recording the computed digest instead of checking it.
+///
+/// Verification always succeeds; the digest is published through the
+/// [`DigestHandle`] returned by [`new`](Self::new). Combine it with a
+/// checking verifier via
+/// [`BothVerifierBuilder`](crate::verify::BothVerifierBuilder) to capture
+/// what was actually downloaded, e.g. for a lockfile.
+#[derive(Debug, Clone)]
+pub struct DigestRecorder<D> {
+    handle: DigestHandle,
+    _digest: PhantomData<D>,
+}
+
+impl<D: Digest> DigestRecorder<D> {
+    /// Create a recorder and the handle its digest is published through.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> (Self, DigestHandle) {
+        let handle = DigestHandle::default();
+        (
+            Self {
+                handle: handle.clone(),
+                _digest: PhantomData,
+            },
+            handle,
+        )
+    }
+}
+
+impl<D: Digest + Send> VerifierBuilder for DigestRecorder<D> {
+    type Verifier = DigestRecorderVerifier<D>;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        Ok(DigestRecorderVerifier {
+            hasher: D::new(),
+            handle: self.handle.clone(),
+        })
+    }
+}
+
+/// The verifier built by [`DigestRecorder`].
+pub struct DigestRecorderVerifier<D> {
+    hasher: D,
+    handle: DigestHandle,
+}
+
+impl<D: Digest + Send> Verifier for DigestRecorderVerifier<D> {
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    fn verify(self) -> Result<()> {
+        let digest = self.hasher.finalize();
+        *self.handle.digest.lock().expect("not poisoned") = Some(digest.to_vec());
+        Ok(())
+    }
+}
+
+/// The handle a [`DigestRecorder`] publishes its digest through.
+///
+/// Empty until the recording verifier has run to completion.
+#[derive(Debug, Clone, Default)]
+pub struct DigestHandle {
+    digest: std::sync::Arc<std::sync::Mutex<Option<Vec<u8>>>>,
+}
+
+impl DigestHandle {
+    /// The recorded raw digest, once available.
+    pub fn digest(&self) -> Option<Vec<u8>> {
+        self.digest.lock().expect("not poisoned").clone()
+    }
+
+    /// The recorded digest in hex, once available.
+    pub fn hex(&self) -> Option<String> {
+        self.digest().map(hex::encode)
+    }
+}
+
+/// [`HashVerifierBuilder`] for SHA-256.
+#[cfg(feature = "sha2")]
+pub type Sha256VerifierBuilder = HashVerifierBuilder<sha2::Sha256>;
+/// [`HashVerifierBuilder`] for SHA-512.
+#[cfg(feature = "sha2")]
+pub type Sha512VerifierBuilder = HashVerifierBuilder<sha2::Sha512>;
+/// [`HashVerifierBuilder`] for SHA-1.
+#[cfg(feature = "sha1")]
+pub type Sha1VerifierBuilder = HashVerifierBuilder<sha1::Sha1>;
+/// [`HashVerifierBuilder`] for MD5.
+#[cfg(feature = "md5")]
+pub type Md5VerifierBuilder = HashVerifierBuilder<md5::Md5>;
+
+/// A hash algorithm known to this crate, selected at runtime.
+///
+/// The set of available variants depends on the enabled features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    /// SHA-256
+    #[cfg(feature = "sha2")]
+    Sha256,
+    /// SHA-512
+    #[cfg(feature = "sha2")]
+    Sha512,
+    /// SHA-1
+    #[cfg(feature = "sha1")]
+    Sha1,
+    /// MD5
+    #[cfg(feature = "md5")]
+    Md5,
+}
+
+impl HashAlgorithm {
+    /// The canonical lower-case name of the algorithm.
+    pub fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "sha2")]
+            HashAlgorithm::Sha256 => "sha256",
+            #[cfg(feature = "sha2")]
+            HashAlgorithm::Sha512 => "sha512",
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::Sha1 => "sha1",
+            #[cfg(feature = "md5")]
+            HashAlgorithm::Md5 => "md5",
+        }
+    }
+
+    /// The digest length in bytes.
+    pub fn digest_len(self) -> usize {
+        match self {
+            #[cfg(feature = "sha2")]
+            HashAlgorithm::Sha256 => 32,
+            #[cfg(feature = "sha2")]
+            HashAlgorithm::Sha512 => 64,
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::Sha1 => 20,
+            #[cfg(feature = "md5")]
+            HashAlgorithm::Md5 => 16,
+        }
+    }
+
+    pub(crate) fn build_verifier(self, candidates: Vec<Vec<u8>>) -> Box<dyn DynVerifier> {
+        Box::new(crate::verify::DynAdapter(DynHashVerifier {
+            algorithm: self,
+            hasher: DynHasher::new(self),
+            candidates,
+        }))
+    }
+}
+
+/// A runtime dispatched hasher over the enabled algorithms.
+pub(crate) enum DynHasher {
+    #[cfg(feature = "sha2")]
+    Sha256(sha2::Sha256),
+    #[cfg(feature = "sha2")]
+    Sha512(sha2::Sha512),
+    #[cfg(feature = "sha1")]
+    Sha1(sha1::Sha1),
+    #[cfg(feature = "md5")]
+    Md5(md5::Md5),
+}
+
+impl DynHasher {
+    pub(crate) fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            #[cfg(feature = "sha2")]
+            HashAlgorithm::Sha256 => DynHasher::Sha256(sha2::Sha256::new()),
+            #[cfg(feature = "sha2")]
+            HashAlgorithm::Sha512 => DynHasher::Sha512(sha2::Sha512::new()),
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::Sha1 => DynHasher::Sha1(sha1::Sha1::new()),
+            #[cfg(feature = "md5")]
+            HashAlgorithm::Md5 => DynHasher::Md5(md5::Md5::new()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            #[cfg(feature = "sha2")]
+            DynHasher::Sha256(h) => h.update(data),
+            #[cfg(feature = "sha2")]
+            DynHasher::Sha512(h) => h.update(data),
+            #[cfg(feature = "sha1")]
+            DynHasher::Sha1(h) => h.update(data),
+            #[cfg(feature = "md5")]
+            DynHasher::Md5(h) => h.update(data),
+        }
+    }
+
+    pub(crate) fn finalize(self) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "sha2")]
+            DynHasher::Sha256(h) => h.finalize().to_vec(),
+            #[cfg(feature = "sha2")]
+            DynHasher::Sha512(h) => h.finalize().to_vec(),
+            #[cfg(feature = "sha1")]
+            DynHasher::Sha1(h) => h.finalize().to_vec(),
+            #[cfg(feature = "md5")]
+            DynHasher::Md5(h) => h.finalize().to_vec(),
+        }
+    }
+}
+
+/// The verifier built by [`DynHashVerifierBuilder`].
+struct DynHashVerifier {
+    algorithm: HashAlgorithm,
+    hasher: DynHasher,
+    candidates: Vec<Vec<u8>>,
+}
+
+impl Verifier for DynHashVerifier {
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    fn verify(self) -> Result<()> {
+        let actual = self.hasher.finalize();
+        check_candidates(Some(self.algorithm.name()), &self.candidates, &actual)
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            #[cfg(feature = "sha2")]
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            #[cfg(feature = "sha2")]
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            #[cfg(feature = "sha1")]
+            "sha1" => Ok(HashAlgorithm::Sha1),
+            #[cfg(feature = "md5")]
+            "md5" => Ok(HashAlgorithm::Md5),
+            _ => Err(Error::new(ErrorKind::Verify)
+                .with_desc_with(|| format!("unknown hash algorithm: {s}"))),
+        }
+    }
+}
+
+/// A runtime dispatched version of [`HashVerifierBuilder`].
+#[derive(Debug, Clone)]
+pub struct DynHashVerifierBuilder {
+    algorithm: HashAlgorithm,
+    candidates: Vec<Vec<u8>>,
+}
+
+impl DynHashVerifierBuilder {
+    /// Create a builder from an algorithm and the raw expected digest.
+    pub fn new(algorithm: HashAlgorithm, expected: Vec<u8>) -> Result<Self> {
+        Self::new_any(algorithm, vec![expected])
+    }
+
+    /// Create a builder accepting any of the raw expected digests.
+    pub fn new_any(algorithm: HashAlgorithm, expected: Vec<Vec<u8>>) -> Result<Self> {
+        for candidate in &expected {
+            if candidate.len() != algorithm.digest_len() {
+                return Err(Error::new(ErrorKind::Verify).with_desc_with(|| {
+                    format!(
+                        "invalid digest length for {}: expected {} bytes, got {}",
+                        algorithm.name(),
+                        algorithm.digest_len(),
+                        candidate.len()
+                    )
+                }));
+            }
+        }
+        Ok(Self {
+            algorithm,
+            candidates: expected,
+        })
+    }
+
+    /// Parse a builder from a `"algo:hex"` string, e.g. `"sha256:9f86…"`.
+    pub fn parse(s: &str) -> Result<Self> {
+        let (algo, hex_digest) = s.split_once(':').ok_or_else(|| {
+            Error::new(ErrorKind::Verify)
+                .with_desc_with(|| format!("expected \"algo:hex\" format, got: {s}"))
+        })?;
+        let algorithm = algo.parse()?;
+        let expected = hex::decode(hex_digest.trim())
+            .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+            .with_desc("invalid hex digest")?;
+        Self::new(algorithm, expected)
+    }
+
+    /// The algorithm of this builder.
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+}
+
+impl VerifierBuilder for DynHashVerifierBuilder {
+    type Verifier = Box<dyn DynVerifier>;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        Ok(self.algorithm.build_verifier(self.candidates.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sha256 of "hello"
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn digest_recorder_reports_the_digest() {
+        let (recorder, handle) = DigestRecorder::<sha2::Sha256>::new();
+        let mut verifier = recorder.build().unwrap();
+        assert_eq!(handle.hex(), None);
+        verifier.update(b"hel");
+        verifier.update(b"lo");
+        verifier.verify().unwrap();
+        assert_eq!(handle.hex().as_deref(), Some(HELLO_SHA256));
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn sha256_match() {
+        let builder = Sha256VerifierBuilder::from_hex(HELLO_SHA256).unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"hel");
+        verifier.update(b"lo");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn sha256_update_bytes_matches_update() {
+        let builder = Sha256VerifierBuilder::from_hex(HELLO_SHA256).unwrap();
+        let mut by_slice = builder.build().unwrap();
+        by_slice.update(b"hello");
+        let mut by_bytes = builder.build().unwrap();
+        by_bytes.update_bytes(bytes::Bytes::from_static(b"hello"));
+        assert!(by_slice.verify().is_ok());
+        assert!(by_bytes.verify().is_ok());
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn sha256_mismatch() {
+        let builder = Sha256VerifierBuilder::from_hex(HELLO_SHA256).unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"bye");
+        let err = verifier.verify().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(err.description().unwrap().contains(HELLO_SHA256));
+        let Some(VerifyDetails::Hash {
+            expected, actual, ..
+        }) = err.verify_details()
+        else {
+            panic!("expected hash details");
+        };
+        assert_eq!(expected, &[hex::decode(HELLO_SHA256).unwrap()]);
+        assert_eq!(hex::encode(actual), sha256_hex(b"bye"));
+    }
+
+    /// The sha256 of `data` as a hex string.
+    #[cfg(feature = "sha2")]
+    fn sha256_hex(data: &[u8]) -> String {
+        use sha2::Digest;
+
+        hex::encode(sha2::Sha256::digest(data))
+    }
+
+    #[cfg(feature = "sha2")]
+    mod any_of {
+        use super::*;
+
+        // sha256 of "bye"
+        const BYE_SHA256: &str =
+            "b49f425a7e1f9cff3856329ada223f2f9d368f15a00cf48df16ca95986137fe8";
+
+        fn builder(candidates: &[&str]) -> Sha256VerifierBuilder {
+            let candidates = candidates.iter().map(|c| hex::decode(c).unwrap()).collect();
+            Sha256VerifierBuilder::new_any(candidates)
+        }
+
+        #[test]
+        fn first_matches() {
+            let mut verifier = builder(&[HELLO_SHA256, BYE_SHA256]).build().unwrap();
+            verifier.update(b"hello");
+            assert!(verifier.verify().is_ok());
+        }
+
+        #[test]
+        fn last_matches() {
+            let mut verifier = builder(&[BYE_SHA256, HELLO_SHA256]).build().unwrap();
+            verifier.update(b"hello");
+            assert!(verifier.verify().is_ok());
+        }
+
+        #[test]
+        fn none_matches() {
+            let mut verifier = builder(&[HELLO_SHA256, BYE_SHA256]).build().unwrap();
+            verifier.update(b"other");
+            let err = verifier.verify().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Verify);
+            let desc = err.description().unwrap();
+            assert!(desc.contains("one of"));
+            assert!(desc.contains(HELLO_SHA256));
+            assert!(desc.contains(BYE_SHA256));
+        }
+
+        #[test]
+        fn dyn_any_of() {
+            let candidates = vec![
+                hex::decode(BYE_SHA256).unwrap(),
+                hex::decode(HELLO_SHA256).unwrap(),
+            ];
+            let builder =
+                DynHashVerifierBuilder::new_any(HashAlgorithm::Sha256, candidates).unwrap();
+            let mut verifier = builder.build().unwrap();
+            verifier.update(b"hello");
+            assert!(verifier.verify().is_ok());
+        }
+    }
+
+    #[test]
+    fn invalid_hex() {
+        #[cfg(feature = "sha2")]
+        assert!(Sha256VerifierBuilder::from_hex("xyz").is_err());
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn dyn_parse_and_verify() {
+        let builder =
+            DynHashVerifierBuilder::parse(&format!("sha256:{HELLO_SHA256}")).unwrap();
+        assert_eq!(builder.algorithm(), HashAlgorithm::Sha256);
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn dyn_parse_unknown_algorithm() {
+        let err = DynHashVerifierBuilder::parse("sm3:abcd").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn dyn_wrong_digest_len() {
+        let err = DynHashVerifierBuilder::new(HashAlgorithm::Sha256, vec![0; 16]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+    }
+}
@@ -0,0 +1,345 @@
+//! Minisign signature verification.
+//!
+//! Requires the `minisign` feature. Keys and signatures are the types of the
+//! [`minisign_verify`] crate, re-exported here for convenience.
+
+use std::path::Path;
+
+use futures_util::StreamExt;
+pub use minisign_verify::{PublicKey, Signature};
+
+use crate::error::{Error, ErrorKind, Result, VerifyDetails, WithDesc};
+use crate::http::{Client, Response};
+use crate::verify::{Verifier, VerifierBuilder};
+
+/// Maximum size accepted by [`MinisignVerifierBuilder::from_urls`] for a
+/// fetched key or signature file; real ones are a few hundred bytes.
+const MAX_FETCH_SIZE: usize = 64 * 1024;
+
+/// A verifier builder checking a minisign signature over the content.
+#[derive(Clone)]
+pub struct MinisignVerifierBuilder {
+    public_key: PublicKey,
+    signature: Signature,
+}
+
+impl std::fmt::Debug for MinisignVerifierBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The key and signature types do not implement Debug.
+        f.debug_struct("MinisignVerifierBuilder").finish_non_exhaustive()
+    }
+}
+
+impl MinisignVerifierBuilder {
+    /// Create a builder from a public key and a signature.
+    pub fn new(public_key: &PublicKey, signature: &Signature) -> Self {
+        Self {
+            public_key: public_key.clone(),
+            signature: signature.clone(),
+        }
+    }
+
+    /// Create a builder from the textual contents of a public key and a
+    /// signature file.
+    ///
+    /// The public key is accepted both as the bare base64 line and as the
+    /// full `.pub` file format with the untrusted comment line; the
+    /// signature must be the full `.minisig` file format.
+    pub fn from_strs(pubkey_text: &str, sig_text: &str) -> Result<Self> {
+        let pubkey_text = pubkey_text.trim();
+        let public_key = if pubkey_text.lines().count() > 1 {
+            PublicKey::decode(pubkey_text)
+        } else {
+            PublicKey::from_base64(pubkey_text)
+        }
+        .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+        .with_desc("failed to parse the minisign public key")?;
+        let signature = Signature::decode(sig_text)
+            .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+            .with_desc("failed to parse the minisign signature")?;
+        Ok(Self::new(&public_key, &signature))
+    }
+
+    /// Create a builder by reading a public key file and a signature file.
+    pub fn from_files(
+        pubkey_path: impl AsRef<Path>,
+        sig_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let pubkey_path = pubkey_path.as_ref();
+        let sig_path = sig_path.as_ref();
+        let pubkey_text = std::fs::read_to_string(pubkey_path)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to read {}", pubkey_path.display()))?;
+        let sig_text = std::fs::read_to_string(sig_path)
+            .map_err(Error::from)
+            .with_desc_with(|| format!("failed to read {}", sig_path.display()))?;
+        Self::from_strs(&pubkey_text, &sig_text)
+    }
+
+    /// Create a builder by fetching a public key file and a signature file
+    /// over HTTP.
+    ///
+    /// Both downloads are capped at 64 KiB; real key and signature files are
+    /// a few hundred bytes, so anything larger is rejected.
+    pub async fn from_urls(
+        client: &impl Client,
+        pubkey_url: &str,
+        sig_url: &str,
+    ) -> Result<Self> {
+        let pubkey_text = fetch_text(client, pubkey_url, "minisign public key").await?;
+        let sig_text = fetch_text(client, sig_url, "minisign signature").await?;
+        Self::from_strs(&pubkey_text, &sig_text)
+    }
+}
+
+/// A verifier builder accepting any of several minisign public keys.
+///
+/// During a key rotation artifacts may be signed by either the old or the
+/// new key. Minisign signatures embed the signer's key ID, so the builder
+/// selects the key matching the signature up front instead of blindly trying
+/// each key against the content.
+#[derive(Debug, Clone)]
+pub struct MinisignAnyKeyVerifierBuilder {
+    inner: MinisignVerifierBuilder,
+}
+
+impl MinisignAnyKeyVerifierBuilder {
+    /// Create a builder selecting the key of `keys` whose key ID matches the
+    /// signature.
+    ///
+    /// Returns a `Verify` error when no key matches.
+    pub fn new(keys: &[PublicKey], signature: &Signature) -> Result<Self> {
+        // The underlying crate does not expose key IDs directly, but
+        // `verify` compares them before touching any content and reports a
+        // mismatch as `UnexpectedKeyId`, so probing with empty content
+        // selects by key ID without real signature checks.
+        for key in keys {
+            match key.verify(&[], signature, true) {
+                Err(minisign_verify::Error::UnexpectedKeyId) => continue,
+                _ => {
+                    return Ok(Self {
+                        inner: MinisignVerifierBuilder::new(key, signature),
+                    });
+                }
+            }
+        }
+        Err(Error::new(ErrorKind::Verify)
+            .with_desc("signature key ID matches none of the provided keys"))
+    }
+}
+
+impl VerifierBuilder for MinisignAnyKeyVerifierBuilder {
+    type Verifier = MinisignVerifier;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        self.inner.build()
+    }
+}
+
+/// Fetch `url` into a string, rejecting bodies over [`MAX_FETCH_SIZE`].
+async fn fetch_text(client: &impl Client, url: &str, what: &str) -> Result<String> {
+    let response = client.get(url).await?;
+    let mut stream = response.bytes_stream();
+    let mut buf = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        if buf.len() + chunk.len() > MAX_FETCH_SIZE {
+            return Err(Error::new(ErrorKind::Verify).with_desc_with(|| {
+                format!("{what} at {url} exceeds {MAX_FETCH_SIZE} bytes")
+            }));
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    String::from_utf8(buf)
+        .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+        .with_desc_with(|| format!("{what} at {url} is not valid UTF-8"))
+}
+
+impl VerifierBuilder for MinisignVerifierBuilder {
+    type Verifier = MinisignVerifier;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        Ok(MinisignVerifier {
+            public_key: self.public_key.clone(),
+            signature: self.signature.clone(),
+            content: Vec::new(),
+        })
+    }
+}
+
+/// The verifier built by [`MinisignVerifierBuilder`].
+///
+/// Legacy (non pre-hashed) signatures require the whole content at once, so
+/// the verifier buffers the content in memory; keep this in mind for very
+/// large files.
+pub struct MinisignVerifier {
+    public_key: PublicKey,
+    signature: Signature,
+    content: Vec<u8>,
+}
+
+impl Verifier for MinisignVerifier {
+    fn update(&mut self, data: &[u8]) {
+        self.content.extend_from_slice(data);
+    }
+
+    fn verify(self) -> Result<()> {
+        self.public_key
+            .verify(&self.content, &self.signature, true)
+            .map_err(|e| {
+                Error::new(ErrorKind::Verify)
+                    .with_source(e)
+                    .with_verify_details(VerifyDetails::Signature {
+                        key_id: None,
+                        reason: "minisign signature verification failed".into(),
+                    })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generated with the minisign tool, the signed content is "test".
+    pub(crate) const PUBLIC_KEY: &str =
+        "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    pub(crate) const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==
+";
+
+    fn builder() -> MinisignVerifierBuilder {
+        let public_key = PublicKey::from_base64(PUBLIC_KEY).unwrap();
+        let signature = Signature::decode(SIGNATURE).unwrap();
+        MinisignVerifierBuilder::new(&public_key, &signature)
+    }
+
+    #[test]
+    fn verify_ok() {
+        let mut verifier = builder().build().unwrap();
+        verifier.update(b"te");
+        verifier.update(b"st");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn verify_bad_content() {
+        let mut verifier = builder().build().unwrap();
+        verifier.update(b"tampered");
+        let err = verifier.verify().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+    }
+
+    #[test]
+    fn from_strs_bare_key() {
+        let builder = MinisignVerifierBuilder::from_strs(PUBLIC_KEY, SIGNATURE).unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"test");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn from_strs_full_key_file() {
+        let full = format!("untrusted comment: minisign public key\n{PUBLIC_KEY}\n");
+        let builder = MinisignVerifierBuilder::from_strs(&full, SIGNATURE).unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"test");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn from_strs_errors_name_the_input() {
+        let err = MinisignVerifierBuilder::from_strs("garbage", SIGNATURE).unwrap_err();
+        assert!(err.description().unwrap().contains("public key"));
+        let err = MinisignVerifierBuilder::from_strs(PUBLIC_KEY, "garbage").unwrap_err();
+        assert!(err.description().unwrap().contains("signature"));
+    }
+
+    #[test]
+    fn from_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let pubkey_path = dir.path().join("minisign.pub");
+        let sig_path = dir.path().join("test.minisig");
+        std::fs::write(&pubkey_path, PUBLIC_KEY).unwrap();
+        std::fs::write(&sig_path, SIGNATURE).unwrap();
+        let builder = MinisignVerifierBuilder::from_files(&pubkey_path, &sig_path).unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"test");
+        assert!(verifier.verify().is_ok());
+    }
+
+    mod any_key {
+        use super::*;
+
+        // Two rotated signing keys plus one the verifier does not know
+        // about; each signed the content "test".
+        const KEY_A_PUB: &str = "RWRBQUFBQUFBQYqI4910CfGV/VLbLTy6XXLKZwm/HZQSG/N0iAG0D29c";
+        const KEY_A_SIG: &str = "untrusted comment: signature from minisign secret key
+RURBQUFBQUFBQWLzS+bFTfbIDD6txLKaxrgn2mAIaVDOQq1BoVE70sX5JCM35s0vY2o3eyth/YQkukF0k1JJ8sHrYOjC5YyqawQ=
+trusted comment: timestamp:0\tfile:test
+LRHPb1zGR6tZjawulQc/NHYHwD1mAGcQ2/MHdmPbmRt7kYy5YNinqjodZl15s8gt3H1Iupu3GOHLTnXkmlowDg==
+";
+        const KEY_B_PUB: &str = "RWRCQkJCQkJCQoE5dw6ofRdfVqNUZsNMfszLjYqRtO43ol32D1uPybOU";
+        const KEY_B_SIG: &str = "untrusted comment: signature from minisign secret key
+RURCQkJCQkJCQt6ETl9FFgKBZBfj2rR+G9ti7qim0ptS4UH2WArBDqfuj2FOsvtGmnDv+LIUySnA2JsQ2CXA4wmw7YyNLG6GNAE=
+trusted comment: timestamp:0\tfile:test
+J1T5t7BgLRnoW4mhTl3OEXOxjcVViLtC8osGz0WbSphd4nnz7KskhHr5m0SOEYxodVHmX5g4iF2M3w4XTb1sAQ==
+";
+        const KEY_C_SIG: &str = "untrusted comment: signature from minisign secret key
+RURDQ0NDQ0NDQ8LAenzL0kGQmIBfeLFJiMptEeR1hueKw4m7Jhpnf2CqJ9gfNUGpIRooB+MTDx974BGXvb6aDjYvoaf9YMk3ywE=
+trusted comment: timestamp:0\tfile:test
+LViejVFTrMPm6IxFISfgcW0R8rZ72zYVTrAf6qxM4KmGj70WbjmKW31iMlCJ1yImc2doXQD50puJWf7TKSbUBw==
+";
+
+        fn keys() -> Vec<PublicKey> {
+            vec![
+                PublicKey::from_base64(KEY_A_PUB).unwrap(),
+                PublicKey::from_base64(KEY_B_PUB).unwrap(),
+            ]
+        }
+
+        #[test]
+        fn selects_matching_key() {
+            for sig in [KEY_A_SIG, KEY_B_SIG] {
+                let signature = Signature::decode(sig).unwrap();
+                let builder = MinisignAnyKeyVerifierBuilder::new(&keys(), &signature).unwrap();
+                let mut verifier = builder.build().unwrap();
+                verifier.update(b"test");
+                assert!(verifier.verify().is_ok());
+            }
+        }
+
+        #[test]
+        fn bad_content_still_fails() {
+            let signature = Signature::decode(KEY_A_SIG).unwrap();
+            let builder = MinisignAnyKeyVerifierBuilder::new(&keys(), &signature).unwrap();
+            let mut verifier = builder.build().unwrap();
+            verifier.update(b"tampered");
+            assert_eq!(verifier.verify().err().unwrap().kind(), ErrorKind::Verify);
+        }
+
+        #[test]
+        fn unknown_key() {
+            let signature = Signature::decode(KEY_C_SIG).unwrap();
+            let err = MinisignAnyKeyVerifierBuilder::new(&keys(), &signature)
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::Verify);
+            assert!(err.description().unwrap().contains("none of the provided keys"));
+        }
+    }
+
+    #[test]
+    fn from_files_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = MinisignVerifierBuilder::from_files(
+            dir.path().join("missing.pub"),
+            dir.path().join("missing.minisig"),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert!(err.description().unwrap().contains("missing.pub"));
+    }
+}
@@ -0,0 +1,320 @@
+//! Content verification.
+//!
+//! Verification is split into two traits: a [`VerifierBuilder`] describes
+//! *what* to check (an expected size, digest or signature) and can be reused,
+//! while the [`Verifier`] it builds holds the running state for a single
+//! pass over the content. The download machinery feeds every received chunk
+//! into the verifier and calls [`Verifier::verify`] once the stream ends, so
+//! verification happens without reading the file back from disk.
+
+use std::io::Read;
+
+use bytes::Bytes;
+
+use crate::error::{Error, ErrorKind, Result, VerifyDetails};
+
+pub mod hash;
+pub mod registry;
+pub mod sri;
+
+pub mod checksum;
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "xz"))]
+pub mod compress;
+#[cfg(feature = "minisign")]
+pub mod minisign;
+#[cfg(feature = "signify")]
+pub mod signify;
+
+/// A verifier holding the running state of a single verification pass.
+pub trait Verifier: Send {
+    /// Feed a chunk of content into the verifier.
+    fn update(&mut self, data: &[u8]);
+
+    /// Feed an owned chunk of content into the verifier.
+    ///
+    /// By default this delegates to [`update`](Self::update); adapters that
+    /// want to retain or forward chunks without copying (fan-out to several
+    /// inner verifiers, hashing on a dedicated thread) can override it. The
+    /// download machinery calls this method, so overrides see every chunk.
+    fn update_bytes(&mut self, data: Bytes) {
+        self.update(&data);
+    }
+
+    /// Consume the verifier and check the accumulated content.
+    fn verify(self) -> Result<()>;
+
+    /// Feed the entire `reader` into the verifier, returning the number of
+    /// bytes read.
+    fn update_reader<R: Read>(&mut self, mut reader: R) -> std::io::Result<u64>
+    where
+        Self: Sized,
+    {
+        let mut buf = [0u8; 64 * 1024];
+        let mut total = 0u64;
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                return Ok(total);
+            }
+            self.update(&buf[..n]);
+            total += n as u64;
+        }
+    }
+}
+
+/// A reusable description of a verification, able to build fresh
+/// [`Verifier`]s.
+pub trait VerifierBuilder {
+    /// The verifier built by this builder.
+    type Verifier: Verifier;
+
+    /// Build a fresh verifier.
+    fn build(&self) -> Result<Self::Verifier>;
+}
+
+/// An object safe version of [`Verifier`].
+///
+/// [`Verifier::verify`] takes `self` by value, which is not object safe;
+/// this trait takes `Box<Self>` instead, so `Box<dyn DynVerifier>` can be
+/// used where the concrete verifier type is only known at runtime. The boxed
+/// trait object implements [`Verifier`] again, and every [`Verifier`] can be
+/// boxed through [`DynVerifierBuilder`].
+pub trait DynVerifier: Send {
+    /// See [`Verifier::update`].
+    fn update(&mut self, data: &[u8]);
+
+    /// See [`Verifier::update_bytes`].
+    fn update_bytes(&mut self, data: Bytes) {
+        self.update(&data);
+    }
+
+    /// See [`Verifier::verify`].
+    fn verify(self: Box<Self>) -> Result<()>;
+}
+
+/// Adapts any [`Verifier`] to [`DynVerifier`].
+pub(crate) struct DynAdapter<V>(pub(crate) V);
+
+impl<V: Verifier> DynVerifier for DynAdapter<V> {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn update_bytes(&mut self, data: Bytes) {
+        self.0.update_bytes(data);
+    }
+
+    fn verify(self: Box<Self>) -> Result<()> {
+        self.0.verify()
+    }
+}
+
+impl Verifier for Box<dyn DynVerifier> {
+    fn update(&mut self, data: &[u8]) {
+        (**self).update(data);
+    }
+
+    fn update_bytes(&mut self, data: Bytes) {
+        (**self).update_bytes(data);
+    }
+
+    fn verify(self) -> Result<()> {
+        DynVerifier::verify(self)
+    }
+}
+
+/// An object safe version of [`VerifierBuilder`] building boxed verifiers.
+///
+/// Implemented for every [`VerifierBuilder`] whose verifier is `'static`.
+pub trait DynVerifierBuilder {
+    /// Build a fresh boxed verifier.
+    fn build_dyn(&self) -> Result<Box<dyn DynVerifier>>;
+}
+
+impl<B: VerifierBuilder> DynVerifierBuilder for B
+where
+    B::Verifier: 'static,
+{
+    fn build_dyn(&self) -> Result<Box<dyn DynVerifier>> {
+        Ok(Box::new(DynAdapter(self.build()?)))
+    }
+}
+
+/// A builder running two verifications over the same content.
+///
+/// Both verifiers see every chunk; [`verify`](Verifier::verify) checks the
+/// first and then the second, so the first failure wins. Nest pairs to
+/// combine more than two.
+#[derive(Debug, Clone)]
+pub struct BothVerifierBuilder<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> BothVerifierBuilder<A, B> {
+    /// Combine two verifier builders.
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A: VerifierBuilder, B: VerifierBuilder> VerifierBuilder for BothVerifierBuilder<A, B> {
+    type Verifier = BothVerifier<A::Verifier, B::Verifier>;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        Ok(BothVerifier {
+            first: self.first.build()?,
+            second: self.second.build()?,
+        })
+    }
+}
+
+/// The verifier built by [`BothVerifierBuilder`].
+pub struct BothVerifier<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Verifier, B: Verifier> Verifier for BothVerifier<A, B> {
+    fn update(&mut self, data: &[u8]) {
+        self.first.update(data);
+        self.second.update(data);
+    }
+
+    fn update_bytes(&mut self, data: Bytes) {
+        self.first.update_bytes(data.clone());
+        self.second.update_bytes(data);
+    }
+
+    fn verify(self) -> Result<()> {
+        self.first.verify()?;
+        self.second.verify()
+    }
+}
+
+/// A verifier checking the content length in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeVerifierBuilder {
+    expected: u64,
+}
+
+impl SizeVerifierBuilder {
+    /// Create a builder expecting `expected` bytes of content.
+    pub fn new(expected: u64) -> Self {
+        Self { expected }
+    }
+}
+
+impl VerifierBuilder for SizeVerifierBuilder {
+    type Verifier = SizeVerifier;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        Ok(SizeVerifier {
+            expected: self.expected,
+            actual: 0,
+        })
+    }
+}
+
+/// The verifier built by [`SizeVerifierBuilder`].
+#[derive(Debug)]
+pub struct SizeVerifier {
+    expected: u64,
+    actual: u64,
+}
+
+impl Verifier for SizeVerifier {
+    fn update(&mut self, data: &[u8]) {
+        self.actual += data.len() as u64;
+    }
+
+    fn verify(self) -> Result<()> {
+        if self.actual == self.expected {
+            Ok(())
+        } else {
+            Err(
+                Error::new(ErrorKind::Verify).with_verify_details(VerifyDetails::Size {
+                    expected: self.expected,
+                    actual: self.actual,
+                }),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_match() {
+        let mut verifier = SizeVerifierBuilder::new(5).build().unwrap();
+        verifier.update(b"hel");
+        verifier.update(b"lo");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn both_runs_two_verifiers() {
+        let builder =
+            BothVerifierBuilder::new(SizeVerifierBuilder::new(5), SizeVerifierBuilder::new(5));
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+        // The first failure wins.
+        let builder =
+            BothVerifierBuilder::new(SizeVerifierBuilder::new(4), SizeVerifierBuilder::new(5));
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"hello");
+        let err = verifier.verify().unwrap_err();
+        assert_eq!(
+            err.verify_details(),
+            Some(&VerifyDetails::Size {
+                expected: 4,
+                actual: 5
+            })
+        );
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let mut verifier = SizeVerifierBuilder::new(4).build().unwrap();
+        verifier.update(b"hello");
+        let err = verifier.verify().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(err.description().unwrap().contains("expected 4 bytes"));
+        assert_eq!(
+            err.verify_details(),
+            Some(&VerifyDetails::Size {
+                expected: 4,
+                actual: 5
+            })
+        );
+    }
+
+    #[test]
+    fn update_reader() {
+        let mut verifier = SizeVerifierBuilder::new(5).build().unwrap();
+        let read = verifier.update_reader(&b"hello"[..]).unwrap();
+        assert_eq!(read, 5);
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn update_bytes_matches_update() {
+        let builder = SizeVerifierBuilder::new(5);
+        let mut by_slice = builder.build().unwrap();
+        by_slice.update(b"hello");
+        let mut by_bytes = builder.build().unwrap();
+        by_bytes.update_bytes(Bytes::from_static(b"hello"));
+        assert_eq!(by_slice.verify().is_ok(), by_bytes.verify().is_ok());
+    }
+
+    #[test]
+    fn boxed_dyn_verifier() {
+        let builder = SizeVerifierBuilder::new(5);
+        let mut verifier = DynVerifierBuilder::build_dyn(&builder).unwrap();
+        verifier.update(b"hello");
+        assert!(Verifier::verify(verifier).is_ok());
+    }
+}
@@ -0,0 +1,279 @@
+//! Runtime registry of hash algorithms.
+//!
+//! [`HashAlgorithm`](crate::verify::hash::HashAlgorithm) covers the
+//! algorithms compiled into the crate, but some consumers resolve algorithm
+//! names from manifests against a deployment-defined set, possibly including
+//! algorithms fetchkit does not know about. A [`Registry`] maps algorithm
+//! names to factories building boxed verifiers; it starts out with the
+//! built-in algorithms of the enabled features and can be extended at
+//! runtime.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::verify::hash::HashAlgorithm;
+use crate::verify::checksum::ChecksumFile;
+use crate::verify::{DynVerifier, VerifierBuilder};
+
+/// A factory building a verifier from the candidate digests.
+///
+/// Verification must succeed when the computed digest matches any candidate;
+/// most downloads pass exactly one. Factories are shared so the builders
+/// handed out by the registry own their factory and outlive it.
+pub type Factory = Arc<dyn Fn(Vec<Vec<u8>>) -> Result<Box<dyn DynVerifier>> + Send + Sync>;
+
+/// A registry mapping hash algorithm names to verifier factories.
+pub struct Registry {
+    factories: HashMap<String, Factory>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    /// Create a registry holding the built-in algorithms of the enabled
+    /// features.
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        let builtin: &[HashAlgorithm] = &[
+            #[cfg(feature = "sha2")]
+            HashAlgorithm::Sha256,
+            #[cfg(feature = "sha2")]
+            HashAlgorithm::Sha512,
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::Sha1,
+            #[cfg(feature = "md5")]
+            HashAlgorithm::Md5,
+        ];
+        for &algorithm in builtin {
+            registry.register(algorithm.name(), move |candidates| {
+                for candidate in &candidates {
+                    if candidate.len() != algorithm.digest_len() {
+                        return Err(Error::new(ErrorKind::Verify).with_desc_with(|| {
+                            format!(
+                                "invalid digest length for {}: expected {} bytes, got {}",
+                                algorithm.name(),
+                                algorithm.digest_len(),
+                                candidate.len()
+                            )
+                        }));
+                    }
+                }
+                Ok(algorithm.build_verifier(candidates))
+            });
+        }
+        registry
+    }
+
+    /// Create a registry without any algorithms.
+    pub fn empty() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Register `factory` under `name`, replacing any previous registration.
+    ///
+    /// Names are matched case-insensitively and with `-` stripped, like
+    /// [`HashAlgorithm`]'s `FromStr`.
+    pub fn register(
+        &mut self,
+        name: impl AsRef<str>,
+        factory: impl Fn(Vec<Vec<u8>>) -> Result<Box<dyn DynVerifier>> + Send + Sync + 'static,
+    ) {
+        self.factories
+            .insert(normalize(name.as_ref()), Arc::new(factory));
+    }
+
+    /// Whether an algorithm named `name` is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(&normalize(name))
+    }
+
+    /// Build a verifier builder for `algorithm` and a single expected digest.
+    pub fn verifier(&self, algorithm: &str, expected: Vec<u8>) -> Result<RegistryVerifierBuilder> {
+        self.verifier_any(algorithm, vec![expected])
+    }
+
+    /// Build a verifier builder for `algorithm` accepting any of the
+    /// expected digests.
+    pub fn verifier_any(
+        &self,
+        algorithm: &str,
+        expected: Vec<Vec<u8>>,
+    ) -> Result<RegistryVerifierBuilder> {
+        let name = normalize(algorithm);
+        let factory = self.factories.get(&name).cloned().ok_or_else(|| {
+            Error::new(ErrorKind::Verify)
+                .with_desc_with(|| format!("unknown hash algorithm: {algorithm}"))
+        })?;
+        Ok(RegistryVerifierBuilder {
+            factory,
+            algorithm: name,
+            candidates: expected,
+        })
+    }
+
+    /// Parse a verifier builder from a `"algo:hex"` string, resolving the
+    /// algorithm through this registry.
+    ///
+    /// This is the extensible counterpart of
+    /// [`DynHashVerifierBuilder::parse`](crate::verify::hash::DynHashVerifierBuilder::parse).
+    pub fn parse(&self, s: &str) -> Result<RegistryVerifierBuilder> {
+        let (algo, hex_digest) = s.split_once(':').ok_or_else(|| {
+            Error::new(ErrorKind::Verify)
+                .with_desc_with(|| format!("expected \"algo:hex\" format, got: {s}"))
+        })?;
+        let expected = hex::decode(hex_digest.trim())
+            .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+            .with_desc("invalid hex digest")?;
+        self.verifier(algo, expected)
+    }
+
+    /// Build a verifier builder for the entry named `name` of `file`,
+    /// resolving the algorithm through this registry.
+    pub fn verifier_for(
+        &self,
+        file: &ChecksumFile,
+        name: &str,
+    ) -> Result<RegistryVerifierBuilder> {
+        let digests = file
+            .get_all(name)
+            .ok_or_else(|| file.not_listed(name))?;
+        self.verifier_any(file.algorithm().name(), digests.to_vec())
+    }
+}
+
+/// Normalize an algorithm name for lookup: lower-case, `-` stripped.
+fn normalize(name: &str) -> String {
+    name.to_ascii_lowercase().replace('-', "")
+}
+
+/// A verifier builder resolving its algorithm through a [`Registry`].
+///
+/// The builder owns its factory, so it stays usable after the registry it
+/// came from is gone.
+#[derive(Clone)]
+pub struct RegistryVerifierBuilder {
+    factory: Factory,
+    algorithm: String,
+    candidates: Vec<Vec<u8>>,
+}
+
+impl RegistryVerifierBuilder {
+    /// The normalized algorithm name of this builder.
+    pub fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+}
+
+impl VerifierBuilder for RegistryVerifierBuilder {
+    type Verifier = Box<dyn DynVerifier>;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        (self.factory)(self.candidates.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify::Verifier;
+
+    /// A toy "algorithm": the digest is the single byte sum of the content.
+    struct Sum8 {
+        sum: u8,
+        candidates: Vec<Vec<u8>>,
+    }
+
+    impl DynVerifier for Sum8 {
+        fn update(&mut self, data: &[u8]) {
+            for &byte in data {
+                self.sum = self.sum.wrapping_add(byte);
+            }
+        }
+
+        fn verify(self: Box<Self>) -> Result<()> {
+            if self.candidates.iter().any(|c| c.as_slice() == [self.sum]) {
+                Ok(())
+            } else {
+                Err(Error::new(ErrorKind::Verify).with_desc("sum8 mismatch"))
+            }
+        }
+    }
+
+    fn registry_with_sum8() -> Registry {
+        let mut registry = Registry::new();
+        registry.register("sum8", |candidates| {
+            Ok(Box::new(Sum8 {
+                sum: 0,
+                candidates,
+            }))
+        });
+        registry
+    }
+
+    #[test]
+    fn custom_algorithm() {
+        let registry = registry_with_sum8();
+        assert!(registry.contains("sum8"));
+        assert!(registry.contains("SUM-8"));
+        // sum8 of "hello" is 0x14
+        let builder = registry.parse("sum8:14").unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn unknown_algorithm() {
+        let registry = Registry::new();
+        let err = registry.parse("sum8:14").err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(err.description().unwrap().contains("sum8"));
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn builtin_algorithm() {
+        let registry = Registry::new();
+        // sha256 of "hello"
+        let builder = registry
+            .parse("sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+            .unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn builtin_rejects_bad_digest_len() {
+        let registry = Registry::new();
+        let builder = registry.verifier("sha256", vec![0; 16]).unwrap();
+        assert!(builder.build().is_err());
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn checksum_file_through_registry() {
+        use crate::verify::checksum::ChecksumFile;
+        use crate::verify::hash::HashAlgorithm;
+
+        let sums = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  hello.txt\n";
+        let file = ChecksumFile::parse(HashAlgorithm::Sha256, sums).unwrap();
+        let registry = Registry::new();
+        let mut verifier = registry
+            .verifier_for(&file, "hello.txt")
+            .unwrap()
+            .build()
+            .unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+        assert!(registry.verifier_for(&file, "missing.txt").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! OpenBSD signify signature verification.
+//!
+//! Requires the `signify` feature. The format is a cousin of minisign but
+//! not identical (different comment handling, no pre-hashed marker), so keys
+//! and signatures are parsed with the [`libsignify`] crate; both the raw
+//! base64 form and the full file format with the untrusted comment line are
+//! accepted.
+
+use libsignify::{Codeable, PublicKey, Signature};
+
+use crate::error::{Error, ErrorKind, Result, VerifyDetails, WithDesc};
+use crate::verify::{Verifier, VerifierBuilder};
+
+/// A verifier builder checking a signify signature over the content.
+#[derive(Debug, Clone)]
+pub struct SignifyVerifierBuilder {
+    public_key: PublicKey,
+    signature: Signature,
+}
+
+impl SignifyVerifierBuilder {
+    /// Create a builder from the textual contents of a signify public key
+    /// file and a signature file.
+    ///
+    /// Both the full file format (untrusted comment line followed by the
+    /// base64 line) and the bare base64 line are accepted.
+    pub fn new(pubkey_text: &str, sig_text: &str) -> Result<Self> {
+        let public_key = parse::<PublicKey>(pubkey_text)
+            .with_desc("failed to parse the signify public key")?;
+        let signature =
+            parse::<Signature>(sig_text).with_desc("failed to parse the signify signature")?;
+        Ok(Self {
+            public_key,
+            signature,
+        })
+    }
+}
+
+/// Parse the full file format, falling back to a bare base64 line.
+fn parse<T: Codeable>(text: &str) -> Result<T> {
+    let result = T::from_base64(text).or_else(|_| {
+        let header = format!("untrusted comment: none\n{}\n", text.trim());
+        T::from_base64(&header)
+    });
+    match result {
+        Ok((parsed, _)) => Ok(parsed),
+        Err(e) => Err(Error::new(ErrorKind::Verify).with_source(e)),
+    }
+}
+
+impl VerifierBuilder for SignifyVerifierBuilder {
+    type Verifier = SignifyVerifier;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        Ok(SignifyVerifier {
+            public_key: self.public_key,
+            signature: self.signature,
+            content: Vec::new(),
+        })
+    }
+}
+
+/// The verifier built by [`SignifyVerifierBuilder`].
+///
+/// Signify signs the raw message, so the verifier buffers the content in
+/// memory; keep this in mind for very large files.
+pub struct SignifyVerifier {
+    public_key: PublicKey,
+    signature: Signature,
+    content: Vec<u8>,
+}
+
+impl Verifier for SignifyVerifier {
+    fn update(&mut self, data: &[u8]) {
+        self.content.extend_from_slice(data);
+    }
+
+    fn verify(self) -> Result<()> {
+        self.public_key
+            .verify(&self.content, &self.signature)
+            .map_err(|e| {
+                let reason = match &e {
+                    libsignify::Error::MismatchedKey { .. } => {
+                        "signify signature was created by a different key"
+                    }
+                    _ => "signify signature verification failed",
+                };
+                Error::new(ErrorKind::Verify)
+                    .with_source(e)
+                    .with_verify_details(VerifyDetails::Signature {
+                        key_id: None,
+                        reason: reason.into(),
+                    })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generated with signify-compatible tooling, the signed content is
+    // tests/fixtures/signify/message.txt.
+    const PUBLIC_KEY: &str = include_str!("../../tests/fixtures/signify/test_key.pub");
+    const SIGNATURE: &str = include_str!("../../tests/fixtures/signify/message.txt.sig");
+    const MESSAGE: &[u8] = include_bytes!("../../tests/fixtures/signify/message.txt");
+
+    #[test]
+    fn verify_ok() {
+        let builder = SignifyVerifierBuilder::new(PUBLIC_KEY, SIGNATURE).unwrap();
+        let mut verifier = builder.build().unwrap();
+        let (first, second) = MESSAGE.split_at(MESSAGE.len() / 2);
+        verifier.update(first);
+        verifier.update(second);
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn verify_bad_content() {
+        let builder = SignifyVerifierBuilder::new(PUBLIC_KEY, SIGNATURE).unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"tampered");
+        let err = verifier.verify().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(err.description().unwrap().contains("verification failed"));
+        assert!(matches!(
+            err.verify_details(),
+            Some(VerifyDetails::Signature { .. })
+        ));
+    }
+
+    #[test]
+    fn verify_wrong_key() {
+        // A different (valid) public key: the key ID no longer matches.
+        let other_key = "RWQBAgMEBQYHCAABAgMEBQYHCAkKCwwNDg8QERITFBUWFxgZGhscHR4f";
+        let builder = SignifyVerifierBuilder::new(other_key, SIGNATURE).unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(MESSAGE);
+        let err = verifier.verify().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(err.description().unwrap().contains("different key"));
+    }
+
+    #[test]
+    fn bare_base64_accepted() {
+        let bare = PUBLIC_KEY.lines().nth(1).unwrap();
+        assert!(SignifyVerifierBuilder::new(bare, SIGNATURE).is_ok());
+    }
+
+    #[test]
+    fn parse_errors_name_the_input() {
+        let err = SignifyVerifierBuilder::new("garbage", SIGNATURE).unwrap_err();
+        assert!(err.description().unwrap().contains("public key"));
+        let err = SignifyVerifierBuilder::new(PUBLIC_KEY, "garbage").unwrap_err();
+        assert!(err.description().unwrap().contains("signature"));
+    }
+}
@@ -0,0 +1,174 @@
+//! Subresource Integrity (SRI) based verification.
+//!
+//! SRI strings look like `sha256-<base64 digest>` and may contain several
+//! whitespace separated entries. Following the [specification], the
+//! strongest supported algorithm is selected, and verification succeeds if
+//! the content matches any entry of that algorithm.
+//!
+//! [specification]: https://www.w3.org/TR/SRI/
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use crate::error::{Error, ErrorKind, Result, WithDesc};
+use crate::verify::hash::{DynHasher, HashAlgorithm};
+use crate::verify::{Verifier, VerifierBuilder};
+
+/// A verifier builder parsed from an SRI string.
+#[derive(Debug, Clone)]
+pub struct SriVerifierBuilder {
+    algorithm: HashAlgorithm,
+    candidates: Vec<Vec<u8>>,
+}
+
+/// The priority of an algorithm for SRI selection, higher is stronger.
+fn priority(algorithm: HashAlgorithm) -> u8 {
+    match algorithm {
+        #[cfg(feature = "sha2")]
+        HashAlgorithm::Sha512 => 3,
+        #[cfg(feature = "sha2")]
+        HashAlgorithm::Sha256 => 2,
+        #[cfg(feature = "sha1")]
+        HashAlgorithm::Sha1 => 1,
+        #[cfg(feature = "md5")]
+        HashAlgorithm::Md5 => 0,
+    }
+}
+
+impl SriVerifierBuilder {
+    /// Parse an SRI string, selecting the strongest supported algorithm.
+    ///
+    /// Entries with unsupported algorithms are ignored; an error is returned
+    /// only if no entry is usable.
+    pub fn parse(sri: &str) -> Result<Self> {
+        let mut selected: Option<(HashAlgorithm, Vec<Vec<u8>>)> = None;
+        for entry in sri.split_ascii_whitespace() {
+            let Some((algo, digest)) = entry.split_once('-') else {
+                continue;
+            };
+            let Ok(algorithm) = algo.parse::<HashAlgorithm>() else {
+                continue;
+            };
+            let digest = STANDARD
+                .decode(digest)
+                .map_err(|e| Error::new(ErrorKind::Verify).with_source(e))
+                .with_desc_with(|| format!("invalid base64 digest in SRI entry: {entry}"))?;
+            if digest.len() != algorithm.digest_len() {
+                return Err(Error::new(ErrorKind::Verify)
+                    .with_desc_with(|| format!("invalid digest length in SRI entry: {entry}")));
+            }
+            match &mut selected {
+                Some((current, candidates)) => {
+                    if priority(algorithm) > priority(*current) {
+                        *current = algorithm;
+                        *candidates = vec![digest];
+                    } else if algorithm == *current {
+                        candidates.push(digest);
+                    }
+                }
+                None => selected = Some((algorithm, vec![digest])),
+            }
+        }
+        let (algorithm, candidates) = selected.ok_or_else(|| {
+            Error::new(ErrorKind::Verify)
+                .with_desc_with(|| format!("no usable entry in SRI string: {sri}"))
+        })?;
+        Ok(Self {
+            algorithm,
+            candidates,
+        })
+    }
+
+    /// The selected algorithm.
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+}
+
+impl VerifierBuilder for SriVerifierBuilder {
+    type Verifier = SriVerifier;
+
+    fn build(&self) -> Result<Self::Verifier> {
+        Ok(SriVerifier {
+            hasher: DynHasher::new(self.algorithm),
+            candidates: self.candidates.clone(),
+        })
+    }
+}
+
+/// The verifier built by [`SriVerifierBuilder`].
+pub struct SriVerifier {
+    hasher: DynHasher,
+    candidates: Vec<Vec<u8>>,
+}
+
+impl Verifier for SriVerifier {
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    fn verify(self) -> Result<()> {
+        let actual = self.hasher.finalize();
+        if self.candidates.contains(&actual) {
+            Ok(())
+        } else {
+            Err(Error::new(ErrorKind::Verify).with_desc_with(|| {
+                format!("digest matches no SRI entry, got {}", hex::encode(&actual))
+            }))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sha2"))]
+mod tests {
+    use super::*;
+
+    // sha256 of "hello", base64 encoded
+    const HELLO_SHA256_B64: &str = "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=";
+
+    #[test]
+    fn parse_and_verify() {
+        let builder = SriVerifierBuilder::parse(&format!("sha256-{HELLO_SHA256_B64}")).unwrap();
+        assert_eq!(builder.algorithm(), HashAlgorithm::Sha256);
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn strongest_algorithm_wins() {
+        // sha512 of "hello"
+        const HELLO_SHA512_B64: &str = "m3HSJL1i83hdltRq0+o9czGb+8KJDKra4t/3JRlnPKcjI8PZm6XBHXx6zG4UuMXaDEZjR1wuXDre9G9zvN7AQw==";
+        let sri = format!("sha256-{HELLO_SHA256_B64} sha512-{HELLO_SHA512_B64}");
+        let builder = SriVerifierBuilder::parse(&sri).unwrap();
+        assert_eq!(builder.algorithm(), HashAlgorithm::Sha512);
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn multiple_candidates() {
+        let other = base64::Engine::encode(&STANDARD, [0u8; 32]);
+        let sri = format!("sha256-{other} sha256-{HELLO_SHA256_B64}");
+        let builder = SriVerifierBuilder::parse(&sri).unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"hello");
+        assert!(verifier.verify().is_ok());
+    }
+
+    #[test]
+    fn no_usable_entry() {
+        let err = SriVerifierBuilder::parse("sm3-AAAA").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+    }
+
+    #[test]
+    fn mismatch() {
+        let builder = SriVerifierBuilder::parse(&format!("sha256-{HELLO_SHA256_B64}")).unwrap();
+        let mut verifier = builder.build().unwrap();
+        verifier.update(b"bye");
+        let err = verifier.verify().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+    }
+}
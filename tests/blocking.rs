@@ -0,0 +1,103 @@
+#![cfg(all(feature = "blocking", feature = "sha2"))]
+
+use std::collections::HashMap;
+
+use fetchkit::ErrorKind;
+use fetchkit::download::{BlockingClient, BlockingResponse, DownloadBuilder, MirrorOptions};
+use fetchkit::error::{Error, Result};
+use fetchkit::progress::NoProgress;
+use fetchkit::verify::hash::Sha256VerifierBuilder;
+
+const HELLO_WORLD_SHA256: &str =
+    "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+/// A trivial in-memory blocking client routing by exact URL.
+#[derive(Default)]
+struct MemoryClient {
+    routes: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryClient {
+    fn route(mut self, url: &str, data: &[u8]) -> Self {
+        self.routes.insert(url.to_owned(), data.to_owned());
+        self
+    }
+}
+
+struct MemoryResponse {
+    data: Vec<u8>,
+}
+
+impl BlockingClient for MemoryClient {
+    type Response = MemoryResponse;
+
+    fn get(&self, url: &str) -> Result<Self::Response> {
+        match self.routes.get(url) {
+            Some(data) => Ok(MemoryResponse { data: data.clone() }),
+            None => Err(Error::new(ErrorKind::Network).with_url(url)),
+        }
+    }
+}
+
+impl BlockingResponse for MemoryResponse {
+    fn content_length(&self) -> Option<u64> {
+        Some(self.data.len() as u64)
+    }
+
+    fn into_body(self) -> impl std::io::Read {
+        std::io::Cursor::new(self.data)
+    }
+}
+
+#[test]
+fn downloads_and_verifies_without_a_runtime() {
+    let client = MemoryClient::default().route("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let report = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .download_blocking(&client, NoProgress)
+        .unwrap();
+    assert_eq!(report.bytes, 11);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[test]
+fn a_bad_digest_fails_and_cleans_up() {
+    let client = MemoryClient::default().route("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .download_blocking(&client, NoProgress)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    assert!(!dest.exists());
+    assert!(!dir.path().join("data.part").exists());
+}
+
+#[test]
+fn mirrors_are_probed_with_blocking_reads() {
+    // The primary is dead; the mirror serves the file.
+    let client =
+        MemoryClient::default().route("https://mirror.example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let mirrors = ["https://mirror.example.com/data"];
+    let report = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .download_blocking(&client, NoProgress)
+        .unwrap();
+    assert_eq!(report.url, "https://mirror.example.com/data");
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[test]
+fn a_wrong_announced_length_is_rejected() {
+    let client = MemoryClient::default().route("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let err = DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 99)
+        .download_blocking(&client, NoProgress)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+}
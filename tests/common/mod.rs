@@ -0,0 +1,476 @@
+//! Shared test helpers: an in-memory mock client and a recording progress
+//! receiver.
+
+// The module is compiled once per test binary and not every binary uses
+// every helper.
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use fetchkit::error::{Error, ErrorKind, Result, WithDesc};
+use fetchkit::http::{Client, Response};
+use fetchkit::progress::{Phase, PhasedProgressBuilder, ProgressReceiver, ProgressReceiverBuilder};
+use futures_util::{Stream, stream};
+
+/// How a mock route answers a request.
+#[derive(Clone)]
+pub enum MockBody {
+    /// Answer with the given body chunks.
+    Chunks(Vec<Bytes>),
+    /// Answer with the given chunks, then fail the stream.
+    ChunksThenError(Vec<Bytes>),
+    /// Fail the stream mid-transfer on the first `n` requests, then serve
+    /// the chunks in full, for retry tests.
+    FlakyChunks(u32, Vec<Bytes>),
+    /// Answer with a stream that never yields, for timeout tests.
+    Stall,
+    /// Answer with the given chunks, then hang forever, for idle timeout
+    /// tests.
+    ChunksThenStall(Vec<Bytes>),
+    /// Answer with the given chunks spaced the given interval apart, for
+    /// stall detection tests. Needs a tokio runtime.
+    Trickle(std::time::Duration, Vec<Bytes>),
+    /// Fail the request itself.
+    ConnectError,
+    /// Answer with the given HTTP error status.
+    Status(u16),
+    /// Answer with the given status and body without turning the status
+    /// into an error, for clients that do not map statuses themselves.
+    RawStatus(u16, Vec<Bytes>),
+}
+
+/// The URLs requested with extra headers, together with those headers.
+type HeaderLog = Vec<(String, Vec<(String, String)>)>;
+
+/// An in-memory [`Client`] serving canned responses per URL.
+#[derive(Default)]
+pub struct MockClient {
+    routes: Mutex<HashMap<String, MockBody>>,
+    heads: Mutex<HashMap<String, u64>>,
+    etags: Mutex<HashMap<String, String>>,
+    dispositions: Mutex<HashMap<String, String>>,
+    modified: Mutex<HashMap<String, String>>,
+    calls: Mutex<Vec<String>>,
+    headers: Mutex<HeaderLog>,
+    wire_lengths: Mutex<HashMap<String, u64>>,
+    decoded: Mutex<HashSet<String>>,
+    digests: Mutex<HashMap<String, String>>,
+}
+
+impl MockClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `body` for `url`.
+    pub fn route(self, url: &str, body: MockBody) -> Self {
+        self.routes.lock().unwrap().insert(url.to_string(), body);
+        self
+    }
+
+    /// Serve `data` as a series of small chunks for `url`.
+    pub fn route_data(self, url: &str, data: &[u8]) -> Self {
+        let chunks = data
+            .chunks(3)
+            .map(Bytes::copy_from_slice)
+            .collect();
+        self.route(url, MockBody::Chunks(chunks))
+    }
+
+    /// Answer HEAD requests for `url` with the given content length.
+    pub fn route_head(self, url: &str, len: u64) -> Self {
+        self.heads.lock().unwrap().insert(url.to_string(), len);
+        self
+    }
+
+    /// Attach an `ETag` to the responses for `url`; conditional requests
+    /// carrying the same tag are answered with `304 Not Modified`.
+    pub fn route_etag(self, url: &str, etag: &str) -> Self {
+        self.etags
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), etag.to_string());
+        self
+    }
+
+    /// Attach a `Content-Disposition` header to the responses for `url`.
+    pub fn route_content_disposition(self, url: &str, value: &str) -> Self {
+        self.dispositions
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), value.to_string());
+        self
+    }
+
+    /// Answer conditional requests for `url` carrying exactly `date` as
+    /// `If-Modified-Since` with `304 Not Modified`.
+    pub fn route_not_modified_since(self, url: &str, date: &str) -> Self {
+        self.modified
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), date.to_string());
+        self
+    }
+
+    /// Announce `len` as the `Content-Length` for `url` instead of the
+    /// actual body size, mimicking a header that describes the wire bytes
+    /// of an on-the-fly compressed response.
+    pub fn route_wire_length(self, url: &str, len: u64) -> Self {
+        self.wire_lengths
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), len);
+        self
+    }
+
+    /// Mark the responses for `url` as transparently decoded by the
+    /// client.
+    pub fn route_decoded(self, url: &str) -> Self {
+        self.decoded.lock().unwrap().insert(url.to_string());
+        self
+    }
+
+    /// Attach an RFC 3230 `Digest` header to the responses for `url`.
+    pub fn route_digest(self, url: &str, value: &str) -> Self {
+        self.digests
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), value.to_string());
+        self
+    }
+
+    /// The URLs requested so far, in order.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// The extra request headers sent so far, per requested URL.
+    pub fn headers(&self) -> HeaderLog {
+        self.headers.lock().unwrap().clone()
+    }
+}
+
+pub struct MockResponse {
+    items: Vec<Result<Bytes>>,
+    content_length: Option<u64>,
+    stall: bool,
+    status: u16,
+    etag: Option<String>,
+    disposition: Option<String>,
+    delay: Option<std::time::Duration>,
+    decoded: bool,
+    digest: Option<String>,
+}
+
+impl Default for MockResponse {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            content_length: None,
+            stall: false,
+            status: 200,
+            etag: None,
+            disposition: None,
+            delay: None,
+            decoded: false,
+            digest: None,
+        }
+    }
+}
+
+impl Client for MockClient {
+    type Response = MockResponse;
+
+    async fn get(&self, url: &str) -> Result<MockResponse> {
+        self.calls.lock().unwrap().push(url.to_string());
+        let mut body = self.routes.lock().unwrap().get(url).cloned();
+        if let Some(MockBody::FlakyChunks(failures, chunks)) = body {
+            if failures == 0 {
+                body = Some(MockBody::Chunks(chunks));
+            } else {
+                self.routes.lock().unwrap().insert(
+                    url.to_string(),
+                    MockBody::FlakyChunks(failures - 1, chunks.clone()),
+                );
+                body = Some(MockBody::ChunksThenError(
+                    chunks.into_iter().take(1).collect(),
+                ));
+            }
+        }
+        match body {
+            Some(MockBody::Chunks(chunks)) => {
+                let content_length = self
+                    .wire_lengths
+                    .lock()
+                    .unwrap()
+                    .get(url)
+                    .copied()
+                    .or_else(|| Some(chunks.iter().map(|c| c.len() as u64).sum()));
+                Ok(MockResponse {
+                    items: chunks.into_iter().map(Ok).collect(),
+                    content_length,
+                    etag: self.etags.lock().unwrap().get(url).cloned(),
+                    disposition: self.dispositions.lock().unwrap().get(url).cloned(),
+                    decoded: self.decoded.lock().unwrap().contains(url),
+                    digest: self.digests.lock().unwrap().get(url).cloned(),
+                    ..Default::default()
+                })
+            }
+            Some(MockBody::ChunksThenError(chunks)) => {
+                let mut items: Vec<Result<Bytes>> = chunks.into_iter().map(Ok).collect();
+                items.push(Err(Error::new(ErrorKind::Network).with_desc("stream interrupted")));
+                Ok(MockResponse {
+                    items,
+                    ..Default::default()
+                })
+            }
+            Some(MockBody::FlakyChunks(..)) => unreachable!("rewritten above"),
+            Some(MockBody::Stall) => Ok(MockResponse {
+                stall: true,
+                ..Default::default()
+            }),
+            Some(MockBody::ChunksThenStall(chunks)) => Ok(MockResponse {
+                items: chunks.into_iter().map(Ok).collect(),
+                stall: true,
+                ..Default::default()
+            }),
+            Some(MockBody::Trickle(delay, chunks)) => Ok(MockResponse {
+                items: chunks.into_iter().map(Ok).collect(),
+                delay: Some(delay),
+                ..Default::default()
+            }),
+            Some(MockBody::RawStatus(status, chunks)) => Ok(MockResponse {
+                items: chunks.into_iter().map(Ok).collect(),
+                status,
+                ..Default::default()
+            }),
+            Some(MockBody::Status(status)) => Err(Error::new(ErrorKind::Network)
+                .with_http_status(status)
+                .with_desc_with(|| format!("failed to fetch {url}"))),
+            Some(MockBody::ConnectError) | None => {
+                Err(Error::new(ErrorKind::Network)
+                    .mark_connect()
+                    .with_desc_with(|| format!("failed to connect to {url}")))
+            }
+        }
+    }
+
+    async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+    ) -> Result<MockResponse> {
+        self.headers
+            .lock()
+            .unwrap()
+            .push((url.to_string(), headers.to_vec()));
+        self.get(url).await
+    }
+
+    async fn get_if_none_match(&self, url: &str, etag: &str) -> Result<MockResponse> {
+        if self.etags.lock().unwrap().get(url).map(String::as_str) == Some(etag) {
+            self.calls.lock().unwrap().push(url.to_string());
+            return Ok(MockResponse {
+                status: 304,
+                ..Default::default()
+            });
+        }
+        self.get(url).await
+    }
+
+    async fn get_if_modified_since(&self, url: &str, date: &str) -> Result<MockResponse> {
+        if self.modified.lock().unwrap().get(url).map(String::as_str) == Some(date) {
+            self.calls.lock().unwrap().push(url.to_string());
+            return Ok(MockResponse {
+                status: 304,
+                ..Default::default()
+            });
+        }
+        self.get(url).await
+    }
+
+    async fn head(&self, url: &str) -> Result<Option<u64>> {
+        Ok(self.heads.lock().unwrap().get(url).copied())
+    }
+}
+
+impl Response for MockResponse {
+    fn status(&self) -> u16 {
+        self.status
+    }
+
+    fn etag(&self) -> Option<String> {
+        self.etag.clone()
+    }
+
+    fn content_disposition(&self) -> Option<String> {
+        self.disposition.clone()
+    }
+
+    fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    fn digest(&self) -> Option<String> {
+        self.digest.clone()
+    }
+
+    fn is_decoded(&self) -> bool {
+        self.decoded
+    }
+
+    fn bytes_stream(self) -> impl Stream<Item = Result<Bytes>> + Send + Unpin {
+        use futures_util::StreamExt;
+
+        let tail = if self.stall {
+            stream::pending().boxed()
+        } else {
+            stream::empty().boxed()
+        };
+        let items = match self.delay {
+            Some(delay) => stream::iter(self.items)
+                .then(move |item| async move {
+                    tokio::time::sleep(delay).await;
+                    item
+                })
+                .boxed(),
+            None => stream::iter(self.items).boxed(),
+        };
+        items.chain(tail)
+    }
+}
+
+/// A recording progress receiver for assertions.
+#[derive(Clone, Default)]
+pub struct TestProgress {
+    state: Arc<Mutex<TestProgressState>>,
+}
+
+#[derive(Default)]
+pub struct TestProgressState {
+    pub total: Option<u64>,
+    pub positions: Vec<u64>,
+    pub messages: Vec<String>,
+    pub finished: bool,
+    pub error: Option<String>,
+    pub abandoned: bool,
+    pub terminal_calls: u32,
+}
+
+impl TestProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn total(&self) -> Option<u64> {
+        self.state.lock().unwrap().total
+    }
+
+    pub fn positions(&self) -> Vec<u64> {
+        self.state.lock().unwrap().positions.clone()
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.state.lock().unwrap().messages.clone()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.state.lock().unwrap().finished
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.state.lock().unwrap().error.clone()
+    }
+
+    pub fn abandoned(&self) -> bool {
+        self.state.lock().unwrap().abandoned
+    }
+
+    /// How many of the terminal methods (`finish`, `finish_with_error`,
+    /// `abandon`) were called; exactly one call is expected per download.
+    pub fn terminal_calls(&self) -> u32 {
+        self.state.lock().unwrap().terminal_calls
+    }
+}
+
+impl ProgressReceiverBuilder for TestProgress {
+    type Receiver = TestProgress;
+
+    fn init(self, total: Option<u64>) -> Self::Receiver {
+        self.state.lock().unwrap().total = total;
+        self
+    }
+}
+
+/// A recording phased progress builder, handing out a shared
+/// [`TestProgress`] per phase.
+#[derive(Clone, Default)]
+pub struct TestPhases {
+    phases: Arc<Mutex<Vec<Phase>>>,
+    progress: TestProgress,
+}
+
+impl TestPhases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The phases opened so far, in order.
+    pub fn phases(&self) -> Vec<Phase> {
+        self.phases.lock().unwrap().clone()
+    }
+
+    /// The shared receiver fed by every phase.
+    pub fn progress(&self) -> &TestProgress {
+        &self.progress
+    }
+}
+
+impl PhasedProgressBuilder for TestPhases {
+    type Receiver = TestProgress;
+
+    fn begin_phase(&self, phase: Phase, total: Option<u64>) -> Self::Receiver {
+        self.phases.lock().unwrap().push(phase);
+        self.progress.clone().init(total)
+    }
+}
+
+impl ProgressReceiver for TestProgress {
+    fn set_position(&self, position: u64) {
+        self.state.lock().unwrap().positions.push(position);
+    }
+
+    fn set_total(&self, total: u64) {
+        self.state.lock().unwrap().total = Some(total);
+    }
+
+    fn inc(&self, delta: u64) {
+        let mut state = self.state.lock().unwrap();
+        let position = state.positions.last().copied().unwrap_or(0) + delta;
+        state.positions.push(position);
+    }
+
+    fn set_message(&self, msg: &str) {
+        self.state.lock().unwrap().messages.push(msg.to_string());
+    }
+
+    fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.finished = true;
+        state.terminal_calls += 1;
+    }
+
+    fn finish_with_error(&self, error: &Error) {
+        let mut state = self.state.lock().unwrap();
+        state.error = Some(error.to_string());
+        state.terminal_calls += 1;
+    }
+
+    fn abandon(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.abandoned = true;
+        state.terminal_calls += 1;
+    }
+}
@@ -0,0 +1,2367 @@
+mod common;
+
+use common::{MockBody, MockClient, TestPhases, TestProgress};
+use fetchkit::progress::NoProgress;
+use fetchkit::ErrorKind;
+use fetchkit::download::{DownloadBuilder, MirrorOptions};
+use fetchkit::verify::hash::Sha256VerifierBuilder;
+
+// sha256 of "hello world"
+const HELLO_WORLD_SHA256: &str =
+    "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+#[tokio::test]
+async fn download_to_file() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let builder = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap());
+    builder
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn download_reports_progress() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let progress = TestProgress::new();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .download(&client, progress.clone())
+        .await
+        .unwrap();
+    assert_eq!(progress.total(), Some(11));
+    assert_eq!(progress.messages(), ["https://example.com/data"]);
+    let positions = progress.positions();
+    assert_eq!(positions.last(), Some(&11));
+    assert!(positions.windows(2).all(|w| w[0] <= w[1]));
+    assert!(progress.finished());
+    assert_eq!(progress.terminal_calls(), 1);
+}
+
+#[tokio::test]
+async fn download_learns_total_from_response() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let progress = TestProgress::new();
+    // Unknown expected size: the total is picked up from the response.
+    DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .download(&client, progress.clone())
+        .await
+        .unwrap();
+    assert_eq!(progress.total(), Some(11));
+    assert!(progress.finished());
+}
+
+#[tokio::test]
+async fn download_verify_failure() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let progress = TestProgress::new();
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .download(&client, progress.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    // The failure resolves the progress receiver exactly once.
+    assert!(!progress.finished());
+    assert_eq!(progress.error(), Some(err.to_string()));
+    assert_eq!(progress.terminal_calls(), 1);
+}
+
+#[tokio::test]
+async fn download_stream_failure() {
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::ChunksThenError(vec![bytes::Bytes::from_static(b"hel")]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let progress = TestProgress::new();
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .download(&client, progress.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Network);
+    assert_eq!(err.url(), Some("https://example.com/data"));
+    assert_eq!(err.path(), Some(dest.as_path()));
+    assert!(!progress.finished());
+    assert_eq!(progress.error(), Some(err.to_string()));
+    assert_eq!(progress.terminal_calls(), 1);
+}
+
+#[tokio::test]
+async fn download_refuses_to_overwrite() {
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::Chunks(vec![bytes::Bytes::from_static(b"hello world")]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    std::fs::write(&dest, b"old content").unwrap();
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Io);
+    assert_eq!(err.io_kind(), Some(std::io::ErrorKind::AlreadyExists));
+}
+
+#[tokio::test]
+async fn exist_checks() {
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let verifier = Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap();
+    let builder = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(verifier.clone());
+    // Missing file
+    assert!(!builder.exist().unwrap());
+    // Wrong size
+    std::fs::write(&dest, b"short").unwrap();
+    assert!(!builder.exist().unwrap());
+    // Right size, wrong content
+    std::fs::write(&dest, b"hello-world").unwrap();
+    assert!(builder.exist().is_err());
+    // Valid file
+    std::fs::write(&dest, b"hello world").unwrap();
+    assert!(builder.exist().unwrap());
+}
+
+#[tokio::test]
+async fn download_with_registry_verifier() {
+    use fetchkit::error::{Error, Result, WithDesc};
+    use fetchkit::verify::DynVerifier;
+    use fetchkit::verify::registry::Registry;
+
+    // A toy custom algorithm: the digest is the content length as one byte.
+    struct Len8 {
+        len: u8,
+        candidates: Vec<Vec<u8>>,
+    }
+
+    impl DynVerifier for Len8 {
+        fn update(&mut self, data: &[u8]) {
+            self.len = self.len.wrapping_add(data.len() as u8);
+        }
+
+        fn verify(self: Box<Self>) -> Result<()> {
+            if self.candidates.iter().any(|c| c.as_slice() == [self.len]) {
+                Ok(())
+            } else {
+                Err(Error::new(ErrorKind::Verify).with_desc("len8 mismatch"))
+            }
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register("len8", |candidates| {
+        Ok(Box::new(Len8 { len: 0, candidates }))
+    });
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+
+    // "hello world" is 11 = 0x0b bytes long.
+    let dest = dir.path().join("ok");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(registry.parse("len8:0b").unwrap())
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+
+    let dest = dir.path().join("bad");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(registry.parse("len8:0c").unwrap())
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+}
+
+#[tokio::test]
+async fn phased_download_reports_phase_sequence() {
+    use fetchkit::progress::Phase;
+
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_data("https://mirror.example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let mirrors = ["https://mirror.example.com/data"];
+    let phases = TestPhases::new();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .download_phased(&client, &phases)
+        .await
+        .unwrap();
+    assert_eq!(
+        phases.phases(),
+        [Phase::SelectingMirror, Phase::Downloading, Phase::Verifying]
+    );
+    // Every phase was resolved.
+    assert_eq!(phases.progress().terminal_calls(), 3);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn status_errors_are_structured() {
+    let dir = tempfile::tempdir().unwrap();
+    for (status, retryable) in [(404u16, false), (429, true), (503, true)] {
+        let client =
+            MockClient::new().route("https://example.com/data", MockBody::Status(status));
+        let dest = dir.path().join(format!("data-{status}"));
+        let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+            .download(&client, NoProgress)
+            .await
+            .unwrap_err();
+        assert_eq!(err.http_status(), Some(status));
+        assert_eq!(err.is_retryable(), retryable);
+        assert_eq!(err.is_not_found(), status == 404);
+        assert!(err.to_string().contains(&format!("HTTP status {status}")));
+    }
+}
+
+#[tokio::test]
+async fn mirror_skips_not_found() {
+    let client = MockClient::new()
+        .route("https://primary.example.com/data", MockBody::Status(404))
+        .route_data("https://mirror.example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let mirrors = ["https://mirror.example.com/data"];
+    // A 404 disqualifies the primary but the other candidates are still
+    // tried.
+    DownloadBuilder::new("https://primary.example.com/data", &dest, 11)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn offline_serves_a_valid_local_file() {
+    use fetchkit::download::OfflinePolicy;
+
+    let client = MockClient::new();
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    std::fs::write(&dest, b"hello world").unwrap();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_offline_policy(OfflinePolicy::Offline)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    // The network was never touched.
+    assert!(client.calls().is_empty());
+}
+
+#[tokio::test]
+async fn offline_without_a_local_copy_fails_fast() {
+    use fetchkit::download::OfflinePolicy;
+
+    let client = MockClient::new();
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let mirrors = ["https://mirror.example.com/data"];
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .with_offline_policy(OfflinePolicy::Offline)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Offline);
+    assert!(err.to_string().contains("offline and no verified local copy"));
+    // Not even the mirror speedtest ran.
+    assert!(client.calls().is_empty());
+}
+
+#[tokio::test]
+async fn prefer_cache_downloads_once() {
+    use fetchkit::download::OfflinePolicy;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let builder = || {
+        DownloadBuilder::new("https://example.com/data", &dest, 11)
+            .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+            .with_offline_policy(OfflinePolicy::PreferCache)
+    };
+    builder().download(&client, NoProgress).await.unwrap();
+    assert_eq!(client.calls().len(), 1);
+    // The second download is served from the valid local copy.
+    builder().download(&client, NoProgress).await.unwrap();
+    assert_eq!(client.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn dest_lock_excludes_a_second_handle() {
+    use fetchkit::download::{DestLock, LockWait};
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let lock = DestLock::acquire(&dest, LockWait::NoWait).unwrap();
+    // A second handle fails fast while the first one is held.
+    let err = DestLock::acquire(&dest, LockWait::NoWait).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Io);
+    assert_eq!(err.path(), Some(lock.path()));
+    // With a short timeout the wait expires into a timeout error.
+    let err =
+        DestLock::acquire(&dest, LockWait::Timeout(std::time::Duration::from_millis(10)))
+            .unwrap_err();
+    assert!(err.is_timeout());
+    // Releasing the first handle unblocks the destination.
+    drop(lock);
+    DestLock::acquire(&dest, LockWait::NoWait).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn locked_download_reuses_the_file_of_the_lock_holder() {
+    use fetchkit::download::{DestLock, LockWait};
+
+    // No route: any request would fail, proving the file was reused.
+    let client = MockClient::new();
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let lock = DestLock::acquire(&dest, LockWait::NoWait).unwrap();
+    let writer = {
+        let dest = dest.clone();
+        std::thread::spawn(move || {
+            // Simulate the first process finishing its download while the
+            // second one waits on the lock.
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            std::fs::write(&dest, b"hello world").unwrap();
+            drop(lock);
+        })
+    };
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_lock(LockWait::Wait)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    writer.join().unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn mirror_failover() {
+    let client = MockClient::new()
+        .route("https://primary.example.com/data", MockBody::ConnectError)
+        .route_data("https://mirror.example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let mirrors = ["https://mirror.example.com/data"];
+    DownloadBuilder::new("https://primary.example.com/data", &dest, 11)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn all_mirrors_fail() {
+    let client = MockClient::new()
+        .route("https://primary.example.com/data", MockBody::ConnectError)
+        .route("https://mirror.example.com/data", MockBody::ConnectError);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let mirrors = ["https://mirror.example.com/data"];
+    let err = DownloadBuilder::new("https://primary.example.com/data", &dest, 11)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Network);
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn retry_recovers_from_transient_failures() {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use fetchkit::download::RetryPolicy;
+
+    let chunks = b"hello world".chunks(3).map(Bytes::copy_from_slice).collect();
+    let client =
+        MockClient::new().route("https://example.com/data", MockBody::FlakyChunks(2, chunks));
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let retried = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorder = retried.clone();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_retry(
+            RetryPolicy::new(3)
+                .with_base_delay(Duration::from_millis(1))
+                .on_retry(move |attempt, _| recorder.lock().unwrap().push(attempt)),
+        )
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    // Two mid-stream failures, then a full transfer passing verification.
+    assert_eq!(client.calls().len(), 3);
+    assert_eq!(*retried.lock().unwrap(), [1, 2]);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn retry_exhausts_its_attempts() {
+    use std::time::Duration;
+
+    use fetchkit::download::RetryPolicy;
+
+    let client = MockClient::new().route("https://example.com/data", MockBody::ConnectError);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_retry(RetryPolicy::new(3).with_base_delay(Duration::from_millis(1)))
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert!(err.is_connect());
+    assert_eq!(client.calls().len(), 3);
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn retry_surfaces_verify_failures_immediately() {
+    use std::time::Duration;
+
+    use fetchkit::download::RetryPolicy;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .with_retry(RetryPolicy::new(3).with_base_delay(Duration::from_millis(1)))
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    assert_eq!(client.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn a_failed_download_leaves_nothing_behind() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    // The transfer went to the part file and verification failed, so
+    // neither the destination nor the part file survives.
+    assert!(!dest.exists());
+    assert!(!dir.path().join("data.part").exists());
+}
+
+#[tokio::test]
+async fn a_stale_part_file_is_overwritten() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    // Leftover from a crashed run.
+    std::fs::write(dir.path().join("data.part"), b"garbage from a crash").unwrap();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+    assert!(!dir.path().join("data.part").exists());
+}
+
+#[tokio::test]
+async fn an_existing_destination_is_still_an_error() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    std::fs::write(&dest, b"do not touch").unwrap();
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.io_kind(), Some(std::io::ErrorKind::AlreadyExists));
+    assert_eq!(std::fs::read(&dest).unwrap(), b"do not touch");
+}
+
+#[tokio::test]
+async fn overwrite_replaces_an_existing_file() {
+    use fetchkit::download::OverwritePolicy;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    std::fs::write(&dest, b"stale content").unwrap();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_overwrite_policy(OverwritePolicy::Overwrite)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn skip_if_valid_avoids_the_network() {
+    use fetchkit::download::OverwritePolicy;
+
+    // No routes: any request would fail.
+    let client = MockClient::new();
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    std::fs::write(&dest, b"hello world").unwrap();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_overwrite_policy(OverwritePolicy::SkipIfValid)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert!(client.calls().is_empty());
+}
+
+#[tokio::test]
+async fn skip_if_valid_replaces_a_file_failing_verification() {
+    use fetchkit::download::OverwritePolicy;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    // Right size, wrong content: `exist` rejects it with a verify error.
+    std::fs::write(&dest, b"jello world").unwrap();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_overwrite_policy(OverwritePolicy::SkipIfValid)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(client.calls().len(), 1);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn download_bytes_returns_verified_content() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let progress = TestProgress::new();
+    let bytes = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .download_bytes(&client, progress.clone())
+        .await
+        .unwrap();
+    assert_eq!(&bytes[..], b"hello world");
+    // Nothing touches the disk.
+    assert!(!dest.exists());
+    assert!(!dir.path().join("data.part").exists());
+    assert!(progress.finished());
+}
+
+#[tokio::test]
+async fn download_bytes_refuses_oversized_responses() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .with_memory_cap(8)
+        .download_bytes(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("memory cap"), "{err}");
+
+    // Without an announced length the cap still trips while buffering.
+    let chunks = b"hello world".chunks(3).map(bytes::Bytes::copy_from_slice).collect();
+    let client =
+        MockClient::new().route("https://example.com/data", MockBody::ChunksThenError(chunks));
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .with_memory_cap(8)
+        .download_bytes(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("memory cap"), "{err}");
+}
+
+#[tokio::test]
+async fn download_bytes_verify_failure() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .download_bytes(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+}
+
+#[tokio::test]
+async fn download_to_writer_streams_into_the_sink() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let progress = TestProgress::new();
+    let mut sink = Vec::new();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .download_to_writer(&client, &mut sink, progress.clone())
+        .await
+        .unwrap();
+    assert_eq!(sink, b"hello world");
+    assert!(!dest.exists());
+    assert_eq!(progress.total(), Some(11));
+    assert!(progress.finished());
+}
+
+#[tokio::test]
+async fn download_to_writer_surfaces_verify_failures() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let mut sink = Vec::new();
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .download_to_writer(&client, &mut sink, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn download_to_async_writer_streams_into_the_sink() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let mut sink = Vec::new();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .download_to_async_writer(&client, &mut sink, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(sink, b"hello world");
+    assert!(!dest.exists());
+}
+
+#[tokio::test]
+async fn head_probe_fills_in_an_unknown_size() {
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_head("https://example.com/data", 11);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let progress = TestProgress::new();
+    DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .with_head_probe()
+        .download(&client, progress.clone())
+        .await
+        .unwrap();
+    // The total is known before the first chunk arrives.
+    assert_eq!(progress.total(), Some(11));
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn head_probe_aborts_on_a_size_mismatch() {
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_head("https://example.com/data", 999);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_head_probe()
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    // Aborted before the GET: no transfer, nothing on disk.
+    assert!(client.calls().is_empty());
+    assert!(!dest.exists());
+}
+
+#[tokio::test]
+async fn head_probe_falls_back_when_unanswered() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_head_probe()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn a_content_length_mismatch_aborts_before_streaming() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 5)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    assert!(!dest.exists());
+    assert!(!dir.path().join("data.part").exists());
+}
+
+#[tokio::test]
+async fn a_bogus_content_length_can_be_ignored() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 5)
+        .skip_content_length_check()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn an_etag_cache_skips_an_unchanged_download() {
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_etag("https://example.com/data", "\"v1\"");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_etag_cache()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read(dir.path().join("data.etag")).unwrap(),
+        b"\"v1\""
+    );
+    // Mark the local copy so a rewrite would be visible, then download
+    // again: the matching etag turns it into a 304 no-op.
+    std::fs::write(&dest, b"sentinel").unwrap();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_etag_cache()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"sentinel");
+    assert_eq!(client.calls().len(), 2);
+}
+
+#[tokio::test]
+async fn a_changed_etag_downloads_the_new_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_etag("https://example.com/data", "\"v1\"");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_etag_cache()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    // The server now has a new version under a new etag.
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello again")
+        .route_etag("https://example.com/data", "\"v2\"");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_etag_cache()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello again");
+    assert_eq!(
+        std::fs::read(dir.path().join("data.etag")).unwrap(),
+        b"\"v2\""
+    );
+}
+
+#[tokio::test]
+async fn servers_without_etags_download_every_time() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    for _ in 0..2 {
+        DownloadBuilder::new("https://example.com/data", &dest, 11)
+            .with_etag_cache()
+            .download(&client, NoProgress)
+            .await
+            .unwrap();
+    }
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+    assert_eq!(client.calls().len(), 2);
+    assert!(!dir.path().join("data.etag").exists());
+}
+
+#[tokio::test]
+async fn a_mtime_check_skips_a_fresh_file() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    std::fs::write(&dest, b"hello world").unwrap();
+    let mtime = UNIX_EPOCH + Duration::from_secs(784_111_777);
+    std::fs::File::options()
+        .write(true)
+        .open(&dest)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"updated!!!!")
+        .route_not_modified_since("https://example.com/data", &fetchkit::http::http_date(mtime));
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_mtime_check()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+    assert_eq!(client.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn a_mtime_check_replaces_a_stale_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    std::fs::write(&dest, b"old content").unwrap();
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    // The mock treats any other date as modified, so the server answers
+    // with the full body and the existing file is replaced, despite the
+    // default overwrite policy.
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_mtime_check()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn a_mtime_check_without_a_local_copy_downloads() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_mtime_check()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn download_if_needed_keeps_a_valid_file() {
+    use fetchkit::download::DownloadOutcome;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    std::fs::write(&dest, b"hello world").unwrap();
+    let outcome = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .download_if_needed(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(outcome, DownloadOutcome::AlreadyValid);
+    assert!(client.calls().is_empty());
+}
+
+#[tokio::test]
+async fn download_if_needed_replaces_a_corrupt_file() {
+    use fetchkit::download::DownloadOutcome;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    // Right size, wrong content: exist() fails verification.
+    std::fs::write(&dest, b"hello-world").unwrap();
+    let outcome = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .download_if_needed(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(outcome, DownloadOutcome::Replaced);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn download_if_needed_replaces_a_truncated_file() {
+    use fetchkit::download::DownloadOutcome;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    // Wrong size: exist() reports the file as not there yet.
+    std::fs::write(&dest, b"hello").unwrap();
+    let outcome = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .download_if_needed(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(outcome, DownloadOutcome::Replaced);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn download_if_needed_fetches_a_missing_file() {
+    use fetchkit::download::DownloadOutcome;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let outcome = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .download_if_needed(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(outcome, DownloadOutcome::Downloaded);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn min_speed_aborts_a_trickling_stream() {
+    use std::time::Duration;
+
+    let chunk = bytes::Bytes::from_static(b"hel");
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::Trickle(Duration::from_millis(30), vec![chunk; 8]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .with_min_speed(10_000, Duration::from_millis(50))
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    // Retryable, so a retry policy or mirror failover can move on.
+    assert!(err.is_retryable());
+    assert!(!dest.exists());
+}
+
+#[tokio::test]
+async fn min_speed_leaves_a_fast_transfer_alone() {
+    use std::time::Duration;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_min_speed(10, Duration::from_millis(10))
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn min_speed_failures_are_retried() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use fetchkit::download::RetryPolicy;
+
+    let chunk = bytes::Bytes::from_static(b"hel");
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::Trickle(Duration::from_millis(30), vec![chunk; 8]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let retries = std::sync::Arc::new(AtomicU32::new(0));
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .with_min_speed(10_000, Duration::from_millis(50))
+        .with_retry(
+            RetryPolicy::new(2)
+                .with_base_delay(Duration::from_millis(1))
+                .on_retry({
+                    let retries = retries.clone();
+                    move |_, _| {
+                        retries.fetch_add(1, Ordering::Relaxed);
+                    }
+                }),
+        )
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    assert_eq!(retries.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn a_deadline_aborts_a_slow_download() {
+    use std::time::Duration;
+
+    let chunk = bytes::Bytes::from_static(b"hel");
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::Trickle(Duration::from_millis(30), vec![chunk; 20]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .with_deadline(Duration::from_millis(50))
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    assert!(!dest.exists());
+    assert!(!dir.path().join("data.part").exists());
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn a_deadline_cuts_retries_short() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use fetchkit::download::RetryPolicy;
+
+    let chunk = bytes::Bytes::from_static(b"hel");
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::Trickle(Duration::from_millis(30), vec![chunk; 20]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let retries = std::sync::Arc::new(AtomicU32::new(0));
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .with_deadline(Duration::from_millis(50))
+        .with_retry(
+            RetryPolicy::new(5)
+                .with_base_delay(Duration::from_millis(1))
+                .on_retry({
+                    let retries = retries.clone();
+                    move |_, _| {
+                        retries.fetch_add(1, Ordering::Relaxed);
+                    }
+                }),
+        )
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    // The budget was already spent after the first attempt.
+    assert_eq!(retries.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn a_download_within_the_deadline_succeeds() {
+    use std::time::Duration;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_deadline(Duration::from_secs(10))
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn idle_timeout_fires_when_the_stream_goes_quiet() {
+    use std::time::Duration;
+
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::ChunksThenStall(vec![bytes::Bytes::from_static(b"hel")]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .with_idle_timeout(Duration::from_millis(50))
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    assert!(err.is_retryable());
+    assert!(!dest.exists());
+    assert!(!dir.path().join("data.part").exists());
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn idle_timeout_tolerates_a_steady_stream() {
+    use std::time::Duration;
+
+    let chunk = bytes::Bytes::from_static(b"hel");
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::Trickle(Duration::from_millis(10), vec![chunk.clone(), chunk.clone(), chunk]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 9)
+        .with_idle_timeout(Duration::from_millis(500))
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"helhelhel");
+}
+
+#[tokio::test]
+async fn a_cancelled_token_stops_the_download_before_it_starts() {
+    use fetchkit::download::CancelToken;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let token = CancelToken::new();
+    token.cancel();
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_cancel_token(token)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Cancelled);
+    assert!(client.calls().is_empty());
+    assert!(!dest.exists());
+}
+
+#[tokio::test]
+async fn cancellation_interrupts_a_running_transfer() {
+    use std::time::Duration;
+
+    use fetchkit::download::CancelToken;
+
+    let chunk = bytes::Bytes::from_static(b"hel");
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::Trickle(Duration::from_millis(10), vec![chunk; 50]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let token = CancelToken::new();
+    let cancel = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        cancel.cancel();
+    });
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .with_cancel_token(token)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Cancelled);
+    assert!(!dest.exists());
+    assert!(!dir.path().join("data.part").exists());
+}
+
+#[tokio::test]
+async fn a_paused_download_parks_until_resumed() {
+    use std::time::Duration;
+
+    use fetchkit::download::{DownloadController, DownloadState};
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let controller = DownloadController::new();
+    controller.pause();
+    assert_eq!(controller.state(), DownloadState::Paused);
+    let handle = controller.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        handle.resume();
+    });
+    let progress = TestProgress::new();
+    let started = std::time::Instant::now();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_controller(controller.clone())
+        .download(&client, progress.clone())
+        .await
+        .unwrap();
+    // The transfer parked until the resume; progress only moved afterward.
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert_eq!(controller.state(), DownloadState::Running);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+    assert!(progress.finished());
+}
+
+#[tokio::test]
+async fn an_unpaused_controller_does_not_slow_the_download() {
+    use fetchkit::download::DownloadController;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_controller(DownloadController::new())
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn max_speed_paces_the_transfer() {
+    use std::time::{Duration, Instant};
+
+    // 24 bytes at 100 B/s should take about 240 ms.
+    let chunk = bytes::Bytes::from_static(b"hel");
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::Chunks(vec![chunk; 8]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let started = Instant::now();
+    DownloadBuilder::new("https://example.com/data", &dest, 24)
+        .with_max_speed(100)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(200), "{elapsed:?}");
+    assert!(elapsed < Duration::from_millis(500), "{elapsed:?}");
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn max_speed_above_the_link_rate_adds_no_latency() {
+    use std::time::{Duration, Instant};
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let started = Instant::now();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_max_speed(1_000_000)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_millis(100));
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn many_tiny_chunks_survive_the_write_buffer() {
+    let payload: Vec<u8> = (0..=255).cycle().take(4096).collect();
+    let chunks = payload
+        .chunks(1)
+        .map(bytes::Bytes::copy_from_slice)
+        .collect();
+    let client = MockClient::new().route("https://example.com/data", MockBody::Chunks(chunks));
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    // A tiny buffer forces many flush cycles mid-transfer.
+    DownloadBuilder::new("https://example.com/data", &dest, 4096)
+        .with_write_buffer(64)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), payload);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(flavor = "current_thread")]
+async fn concurrent_downloads_share_a_single_threaded_runtime() {
+    use std::time::Duration;
+
+    // With async file I/O neither download blocks the only worker thread,
+    // so the trickling transfers interleave instead of serializing.
+    let chunk = bytes::Bytes::from_static(b"hel");
+    let client = MockClient::new()
+        .route(
+            "https://example.com/a",
+            MockBody::Trickle(Duration::from_millis(10), vec![chunk.clone(); 4]),
+        )
+        .route(
+            "https://example.com/b",
+            MockBody::Trickle(Duration::from_millis(10), vec![chunk; 4]),
+        );
+    let dir = tempfile::tempdir().unwrap();
+    let dest_a = dir.path().join("a");
+    let dest_b = dir.path().join("b");
+    let (a, b) = tokio::join!(
+        DownloadBuilder::new("https://example.com/a", &dest_a, 12).download(&client, NoProgress),
+        DownloadBuilder::new("https://example.com/b", &dest_b, 12).download(&client, NoProgress),
+    );
+    a.unwrap();
+    b.unwrap();
+    assert_eq!(std::fs::read(&dest_a).unwrap(), b"helhelhelhel");
+    assert_eq!(std::fs::read(&dest_b).unwrap(), b"helhelhelhel");
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn pipelined_write_produces_the_same_file() {
+    let payload: Vec<u8> = (0..=255).cycle().take(4096).collect();
+    let chunks = payload
+        .chunks(7)
+        .map(bytes::Bytes::copy_from_slice)
+        .collect();
+    let client = MockClient::new().route("https://example.com/data", MockBody::Chunks(chunks));
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 4096)
+        .with_pipelined_write()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), payload);
+    assert!(!dir.path().join("data.part").exists());
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn pipelined_write_feeds_the_verifier() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_pipelined_write()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn sync_still_delivers_the_file() {
+    // The caches are invisible from here; this only checks the sync calls
+    // do not disturb the normal flow.
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_sync(true)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn short_stream_is_trimmed_after_preallocation() {
+    // The server announces less than expected; with the length check off
+    // the transfer proceeds, and the preallocated tail must not survive.
+    let client = MockClient::new().route_data("https://example.com/data", b"hello");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 100)
+        .skip_content_length_check()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello");
+}
+
+#[tokio::test]
+async fn skip_preallocate_still_downloads() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .skip_preallocate()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn download_report_records_the_transfer() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let report = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(report.url, "https://example.com/data");
+    assert_eq!(report.bytes, 11);
+    assert_eq!(report.retries, 0);
+    assert!(report.throughput() > 0.0);
+}
+
+#[tokio::test]
+async fn download_report_names_the_selected_mirror() {
+    let client = MockClient::new().route_data("https://mirror.example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let mirrors = ["https://mirror.example.com/data"];
+    let report = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(report.url, "https://mirror.example.com/data");
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn download_report_counts_the_retries() {
+    use std::time::Duration;
+
+    use fetchkit::download::RetryPolicy;
+
+    let chunks = b"hello world".chunks(3).map(bytes::Bytes::copy_from_slice).collect();
+    let client =
+        MockClient::new().route("https://example.com/data", MockBody::FlakyChunks(2, chunks));
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let report = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_retry(RetryPolicy::new(3).with_base_delay(Duration::from_millis(1)))
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(report.retries, 2);
+    assert_eq!(report.bytes, 11);
+}
+
+#[tokio::test]
+async fn builder_moves_into_a_spawned_task() {
+    let client =
+        std::sync::Arc::new(MockClient::new().route_data("https://example.com/data", b"hello world"));
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    // The builder owns its url and dest, so it outlives this scope.
+    let builder = DownloadBuilder::new("https://example.com/data", &dest, 11);
+    let worker = client.clone();
+    tokio::spawn(async move { builder.download(&*worker, NoProgress).await })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn new_in_dir_derives_the_name_from_the_url() {
+    let client = MockClient::new()
+        .route_data("https://example.com/v1/hello%20world.txt?token=abc", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("downloads");
+    let builder =
+        DownloadBuilder::new_in_dir("https://example.com/v1/hello%20world.txt?token=abc", &nested, 11)
+            .unwrap();
+    assert_eq!(builder.dest(), nested.join("hello world.txt"));
+    let report = builder.download(&client, NoProgress).await.unwrap();
+    assert_eq!(report.dest, nested.join("hello world.txt"));
+    assert_eq!(std::fs::read(report.dest).unwrap(), b"hello world");
+}
+
+#[test]
+fn new_in_dir_rejects_unusable_names() {
+    let dir = tempfile::tempdir().unwrap();
+    for url in [
+        "https://example.com/",
+        "https://example.com/dir/?query",
+        "https://example.com/%2e%2e",
+        "https://example.com/a%2fb",
+        "https://example.com/bad%zz",
+    ] {
+        assert!(DownloadBuilder::new_in_dir(url, dir.path(), 0).is_err(), "{url}");
+    }
+}
+
+#[tokio::test]
+async fn content_disposition_names_the_file() {
+    let client = MockClient::new()
+        .route_data("https://example.com/assets/12345", b"hello world")
+        .route_content_disposition(
+            "https://example.com/assets/12345",
+            "attachment; filename=\"release notes.txt\"",
+        );
+    let dir = tempfile::tempdir().unwrap();
+    let report = DownloadBuilder::new_in_dir("https://example.com/assets/12345", dir.path(), 11)
+        .unwrap()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(report.dest, dir.path().join("release notes.txt"));
+    assert_eq!(std::fs::read(report.dest).unwrap(), b"hello world");
+    assert!(!dir.path().join("12345").exists());
+}
+
+#[tokio::test]
+async fn content_disposition_prefers_the_utf8_form() {
+    let client = MockClient::new()
+        .route_data("https://example.com/assets/12345", b"hello world")
+        .route_content_disposition(
+            "https://example.com/assets/12345",
+            "attachment; filename=\"fallback.txt\"; filename*=UTF-8''na%C3%AFve.txt",
+        );
+    let dir = tempfile::tempdir().unwrap();
+    let report = DownloadBuilder::new_in_dir("https://example.com/assets/12345", dir.path(), 11)
+        .unwrap()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(report.dest, dir.path().join("naïve.txt"));
+}
+
+#[tokio::test]
+async fn unsafe_content_disposition_falls_back_to_the_url_name() {
+    let client = MockClient::new()
+        .route_data("https://example.com/assets/12345", b"hello world")
+        .route_content_disposition(
+            "https://example.com/assets/12345",
+            "attachment; filename=\"../escape.txt\"",
+        );
+    let dir = tempfile::tempdir().unwrap();
+    let report = DownloadBuilder::new_in_dir("https://example.com/assets/12345", dir.path(), 11)
+        .unwrap()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(report.dest, dir.path().join("12345"));
+    assert_eq!(std::fs::read(report.dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn content_disposition_is_ignored_for_an_explicit_dest() {
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_content_disposition("https://example.com/data", "attachment; filename=other.txt");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let report = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(report.dest, dest);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn keep_partial_preserves_the_part_file() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .with_keep_partial()
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    // The rejected data survives next to the untouched destination.
+    assert!(!dest.exists());
+    assert_eq!(
+        std::fs::read(dir.path().join("data.part")).unwrap(),
+        b"hello world"
+    );
+}
+
+#[tokio::test]
+async fn custom_part_suffix_names_the_partial_file() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .with_part_suffix(".download")
+        .with_keep_partial()
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        std::fs::read(dir.path().join("data.download")).unwrap(),
+        b"hello world"
+    );
+    assert!(!dir.path().join("data.part").exists());
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn shared_rate_limiter_caps_combined_throughput() {
+    use fetchkit::download::RateLimiter;
+
+    let body: Vec<u8> = (0..4000u32).map(|i| i as u8).collect();
+    let chunks: Vec<bytes::Bytes> = body.chunks(500).map(bytes::Bytes::copy_from_slice).collect();
+    let client = MockClient::new()
+        .route("https://example.com/a", MockBody::Chunks(chunks.clone()))
+        .route("https://example.com/b", MockBody::Chunks(chunks));
+    let dir = tempfile::tempdir().unwrap();
+    let limiter = RateLimiter::new(16_000);
+
+    // 8000 bytes through a 16 kB/s shared budget: the first chunk of each
+    // stream passes for free, the remaining ~7000 bytes take ~0.44 s.
+    let started = std::time::Instant::now();
+    let a = DownloadBuilder::new("https://example.com/a", dir.path().join("a"), 4000)
+        .with_rate_limiter(limiter.clone())
+        .download(&client, NoProgress);
+    let b = DownloadBuilder::new("https://example.com/b", dir.path().join("b"), 4000)
+        .with_rate_limiter(limiter.clone())
+        .download(&client, NoProgress);
+    let (a, b) = tokio::join!(a, b);
+    a.unwrap();
+    b.unwrap();
+    assert!(started.elapsed() >= std::time::Duration::from_millis(400));
+    assert_eq!(std::fs::read(dir.path().join("a")).unwrap(), body);
+    assert_eq!(std::fs::read(dir.path().join("b")).unwrap(), body);
+}
+
+#[tokio::test]
+async fn fetch_bytes_returns_verified_content() {
+    let client = MockClient::new().route_data("https://example.com/data.sha256", b"hello world");
+    let bytes = fetchkit::download::fetch_bytes(
+        &client,
+        "https://example.com/data.sha256",
+        1024,
+        Some(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(&bytes[..], b"hello world");
+}
+
+#[tokio::test]
+async fn fetch_bytes_enforces_the_size_cap() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let err = fetchkit::download::fetch_bytes::<_, Sha256VerifierBuilder>(
+        &client,
+        "https://example.com/data",
+        10,
+        None,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+}
+
+#[tokio::test]
+async fn a_prepared_receiver_is_reused_across_downloads() {
+    use fetchkit::progress::Prepared;
+
+    let client = MockClient::new()
+        .route_data("https://example.com/a", b"hello world")
+        .route_data("https://example.com/b", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let progress = TestProgress::new();
+    // One pre-created receiver observes both downloads in turn.
+    for url in ["https://example.com/a", "https://example.com/b"] {
+        DownloadBuilder::new(url, dir.path().join(url.rsplit('/').next().unwrap()), 11)
+            .download(&client, Prepared(progress.clone()))
+            .await
+            .unwrap();
+    }
+    assert_eq!(progress.total(), Some(11));
+    assert_eq!(
+        progress.messages(),
+        ["https://example.com/a", "https://example.com/b"]
+    );
+    assert_eq!(progress.terminal_calls(), 2);
+}
+
+#[tokio::test]
+async fn exist_with_progress_reports_the_verification() {
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    std::fs::write(&dest, b"hello world").unwrap();
+    let progress = TestProgress::new();
+    let valid = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_progress_throttle(Duration::ZERO)
+        .exist_with_progress(progress.clone())
+        .unwrap();
+    assert!(valid);
+    assert_eq!(progress.total(), Some(11));
+    assert_eq!(progress.positions().last(), Some(&11));
+    assert!(progress.finished());
+    assert_eq!(progress.terminal_calls(), 1);
+}
+
+#[tokio::test]
+async fn exist_with_progress_resolves_for_a_missing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let progress = TestProgress::new();
+    let valid = DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .exist_with_progress(progress.clone())
+        .unwrap();
+    assert!(!valid);
+    assert_eq!(progress.total(), None);
+    assert!(progress.finished());
+}
+
+#[tokio::test]
+async fn checksum_sidecar_configures_the_verifier() {
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_data(
+            "https://example.com/data.sha256",
+            format!("{HELLO_WORLD_SHA256}  data\n").as_bytes(),
+        );
+    let dir = tempfile::tempdir().unwrap();
+    DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_checksum_sidecar("sha256")
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn checksum_sidecar_rejects_a_mismatch() {
+    // The digest of "bye", not of the served body.
+    let wrong = "b49f425a7e1f9cff3856329ada223f2f9d368f15a00cf48df16ca95986137fe8";
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_data("https://example.com/data.sha256", wrong.as_bytes());
+    let dir = tempfile::tempdir().unwrap();
+    let err = DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_checksum_sidecar("sha256")
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+}
+
+#[tokio::test]
+async fn a_missing_sidecar_fails_unless_allowed() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let err = DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_checksum_sidecar("sha256")
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert!(
+        err.description()
+            .unwrap()
+            .contains("https://example.com/data.sha256")
+    );
+
+    DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_checksum_sidecar("sha256")
+        .allow_missing_sidecar()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn the_sidecar_is_fetched_from_the_selected_mirror() {
+    // The sidecar only exists next to the mirror's copy, not the primary's.
+    let client = MockClient::new()
+        .route_data("https://mirror.example.com/data", b"hello world")
+        .route_data(
+            "https://mirror.example.com/data.sha256",
+            HELLO_WORLD_SHA256.as_bytes(),
+        );
+    let dir = tempfile::tempdir().unwrap();
+    let mirrors = ["https://mirror.example.com/data"];
+    DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .with_checksum_sidecar("sha256")
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn a_fetched_manifest_verifies_its_entries() {
+    use fetchkit::verify::checksum::ChecksumFile;
+    use fetchkit::verify::hash::HashAlgorithm;
+
+    let sums = format!("{HELLO_WORLD_SHA256}  data\nSHA256 (other) = {HELLO_WORLD_SHA256}\n");
+    let client = MockClient::new()
+        .route_data("https://example.com/SHA256SUMS", sums.as_bytes())
+        .route_data("https://example.com/data", b"hello world")
+        .route_data("https://example.com/other", b"hello world");
+    let manifest = ChecksumFile::fetch(&client, HashAlgorithm::Sha256, "https://example.com/SHA256SUMS")
+        .await
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["data", "other"] {
+        DownloadBuilder::new(format!("https://example.com/{name}"), dir.path().join(name), 11)
+            .with_manifest(&manifest)
+            .unwrap()
+            .download(&client, NoProgress)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn a_file_missing_from_the_manifest_fails_clearly() {
+    use fetchkit::verify::checksum::ChecksumFile;
+    use fetchkit::verify::hash::HashAlgorithm;
+
+    let sums = format!("{HELLO_WORLD_SHA256}  data\n");
+    let client = MockClient::new()
+        .route_data("https://example.com/SHA256SUMS", sums.as_bytes())
+        .route_data("https://example.com/unlisted", b"hello world");
+    let manifest = ChecksumFile::fetch(&client, HashAlgorithm::Sha256, "https://example.com/SHA256SUMS")
+        .await
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let err = DownloadBuilder::new(
+        "https://example.com/unlisted",
+        dir.path().join("unlisted"),
+        11,
+    )
+    .with_manifest(&manifest)
+    .unwrap()
+    .download(&client, NoProgress)
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    assert!(
+        err.description()
+            .unwrap()
+            .contains("unlisted is not listed in checksum file SHA256SUMS")
+    );
+}
+
+#[tokio::test]
+async fn file_urls_stream_from_disk() {
+    // No routes: a file URL must never reach the client.
+    let client = MockClient::new();
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("source");
+    std::fs::write(&source, b"hello world").unwrap();
+    let dest = dir.path().join("data");
+    let url = format!("file://{}", source.display());
+    DownloadBuilder::new(&url, &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn file_url_paths_are_percent_decoded() {
+    let client = MockClient::new();
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("hello world"), b"hello world").unwrap();
+    let dest = dir.path().join("data");
+    let url = format!("file://{}/hello%20world", dir.path().display());
+    DownloadBuilder::new(&url, &dest, 11)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn a_file_url_with_a_remote_host_is_rejected() {
+    let client = MockClient::new();
+    let dir = tempfile::tempdir().unwrap();
+    let err = DownloadBuilder::new("file://somehost/data", dir.path().join("data"), 0)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("file URLs with a remote host are not supported")
+    );
+}
+
+#[tokio::test]
+async fn custom_headers_are_sent_with_the_request() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_header("Authorization", "Bearer token")
+        .with_header("Accept", "application/octet-stream")
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    let headers = client.headers();
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers[0].0, "https://example.com/data");
+    assert_eq!(
+        headers[0].1,
+        [
+            ("Authorization".to_owned(), "Bearer token".to_owned()),
+            ("Accept".to_owned(), "application/octet-stream".to_owned()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn custom_headers_reach_the_mirror_probes_and_sidecar() {
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_data(
+            "https://example.com/data.sha256",
+            format!("{HELLO_WORLD_SHA256}  data\n").as_bytes(),
+        );
+    let dir = tempfile::tempdir().unwrap();
+    let mirrors = ["https://mirror.example.com/data"];
+    DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .with_checksum_sidecar("sha256")
+        .with_header("Authorization", "Bearer token")
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    let auth = ("Authorization".to_owned(), "Bearer token".to_owned());
+    let headers = client.headers();
+    // Both probes, the sidecar fetch and the transfer carry the header.
+    for url in [
+        "https://example.com/data",
+        "https://mirror.example.com/data",
+        "https://example.com/data.sha256",
+    ] {
+        assert!(
+            headers.iter().any(|(u, h)| u == url && h.contains(&auth)),
+            "no authorized request to {url}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn a_raw_error_status_is_not_saved_to_disk() {
+    use bytes::Bytes;
+
+    // A client that does not map statuses itself hands the error page on;
+    // the download must reject it instead of saving it.
+    let client = MockClient::new().route(
+        "https://example.com/data",
+        MockBody::RawStatus(404, vec![Bytes::from_static(b"<html>not here</html>")]),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 0)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Network);
+    assert_eq!(err.http_status(), Some(404));
+    assert!(err.to_string().contains("not here"));
+    assert!(!dest.exists());
+}
+
+#[tokio::test]
+async fn a_raw_error_status_disqualifies_a_mirror() {
+    let client = MockClient::new()
+        .route(
+            "https://example.com/data",
+            MockBody::RawStatus(503, Vec::new()),
+        )
+        .route_data("https://mirror.example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let mirrors = ["https://mirror.example.com/data"];
+    let report = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(report.url, "https://mirror.example.com/data");
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn a_permanent_error_is_not_retried() {
+    use fetchkit::download::RetryPolicy;
+
+    let client = MockClient::new().route("https://example.com/data", MockBody::Status(404));
+    let dir = tempfile::tempdir().unwrap();
+    let err = DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_retry(RetryPolicy::new(3))
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.http_status(), Some(404));
+    // A 404 will not fix itself; exactly one request was made.
+    assert_eq!(client.calls().len(), 1);
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn a_custom_classifier_overrides_the_heuristic() {
+    use bytes::Bytes;
+    use fetchkit::download::RetryPolicy;
+
+    // A stream error is retryable by default; the classifier vetoes it.
+    let chunks = b"hello world".chunks(3).map(Bytes::copy_from_slice).collect();
+    let client =
+        MockClient::new().route("https://example.com/data", MockBody::FlakyChunks(2, chunks));
+    let dir = tempfile::tempdir().unwrap();
+    let err = DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_retry(RetryPolicy::new(3).retry_if(|_| false))
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Network);
+    assert_eq!(client.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn a_permanent_probe_error_stops_mirror_selection() {
+    // 403 on the primary means the mirrors would reject us too; the
+    // mirror is never probed.
+    let client = MockClient::new()
+        .route("https://example.com/data", MockBody::Status(403))
+        .route_data("https://mirror.example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let mirrors = ["https://mirror.example.com/data"];
+    let err = DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.http_status(), Some(403));
+    assert_eq!(client.calls(), ["https://example.com/data"]);
+}
+
+#[tokio::test]
+async fn a_decoded_response_skips_the_length_fast_fail() {
+    // The header describes the compressed wire bytes; the client streams
+    // the decompressed data.
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_wire_length("https://example.com/data", 7)
+        .route_decoded("https://example.com/data");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+
+    // Without the decoded marker the mismatching header is still a
+    // fast-fail.
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_wire_length("https://example.com/data", 7);
+    let err = DownloadBuilder::new("https://example.com/data", dir.path().join("data2"), 11)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+}
+
+#[tokio::test]
+async fn identity_encoding_is_a_request_header() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .request_identity_encoding()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    let headers = client.headers();
+    assert_eq!(headers.len(), 1);
+    assert!(headers[0]
+        .1
+        .contains(&("Accept-Encoding".to_owned(), "identity".to_owned())));
+}
+
+#[tokio::test]
+async fn a_server_digest_verifies_the_body() {
+    // base64 of the sha256 of "hello world".
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_digest(
+            "https://example.com/data",
+            "sha-256=uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=",
+        );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .verify_server_digest()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn a_mismatching_server_digest_fails_the_download() {
+    // base64 of the sha256 of something else.
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_digest(
+            "https://example.com/data",
+            "sha-256=AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=",
+        );
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .verify_server_digest()
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    assert!(!dest.exists());
+}
+
+#[tokio::test]
+async fn an_unusable_server_digest_is_ignored() {
+    for value in ["sha-256=!!!not-base64!!!", "whirlpool=AAAA", "garbage"] {
+        let client = MockClient::new()
+            .route_data("https://example.com/data", b"hello world")
+            .route_digest("https://example.com/data", value);
+        let dir = tempfile::tempdir().unwrap();
+        DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+            .verify_server_digest()
+            .download(&client, NoProgress)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn an_explicit_verifier_outranks_the_server_digest() {
+    // The server's digest matches the body, but the pinned checksum does
+    // not — the pin must win.
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_digest(
+            "https://example.com/data",
+            "sha-256=uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=",
+        );
+    let dir = tempfile::tempdir().unwrap();
+    let err = DownloadBuilder::new("https://example.com/data", dir.path().join("data"), 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .verify_server_digest()
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+}
+
+#[cfg(any(feature = "tokio", feature = "smol"))]
+#[tokio::test]
+async fn exist_async_matches_the_sync_verdict() {
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    std::fs::write(&dest, b"hello world").unwrap();
+
+    let valid = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap());
+    assert!(valid.exist_async().await.unwrap());
+    assert!(valid.exist().unwrap());
+
+    let mismatch = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap());
+    assert_eq!(
+        mismatch.exist_async().await.unwrap_err().kind(),
+        ErrorKind::Verify
+    );
+    assert_eq!(mismatch.exist().unwrap_err().kind(), ErrorKind::Verify);
+
+    let missing = DownloadBuilder::new("https://example.com/data", dir.path().join("gone"), 11);
+    assert!(!missing.exist_async().await.unwrap());
+    assert!(!missing.exist().unwrap());
+}
+
+#[tokio::test]
+async fn status_distinguishes_the_failure_modes() {
+    use fetchkit::download::FileStatus;
+
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+
+    let builder = || {
+        DownloadBuilder::new("https://example.com/data", &dest, 11)
+            .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+    };
+    assert_eq!(builder().status().unwrap(), FileStatus::Missing);
+
+    std::fs::write(&dest, b"hello").unwrap();
+    assert_eq!(
+        builder().status().unwrap(),
+        FileStatus::SizeMismatch {
+            expected: 11,
+            actual: 5
+        }
+    );
+
+    std::fs::write(&dest, b"hello earth").unwrap();
+    assert_eq!(builder().status().unwrap(), FileStatus::VerifyFailed);
+
+    std::fs::write(&dest, b"hello world").unwrap();
+    assert_eq!(builder().status().unwrap(), FileStatus::Valid);
+    assert!(builder().exist().unwrap());
+}
+
+#[tokio::test]
+async fn after_download_mode_verifies_the_finished_file() {
+    use fetchkit::download::VerificationMode;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_verification_mode(VerificationMode::AfterDownload)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn after_download_mode_rejects_a_bad_digest() {
+    use fetchkit::download::VerificationMode;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .with_verification_mode(VerificationMode::AfterDownload)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    // Neither the destination nor the part file survives the failure.
+    assert!(!dest.exists());
+    assert!(!dest.with_file_name("data.part").exists());
+}
+
+#[tokio::test]
+async fn the_after_download_pass_is_its_own_phase() {
+    use fetchkit::download::VerificationMode;
+    use fetchkit::progress::Phase;
+
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let phases = TestPhases::new();
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+        .with_verification_mode(VerificationMode::AfterDownload)
+        .download_phased(&client, &phases)
+        .await
+        .unwrap();
+    assert_eq!(phases.phases(), [Phase::Downloading, Phase::Verifying]);
+    // The re-read knows the file size, so the phase has a total and the
+    // positions walk up to it.
+    assert_eq!(phases.progress().total(), Some(11));
+    assert_eq!(phases.progress().positions().last(), Some(&11));
+}
+
+#[cfg(feature = "metalink")]
+#[tokio::test]
+async fn a_metalink_document_drives_a_download() {
+    use fetchkit::metalink::Metalink;
+
+    let document = format!(
+        r#"<metalink xmlns="urn:ietf:params:xml:ns:metalink">
+  <file name="data">
+    <size>11</size>
+    <hash type="sha-256">{HELLO_WORLD_SHA256}</hash>
+    <url>https://example.com/data</url>
+    <url>https://mirror.example.com/data</url>
+  </file>
+</metalink>"#
+    );
+    let metalink = Metalink::parse(&document).unwrap();
+    let client = MockClient::new()
+        .route_data("https://example.com/data", b"hello world")
+        .route_data("https://mirror.example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::from_metalink(&metalink, &dest)
+        .unwrap()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+
+    // The hash from the document is enforced.
+    let bad = Metalink {
+        hashes: vec![("sha-256".to_owned(), "0".repeat(64))],
+        ..metalink
+    };
+    let err = DownloadBuilder::from_metalink(&bad, dir.path().join("bad"))
+        .unwrap()
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+}
+
+#[tokio::test]
+async fn mirror_bases_are_joined_with_the_path() {
+    let primary = "https://example.com/pub/tool/data";
+    let client = MockClient::new()
+        .route_data(primary, b"hello world")
+        .route_data("https://mirror-a.example.com/pub/tool/data", b"hello world")
+        .route_data("https://mirror-b.example.com/pub/tool/data", b"hello world")
+        .route_data(
+            "https://mirror-c.example.com/pub/tool/data?token=x",
+            b"hello world",
+        )
+        .route_data(
+            "https://mirror-d.example.com/get?f=tool/data",
+            b"hello world",
+        );
+    let bases = [
+        // Trailing slash on the base, leading slash on the path: one
+        // slash survives the join.
+        "https://mirror-a.example.com/pub/",
+        "https://mirror-b.example.com/pub",
+        // A query string stays at the end of the joined URL.
+        "https://mirror-c.example.com/pub?token=x",
+        // A placeholder puts the path wherever the mirror wants it.
+        "https://mirror-d.example.com/get?f={path}",
+    ];
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    DownloadBuilder::new(primary, &dest, 11)
+        .with_mirrors(MirrorOptions::from_bases(&bases, "/tool/data"))
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+    // Every base was joined into exactly the URL the mirror serves.
+    let probed: std::collections::HashSet<String> = client.calls().into_iter().collect();
+    let expected = [
+        primary,
+        "https://mirror-a.example.com/pub/tool/data",
+        "https://mirror-b.example.com/pub/tool/data",
+        "https://mirror-c.example.com/pub/tool/data?token=x",
+        "https://mirror-d.example.com/get?f=tool/data",
+    ];
+    assert_eq!(probed, expected.map(str::to_owned).into_iter().collect());
+}
+
+#[tokio::test]
+async fn the_space_check_fails_fast_on_a_full_disk() {
+    let client = MockClient::new().route_data("https://example.com/data", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("data");
+    let err = DownloadBuilder::new("https://example.com/data", &dest, u64::MAX / 2)
+        .with_space_check(0.05)
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Io);
+    let message = err.to_string();
+    assert!(message.contains("not enough disk space"));
+    assert!(message.contains("bytes required"));
+    // Nothing was fetched and no part file was created.
+    assert!(client.calls().is_empty());
+    assert!(!dest.with_file_name("data.part").exists());
+
+    // With a realistic size the check passes and the download runs.
+    DownloadBuilder::new("https://example.com/data", &dest, 11)
+        .with_space_check(0.05)
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
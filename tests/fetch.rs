@@ -0,0 +1,324 @@
+#![cfg(all(feature = "tar", feature = "sha2"))]
+
+mod common;
+
+use std::path::Path;
+
+use common::{MockClient, TestPhases, TestProgress};
+use fetchkit::download::MirrorOptions;
+use fetchkit::progress::Phase;
+use fetchkit::verify::hash::Sha256VerifierBuilder;
+use fetchkit::{fetch, ErrorKind, FetchSpec, Fetcher};
+use sha2::{Digest, Sha256};
+
+/// A small tar archive with `bin/tool` and `doc/README`.
+fn tar_bytes() -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(5);
+    header.set_mode(0o755);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "bin/tool", &b"tool\n"[..])
+        .unwrap();
+    let mut header = tar::Header::new_gnu();
+    header.set_size(7);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "doc/README", &b"readme\n"[..])
+        .unwrap();
+    builder.into_inner().unwrap()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+#[tokio::test]
+async fn fetch_downloads_verifies_and_extracts() {
+    let archive = tar_bytes();
+    let client = MockClient::new().route_data("https://example.com/tool.tar", &archive);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("out");
+    let phases = TestPhases::new();
+    let report = Fetcher::new("https://example.com/tool.tar", &dest, archive.len() as u64)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&sha256_hex(&archive)).unwrap())
+        .run(&client, &phases)
+        .await
+        .unwrap();
+    assert!(report.downloaded);
+    assert_eq!(report.archive, None);
+    assert_eq!(report.extract.files.len(), 2);
+    assert!(dest.join("bin/tool").is_file());
+    assert!(dest.join("doc/README").is_file());
+    // The archive was placed next to the destination and cleaned up.
+    assert!(!dir.path().join("tool.tar").exists());
+    assert_eq!(
+        phases.phases(),
+        [Phase::Downloading, Phase::Verifying, Phase::Extracting]
+    );
+}
+
+#[tokio::test]
+async fn fetch_with_mirrors_and_mapper() {
+    let archive = tar_bytes();
+    let client = MockClient::new()
+        .route_data("https://example.com/tool.tar", &archive)
+        .route_data("https://mirror.example.com/tool.tar", &archive);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("out");
+    let mirrors = ["https://mirror.example.com/tool.tar"];
+    let report = Fetcher::new("https://example.com/tool.tar", &dest, archive.len() as u64)
+        .with_mirrors(MirrorOptions::new(&mirrors))
+        .with_mapper(|path| path.strip_prefix("bin").ok().map(Path::to_path_buf))
+        .run(&client, &TestPhases::new())
+        .await
+        .unwrap();
+    assert_eq!(report.extract.files, [Path::new("tool")]);
+    assert!(dest.join("tool").is_file());
+    assert!(!dest.join("doc").exists());
+}
+
+#[tokio::test]
+async fn fetch_verify_failure_removes_the_archive() {
+    let archive = tar_bytes();
+    let client = MockClient::new().route_data("https://example.com/tool.tar", &archive);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("out");
+    let err = Fetcher::new("https://example.com/tool.tar", &dest, archive.len() as u64)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+        .run(&client, &TestPhases::new())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    assert!(!dir.path().join("tool.tar").exists());
+    assert!(!dest.exists());
+}
+
+#[tokio::test]
+async fn fetch_extract_failure_keeps_the_archive() {
+    // Valid download and digest, but the content is not a tar archive.
+    let junk = b"this is not a tar archive".to_vec();
+    let client = MockClient::new().route_data("https://example.com/tool.tar", &junk);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("out");
+    let err = Fetcher::new("https://example.com/tool.tar", &dest, junk.len() as u64)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&sha256_hex(&junk)).unwrap())
+        .run(&client, &TestPhases::new())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Extract);
+    // The verified archive is kept so a rerun skips the download.
+    assert!(dir.path().join("tool.tar").exists());
+}
+
+#[tokio::test]
+async fn fetch_skips_download_of_an_existing_archive() {
+    let archive = tar_bytes();
+    // No route: any request would fail, proving nothing was downloaded.
+    let client = MockClient::new();
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("out");
+    let archive_path = dir.path().join("tool.tar");
+    std::fs::write(&archive_path, &archive).unwrap();
+    let report = Fetcher::new("https://example.com/tool.tar", &dest, archive.len() as u64)
+        .with_verifier(Sha256VerifierBuilder::from_hex(&sha256_hex(&archive)).unwrap())
+        .keep_archive(true)
+        .run(&client, &TestPhases::new())
+        .await
+        .unwrap();
+    assert!(!report.downloaded);
+    assert_eq!(report.archive.as_deref(), Some(archive_path.as_path()));
+    assert!(archive_path.exists());
+    assert!(dest.join("bin/tool").is_file());
+}
+
+#[tokio::test]
+async fn fetch_reports_phase_progress() {
+    let archive = tar_bytes();
+    let client = MockClient::new().route_data("https://example.com/tool.tar", &archive);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("out");
+    let phases = TestPhases::new();
+    Fetcher::new("https://example.com/tool.tar", &dest, archive.len() as u64)
+        .run(&client, &phases)
+        .await
+        .unwrap();
+    let progress: &TestProgress = phases.progress();
+    // Downloading and Extracting both resolved their receivers.
+    assert_eq!(progress.terminal_calls(), 2);
+}
+
+#[tokio::test]
+async fn one_shot_fetch_extracts_with_checksum() {
+    let archive = tar_bytes();
+    let client = MockClient::new().route_data("https://example.com/tool.tar", &archive);
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("out");
+    let report = fetch(
+        &client,
+        "https://example.com/tool.tar",
+        FetchSpec {
+            dest_dir: dest.clone(),
+            checksum: Some(format!("sha256:{}", sha256_hex(&archive))),
+            strip_components: 1,
+            size: archive.len() as u64,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert!(report.downloaded);
+    // strip_components flattened bin/tool and doc/README.
+    assert!(dest.join("tool").is_file());
+    assert!(dest.join("README").is_file());
+    assert!(!dir.path().join("tool.tar").exists());
+}
+
+#[tokio::test]
+async fn one_shot_fetch_rejects_a_bad_checksum() {
+    let archive = tar_bytes();
+    let client = MockClient::new().route_data("https://example.com/tool.tar", &archive);
+    let dir = tempfile::tempdir().unwrap();
+    let err = fetch(
+        &client,
+        "https://example.com/tool.tar",
+        FetchSpec {
+            dest_dir: dir.path().join("out"),
+            checksum: Some(format!("sha256:{}", "0".repeat(64))),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    assert!(!dir.path().join("tool.tar").exists());
+}
+
+#[tokio::test]
+async fn one_shot_fetch_downloads_plain_files() {
+    let client = MockClient::new().route_data("https://example.com/tool.txt", b"plain");
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("out");
+    let spec = || FetchSpec {
+        dest_dir: dest.clone(),
+        checksum: Some(format!("sha256:{}", sha256_hex(b"plain"))),
+        ..Default::default()
+    };
+    let report = fetch(&client, "https://example.com/tool.txt", spec())
+        .await
+        .unwrap();
+    assert!(report.downloaded);
+    assert_eq!(report.extract.files, [Path::new("tool.txt")]);
+    assert_eq!(std::fs::read(dest.join("tool.txt")).unwrap(), b"plain");
+    // A second fetch finds the valid file and skips the download.
+    let report = fetch(&client, "https://example.com/tool.txt", spec())
+        .await
+        .unwrap();
+    assert!(!report.downloaded);
+}
+
+// `download_and_extract` needs a runtime feature for its blocking pool.
+#[cfg(any(feature = "tokio", feature = "smol"))]
+mod streaming {
+    use fetchkit::download::DownloadBuilder;
+    use fetchkit::extract::{ArchiveFormat, ExtractOptions};
+    use fetchkit::progress::NoProgress;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn download_and_extract_skips_the_archive_file() {
+        let archive = tar_bytes();
+        let client = MockClient::new().route_data("https://example.com/tool.tar", &archive);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("out");
+        let report = DownloadBuilder::new("https://example.com/tool.tar", &dest, 0)
+            .with_verifier(Sha256VerifierBuilder::from_hex(&sha256_hex(&archive)).unwrap())
+            .download_and_extract(
+                &client,
+                ArchiveFormat::Tar,
+                ExtractOptions::new(&dest),
+                NoProgress,
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.files.len(), 2);
+        assert!(dest.join("bin/tool").is_file());
+        // Only the extracted tree exists; no archive or part file was
+        // written next to it.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_mapper_filters_the_streamed_entries() {
+        let archive = tar_bytes();
+        let client = MockClient::new().route_data("https://example.com/tool.tar", &archive);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("out");
+        let options = ExtractOptions::new(&dest)
+            .with_mapper(|path| path.strip_prefix("bin").ok().map(Path::to_path_buf));
+        let report = DownloadBuilder::new("https://example.com/tool.tar", &dest, 0)
+            .download_and_extract(&client, ArchiveFormat::Tar, options, NoProgress)
+            .await
+            .unwrap();
+        assert_eq!(report.files, [Path::new("tool")]);
+        assert!(!dest.join("doc").exists());
+    }
+
+    #[tokio::test]
+    async fn a_bad_digest_still_fails_the_pipeline() {
+        let archive = tar_bytes();
+        let client = MockClient::new().route_data("https://example.com/tool.tar", &archive);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("out");
+        let err = DownloadBuilder::new("https://example.com/tool.tar", &dest, 0)
+            .with_verifier(Sha256VerifierBuilder::from_hex(&"0".repeat(64)).unwrap())
+            .download_and_extract(
+                &client,
+                ArchiveFormat::Tar,
+                ExtractOptions::new(&dest),
+                NoProgress,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+    }
+
+    #[tokio::test]
+    async fn garbage_surfaces_the_extraction_error() {
+        let client = MockClient::new().route_data("https://example.com/tool.tar", &[0xffu8; 4096]);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("out");
+        let err = DownloadBuilder::new("https://example.com/tool.tar", &dest, 0)
+            .download_and_extract(
+                &client,
+                ArchiveFormat::Tar,
+                ExtractOptions::new(&dest),
+                NoProgress,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Extract);
+    }
+
+    #[cfg(feature = "zip")]
+    #[tokio::test]
+    async fn zip_archives_are_rejected_up_front() {
+        let client = MockClient::new().route_data("https://example.com/tool.zip", b"PK");
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("out");
+        let err = DownloadBuilder::new("https://example.com/tool.zip", &dest, 0)
+            .download_and_extract(
+                &client,
+                ArchiveFormat::Zip,
+                ExtractOptions::new(&dest),
+                NoProgress,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Extract);
+        assert!(err.description().unwrap().contains("random access"));
+    }
+}
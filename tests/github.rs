@@ -0,0 +1,133 @@
+#![cfg(all(feature = "github", feature = "sha2"))]
+
+mod common;
+
+use common::{MockBody, MockClient};
+use fetchkit::github::Release;
+use fetchkit::progress::NoProgress;
+use fetchkit::ErrorKind;
+
+// sha256 of "hello world"
+const HELLO_WORLD_SHA256: &str =
+    "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+fn release_json() -> String {
+    format!(
+        r#"{{
+            "tag_name": "v1.2.3",
+            "name": "Release 1.2.3",
+            "prerelease": false,
+            "html_url": "https://github.com/acme/tool/releases/tag/v1.2.3",
+            "assets": [
+                {{
+                    "name": "tool-x86_64-linux.tar.gz",
+                    "size": 11,
+                    "browser_download_url": "https://example.com/tool-x86_64-linux.tar.gz",
+                    "content_type": "application/gzip",
+                    "digest": "sha256:{HELLO_WORLD_SHA256}"
+                }},
+                {{
+                    "name": "tool-aarch64-darwin.tar.gz",
+                    "size": 2048,
+                    "browser_download_url": "https://example.com/tool-aarch64-darwin.tar.gz"
+                }}
+            ]
+        }}"#
+    )
+}
+
+#[tokio::test]
+async fn fetches_and_parses_a_release() {
+    let client = MockClient::new().route_data(
+        "https://api.github.com/repos/acme/tool/releases/tags/v1.2.3",
+        release_json().as_bytes(),
+    );
+    let release = Release::fetch(&client, "acme", "tool", Some("v1.2.3"))
+        .await
+        .unwrap();
+    assert_eq!(release.tag_name, "v1.2.3");
+    assert_eq!(release.name.as_deref(), Some("Release 1.2.3"));
+    assert_eq!(release.assets.len(), 2);
+    let asset = release.asset("tool-x86_64-linux.tar.gz").unwrap();
+    assert_eq!(asset.size, 11);
+    assert_eq!(asset.content_type.as_deref(), Some("application/gzip"));
+    let darwin = release
+        .find_asset(|a| a.name.contains("aarch64-darwin"))
+        .unwrap();
+    assert_eq!(darwin.digest, None);
+}
+
+#[tokio::test]
+async fn latest_release_uses_the_latest_endpoint() {
+    let client = MockClient::new().route_data(
+        "https://api.github.com/repos/acme/tool/releases/latest",
+        release_json().as_bytes(),
+    );
+    let release = Release::fetch(&client, "acme", "tool", None).await.unwrap();
+    assert_eq!(release.tag_name, "v1.2.3");
+}
+
+#[tokio::test]
+async fn missing_release_is_not_found() {
+    let client = MockClient::new().route(
+        "https://api.github.com/repos/acme/tool/releases/tags/v9.9.9",
+        MockBody::Status(404),
+    );
+    let err = Release::fetch(&client, "acme", "tool", Some("v9.9.9"))
+        .await
+        .unwrap_err();
+    assert!(err.is_not_found());
+}
+
+#[tokio::test]
+async fn malformed_json_is_an_error() {
+    let client = MockClient::new().route_data(
+        "https://api.github.com/repos/acme/tool/releases/latest",
+        b"not json",
+    );
+    let err = Release::fetch(&client, "acme", "tool", None).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert!(err.url().is_some());
+}
+
+#[tokio::test]
+async fn asset_bridges_to_a_verified_download() {
+    let client = MockClient::new()
+        .route_data(
+            "https://api.github.com/repos/acme/tool/releases/latest",
+            release_json().as_bytes(),
+        )
+        .route_data("https://example.com/tool-x86_64-linux.tar.gz", b"hello world");
+    let release = Release::fetch(&client, "acme", "tool", None).await.unwrap();
+    let asset = release.asset("tool-x86_64-linux.tar.gz").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join(&asset.name);
+    asset
+        .download_builder(&dest)
+        .unwrap()
+        .download(&client, NoProgress)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+}
+
+#[tokio::test]
+async fn asset_digest_mismatch_fails_verification() {
+    let client = MockClient::new()
+        .route_data(
+            "https://api.github.com/repos/acme/tool/releases/latest",
+            release_json().as_bytes(),
+        )
+        .route_data("https://example.com/tool-x86_64-linux.tar.gz", b"tampered!!!");
+    let release = Release::fetch(&client, "acme", "tool", None).await.unwrap();
+    let asset = release.asset("tool-x86_64-linux.tar.gz").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join(&asset.name);
+    let err = asset
+        .download_builder(&dest)
+        .unwrap()
+        .download(&client, NoProgress)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+}
@@ -0,0 +1,295 @@
+#![cfg(all(feature = "manifest", feature = "sha2"))]
+
+mod common;
+
+use common::{MockBody, MockClient, TestProgress};
+use fetchkit::manifest::{ExecuteOptions, FetchManifest};
+use fetchkit::progress::Group;
+use fetchkit::ErrorKind;
+use sha2::{Digest, Sha256};
+
+/// A small tar archive with `bin/tool` and `doc/README`.
+fn tar_bytes() -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(5);
+    header.set_mode(0o755);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "bin/tool", &b"tool\n"[..])
+        .unwrap();
+    builder.into_inner().unwrap()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn manifest_toml(dir: &std::path::Path, archive: &[u8]) -> String {
+    format!(
+        r#"
+        [artifacts.tool]
+        url = "https://example.com/tool.tar"
+        mirrors = ["https://mirror.example.com/tool.tar"]
+        checksum = "sha256:{tool_sha}"
+        size = {tool_size}
+        dest = "{dir}/tool"
+        strip_components = 1
+
+        [artifacts.data]
+        url = "https://example.com/data.txt"
+        checksum = "sha256:{data_sha}"
+        dest = "{dir}/data"
+        "#,
+        tool_sha = sha256_hex(archive),
+        tool_size = archive.len(),
+        data_sha = sha256_hex(b"hello world"),
+        dir = dir.display(),
+    )
+}
+
+#[test]
+fn parses_a_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = FetchManifest::from_toml(&manifest_toml(dir.path(), b"fake")).unwrap();
+    assert_eq!(manifest.artifacts.len(), 2);
+    let tool = &manifest.artifacts["tool"];
+    assert_eq!(tool.url, "https://example.com/tool.tar");
+    assert_eq!(tool.mirrors, ["https://mirror.example.com/tool.tar"]);
+    assert_eq!(tool.strip_components, 1);
+    assert_eq!(tool.size, 4);
+    let data = &manifest.artifacts["data"];
+    assert!(data.mirrors.is_empty());
+    assert_eq!(data.size, 0);
+}
+
+#[test]
+fn rejects_invalid_manifests() {
+    // Unknown field
+    assert!(FetchManifest::from_toml(
+        r#"
+        [artifacts.a]
+        url = "https://example.com/a"
+        dest = "a"
+        tarball = true
+        "#
+    )
+    .is_err());
+    // Missing dest
+    assert!(FetchManifest::from_toml(
+        r#"
+        [artifacts.a]
+        url = "https://example.com/a"
+        "#
+    )
+    .is_err());
+    // Duplicate destination
+    let err = FetchManifest::from_toml(
+        r#"
+        [artifacts.a]
+        url = "https://example.com/a"
+        dest = "out"
+        [artifacts.b]
+        url = "https://example.com/b"
+        dest = "out"
+        "#,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("invalid artifact b"));
+    // Unknown hash algorithm
+    let err = FetchManifest::from_toml(
+        r#"
+        [artifacts.a]
+        url = "https://example.com/a"
+        dest = "out"
+        checksum = "crc32:deadbeef"
+        "#,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("invalid artifact a"));
+}
+
+#[test]
+fn json_manifests_parse_too() {
+    let manifest = FetchManifest::from_json(
+        r#"{
+            "artifacts": {
+                "a": { "url": "https://example.com/a", "dest": "out" }
+            }
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(manifest.artifacts["a"].dest, std::path::Path::new("out"));
+}
+
+#[test]
+fn templates_expand_across_the_manifest() {
+    use fetchkit::target::TargetVars;
+
+    let manifest = FetchManifest::from_toml(
+        r#"
+        [artifacts.tool]
+        url = "https://example.com/tool-{version}-{os}.tar.gz"
+        mirrors = ["https://mirror.example.com/tool-{version}-{os}.tar.gz"]
+        dest = "out"
+        "#,
+    )
+    .unwrap();
+    let vars = TargetVars::default().os_alias("linux").version("1.0.0");
+    let manifest = manifest.expand(&vars).unwrap();
+    let tool = &manifest.artifacts["tool"];
+    assert_eq!(tool.url, "https://example.com/tool-1.0.0-linux.tar.gz");
+    assert_eq!(
+        tool.mirrors,
+        ["https://mirror.example.com/tool-1.0.0-linux.tar.gz"]
+    );
+}
+
+#[tokio::test]
+async fn executes_all_artifacts() {
+    let archive = tar_bytes();
+    let client = MockClient::new()
+        .route_data("https://example.com/tool.tar", &archive)
+        // The mirror host is dead; the shared ranking falls back to the
+        // primary.
+        .route("https://mirror.example.com/tool.tar", MockBody::ConnectError)
+        .route_data("https://example.com/data.txt", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = FetchManifest::from_toml(&manifest_toml(dir.path(), &archive)).unwrap();
+    let progress = TestProgress::new();
+    let report = manifest
+        .execute(
+            &client,
+            ExecuteOptions {
+                concurrency: 2,
+                progress: Group::forward_to(progress.clone()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(report.is_success());
+    assert_eq!(
+        report.artifacts.keys().map(String::as_str).collect::<Vec<_>>(),
+        ["data", "tool"]
+    );
+    assert!(report.artifacts["tool"].as_ref().unwrap().downloaded);
+    // strip_components flattened bin/tool.
+    assert!(dir.path().join("tool/tool").is_file());
+    assert_eq!(
+        std::fs::read(dir.path().join("data/data.txt")).unwrap(),
+        b"hello world"
+    );
+    // The aggregated progress covers both downloads and was finished.
+    assert_eq!(progress.positions().last(), Some(&(archive.len() as u64 + 11)));
+    assert!(progress.finished());
+}
+
+#[tokio::test]
+async fn a_failing_artifact_is_collected_with_continue_on_error() {
+    let archive = tar_bytes();
+    let client = MockClient::new()
+        .route_data("https://example.com/tool.tar", &archive)
+        .route_data("https://mirror.example.com/tool.tar", &archive)
+        // The data artifact fails its checksum.
+        .route_data("https://example.com/data.txt", b"tampered!!!");
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = FetchManifest::from_toml(&manifest_toml(dir.path(), &archive)).unwrap();
+    let report = manifest
+        .execute(
+            &client,
+            ExecuteOptions {
+                continue_on_error: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(!report.is_success());
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 1);
+    let (name, err) = failures[0];
+    assert_eq!(name, "data");
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    assert!(err.to_string().contains("failed to fetch artifact data"));
+    // The other artifact still completed.
+    assert!(dir.path().join("tool/tool").is_file());
+}
+
+#[cfg(feature = "lockfile")]
+#[tokio::test]
+async fn lock_entries_pin_artifact_digests() {
+    use fetchkit::lockfile::{LockEntry, Lockfile};
+
+    let client = MockClient::new().route_data("https://example.com/data.txt", b"hello world");
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = FetchManifest::from_toml(&format!(
+        r#"
+        [artifacts.data]
+        url = "https://example.com/data.txt"
+        dest = "{dir}/data"
+        "#,
+        dir = dir.path().display(),
+    ))
+    .unwrap();
+
+    // A drifted pin is a hard verification error naming both digests.
+    let drifted = "0".repeat(64);
+    let mut lockfile = Lockfile::new();
+    lockfile.update_from(
+        "data",
+        LockEntry::new("https://example.com/data.txt", 11, drifted.clone()),
+    );
+    let err = manifest
+        .execute(
+            &client,
+            ExecuteOptions {
+                lockfile: Some(lockfile.clone()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    let msg = err.to_string();
+    assert!(msg.contains(&drifted) && msg.contains(&sha256_hex(b"hello world")));
+
+    // The matching pin verifies.
+    lockfile.update_from(
+        "data",
+        LockEntry::new("https://example.com/data.txt", 11, sha256_hex(b"hello world")),
+    );
+    let report = manifest
+        .execute(
+            &client,
+            ExecuteOptions {
+                lockfile: Some(lockfile),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(report.is_success());
+}
+
+#[tokio::test]
+async fn aborts_on_the_first_failure_by_default() {
+    let client =
+        MockClient::new().route("https://example.com/data.txt", MockBody::Status(503));
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = FetchManifest::from_toml(&format!(
+        r#"
+        [artifacts.data]
+        url = "https://example.com/data.txt"
+        dest = "{dir}/data"
+        "#,
+        dir = dir.path().display(),
+    ))
+    .unwrap();
+    let err = manifest
+        .execute(&client, ExecuteOptions::default())
+        .await
+        .unwrap_err();
+    assert_eq!(err.http_status(), Some(503));
+    assert!(err.to_string().contains("failed to fetch artifact data"));
+}
@@ -0,0 +1,137 @@
+//! Integration tests for fetching minisign keys and signatures over HTTP.
+
+#![cfg(feature = "minisign")]
+
+mod common;
+
+use common::MockClient;
+use fetchkit::error::ErrorKind;
+use fetchkit::verify::minisign::MinisignVerifierBuilder;
+use fetchkit::verify::{Verifier, VerifierBuilder};
+
+// Generated with the minisign tool, the signed content is "test".
+const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==
+";
+
+#[tokio::test]
+async fn from_urls() {
+    let client = MockClient::new()
+        .route_data("https://example.com/minisign.pub", PUBLIC_KEY.as_bytes())
+        .route_data("https://example.com/test.minisig", SIGNATURE.as_bytes());
+    let builder = MinisignVerifierBuilder::from_urls(
+        &client,
+        "https://example.com/minisign.pub",
+        "https://example.com/test.minisig",
+    )
+    .await
+    .unwrap();
+    let mut verifier = builder.build().unwrap();
+    verifier.update(b"test");
+    assert!(verifier.verify().is_ok());
+}
+
+#[tokio::test]
+async fn from_urls_unreachable() {
+    let client = MockClient::new();
+    let err = MinisignVerifierBuilder::from_urls(
+        &client,
+        "https://example.com/minisign.pub",
+        "https://example.com/test.minisig",
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Network);
+}
+
+#[tokio::test]
+async fn from_urls_size_cap() {
+    let huge = vec![b'a'; 80 * 1024];
+    let client = MockClient::new()
+        .route_data("https://example.com/minisign.pub", &huge)
+        .route_data("https://example.com/test.minisig", SIGNATURE.as_bytes());
+    let err = MinisignVerifierBuilder::from_urls(
+        &client,
+        "https://example.com/minisign.pub",
+        "https://example.com/test.minisig",
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Verify);
+    assert!(err.description().unwrap().contains("exceeds"));
+}
+
+mod sidecar {
+    use fetchkit::download::DownloadBuilder;
+    use fetchkit::progress::NoProgress;
+    use fetchkit::verify::minisign::PublicKey;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn verifies_the_download() {
+        let client = MockClient::new()
+            .route_data("https://example.com/test", b"test")
+            .route_data("https://example.com/test.minisig", SIGNATURE.as_bytes());
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("test");
+        DownloadBuilder::new("https://example.com/test", &dest, 4)
+            .with_minisign(&PublicKey::from_base64(PUBLIC_KEY).unwrap(), "minisig")
+            .download(&client, NoProgress)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"test");
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_content() {
+        let client = MockClient::new()
+            .route_data("https://example.com/test", b"tampered")
+            .route_data("https://example.com/test.minisig", SIGNATURE.as_bytes());
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("test");
+        let err = DownloadBuilder::new("https://example.com/test", &dest, 8)
+            .with_minisign(&PublicKey::from_base64(PUBLIC_KEY).unwrap(), "minisig")
+            .download(&client, NoProgress)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(!dest.exists());
+    }
+
+    #[tokio::test]
+    async fn a_missing_signature_fails_before_any_write() {
+        let client = MockClient::new().route_data("https://example.com/test", b"test");
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("test");
+        let err = DownloadBuilder::new("https://example.com/test", &dest, 4)
+            .with_minisign(&PublicKey::from_base64(PUBLIC_KEY).unwrap(), "minisig")
+            .download(&client, NoProgress)
+            .await
+            .unwrap_err();
+        assert!(
+            err.description()
+                .unwrap()
+                .contains("https://example.com/test.minisig")
+        );
+        assert!(!dest.exists());
+        assert!(!dir.path().join("test.part").exists());
+    }
+
+    #[tokio::test]
+    async fn a_garbled_signature_is_a_verify_error() {
+        let client = MockClient::new()
+            .route_data("https://example.com/test", b"test")
+            .route_data("https://example.com/test.minisig", b"not a signature");
+        let dir = tempfile::tempdir().unwrap();
+        let err = DownloadBuilder::new("https://example.com/test", dir.path().join("test"), 4)
+            .with_minisign(&PublicKey::from_base64(PUBLIC_KEY).unwrap(), "minisig")
+            .download(&client, NoProgress)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+    }
+}
@@ -0,0 +1,51 @@
+#![cfg(all(feature = "smol", feature = "sha2"))]
+
+//! The download machinery running on a non-tokio executor.
+
+mod common;
+
+use std::time::Duration;
+
+use common::{MockBody, MockClient};
+use fetchkit::download::{DownloadBuilder, MirrorOptions};
+use fetchkit::progress::NoProgress;
+use fetchkit::verify::hash::Sha256VerifierBuilder;
+
+// sha256 of "hello world"
+const HELLO_WORLD_SHA256: &str =
+    "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+#[test]
+fn download_with_mirrors_and_timeout_on_smol() {
+    smol::block_on(async {
+        let client = MockClient::new()
+            .route("https://primary.example.com/data", MockBody::ConnectError)
+            .route_data("https://mirror.example.com/data", b"hello world");
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("data");
+        let mirrors = ["https://mirror.example.com/data"];
+        DownloadBuilder::new("https://primary.example.com/data", &dest, 11)
+            .with_verifier(Sha256VerifierBuilder::from_hex(HELLO_WORLD_SHA256).unwrap())
+            .with_mirrors(MirrorOptions::new(&mirrors))
+            .with_timeout(Duration::from_secs(5))
+            .download(&client, NoProgress)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"hello world");
+    });
+}
+
+#[test]
+fn timeout_fires_on_a_stalled_stream_on_smol() {
+    smol::block_on(async {
+        let client = MockClient::new().route("https://example.com/data", MockBody::Stall);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("data");
+        let err = DownloadBuilder::new("https://example.com/data", &dest, 11)
+            .with_timeout(Duration::from_millis(50))
+            .download(&client, NoProgress)
+            .await
+            .unwrap_err();
+        assert!(err.is_timeout());
+    });
+}